@@ -5,6 +5,7 @@ use image::ImageOutputFormat;
 use lazy_static::lazy_static;
 use mime::{Mime, IMAGE_GIF, IMAGE_JPEG, IMAGE_PNG};
 use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -46,6 +47,23 @@ pub struct Settings {
 
     #[serde(default)]
     pub auth: Auth,
+
+    #[serde(default)]
+    pub transcoding: Transcoding,
+    #[serde(default)]
+    pub hls: Hls,
+    #[serde(default)]
+    pub now_playing: NowPlaying,
+    #[serde(default)]
+    pub dlna: Dlna,
+    #[serde(default)]
+    pub audioscrobbler: Audioscrobbler,
+    #[serde(default)]
+    pub dav: Dav,
+    #[serde(default)]
+    pub graphql: Graphql,
+    #[serde(default)]
+    pub openapi: OpenApi,
 }
 
 impl Default for Settings {
@@ -60,6 +78,14 @@ impl Default for Settings {
             tasks: Tasks::default(),
             connections: Connections::default(),
             auth: Auth::default(),
+            transcoding: Transcoding::default(),
+            hls: Hls::default(),
+            now_playing: NowPlaying::default(),
+            dlna: Dlna::default(),
+            audioscrobbler: Audioscrobbler::default(),
+            dav: Dav::default(),
+            graphql: Graphql::default(),
+            openapi: OpenApi::default(),
         }
     }
 }
@@ -100,6 +126,9 @@ pub struct Library {
     pub release_name: String,
     #[serde(default = "default_track_name")]
     pub track_name: String,
+    /// The user allowed to edit the files of the library, such as their lyrics
+    #[serde(default)]
+    pub owner: Option<String>,
 
     #[serde(default)]
     pub tagging: Tagging,
@@ -115,6 +144,9 @@ pub struct Tagging {
     pub genre_limit: Option<usize>, // TODO: reimplement genre limits
     #[serde(default = "default_title_format")]
     pub title_format: String,
+    /// Whether the ratings of the library owner are written into its files
+    #[serde(default)]
+    pub write_ratings: bool,
 
     #[serde(default = "default_id3_separator")]
     pub id3_separator: String,
@@ -146,6 +178,7 @@ impl Default for Tagging {
             clear: default_true(),
             genre_limit: Option::default(),
             title_format: default_title_format(),
+            write_ratings: false,
 
             id3_separator: default_id3_separator(),
             mp4_separator: default_separator(),
@@ -154,7 +187,7 @@ impl Default for Tagging {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, JsonSchema)]
 pub enum ArtProvider {
     CoverArtArchive,
     Itunes,
@@ -302,6 +335,10 @@ fn get_search_index(path: &Path) -> PathBuf {
     path.join(".search")
 }
 
+fn get_hls_cache(path: &Path) -> PathBuf {
+    path.join(".hls")
+}
+
 pub fn load(path: Option<PathBuf>) -> Result<Settings> {
     let path = path.unwrap_or({
         let dirs = ProjectDirs::from("com", "github", CLI_NAME)
@@ -329,6 +366,9 @@ pub fn generate_default(mut set: Settings) -> Result<Settings> {
     if set.search_index == PathBuf::default() {
         set.search_index = get_search_index(&set.library.path);
     }
+    if set.hls.cache == PathBuf::default() {
+        set.hls.cache = get_hls_cache(&set.library.path);
+    }
     if set.tasks.recurring == HashMap::default() {
         set.tasks.recurring = default_recurring();
     }
@@ -358,6 +398,9 @@ pub enum JobType {
     ArtistDescription,
     LastFMArtistImage,
     IndexSearch,
+    ReplayGain,
+    Waveform,
+    YearReview,
 }
 
 fn default_outdated() -> time::Duration {
@@ -368,6 +411,10 @@ fn default_recurring() -> HashMap<JobType, String> {
     [
         (JobType::ArtistUrl, "0 0 3 * * * *".to_string()),
         (JobType::ArtistDescription, "0 0 4 * * * *".to_string()),
+        (JobType::ReplayGain, "0 0 5 * * * *".to_string()),
+        (JobType::Waveform, "0 30 5 * * * *".to_string()),
+        // a first review in December, then the complete one once the year is over
+        (JobType::YearReview, "0 0 6 1 1,12 * *".to_string()),
         // (TaskType::ArtistImagesLastfm, "0 0 4 * * * *".to_string()),
     ]
     .into()
@@ -508,6 +555,333 @@ pub struct LastFMConnection {
     pub shared_secret: String,
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscodingFormat {
+    #[default]
+    Opus,
+    Mp3,
+    Aac,
+}
+
+impl TranscodingFormat {
+    pub fn codec(&self) -> &'static str {
+        match self {
+            TranscodingFormat::Opus => "libopus",
+            TranscodingFormat::Mp3 => "libmp3lame",
+            TranscodingFormat::Aac => "aac",
+        }
+    }
+    pub fn container(&self) -> &'static str {
+        match self {
+            TranscodingFormat::Opus => "ogg",
+            TranscodingFormat::Mp3 => "mp3",
+            TranscodingFormat::Aac => "adts",
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscodingFormat::Opus => "opus",
+            TranscodingFormat::Mp3 => "mp3",
+            TranscodingFormat::Aac => "aac",
+        }
+    }
+    pub fn mime(&self) -> Mime {
+        match self {
+            TranscodingFormat::Opus => Mime::from_str("audio/ogg").unwrap(),
+            TranscodingFormat::Mp3 => Mime::from_str("audio/mpeg").unwrap(),
+            TranscodingFormat::Aac => Mime::from_str("audio/aac").unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscodingProfile {
+    pub format: TranscodingFormat,
+    #[serde(default)]
+    pub max_bitrate: Option<u32>,
+}
+
+fn default_ffmpeg() -> String {
+    "ffmpeg".to_string()
+}
+
+fn default_ffprobe() -> String {
+    "ffprobe".to_string()
+}
+
+fn default_transcoding_command() -> Vec<String> {
+    [
+        "{ffmpeg}",
+        "-v",
+        "quiet",
+        "-ss",
+        "{offset}",
+        "-i",
+        "{input}",
+        "-map",
+        "0:a:0",
+        "-c:a",
+        "{codec}",
+        "-b:a",
+        "{bitrate}k",
+        "-f",
+        "{container}",
+        "-",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_probe_command() -> Vec<String> {
+    [
+        "{ffprobe}",
+        "-v",
+        "quiet",
+        "-print_format",
+        "json",
+        "-show_format",
+        "-show_streams",
+        "-select_streams",
+        "a:0",
+        "{input}",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_transcoding_bitrate() -> u32 {
+    192
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcoding {
+    /// The ffmpeg executable, run directly by the tasks which analyze the audio
+    #[serde(default = "default_ffmpeg")]
+    pub ffmpeg: String,
+    /// The ffprobe executable
+    #[serde(default = "default_ffprobe")]
+    pub ffprobe: String,
+    /// The encoder invocation, the first element being the executable. Each argument can
+    /// reference the {ffmpeg}, {input}, {offset}, {codec}, {container} and {bitrate}
+    /// placeholders.
+    #[serde(default = "default_transcoding_command")]
+    pub command: Vec<String>,
+    #[serde(default = "default_transcoding_bitrate")]
+    pub default_bitrate: u32,
+    /// The prober invocation used to inspect the audio stream of imported files.
+    /// It must output ffprobe-compatible JSON and can reference the {ffprobe} and
+    /// {input} placeholders.
+    #[serde(default = "default_probe_command")]
+    pub probe_command: Vec<String>,
+
+    #[serde(default)]
+    pub profiles: HashMap<String, TranscodingProfile>,
+    /// Maps a username to the name of the profile used when none is requested.
+    #[serde(default)]
+    pub users: HashMap<String, String>,
+    /// Maps a client name to the name of the profile used when none is requested.
+    /// Client profiles take precedence over user profiles.
+    #[serde(default)]
+    pub clients: HashMap<String, String>,
+}
+
+impl Default for Transcoding {
+    fn default() -> Self {
+        Self {
+            ffmpeg: default_ffmpeg(),
+            ffprobe: default_ffprobe(),
+            command: default_transcoding_command(),
+            default_bitrate: default_transcoding_bitrate(),
+            probe_command: default_probe_command(),
+
+            profiles: HashMap::new(),
+            users: HashMap::new(),
+            clients: HashMap::new(),
+        }
+    }
+}
+
+fn default_hls_cache_size() -> u64 {
+    1024
+}
+
+fn default_hls_segment_duration() -> u32 {
+    10
+}
+
+fn default_hls_bitrate() -> u32 {
+    192
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hls {
+    #[serde(default)]
+    pub cache: PathBuf,
+    /// Maximum size of the segment cache, in megabytes. The segments of the least
+    /// recently used tracks and releases are evicted once the limit is exceeded.
+    #[serde(default = "default_hls_cache_size")]
+    pub cache_size: u64,
+    /// Target duration of each segment, in seconds
+    #[serde(default = "default_hls_segment_duration")]
+    pub segment_duration: u32,
+    /// Bitrate of the AAC stream in each segment, in kbps
+    #[serde(default = "default_hls_bitrate")]
+    pub bitrate: u32,
+}
+
+impl Default for Hls {
+    fn default() -> Self {
+        Self {
+            cache: PathBuf::default(),
+            cache_size: default_hls_cache_size(),
+            segment_duration: default_hls_segment_duration(),
+            bitrate: default_hls_bitrate(),
+        }
+    }
+}
+
+fn default_now_playing_timeout() -> u64 {
+    120
+}
+
+fn default_scrobble_fraction() -> f64 {
+    0.5
+}
+
+fn default_scrobble_after() -> u64 {
+    240
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NowPlaying {
+    /// Seconds a session is kept after its track should have ended without any
+    /// new report from the client
+    #[serde(default = "default_now_playing_timeout")]
+    pub timeout: u64,
+    /// Fraction of a track that has to be played for it to be scrobbled
+    #[serde(default = "default_scrobble_fraction")]
+    pub scrobble_fraction: f64,
+    /// Position, in seconds, after which a track is scrobbled regardless of its length
+    #[serde(default = "default_scrobble_after")]
+    pub scrobble_after: u64,
+    /// Whether users can see what everyone else is playing, instead of only
+    /// their own sessions
+    #[serde(default)]
+    pub public: bool,
+}
+
+impl Default for NowPlaying {
+    fn default() -> Self {
+        Self {
+            timeout: default_now_playing_timeout(),
+            scrobble_fraction: default_scrobble_fraction(),
+            scrobble_after: default_scrobble_after(),
+            public: bool::default(),
+        }
+    }
+}
+
+fn default_dlna_name() -> String {
+    CLI_NAME.to_string()
+}
+
+fn default_dlna_announce_interval() -> u64 {
+    900
+}
+
+/// The UPnP/DLNA media server, only available when built with the `dlna` feature.
+/// Devices are pointed to the server through the `url` setting, which must then
+/// be reachable from the local network. Requests from other networks are refused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dlna {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Name shown by devices browsing the network
+    #[serde(default = "default_dlna_name")]
+    pub name: String,
+    /// Seconds between SSDP announcements. Devices forget the server after
+    /// twice this interval without hearing from it.
+    #[serde(default = "default_dlna_announce_interval")]
+    pub announce_interval: u64,
+}
+
+impl Default for Dlna {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            name: default_dlna_name(),
+            announce_interval: default_dlna_announce_interval(),
+        }
+    }
+}
+
+/// The Last.fm compatible endpoint for players which can only scrobble there.
+/// Players sign their requests with the API key and shared secret they were built
+/// with, so only the clients listed here are accepted.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Audioscrobbler {
+    /// Shared secrets by API key
+    #[serde(default)]
+    pub clients: HashMap<String, String>,
+    /// Whether received plays are also scrobbled to the users' own connections
+    #[serde(default)]
+    pub relay: bool,
+}
+
+/// Read-only WebDAV access to the library, for tools which can only work with
+/// files. Users log in with the same credentials as in the API.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dav {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+fn default_graphql_max_depth() -> usize {
+    12
+}
+
+fn default_graphql_max_complexity() -> usize {
+    10000
+}
+
+/// The GraphQL endpoint, only available when built with the `graphql` feature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Graphql {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Deepest nesting of fields accepted in a query
+    #[serde(default = "default_graphql_max_depth")]
+    pub max_depth: usize,
+    /// Highest complexity accepted in a query, each field counting once for
+    /// every item of the lists it is nested in
+    #[serde(default = "default_graphql_max_complexity")]
+    pub max_complexity: usize,
+    /// Whether the GraphiQL explorer is served on GET requests
+    #[serde(default)]
+    pub explorer: bool,
+}
+
+impl Default for Graphql {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_depth: default_graphql_max_depth(),
+            max_complexity: default_graphql_max_complexity(),
+            explorer: false,
+        }
+    }
+}
+
+/// The OpenAPI document is always served, the page rendering it only on demand
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenApi {
+    #[serde(default)]
+    pub docs: bool,
+}
+
 #[derive(Error, Debug)]
 pub enum SettingsError {
     #[error("Settings global store is unitialized")]
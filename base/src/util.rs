@@ -26,6 +26,26 @@ pub fn dedup<T: Ord>(mut vec: Vec<T>) -> Vec<T> {
     vec
 }
 
+/// Escapes text for use in XML content and attribute values
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Reverses [escape_xml]
+pub fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
 pub fn mkdirp<P: AsRef<Path>>(path: P) -> Result<(), UtilError> {
     if let Err(e) = create_dir_all(path) {
         if e.kind() != io::ErrorKind::AlreadyExists {
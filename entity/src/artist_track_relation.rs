@@ -1,4 +1,5 @@
 use sea_orm::entity::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -14,6 +15,7 @@ use uuid::Uuid;
     Ord,
     EnumIter,
     DeriveActiveEnum,
+    JsonSchema,
 )]
 #[sea_orm(rs_type = "String", db_type = "String(Some(1))")]
 pub enum RelationType {
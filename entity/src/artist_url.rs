@@ -1,4 +1,5 @@
 use sea_orm::entity::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -25,6 +26,7 @@ pub struct Model {
     PartialOrd,
     Ord,
     Hash,
+    JsonSchema,
 )]
 #[sea_orm(rs_type = "String", db_type = "String(None)")]
 pub enum UrlType {
@@ -78,6 +78,17 @@ lazy_static! {
             TrackColumn::Number,
             TrackColumn::Format,
             TrackColumn::Path,
+            TrackColumn::Bpm,
+            TrackColumn::Framerate,
+            TrackColumn::Framecount,
+            TrackColumn::Channels,
+            TrackColumn::Bitrate,
+            TrackColumn::Bitdepth,
+            TrackColumn::Size,
+            TrackColumn::EncoderDelay,
+            TrackColumn::EncoderPadding,
+            TrackColumn::SampleCount,
+            TrackColumn::GaplessPlayback,
         ])
         .to_owned();
     pub static ref ARTIST_CREDIT_TRACK_CONFLICT: OnConflict = OnConflict::columns([
@@ -123,4 +134,28 @@ lazy_static! {
         OnConflict::columns([GenreReleaseColumn::GenreId, GenreReleaseColumn::ReleaseId])
             .do_nothing()
             .to_owned();
+    pub static ref WAVEFORM_CONFLICT: OnConflict = OnConflict::column(WaveformColumn::TrackId)
+        .update_column(WaveformColumn::Peaks)
+        .to_owned();
+    pub static ref FAVORITE_CONFLICT: OnConflict = OnConflict::columns([
+        FavoriteColumn::User,
+        FavoriteColumn::Kind,
+        FavoriteColumn::Resource
+    ])
+    .do_nothing()
+    .to_owned();
+    pub static ref RATING_CONFLICT: OnConflict = OnConflict::columns([
+        RatingColumn::User,
+        RatingColumn::Kind,
+        RatingColumn::Resource
+    ])
+    .update_columns([RatingColumn::Rating, RatingColumn::UpdatedAt])
+    .to_owned();
+    pub static ref YEAR_REVIEW_CONFLICT: OnConflict =
+        OnConflict::columns([YearReviewColumn::User, YearReviewColumn::Year])
+            .update_columns([YearReviewColumn::Report, YearReviewColumn::UpdatedAt])
+            .to_owned();
+    pub static ref LYRICS_CONFLICT: OnConflict = OnConflict::column(LyricsColumn::TrackId)
+        .update_columns([LyricsColumn::Text, LyricsColumn::Lines])
+        .to_owned();
 }
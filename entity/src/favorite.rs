@@ -0,0 +1,76 @@
+use std::hash::Hash;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "snake_case")]
+pub enum FavoriteKind {
+    #[sea_orm(num_value = 0)]
+    Track,
+    #[sea_orm(num_value = 1)]
+    Release,
+    #[sea_orm(num_value = 2)]
+    Artist,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "favorite")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub kind: FavoriteKind,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub resource: Uuid,
+
+    pub created_at: TimeDateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "kind" => Ok(Column::Kind),
+            "resource" => Ok(Column::Resource),
+            "created_at" => Ok(Column::CreatedAt),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use base::setting::ArtProvider;
 use sea_orm::entity::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, collections::HashMap, hash::Hash};
 use uuid::Uuid;
 
 use crate::full::ArtistInfo;
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct InternalTrack {
     pub title: String,
     pub artists: Vec<String>,
@@ -16,7 +17,7 @@ pub struct InternalTrack {
     pub path: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema)]
 pub struct InternalRelease {
     pub title: String,
     pub artists: Vec<String>,
@@ -87,7 +88,7 @@ impl From<crate::full::FullTrack> for InternalTrack {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Cover {
     pub provider: ArtProvider,
     pub url: String,
@@ -158,7 +159,7 @@ pub struct ArtistCreditTracks(pub Vec<super::ArtistCreditTrack>);
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult)]
 pub struct Covers(pub Vec<Cover>);
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema)]
 pub struct ReleaseRating {
     pub score: i64,
     pub assignment: HashMap<usize, Uuid>,
@@ -1,7 +1,9 @@
+mod lyrics;
 mod medium;
 mod release;
 mod track;
 mod track_format;
+mod waveform;
 
 mod artist;
 mod artist_credit;
@@ -19,9 +21,19 @@ mod genre;
 mod genre_release;
 mod genre_track;
 
+mod api_token;
+mod favorite;
+mod play_queue;
+mod playlist;
+mod playlist_track;
+mod rating;
 mod scrobble;
+mod share;
+mod smart_playlist;
+mod unmatched_listen;
 mod user;
 pub mod user_connection;
+mod year_review;
 
 pub mod import;
 
@@ -109,6 +121,19 @@ pub use genre_track::Entity as GenreTrackEntity;
 pub use genre_track::Model as GenreTrack;
 pub use genre_track::Relation as GenreTrackRelation;
 
+pub use waveform::ActiveModel as WaveformActive;
+pub use waveform::Column as WaveformColumn;
+pub use waveform::Entity as WaveformEntity;
+pub use waveform::Model as Waveform;
+pub use waveform::Relation as WaveformRelation;
+
+pub use lyrics::ActiveModel as LyricsActive;
+pub use lyrics::Column as LyricsColumn;
+pub use lyrics::Entity as LyricsEntity;
+pub use lyrics::Model as Lyrics;
+pub use lyrics::Relation as LyricsRelation;
+pub use lyrics::{LyricsLine, LyricsLines};
+
 pub use image::ActiveModel as ImageActive;
 pub use image::Column as ImageColumn;
 pub use image::Entity as ImageEntity;
@@ -125,11 +150,61 @@ pub use image_release::Entity as ImageReleaseEntity;
 pub use image_release::Model as ImageRelease;
 pub use image_release::Relation as ImageReleaseRelation;
 
+pub use api_token::ActiveModel as ApiTokenActive;
+pub use api_token::Column as ApiTokenColumn;
+pub use api_token::Entity as ApiTokenEntity;
+pub use api_token::Model as ApiToken;
+pub use api_token::Relation as ApiTokenRelation;
+pub use favorite::ActiveModel as FavoriteActive;
+pub use favorite::Column as FavoriteColumn;
+pub use favorite::Entity as FavoriteEntity;
+pub use favorite::FavoriteKind;
+pub use favorite::Model as Favorite;
+pub use favorite::Relation as FavoriteRelation;
+pub use play_queue::ActiveModel as PlayQueueActive;
+pub use play_queue::Column as PlayQueueColumn;
+pub use play_queue::Entity as PlayQueueEntity;
+pub use play_queue::Model as PlayQueue;
+pub use play_queue::PlayQueueTracks;
+pub use play_queue::Relation as PlayQueueRelation;
+pub use playlist::ActiveModel as PlaylistActive;
+pub use playlist::Column as PlaylistColumn;
+pub use playlist::Entity as PlaylistEntity;
+pub use playlist::Model as Playlist;
+pub use playlist::Relation as PlaylistRelation;
+pub use playlist_track::ActiveModel as PlaylistTrackActive;
+pub use playlist_track::Column as PlaylistTrackColumn;
+pub use playlist_track::Entity as PlaylistTrackEntity;
+pub use playlist_track::Model as PlaylistTrack;
+pub use playlist_track::Relation as PlaylistTrackRelation;
+pub use rating::ActiveModel as RatingActive;
+pub use rating::Column as RatingColumn;
+pub use rating::Entity as RatingEntity;
+pub use rating::Model as Rating;
+pub use rating::RatingKind;
+pub use rating::Relation as RatingRelation;
 pub use scrobble::ActiveModel as ScrobbleActive;
 pub use scrobble::Column as ScrobbleColumn;
 pub use scrobble::Entity as ScrobbleEntity;
 pub use scrobble::Model as Scrobble;
 pub use scrobble::Relation as ScrobbleRelation;
+pub use share::ActiveModel as ShareActive;
+pub use share::Column as ShareColumn;
+pub use share::Entity as ShareEntity;
+pub use share::Model as Share;
+pub use share::Relation as ShareRelation;
+pub use share::ShareKind;
+pub use smart_playlist::ActiveModel as SmartPlaylistActive;
+pub use smart_playlist::Column as SmartPlaylistColumn;
+pub use smart_playlist::Entity as SmartPlaylistEntity;
+pub use smart_playlist::Model as SmartPlaylist;
+pub use smart_playlist::Relation as SmartPlaylistRelation;
+pub use smart_playlist::{NumberCondition, Rule, RuleOrder, SmartPlaylistRules, TextCondition};
+pub use unmatched_listen::ActiveModel as UnmatchedListenActive;
+pub use unmatched_listen::Column as UnmatchedListenColumn;
+pub use unmatched_listen::Entity as UnmatchedListenEntity;
+pub use unmatched_listen::Model as UnmatchedListen;
+pub use unmatched_listen::Relation as UnmatchedListenRelation;
 pub use user::ActiveModel as UserActive;
 pub use user::Column as UserColumn;
 pub use user::Entity as UserEntity;
@@ -141,6 +216,14 @@ pub use user_connection::ConnectionProvider;
 pub use user_connection::Entity as UserConnectionEntity;
 pub use user_connection::Model as UserConnection;
 pub use user_connection::Relation as UserConnectionRelation;
+pub use year_review::ActiveModel as YearReviewActive;
+pub use year_review::Column as YearReviewColumn;
+pub use year_review::Entity as YearReviewEntity;
+pub use year_review::Model as YearReview;
+pub use year_review::Relation as YearReviewRelation;
+pub use year_review::{
+    YearReviewDay, YearReviewEntry, YearReviewMonth, YearReviewReport, YearReviewStreak,
+};
 
 pub use import::ActiveModel as ImportActive;
 pub use import::Column as ImportColumn;
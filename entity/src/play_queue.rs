@@ -0,0 +1,75 @@
+use std::hash::Hash;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct PlayQueueTracks(pub Vec<Uuid>);
+
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "play_queue")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user: String,
+    /// The queued tracks, in order. The same track can be queued many times.
+    pub tracks: PlayQueueTracks,
+    /// Index of the current track in the queue
+    pub index: i32,
+    /// Playback position in the current track, in milliseconds
+    pub position: i64,
+    /// The client which last changed the queue
+    pub client: Option<String>,
+    /// Incremented on every change, so that clients can detect the queue was
+    /// changed by someone else since they last read it
+    pub version: i64,
+
+    pub updated_at: TimeDateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "user" => Ok(Column::User),
+            "index" => Ok(Column::Index),
+            "position" => Ok(Column::Position),
+            "client" => Ok(Column::Client),
+            "version" => Ok(Column::Version),
+            "updated_at" => Ok(Column::UpdatedAt),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
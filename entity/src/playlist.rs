@@ -0,0 +1,88 @@
+use std::hash::Hash;
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "playlist")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether other users can see the playlist
+    pub public: bool,
+    pub image_id: Option<String>,
+
+    pub created_at: TimeDateTimeWithTimeZone,
+    pub updated_at: TimeDateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::image::Entity",
+        from = "Column::ImageId",
+        to = "super::image::Column::Id"
+    )]
+    Image,
+    #[sea_orm(has_many = "super::playlist_track::Entity")]
+    PlaylistTrack,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::image::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Image.def()
+    }
+}
+
+impl Related<super::playlist_track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlaylistTrack.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "id" => Ok(Column::Id),
+            "user" => Ok(Column::User),
+            "name" => Ok(Column::Name),
+            "public" => Ok(Column::Public),
+            "created_at" => Ok(Column::CreatedAt),
+            "updated_at" => Ok(Column::UpdatedAt),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// An entry of a playlist. The same track can appear more than once, so entries
+/// are identified by their position in the playlist.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "playlist_track")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub playlist_id: Uuid,
+    /// Starting from 0, without gaps
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub position: i32,
+    pub track_id: Uuid,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::playlist::Entity",
+        from = "Column::PlaylistId",
+        to = "super::playlist::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Playlist,
+    #[sea_orm(
+        belongs_to = "super::track::Entity",
+        from = "Column::TrackId",
+        to = "super::track::Column::Id"
+    )]
+    Track,
+}
+
+impl Related<super::playlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Playlist.def()
+    }
+}
+
+impl Related<super::track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Track.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
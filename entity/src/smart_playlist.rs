@@ -0,0 +1,142 @@
+use std::hash::Hash;
+
+use schemars::JsonSchema;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A condition on a text property, compared regardless of case
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextCondition {
+    Is(String),
+    Contains(String),
+    StartsWith(String),
+}
+
+/// A condition on a number property, bounds included
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberCondition {
+    Is(i64),
+    Between(i64, i64),
+    AtLeast(i64),
+    AtMost(i64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// Matches when every rule matches
+    All(Vec<Rule>),
+    /// Matches when at least one rule matches
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+
+    Title(TextCondition),
+    /// Any of the credited artists of the track
+    Artist(TextCondition),
+    /// Title of the release
+    Release(TextCondition),
+    /// Any genre of the track or of its release
+    Genre(TextCondition),
+    /// Original year of the release, or its year when unknown
+    Year(NumberCondition),
+    /// Length of the track, in milliseconds
+    Length(NumberCondition),
+    Bpm(NumberCondition),
+    /// Number of times the owner of the playlist listened to the track
+    Scrobbles(NumberCondition),
+    /// Whether the owner of the playlist listened to the track in the given
+    /// number of days
+    ScrobbledWithin(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOrder {
+    Random,
+    Title,
+    Year,
+    Length,
+    Bpm,
+    Scrobbles,
+    LastScrobbled,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema)]
+pub struct SmartPlaylistRules {
+    pub rule: Rule,
+    /// Tracks are in release order when missing
+    #[serde(default)]
+    pub order: Option<RuleOrder>,
+    #[serde(default)]
+    pub descending: bool,
+    /// Maximum number of tracks
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "smart_playlist")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether other users can see the playlist. The tracks are always picked
+    /// with the scrobbles of the owner.
+    pub public: bool,
+    pub rules: SmartPlaylistRules,
+
+    pub created_at: TimeDateTimeWithTimeZone,
+    pub updated_at: TimeDateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "id" => Ok(Column::Id),
+            "user" => Ok(Column::User),
+            "name" => Ok(Column::Name),
+            "public" => Ok(Column::Public),
+            "created_at" => Ok(Column::CreatedAt),
+            "updated_at" => Ok(Column::UpdatedAt),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
@@ -18,6 +18,26 @@ pub struct Model {
 
     pub format: Option<TrackFormat>,
     pub path: Option<String>,
+
+    pub bpm: Option<i32>,
+    pub framerate: Option<i32>,
+    pub framecount: Option<i32>,
+    pub channels: Option<i32>,
+    pub bitrate: Option<i32>,
+    pub bitdepth: Option<i32>,
+    pub size: Option<i64>,
+
+    pub replay_gain_track_gain: Option<f64>,
+    pub replay_gain_track_peak: Option<f64>,
+    pub replay_gain_track_range: Option<f64>,
+    pub replay_gain_album_gain: Option<f64>,
+    pub replay_gain_album_peak: Option<f64>,
+    pub replay_gain_album_range: Option<f64>,
+
+    pub encoder_delay: Option<i32>,
+    pub encoder_padding: Option<i32>,
+    pub sample_count: Option<i64>,
+    pub gapless_playback: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -34,6 +54,10 @@ pub enum Relation {
     Genre,
     #[sea_orm(has_many = "super::genre_track::Entity")]
     GentreTrack,
+    #[sea_orm(has_one = "super::waveform::Entity")]
+    Waveform,
+    #[sea_orm(has_one = "super::lyrics::Entity")]
+    Lyrics,
 }
 
 impl Related<super::medium::Entity> for Entity {
@@ -74,6 +98,18 @@ impl Related<super::genre_track::Entity> for Entity {
     }
 }
 
+impl Related<super::waveform::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waveform.def()
+    }
+}
+
+impl Related<super::lyrics::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Lyrics.def()
+    }
+}
+
 #[derive(Debug)]
 pub struct TrackToRelease;
 
@@ -149,6 +185,15 @@ impl TryFrom<String> for Column {
             "number" => Ok(Column::Number),
             "recording_mbid" => Ok(Column::RecordingId),
             "mimetype" => Ok(Column::Format),
+            "bpm" => Ok(Column::Bpm),
+            "framerate" => Ok(Column::Framerate),
+            "framecount" => Ok(Column::Framecount),
+            "channels" => Ok(Column::Channels),
+            "bitrate" => Ok(Column::Bitrate),
+            "bitdepth" => Ok(Column::Bitdepth),
+            "size" => Ok(Column::Size),
+            "replay_gain_track_gain" => Ok(Column::ReplayGainTrackGain),
+            "replay_gain_album_gain" => Ok(Column::ReplayGainAlbumGain),
             &_ => Err("Invalid column name".to_owned()),
         }
     }
@@ -0,0 +1,71 @@
+use std::hash::Hash;
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A listen submitted by an external scrobbler which could not be matched to any
+/// track in the library, kept for later review
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "unmatched_listen")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub user: String,
+    pub listened_at: TimeDateTimeWithTimeZone,
+
+    pub artist_name: String,
+    pub track_name: String,
+    pub release_name: Option<String>,
+    pub recording_mbid: Option<Uuid>,
+    pub track_mbid: Option<Uuid>,
+    pub release_mbid: Option<Uuid>,
+    /// In milliseconds
+    pub duration: Option<i32>,
+    pub submission_client: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "user" => Ok(Column::User),
+            "listened_at" => Ok(Column::ListenedAt),
+            "artist_name" => Ok(Column::ArtistName),
+            "track_name" => Ok(Column::TrackName),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use sea_orm::entity::prelude::*;
 use sea_query::{Expr, IntoCondition, SimpleExpr};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
@@ -26,6 +27,7 @@ pub struct Model {
     PartialOrd,
     Ord,
     Hash,
+    JsonSchema,
 )]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum UpdateType {
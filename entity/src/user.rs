@@ -48,6 +48,24 @@ pub enum Relation {
     Scrobble,
     #[sea_orm(has_many = "super::user_connection::Entity")]
     Connection,
+    #[sea_orm(has_many = "super::share::Entity")]
+    Share,
+    #[sea_orm(has_many = "super::favorite::Entity")]
+    Favorite,
+    #[sea_orm(has_many = "super::unmatched_listen::Entity")]
+    UnmatchedListen,
+    #[sea_orm(has_many = "super::playlist::Entity")]
+    Playlist,
+    #[sea_orm(has_many = "super::smart_playlist::Entity")]
+    SmartPlaylist,
+    #[sea_orm(has_many = "super::rating::Entity")]
+    Rating,
+    #[sea_orm(has_one = "super::play_queue::Entity")]
+    PlayQueue,
+    #[sea_orm(has_many = "super::year_review::Entity")]
+    YearReview,
+    #[sea_orm(has_many = "super::api_token::Entity")]
+    ApiToken,
 }
 
 impl Related<super::scrobble::Entity> for Entity {
@@ -62,6 +80,60 @@ impl Related<super::user_connection::Entity> for Entity {
     }
 }
 
+impl Related<super::share::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Share.def()
+    }
+}
+
+impl Related<super::favorite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Favorite.def()
+    }
+}
+
+impl Related<super::unmatched_listen::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UnmatchedListen.def()
+    }
+}
+
+impl Related<super::playlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Playlist.def()
+    }
+}
+
+impl Related<super::smart_playlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SmartPlaylist.def()
+    }
+}
+
+impl Related<super::rating::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Rating.def()
+    }
+}
+
+impl Related<super::play_queue::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayQueue.def()
+    }
+}
+
+impl Related<super::year_review::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::YearReview.def()
+    }
+}
+
+impl Related<super::api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiToken.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Hash for Column {
@@ -1,7 +1,8 @@
 use sea_orm::entity::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, EnumIter, DeriveActiveEnum)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, EnumIter, DeriveActiveEnum, JsonSchema)]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum ConnectionProvider {
     #[sea_orm(num_value = 0)]
@@ -0,0 +1,114 @@
+use std::hash::Hash;
+
+use schemars::JsonSchema;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct YearReviewEntry<Id> {
+    pub id: Id,
+    pub name: String,
+    pub scrobbles: u64,
+    /// In milliseconds
+    pub listening_time: u64,
+}
+
+/// Consecutive days with at least one scrobble, in UTC
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct YearReviewStreak {
+    /// First day of the streak, as YYYY-MM-DD
+    pub from: String,
+    /// Last day of the streak, as YYYY-MM-DD
+    pub to: String,
+    pub days: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct YearReviewDay {
+    /// As YYYY-MM-DD, in UTC
+    pub date: String,
+    pub scrobbles: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct YearReviewMonth {
+    pub scrobbles: u64,
+    /// In milliseconds
+    pub listening_time: u64,
+    /// The most listened genres of the month
+    pub genres: Vec<YearReviewEntry<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult, JsonSchema)]
+pub struct YearReviewReport {
+    pub scrobbles: u64,
+    /// In milliseconds
+    pub listening_time: u64,
+    pub artists: Vec<YearReviewEntry<Uuid>>,
+    pub releases: Vec<YearReviewEntry<Uuid>>,
+    pub tracks: Vec<YearReviewEntry<Uuid>>,
+    pub genres: Vec<YearReviewEntry<String>>,
+    /// Artists listened to for the first time during the year
+    pub new_artists: Vec<YearReviewEntry<Uuid>>,
+    pub longest_streak: Option<YearReviewStreak>,
+    /// The day with the most scrobbles
+    pub top_day: Option<YearReviewDay>,
+    /// From January to December
+    pub months: Vec<YearReviewMonth>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "year_review")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub year: i32,
+
+    pub report: YearReviewReport,
+    pub updated_at: TimeDateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::User",
+        to = "super::user::Column::Username"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Hash for Column {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state)
+    }
+}
+
+impl PartialEq for Column {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string().eq(&other.to_string())
+    }
+}
+
+impl Eq for Column {}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "year" => Ok(Column::Year),
+            "updated_at" => Ok(Column::UpdatedAt),
+            &_ => Err("Invalid column name".to_owned()),
+        }
+    }
+}
@@ -13,6 +13,20 @@ mod m20231118_000001_update_artist;
 mod m20231126_000001_artist_picture;
 mod m20231126_000002_genres;
 mod m20231209_000001_release_disambiguation;
+mod m20261016_000001_track_properties;
+mod m20261016_000002_track_replay_gain;
+mod m20261016_000003_waveform;
+mod m20261016_000004_share;
+mod m20261016_000005_lyrics;
+mod m20261016_000006_track_gapless;
+mod m20261016_000007_unmatched_listen;
+mod m20261016_000008_playlist;
+mod m20261016_000009_smart_playlist;
+mod m20261016_000010_favorite;
+mod m20261016_000011_rating;
+mod m20261016_000012_play_queue;
+mod m20261016_000013_year_review;
+mod m20261017_000001_api_token;
 
 pub struct Migrator;
 
@@ -33,6 +47,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20231126_000001_artist_picture::Migration),
             Box::new(m20231126_000002_genres::Migration),
             Box::new(m20231209_000001_release_disambiguation::Migration),
+            Box::new(m20261016_000001_track_properties::Migration),
+            Box::new(m20261016_000002_track_replay_gain::Migration),
+            Box::new(m20261016_000003_waveform::Migration),
+            Box::new(m20261016_000004_share::Migration),
+            Box::new(m20261016_000005_lyrics::Migration),
+            Box::new(m20261016_000006_track_gapless::Migration),
+            Box::new(m20261016_000007_unmatched_listen::Migration),
+            Box::new(m20261016_000008_playlist::Migration),
+            Box::new(m20261016_000009_smart_playlist::Migration),
+            Box::new(m20261016_000010_favorite::Migration),
+            Box::new(m20261016_000011_rating::Migration),
+            Box::new(m20261016_000012_play_queue::Migration),
+            Box::new(m20261016_000013_year_review::Migration),
+            Box::new(m20261017_000001_api_token::Migration),
         ]
     }
 }
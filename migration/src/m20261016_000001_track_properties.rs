@@ -0,0 +1,34 @@
+use entity::{TrackColumn, TrackEntity};
+use sea_orm::ColumnTrait;
+use sea_orm_migration::prelude::*;
+use sea_query::Table;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            TrackColumn::Bpm,
+            TrackColumn::Framerate,
+            TrackColumn::Framecount,
+            TrackColumn::Channels,
+            TrackColumn::Bitrate,
+            TrackColumn::Bitdepth,
+            TrackColumn::Size,
+        ];
+        // SQLite only supports adding one column per ALTER TABLE statement
+        for col in columns.into_iter() {
+            let table = Table::alter()
+                .table(TrackEntity)
+                .add_column_if_not_exists(&mut ColumnDef::new_with_type(
+                    col,
+                    col.def().get_column_type().clone(),
+                ))
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+        Ok(())
+    }
+}
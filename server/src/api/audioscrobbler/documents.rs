@@ -0,0 +1,185 @@
+use serde_json::{json, Value};
+
+use base::util::escape_xml;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Xml,
+    Json,
+}
+
+/// Why a track was not scrobbled, as codes Last.fm clients understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ignored {
+    Artist,
+    Track,
+    TimestampTooNew,
+}
+
+impl Ignored {
+    fn code(&self) -> u8 {
+        match self {
+            Ignored::Artist => 1,
+            Ignored::Track => 2,
+            Ignored::TimestampTooNew => 4,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            Ignored::Artist => "Artist was ignored",
+            Ignored::Track => "Track was ignored",
+            Ignored::TimestampTooNew => "Timestamp was too new",
+        }
+    }
+}
+
+/// A track as the player sent it, which is echoed back as there are no corrections
+#[derive(Debug, Default)]
+pub struct Echo {
+    pub track: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub ignored: Option<Ignored>,
+}
+
+impl Echo {
+    fn xml(&self) -> String {
+        let (code, message) = self
+            .ignored
+            .map_or((0, ""), |ignored| (ignored.code(), ignored.message()));
+        format!(
+            "{}{}{}{}<ignoredMessage code=\"{}\">{}</ignoredMessage>",
+            corrected_xml("track", &self.track),
+            corrected_xml("artist", &self.artist),
+            corrected_xml("album", self.album.as_deref().unwrap_or_default()),
+            corrected_xml(
+                "albumArtist",
+                self.album_artist.as_deref().unwrap_or_default()
+            ),
+            code,
+            message
+        )
+    }
+
+    fn json(&self) -> Value {
+        let (code, message) = self
+            .ignored
+            .map_or((0, ""), |ignored| (ignored.code(), ignored.message()));
+        json!({
+            "track": corrected_json(&self.track),
+            "artist": corrected_json(&self.artist),
+            "album": corrected_json(self.album.as_deref().unwrap_or_default()),
+            "albumArtist": corrected_json(self.album_artist.as_deref().unwrap_or_default()),
+            "ignoredMessage": { "code": code.to_string(), "#text": message },
+        })
+    }
+}
+
+fn corrected_xml(name: &str, value: &str) -> String {
+    format!(
+        "<{name} corrected=\"0\">{}</{name}>",
+        escape_xml(value),
+        name = name
+    )
+}
+
+fn corrected_json(value: &str) -> Value {
+    json!({ "corrected": "0", "#text": value })
+}
+
+#[derive(Debug)]
+pub struct Scrobbled {
+    pub echo: Echo,
+    pub timestamp: i64,
+}
+
+#[derive(Debug)]
+pub enum Lfm {
+    Session { name: String, key: String },
+    NowPlaying(Echo),
+    Scrobbles(Vec<Scrobbled>),
+}
+
+impl Lfm {
+    pub fn xml(&self) -> String {
+        let content = match self {
+            Lfm::Session { name, key } => format!(
+                "<session><name>{}</name><key>{}</key><subscriber>0</subscriber></session>",
+                escape_xml(name),
+                escape_xml(key)
+            ),
+            Lfm::NowPlaying(echo) => format!("<nowplaying>{}</nowplaying>", echo.xml()),
+            Lfm::Scrobbles(scrobbles) => {
+                let ignored = scrobbles
+                    .iter()
+                    .filter(|s| s.echo.ignored.is_some())
+                    .count();
+                format!(
+                    "<scrobbles accepted=\"{}\" ignored=\"{}\">{}</scrobbles>",
+                    scrobbles.len() - ignored,
+                    ignored,
+                    scrobbles
+                        .iter()
+                        .map(|s| format!(
+                            "<scrobble>{}<timestamp>{}</timestamp></scrobble>",
+                            s.echo.xml(),
+                            s.timestamp
+                        ))
+                        .collect::<String>()
+                )
+            }
+        };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><lfm status=\"ok\">{}</lfm>",
+            content
+        )
+    }
+
+    pub fn json(&self) -> Value {
+        match self {
+            Lfm::Session { name, key } => json!({
+                "session": { "name": name, "key": key, "subscriber": 0 }
+            }),
+            Lfm::NowPlaying(echo) => json!({ "nowplaying": echo.json() }),
+            Lfm::Scrobbles(scrobbles) => {
+                let ignored = scrobbles
+                    .iter()
+                    .filter(|s| s.echo.ignored.is_some())
+                    .count();
+                let mut documents: Vec<Value> = scrobbles
+                    .iter()
+                    .map(|s| {
+                        let mut document = s.echo.json();
+                        document["timestamp"] = json!(s.timestamp.to_string());
+                        document
+                    })
+                    .collect();
+                // like Last.fm, a single scrobble isn't wrapped in an array
+                let scrobble = match documents.len() {
+                    1 => documents.remove(0),
+                    _ => Value::Array(documents),
+                };
+                json!({
+                    "scrobbles": {
+                        "@attr": { "accepted": scrobbles.len() - ignored, "ignored": ignored },
+                        "scrobble": scrobble,
+                    }
+                })
+            }
+        }
+    }
+}
+
+pub fn error_xml(code: u8, message: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?><lfm status=\"failed\"><error code=\"{}\">{}</error></lfm>",
+        code,
+        escape_xml(message)
+    )
+}
+
+pub fn error_json(code: u8, message: &str) -> Value {
+    json!({ "error": code, "message": message })
+}
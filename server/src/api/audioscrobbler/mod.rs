@@ -0,0 +1,449 @@
+pub mod documents;
+
+use axum::{
+    body::Bytes,
+    extract::{RawQuery, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use sea_orm::{ActiveValue, ConnectionTrait, DbErr, EntityTrait, TransactionTrait};
+use serde_json::Value;
+use std::collections::HashMap;
+use thiserror::Error;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::api::{
+    auth::{authenticate, authenticate_api_token, AuthError},
+    tempo::{
+        api_tokens, now_playing,
+        scrobbles::{match_track, schedule_scrobble_tasks, TrackQuery},
+        stats,
+    },
+    AppState, Error,
+};
+use crate::fetch::lastfm::signature;
+use base::setting::{get_settings, Settings};
+use documents::{error_json, error_xml, Echo, Format, Ignored, Lfm, Scrobbled};
+
+/// The maximum number of scrobbles accepted in a single request, as in Last.fm
+static MAX_SCROBBLES_PER_REQUEST: usize = 50;
+
+/// The client name used for now playing sessions and unmatched listens
+static CLIENT: &str = "audioscrobbler";
+
+// Players are configured with a base URL and call `<base>/2.0/`
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/2.0", get(api).post(api))
+        .route("/2.0/", get(api).post(api))
+}
+
+#[derive(Error, Debug)]
+pub enum AudioscrobblerError {
+    #[error("Invalid Method - No method with that name in this package")]
+    InvalidMethod,
+    #[error("Authentication Failed - You do not have permissions to access the service")]
+    AuthenticationFailed,
+    #[error("Invalid parameters - {0}")]
+    InvalidParameters(String),
+    #[error("Invalid session key - Please re-authenticate")]
+    InvalidSessionKey,
+    #[error("Invalid API key - This key is not allowed to scrobble to this server")]
+    InvalidApiKey,
+    #[error("Invalid method signature supplied")]
+    InvalidSignature,
+    #[error("There was a temporary error processing your request. Please try again")]
+    Api(#[from] Error),
+}
+
+impl From<DbErr> for AudioscrobblerError {
+    fn from(value: DbErr) -> Self {
+        Self::Api(value.into())
+    }
+}
+
+impl AudioscrobblerError {
+    fn code(&self) -> u8 {
+        match self {
+            AudioscrobblerError::InvalidMethod => 3,
+            AudioscrobblerError::AuthenticationFailed => 4,
+            AudioscrobblerError::InvalidParameters(_) => 6,
+            AudioscrobblerError::InvalidSessionKey => 9,
+            AudioscrobblerError::InvalidApiKey => 10,
+            AudioscrobblerError::InvalidSignature => 13,
+            AudioscrobblerError::Api(_) => 16,
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            AudioscrobblerError::InvalidMethod | AudioscrobblerError::InvalidParameters(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            AudioscrobblerError::Api(error) => error.status(),
+            _ => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn render(self, format: Format) -> Response {
+        let status = self.status();
+        if let AudioscrobblerError::Api(error) = &self {
+            tracing::warn!(%error, "Audioscrobbler request failed");
+        }
+        let message = self.to_string();
+        respond(
+            format,
+            status,
+            error_xml(self.code(), &message),
+            error_json(self.code(), &message),
+        )
+    }
+}
+
+fn invalid<S: Into<String>>(message: S) -> AudioscrobblerError {
+    AudioscrobblerError::InvalidParameters(message.into())
+}
+
+fn respond(format: Format, status: StatusCode, xml: String, json: Value) -> Response {
+    match format {
+        Format::Xml => (
+            status,
+            [(header::CONTENT_TYPE, "text/xml; charset=utf-8")],
+            xml,
+        )
+            .into_response(),
+        Format::Json => (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            json.to_string(),
+        )
+            .into_response(),
+    }
+}
+
+/// The parameters of a request, from both the query and the form encoded body
+struct Params(HashMap<String, String>);
+
+impl Params {
+    fn parse(query: Option<&str>, body: &[u8]) -> Result<Self, AudioscrobblerError> {
+        let mut params = HashMap::new();
+        let query: Vec<(String, String)> = serde_urlencoded::from_str(query.unwrap_or_default())
+            .map_err(|e| invalid(e.to_string()))?;
+        let body: Vec<(String, String)> =
+            serde_urlencoded::from_bytes(body).map_err(|e| invalid(e.to_string()))?;
+        params.extend(query);
+        params.extend(body);
+        Ok(Self(params))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .get(name)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, AudioscrobblerError> {
+        self.get(name)
+            .ok_or_else(|| invalid(format!("Missing required parameter {}", name)))
+    }
+
+    /// An element of an array parameter, sent as `name[index]`. Single scrobbles
+    /// may also be sent without the index.
+    fn indexed(&self, name: &str, index: usize) -> Option<&str> {
+        self.get(&format!("{}[{}]", name, index))
+            .or_else(|| match index {
+                0 => self.get(name),
+                _ => None,
+            })
+    }
+
+    fn format(&self) -> Format {
+        match self.get("format") {
+            Some("json") => Format::Json,
+            _ => Format::Xml,
+        }
+    }
+
+    // Requests are signed with the shared secret of the API key the player was built with
+    fn verify(&self, settings: &Settings) -> Result<String, AudioscrobblerError> {
+        let api_key = self
+            .get("api_key")
+            .ok_or(AudioscrobblerError::InvalidApiKey)?;
+        let secret = settings
+            .audioscrobbler
+            .clients
+            .get(api_key)
+            .ok_or(AudioscrobblerError::InvalidApiKey)?;
+        let api_sig = self.required("api_sig")?;
+        let expected = signature(
+            self.0
+                .iter()
+                .filter(|(key, _)| key.as_str() != "api_sig" && key.as_str() != "callback")
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+            secret,
+        );
+        match expected.eq_ignore_ascii_case(api_sig) {
+            true => Ok(api_key.to_owned()),
+            false => Err(AudioscrobblerError::InvalidSignature),
+        }
+    }
+
+    // Players never ask for a new session, so session keys are API tokens which
+    // users can list and revoke
+    async fn session<C>(&self, db: &C) -> Result<String, AudioscrobblerError>
+    where
+        C: ConnectionTrait,
+    {
+        let key = self.required("sk")?;
+        authenticate_api_token(db, key)
+            .await
+            .map_err(|error| match error {
+                AuthError::Database(error) => error.into(),
+                _ => AudioscrobblerError::InvalidSessionKey,
+            })
+    }
+}
+
+/// A track played by the user, as sent by the player
+struct Play<'a> {
+    artist: Option<&'a str>,
+    track: Option<&'a str>,
+    album: Option<&'a str>,
+    album_artist: Option<&'a str>,
+    /// The MusicBrainz recording, which Last.fm calls a track
+    mbid: Option<Uuid>,
+    /// In seconds
+    duration: Option<i32>,
+}
+
+impl<'a> Play<'a> {
+    fn new(params: &'a Params, index: usize) -> Self {
+        Self {
+            artist: params.indexed("artist", index),
+            track: params.indexed("track", index),
+            album: params.indexed("album", index),
+            album_artist: params.indexed("albumArtist", index),
+            mbid: params
+                .indexed("mbid", index)
+                .and_then(|mbid| Uuid::parse_str(mbid).ok()),
+            duration: params
+                .indexed("duration", index)
+                .and_then(|duration| duration.parse().ok()),
+        }
+    }
+
+    fn query(&self) -> TrackQuery<'a> {
+        TrackQuery {
+            artist: self.artist.unwrap_or_default(),
+            title: self.track.unwrap_or_default(),
+            release: self.album,
+            recording_mbid: self.mbid,
+            ..Default::default()
+        }
+    }
+
+    fn echo(&self, ignored: Option<Ignored>) -> Echo {
+        Echo {
+            track: self.track.unwrap_or_default().to_owned(),
+            artist: self.artist.unwrap_or_default().to_owned(),
+            album: self.album.map(str::to_owned),
+            album_artist: self.album_artist.map(str::to_owned),
+            ignored,
+        }
+    }
+
+    fn ignored(&self) -> Option<Ignored> {
+        match (self.artist, self.track) {
+            (None, _) => Some(Ignored::Artist),
+            (_, None) => Some(Ignored::Track),
+            _ => None,
+        }
+    }
+}
+
+pub async fn api(
+    State(AppState(db)): State<AppState>,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Response {
+    let params = match Params::parse(query.as_deref(), &body) {
+        Ok(params) => params,
+        Err(error) => return error.render(Format::Xml),
+    };
+    let format = params.format();
+    match dispatch(&db, &params).await {
+        Ok(lfm) => respond(format, StatusCode::OK, lfm.xml(), lfm.json()),
+        Err(error) => error.render(format),
+    }
+}
+
+async fn dispatch<C>(db: &C, params: &Params) -> Result<Lfm, AudioscrobblerError>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let settings = get_settings().map_err(Error::from)?;
+    let method = params.required("method")?.to_lowercase();
+    if !matches!(
+        method.as_str(),
+        "auth.getmobilesession" | "track.updatenowplaying" | "track.scrobble"
+    ) {
+        return Err(AudioscrobblerError::InvalidMethod);
+    }
+    let api_key = params.verify(settings)?;
+    match method.as_str() {
+        "auth.getmobilesession" => mobile_session(db, params, &api_key).await,
+        "track.updatenowplaying" => {
+            update_now_playing(db, settings, params, &params.session(db).await?).await
+        }
+        _ => scrobble(db, settings, params, &params.session(db).await?).await,
+    }
+}
+
+async fn mobile_session<C>(
+    db: &C,
+    params: &Params,
+    api_key: &str,
+) -> Result<Lfm, AudioscrobblerError>
+where
+    C: ConnectionTrait,
+{
+    let username = params.required("username")?;
+    // the older authToken variant needs the plain password, which isn't stored
+    let password = params
+        .0
+        .get("password")
+        .ok_or(AudioscrobblerError::AuthenticationFailed)?;
+    let user = authenticate(db, username, password)
+        .await
+        .map_err(|_| AudioscrobblerError::AuthenticationFailed)?;
+    let name = format!("Audioscrobbler ({})", api_key);
+    let (api_token, key) = api_tokens::create(db, &user.username, &name).await?;
+    tracing::info!(user = %user.username, id = %api_token.id, "Started Audioscrobbler session");
+    Ok(Lfm::Session {
+        name: user.username,
+        key,
+    })
+}
+
+async fn update_now_playing<C>(
+    db: &C,
+    settings: &Settings,
+    params: &Params,
+    username: &str,
+) -> Result<Lfm, AudioscrobblerError>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let play = Play::new(params, 0);
+    if let Some(ignored) = play.ignored() {
+        return Ok(Lfm::NowPlaying(play.echo(Some(ignored))));
+    }
+    // there's nowhere to keep unknown tracks which are playing
+    if let Some(track) = match_track(db, &play.query()).await? {
+        now_playing::report(
+            db,
+            username,
+            CLIENT,
+            track.id,
+            0,
+            settings.audioscrobbler.relay,
+        )
+        .await?;
+    }
+    Ok(Lfm::NowPlaying(play.echo(None)))
+}
+
+async fn scrobble<C>(
+    db: &C,
+    settings: &Settings,
+    params: &Params,
+    username: &str,
+) -> Result<Lfm, AudioscrobblerError>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    // players only send a few minutes ahead of time when their clock is off
+    let latest = OffsetDateTime::now_utc() + Duration::days(1);
+    let tx = db.begin().await?;
+    let mut results = Vec::new();
+    let mut scrobbles = Vec::new();
+    let mut unmatched = Vec::new();
+    for index in 0..MAX_SCROBBLES_PER_REQUEST {
+        let Some(timestamp) = params.indexed("timestamp", index) else {
+            break;
+        };
+        let at = timestamp
+            .parse()
+            .ok()
+            .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+            .ok_or_else(|| invalid(format!("Invalid timestamp for scrobble {}", index)))?;
+        let play = Play::new(params, index);
+        let ignored = play.ignored().or(match at > latest {
+            true => Some(Ignored::TimestampTooNew),
+            false => None,
+        });
+        if ignored.is_none() {
+            match match_track(&tx, &play.query()).await? {
+                Some(track) => scrobbles.push((track.id, at)),
+                None => unmatched.push(entity::UnmatchedListenActive {
+                    id: ActiveValue::NotSet,
+                    user: ActiveValue::Set(username.to_owned()),
+                    listened_at: ActiveValue::Set(at),
+                    artist_name: ActiveValue::Set(play.artist.unwrap_or_default().to_owned()),
+                    track_name: ActiveValue::Set(play.track.unwrap_or_default().to_owned()),
+                    release_name: ActiveValue::Set(play.album.map(str::to_owned)),
+                    recording_mbid: ActiveValue::Set(play.mbid),
+                    track_mbid: ActiveValue::Set(None),
+                    release_mbid: ActiveValue::Set(None),
+                    duration: ActiveValue::Set(play.duration.map(|d| d.saturating_mul(1000))),
+                    submission_client: ActiveValue::Set(Some(CLIENT.to_string())),
+                }),
+            }
+        }
+        results.push(Scrobbled {
+            echo: play.echo(ignored),
+            timestamp: at.unix_timestamp(),
+        });
+    }
+    if results.is_empty() {
+        return Err(invalid("Missing required parameter timestamp"));
+    }
+    tracing::info!(
+        user = %username,
+        matched = scrobbles.len(),
+        unmatched = unmatched.len(),
+        "Received Audioscrobbler scrobbles"
+    );
+    if !scrobbles.is_empty() {
+        entity::ScrobbleEntity::insert_many(scrobbles.iter().map(|(id, at)| {
+            entity::ScrobbleActive {
+                id: ActiveValue::NotSet,
+                at: ActiveValue::Set(*at),
+                user: ActiveValue::Set(username.to_owned()),
+                track: ActiveValue::Set(*id),
+            }
+        }))
+        .exec(&tx)
+        .await?;
+    }
+    if !unmatched.is_empty() {
+        entity::UnmatchedListenEntity::insert_many(unmatched)
+            .exec(&tx)
+            .await?;
+    }
+    tx.commit().await?;
+    if !scrobbles.is_empty() {
+        stats::invalidate(username).await;
+    }
+
+    if settings.audioscrobbler.relay && !scrobbles.is_empty() {
+        if let Err(error) = schedule_scrobble_tasks(username, scrobbles.into_iter()).await {
+            tracing::warn!(%error, user = %username, "Could not schedule scrobble tasks");
+        }
+    }
+    Ok(Lfm::Scrobbles(results))
+}
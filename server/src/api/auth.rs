@@ -6,16 +6,20 @@ use axum::{
     response::Response,
 };
 use jsonwebtoken::{encode, errors::Error as JWTError, EncodingKey, Header};
+use lazy_static::lazy_static;
 use ldap3::{LdapConnAsync, LdapError, Scope, SearchEntry};
 use password_hash::{Error as PasswordHashError, PasswordHash, PasswordVerifier};
+use schemars::JsonSchema;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, ops::Add};
+use std::{collections::HashMap, ops::Add, sync::Arc, time::Instant};
 use strfmt::{strfmt, FmtError};
 use thiserror::Error;
 use time::{Duration, OffsetDateTime};
+use tokio::sync::Mutex;
 
 use argon2::Argon2;
 use pbkdf2::Pbkdf2;
@@ -24,7 +28,7 @@ use scrypt::Scrypt;
 use super::documents::Included;
 use crate::api::{
     documents::{AuthAttributes, AuthRelation, AuthResource, ResourceType, Token},
-    extract::{check_token, Claims, ClaimsSubject, Json, TypedHeader},
+    extract::{check_token, Claims, ClaimsSubject, Json, TypedHeader, LOGIN_AUDIENCE},
     jsonapi::{
         Document, DocumentData, Related, Relation, Relationship, Resource, ResourceIdentifier,
     },
@@ -32,6 +36,20 @@ use crate::api::{
 };
 use base::setting::{get_settings, AuthMethod, Settings, SettingsError};
 
+/// How long a successful password check is remembered for clients which send their
+/// credentials with every request
+const CACHED_LOGIN: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// API tokens are only marked as used again after this long, so that clients making
+/// many requests don't write to the database every time
+const API_TOKEN_USE_PRECISION: Duration = Duration::minutes(5);
+
+lazy_static! {
+    /// Recent successful logins, keyed by a digest of the credentials
+    static ref LOGINS: Arc<Mutex<HashMap<String, (String, Instant)>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
 #[derive(Error, Debug)]
 pub enum AuthError {
     #[error("Database error: {0}")]
@@ -45,6 +63,8 @@ pub enum AuthError {
     NoCandidate,
     #[error("No user with matching username found")]
     NoMatchingUser,
+    #[error("No matching API token found")]
+    InvalidApiToken,
     #[error("Invalid password hash: {0}")]
     InvalidPasswordHash(#[from] PasswordHashError),
 
@@ -214,11 +234,76 @@ where
     Err(AuthError::NoCandidate)
 }
 
+/// Authenticates like `authenticate`, but remembers successful logins for a short
+/// while so that the compatibility APIs, whose clients send the password with every
+/// request, don't hit the password hashes or the LDAP server each time. Returns the
+/// username of the authenticated user.
+pub async fn authenticate_cached<C>(
+    db: &C,
+    username: &str,
+    password: &str,
+) -> Result<String, AuthError>
+where
+    C: ConnectionTrait,
+{
+    let settings = get_settings()?;
+    let key = sha256::digest(
+        format!("{}\0{}\0{}", settings.auth.jwt_secret, username, password).as_str(),
+    );
+    {
+        let mut logins = LOGINS.lock().await;
+        logins.retain(|_, (_, at)| at.elapsed() < CACHED_LOGIN);
+        if let Some((username, _)) = logins.get(&key) {
+            return Ok(username.to_owned());
+        }
+    }
+
+    let user = authenticate(db, username, password).await?;
+    LOGINS
+        .lock()
+        .await
+        .insert(key, (user.username.to_owned(), Instant::now()));
+    Ok(user.username)
+}
+
+/// The digest under which API tokens are stored, so that a leaked database doesn't
+/// leak usable tokens
+pub fn api_token_digest(token: &str) -> String {
+    sha256::digest(token)
+}
+
+/// Returns the owner of an API token, the credentials used by the compatibility APIs.
+/// Tokens are looked up on every request so that revoking one takes effect at once.
+pub async fn authenticate_api_token<C>(db: &C, token: &str) -> Result<String, AuthError>
+where
+    C: ConnectionTrait,
+{
+    let api_token = entity::ApiTokenEntity::find()
+        .filter(ColumnTrait::eq(
+            &entity::ApiTokenColumn::Digest,
+            api_token_digest(token),
+        ))
+        .one(db)
+        .await?
+        .ok_or(AuthError::InvalidApiToken)?;
+    let now = OffsetDateTime::now_utc();
+    if api_token
+        .last_used_at
+        .map_or(true, |at| now - at > API_TOKEN_USE_PRECISION)
+    {
+        let mut active = api_token.clone().into_active_model();
+        active.last_used_at = ActiveValue::Set(Some(now));
+        active.update(db).await?;
+    }
+    Ok(api_token.user)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct RefreshClaims {
     pub username: String,
     pub exp: usize,
     pub sub: ClaimsSubject,
+    pub aud: String,
 }
 
 pub async fn auth_middleware<B>(
@@ -253,7 +338,7 @@ fn auth_resource(token: Token, refresh: Option<Token>, username: String) -> Auth
 pub async fn auth(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
 ) -> Result<Json<Document<AuthResource, Included>>, Error> {
-    let token_data = check_token::<Claims>(auth.token())?;
+    let token_data = check_token::<Claims>(auth.token(), LOGIN_AUDIENCE)?;
     Ok(Json(Document {
         data: DocumentData::Single(auth_resource(
             Token {
@@ -268,7 +353,7 @@ pub async fn auth(
     }))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct LoginData {
     username: String,
     password: String,
@@ -280,6 +365,7 @@ fn token_pair(settings: &Settings, username: &str) -> Result<(Token, Token), Aut
         username: username.to_owned(),
         exp: token_expiry.unix_timestamp() as usize,
         sub: ClaimsSubject::Token,
+        aud: LOGIN_AUDIENCE.to_string(),
     };
     let token = encode(
         &Header::default(),
@@ -292,6 +378,7 @@ fn token_pair(settings: &Settings, username: &str) -> Result<(Token, Token), Aut
         username: username.to_owned(),
         exp: refresh_expiry.unix_timestamp() as usize,
         sub: ClaimsSubject::Refresh,
+        aud: LOGIN_AUDIENCE.to_string(),
     };
     let refresh_token = encode(
         &Header::default(),
@@ -334,7 +421,7 @@ pub async fn login(
 pub async fn refresh(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
 ) -> Result<Json<Document<AuthResource, Included>>, Error> {
-    let token_data = check_token::<Claims>(auth.token())?;
+    let token_data = check_token::<Claims>(auth.token(), LOGIN_AUDIENCE)?;
     if token_data.claims.sub != ClaimsSubject::Refresh {
         return Err(Error::BadRequest(Some("Invalid refresh token".to_owned())));
     }
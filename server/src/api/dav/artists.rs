@@ -0,0 +1,244 @@
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, JoinType, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, RelationTrait,
+};
+use std::collections::HashMap;
+use std::path::{Path as StdPath, PathBuf};
+use uuid::Uuid;
+
+use super::{content_type, DavError, Found, Node, Resource, ARTISTS};
+use crate::api::tempo::releases::{release_tracks, sanitize};
+
+// Names are sanitized for file systems, and made unique by appending the id to
+// the ones which would otherwise be the same
+fn unique_names<T, F, G>(items: Vec<T>, name: F, id: G) -> Vec<(String, T)>
+where
+    F: Fn(&T) -> String,
+    G: Fn(&T) -> Uuid,
+{
+    let names: Vec<String> = items.iter().map(|item| sanitize(&name(item))).collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names.iter() {
+        *counts.entry(name.as_str()).or_default() += 1;
+    }
+    let unique: Vec<String> = names
+        .iter()
+        .zip(items.iter())
+        .map(|(name, item)| match counts.get(name.as_str()) {
+            Some(count) if *count > 1 => format!("{} [{}]", name, id(item)),
+            _ => name.to_owned(),
+        })
+        .collect();
+    unique.into_iter().zip(items).collect()
+}
+
+// The names in the database a directory name may come from, without the id making it
+// unique or the year of a release. Sanitized characters became an underscore, the
+// single character wildcard of LIKE, and other wildcards can only widen the
+// candidates, which are then named as in the listing.
+fn named<C: ColumnTrait>(column: C, name: &str) -> Condition {
+    let mut patterns = vec![name];
+    let mut base = name;
+    if let Some((rest, _)) = base.strip_suffix(']').and_then(|n| n.rsplit_once(" [")) {
+        base = rest;
+        patterns.push(base);
+    }
+    if let Some((rest, _)) = base.strip_suffix(')').and_then(|n| n.rsplit_once(" (")) {
+        patterns.push(rest);
+    }
+    patterns
+        .into_iter()
+        .fold(Condition::any(), |condition, pattern| {
+            condition.add(column.like(pattern))
+        })
+}
+
+fn find<T>(entries: Vec<(String, T)>, name: &str) -> Option<T> {
+    entries
+        .into_iter()
+        .find(|(entry, _)| entry == name)
+        .map(|(_, item)| item)
+}
+
+fn collection<T>(name: &str, entries: &[(String, T)]) -> Found {
+    Found {
+        node: Node::collection(name),
+        resource: Resource::Collection(
+            entries
+                .iter()
+                .map(|(name, _)| Node::collection(name))
+                .collect(),
+        ),
+    }
+}
+
+// Artists without releases, only credited on tracks, would be empty. When looking
+// for a name, only the artists which could have it are loaded.
+async fn artists<C>(db: &C, name: Option<&str>) -> Result<Vec<(String, entity::Artist)>, DavError>
+where
+    C: ConnectionTrait,
+{
+    let mut query = entity::ArtistEntity::find()
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistRelation::ArtistCredit.def(),
+        )
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditRelation::ArtistCreditRelease.def(),
+        )
+        .distinct();
+    if let Some(name) = name {
+        query = query.filter(named(entity::ArtistColumn::Name, name));
+    }
+    let artists = query
+        .order_by_asc(entity::ArtistColumn::SortName)
+        .all(db)
+        .await?;
+    Ok(unique_names(artists, |a| a.name.to_owned(), |a| a.id))
+}
+
+async fn releases<C>(
+    db: &C,
+    artist: &entity::Artist,
+    name: Option<&str>,
+) -> Result<Vec<(String, entity::Release)>, DavError>
+where
+    C: ConnectionTrait,
+{
+    let mut query = entity::ReleaseEntity::find()
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditReleaseRelation::Release.def().rev(),
+        )
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditReleaseRelation::ArtistCredit.def(),
+        )
+        .filter(entity::ArtistCreditColumn::ArtistId.eq(artist.id))
+        .distinct();
+    if let Some(name) = name {
+        query = query.filter(named(entity::ReleaseColumn::Title, name));
+    }
+    let releases = query
+        .order_by_asc(entity::ReleaseColumn::Year)
+        .order_by_asc(entity::ReleaseColumn::Title)
+        .all(db)
+        .await?;
+    Ok(unique_names(
+        releases,
+        |r| match r.year {
+            Some(year) => format!("{} ({})", r.title, year),
+            None => r.title.to_owned(),
+        },
+        |r| r.id,
+    ))
+}
+
+// Tracks are named after their position, prefixed by the medium when there are many
+async fn tracks<C>(
+    db: &C,
+    release: &entity::Release,
+) -> Result<Vec<(String, entity::Track)>, DavError>
+where
+    C: ConnectionTrait,
+{
+    let mediums: HashMap<Uuid, i32> = release
+        .find_related(entity::MediumEntity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|medium| (medium.id, medium.position))
+        .collect();
+    let tracks: Vec<entity::Track> = release_tracks(db, release)
+        .await?
+        .into_iter()
+        .filter(|track| track.path.is_some())
+        .collect();
+    Ok(unique_names(
+        tracks,
+        |t| {
+            let extension = t
+                .path
+                .as_deref()
+                .and_then(|path| StdPath::new(path).extension())
+                .and_then(|extension| extension.to_str())
+                .map(str::to_owned)
+                .or_else(|| t.format.map(|format| format.ext().to_owned()))
+                .unwrap_or_default();
+            match mediums.len() {
+                1 => format!("{:02} {}.{}", t.number, t.title, extension),
+                _ => format!(
+                    "{}-{:02} {}.{}",
+                    mediums.get(&t.medium_id).copied().unwrap_or_default(),
+                    t.number,
+                    t.title,
+                    extension
+                ),
+            }
+        },
+        |t| t.id,
+    ))
+}
+
+async fn track_file(name: &str, track: entity::Track) -> Result<Option<Found>, DavError> {
+    let Some(path) = track.path.map(PathBuf::from) else {
+        return Ok(None);
+    };
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    Ok(Some(Found {
+        node: Node::from_metadata(name, &metadata),
+        resource: Resource::File(path),
+    }))
+}
+
+/// Resolves a path in the virtual layout generated from the database, with a
+/// directory for each artist and one for each of their releases
+pub async fn resolve<C>(db: &C, segments: &[String]) -> Result<Option<Found>, DavError>
+where
+    C: ConnectionTrait,
+{
+    let Some((artist_name, rest)) = segments.split_first() else {
+        return Ok(Some(collection(ARTISTS, &artists(db, None).await?)));
+    };
+    let Some(artist) = find(artists(db, Some(artist_name)).await?, artist_name) else {
+        return Ok(None);
+    };
+    let Some((release_name, rest)) = rest.split_first() else {
+        return Ok(Some(collection(
+            artist_name,
+            &releases(db, &artist, None).await?,
+        )));
+    };
+    let releases = releases(db, &artist, Some(release_name)).await?;
+    let Some(release) = find(releases, release_name) else {
+        return Ok(None);
+    };
+    let tracks = tracks(db, &release).await?;
+    match rest {
+        [] => Ok(Some(Found {
+            node: Node::collection(release_name),
+            resource: Resource::Collection(
+                tracks
+                    .iter()
+                    .map(|(name, track)| Node {
+                        name: name.to_owned(),
+                        collection: false,
+                        length: track.size.map(|size| size.max(0) as u64),
+                        modified: None,
+                        mime: content_type(name),
+                    })
+                    .collect(),
+            ),
+        })),
+        [track_name] => match find(tracks, track_name) {
+            Some(track) => track_file(track_name, track).await,
+            None => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
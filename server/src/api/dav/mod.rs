@@ -0,0 +1,346 @@
+mod artists;
+
+use axum::{
+    body::Body,
+    extract::State,
+    headers::{authorization::Basic, Authorization, Header, HeaderMapExt, LastModified},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use sea_orm::{ConnectionTrait, DbErr};
+use std::fs::Metadata;
+use std::path::{Path as StdPath, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+use tower::ServiceExt;
+
+use crate::api::{auth::authenticate_cached, AppState, Error};
+use base::setting::get_settings;
+use base::util::escape_xml;
+
+/// Where the router is nested, which hrefs in responses are relative to
+static PREFIX: &str = "/dav";
+static ALLOW: &str = "OPTIONS, GET, HEAD, PROPFIND";
+static LIBRARY: &str = "library";
+static ARTISTS: &str = "artists";
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", any(dav)).route("/*path", any(dav))
+}
+
+#[derive(Error, Debug)]
+pub enum DavError {
+    #[error("Authentication required")]
+    Unauthorized,
+    #[error("Not found")]
+    NotFound,
+    #[error("The library is read-only")]
+    MethodNotAllowed,
+    #[error("Error during IO operation: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Api(#[from] Error),
+}
+
+impl From<DbErr> for DavError {
+    fn from(value: DbErr) -> Self {
+        Self::Api(value.into())
+    }
+}
+
+impl IntoResponse for DavError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            DavError::Unauthorized => StatusCode::UNAUTHORIZED,
+            DavError::NotFound => StatusCode::NOT_FOUND,
+            DavError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            DavError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            DavError::Api(error) => error.status(),
+        };
+        if status.is_server_error() {
+            tracing::warn!(error = %self, "WebDAV request failed");
+        }
+        let mut response = (status, self.to_string()).into_response();
+        let headers = response.headers_mut();
+        match status {
+            StatusCode::UNAUTHORIZED => {
+                headers.insert(
+                    header::WWW_AUTHENTICATE,
+                    format!("Basic realm=\"{}\", charset=\"UTF-8\"", base::CLI_NAME)
+                        .parse()
+                        .unwrap(),
+                );
+            }
+            StatusCode::METHOD_NOT_ALLOWED => {
+                headers.insert(header::ALLOW, ALLOW.parse().unwrap());
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+/// A file or collection, as described in PROPFIND responses
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub collection: bool,
+    pub length: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub mime: Option<&'static str>,
+}
+
+impl Node {
+    fn collection(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            collection: true,
+            length: None,
+            modified: None,
+            mime: None,
+        }
+    }
+
+    fn from_metadata(name: &str, metadata: &Metadata) -> Self {
+        Self {
+            name: name.to_owned(),
+            collection: metadata.is_dir(),
+            length: (!metadata.is_dir()).then(|| metadata.len()),
+            modified: metadata.modified().ok(),
+            mime: (!metadata.is_dir()).then(|| content_type(name)).flatten(),
+        }
+    }
+}
+
+pub enum Resource {
+    Collection(Vec<Node>),
+    File(PathBuf),
+}
+
+/// What a path resolved to, with the node describing it
+pub struct Found {
+    pub node: Node,
+    pub resource: Resource,
+}
+
+// Only the types of the files usually found in a library are known
+fn content_type(name: &str) -> Option<&'static str> {
+    let extension = StdPath::new(name).extension()?.to_str()?.to_lowercase();
+    Some(match extension.as_str() {
+        "flac" => "audio/flac",
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "ape" => "audio/x-ape",
+        "wav" => "audio/wav",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "m3u" | "m3u8" => "audio/x-mpegurl",
+        "cue" | "log" | "txt" => "text/plain",
+        _ => return None,
+    })
+}
+
+async fn check_credentials<C>(db: &C, headers: &HeaderMap) -> Result<String, DavError>
+where
+    C: ConnectionTrait,
+{
+    let credentials = headers
+        .typed_get::<Authorization<Basic>>()
+        .ok_or(DavError::Unauthorized)?;
+    // clients send the credentials with every request, and browsing a folder
+    // takes many of them
+    authenticate_cached(db, credentials.username(), credentials.password())
+        .await
+        .map_err(|_| DavError::Unauthorized)
+}
+
+// The decoded segments of the request path, which are rejected when they could
+// escape the directory they are resolved in
+fn segments(path: &str) -> Result<Vec<String>, DavError> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let segment = urlencoding::decode(segment).map_err(|_| DavError::NotFound)?;
+            match segment.as_ref() {
+                "." | ".." => Err(DavError::NotFound),
+                s if s.contains(['/', '\\']) => Err(DavError::NotFound),
+                _ => Ok(segment.into_owned()),
+            }
+        })
+        .collect()
+}
+
+async fn library(root: &StdPath, segments: &[String]) -> Result<Option<Found>, DavError> {
+    let root = tokio::fs::canonicalize(root).await?;
+    let path = match tokio::fs::canonicalize(root.join(segments.iter().collect::<PathBuf>())).await
+    {
+        Ok(path) => path,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    // symbolic links may point outside of the library
+    if !path.starts_with(&root) {
+        return Ok(None);
+    }
+    let name = segments.last().map_or(LIBRARY, String::as_str);
+    let metadata = tokio::fs::metadata(&path).await?;
+    if !metadata.is_dir() {
+        return Ok(Some(Found {
+            node: Node::from_metadata(name, &metadata),
+            resource: Resource::File(path),
+        }));
+    }
+
+    let mut children = Vec::new();
+    let mut entries = tokio::fs::read_dir(&path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if let Ok(metadata) = tokio::fs::metadata(entry.path()).await {
+            children.push(Node::from_metadata(&name, &metadata));
+        }
+    }
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Some(Found {
+        node: Node::from_metadata(name, &metadata),
+        resource: Resource::Collection(children),
+    }))
+}
+
+async fn resolve<C>(db: &C, segments: &[String]) -> Result<Option<Found>, DavError>
+where
+    C: ConnectionTrait,
+{
+    match segments.split_first() {
+        None => Ok(Some(Found {
+            node: Node::collection(""),
+            resource: Resource::Collection(vec![
+                Node::collection(LIBRARY),
+                Node::collection(ARTISTS),
+            ]),
+        })),
+        Some((first, rest)) if first == LIBRARY => {
+            let settings = get_settings().map_err(Error::from)?;
+            library(&settings.library.path, rest).await
+        }
+        Some((first, rest)) if first == ARTISTS => artists::resolve(db, rest).await,
+        _ => Ok(None),
+    }
+}
+
+fn http_date(time: SystemTime) -> String {
+    let mut values = Vec::new();
+    LastModified::from(time).encode(&mut values);
+    values
+        .first()
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn href(segments: &[String], node: &Node) -> String {
+    let mut href = PREFIX.to_string();
+    for segment in segments.iter() {
+        href += "/";
+        href += &urlencoding::encode(segment);
+    }
+    if node.collection {
+        href += "/";
+    }
+    href
+}
+
+fn response_xml(segments: &[String], node: &Node) -> String {
+    let mut props = format!("<D:displayname>{}</D:displayname>", escape_xml(&node.name));
+    props += match node.collection {
+        true => "<D:resourcetype><D:collection/></D:resourcetype>",
+        false => "<D:resourcetype/>",
+    };
+    if let Some(length) = node.length {
+        props += &format!("<D:getcontentlength>{}</D:getcontentlength>", length);
+    }
+    if let Some(modified) = node.modified {
+        props += &format!(
+            "<D:getlastmodified>{}</D:getlastmodified>",
+            http_date(modified)
+        );
+    }
+    if let Some(mime) = node.mime {
+        props += &format!("<D:getcontenttype>{}</D:getcontenttype>", mime);
+    }
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        escape_xml(&href(segments, node)),
+        props
+    )
+}
+
+// Properties aren't negotiated, all of the live ones are always returned.
+// Infinite depth is answered as depth 1, as listing the whole library is too much.
+fn propfind(
+    headers: &HeaderMap,
+    segments: &[String],
+    node: &Node,
+    resource: &Resource,
+) -> Response {
+    let depth = headers
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("infinity");
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>");
+    xml += "<D:multistatus xmlns:D=\"DAV:\">";
+    xml += &response_xml(segments, node);
+    if let (Resource::Collection(children), false) = (resource, depth == "0") {
+        let mut child_segments = segments.to_vec();
+        for child in children.iter() {
+            child_segments.push(child.name.to_owned());
+            xml += &response_xml(&child_segments, child);
+            child_segments.pop();
+        }
+    }
+    xml += "</D:multistatus>";
+    (
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    )
+        .into_response()
+}
+
+pub async fn dav(
+    State(AppState(db)): State<AppState>,
+    request: Request<Body>,
+) -> Result<Response, DavError> {
+    if request.method() == Method::OPTIONS {
+        return Ok((
+            [
+                (header::ALLOW, ALLOW),
+                (header::HeaderName::from_static("dav"), "1"),
+            ],
+            "",
+        )
+            .into_response());
+    }
+    let username = check_credentials(&db, request.headers()).await?;
+    let segments = segments(request.uri().path())?;
+    let Found { node, resource } = resolve(&db, &segments).await?.ok_or(DavError::NotFound)?;
+    let method = request.method().clone();
+    tracing::trace!(user = %username, %method, ?segments, "WebDAV request");
+    match (method.as_str(), resource) {
+        ("PROPFIND", resource) => Ok(propfind(request.headers(), &segments, &node, &resource)),
+        // ranges and conditional requests are handled when serving the file
+        ("GET" | "HEAD", Resource::File(path)) => {
+            Ok(tower_http::services::fs::ServeFile::new(path)
+                .oneshot(request)
+                .await
+                .into_response())
+        }
+        _ => Err(DavError::MethodNotAllowed),
+    }
+}
@@ -0,0 +1,89 @@
+use axum::{http::HeaderMap, response::Response};
+use itertools::Itertools;
+use sea_orm::Iterable;
+
+use super::{
+    soap::{action, response, SoapError},
+    CONNECTION_MANAGER,
+};
+use base::setting::TranscodingFormat;
+
+pub static SCPD: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+    "<scpd xmlns=\"urn:schemas-upnp-org:service-1-0\">",
+    "<specVersion><major>1</major><minor>0</minor></specVersion>",
+    "<actionList>",
+    "<action><name>GetProtocolInfo</name><argumentList>",
+    "<argument><name>Source</name><direction>out</direction><relatedStateVariable>SourceProtocolInfo</relatedStateVariable></argument>",
+    "<argument><name>Sink</name><direction>out</direction><relatedStateVariable>SinkProtocolInfo</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "<action><name>GetCurrentConnectionIDs</name><argumentList>",
+    "<argument><name>ConnectionIDs</name><direction>out</direction><relatedStateVariable>CurrentConnectionIDs</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "<action><name>GetCurrentConnectionInfo</name><argumentList>",
+    "<argument><name>ConnectionID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_ConnectionID</relatedStateVariable></argument>",
+    "<argument><name>RcsID</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_RcsID</relatedStateVariable></argument>",
+    "<argument><name>AVTransportID</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_AVTransportID</relatedStateVariable></argument>",
+    "<argument><name>ProtocolInfo</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_ProtocolInfo</relatedStateVariable></argument>",
+    "<argument><name>PeerConnectionManager</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_ConnectionManager</relatedStateVariable></argument>",
+    "<argument><name>PeerConnectionID</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_ConnectionID</relatedStateVariable></argument>",
+    "<argument><name>Direction</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Direction</relatedStateVariable></argument>",
+    "<argument><name>Status</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_ConnectionStatus</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "</actionList>",
+    "<serviceStateTable>",
+    "<stateVariable sendEvents=\"yes\"><name>SourceProtocolInfo</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"yes\"><name>SinkProtocolInfo</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"yes\"><name>CurrentConnectionIDs</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_ConnectionStatus</name><dataType>string</dataType>",
+    "<allowedValueList><allowedValue>OK</allowedValue><allowedValue>ContentFormatMismatch</allowedValue>",
+    "<allowedValue>InsufficientBandwidth</allowedValue><allowedValue>UnreliableChannel</allowedValue>",
+    "<allowedValue>Unknown</allowedValue></allowedValueList></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_ConnectionManager</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_Direction</name><dataType>string</dataType>",
+    "<allowedValueList><allowedValue>Input</allowedValue><allowedValue>Output</allowedValue></allowedValueList></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_ProtocolInfo</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_ConnectionID</name><dataType>i4</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_AVTransportID</name><dataType>i4</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_RcsID</name><dataType>i4</dataType></stateVariable>",
+    "</serviceStateTable>",
+    "</scpd>"
+);
+
+// Every format a track can be served in, either as stored or transcoded
+fn source_protocol_info() -> String {
+    entity::TrackFormat::iter()
+        .map(|format| format.mime().to_string())
+        .chain(
+            [
+                TranscodingFormat::Opus,
+                TranscodingFormat::Mp3,
+                TranscodingFormat::Aac,
+            ]
+            .iter()
+            .map(|format| format.mime().to_string()),
+        )
+        .unique()
+        .map(|mime| format!("http-get:*:{}:*", mime))
+        .join(",")
+}
+
+pub async fn control(headers: HeaderMap) -> Result<Response, SoapError> {
+    let action = action(&headers)?;
+    let arguments = match action.as_str() {
+        "GetProtocolInfo" => vec![("Source", source_protocol_info()), ("Sink", String::new())],
+        "GetCurrentConnectionIDs" => vec![("ConnectionIDs", "0".to_string())],
+        // connections are not managed, there's only the default one
+        "GetCurrentConnectionInfo" => vec![
+            ("RcsID", "-1".to_string()),
+            ("AVTransportID", "-1".to_string()),
+            ("ProtocolInfo", String::new()),
+            ("PeerConnectionManager", String::new()),
+            ("PeerConnectionID", "-1".to_string()),
+            ("Direction", "Output".to_string()),
+            ("Status", "OK".to_string()),
+        ],
+        _ => return Err(SoapError::InvalidAction),
+    };
+    Ok(response(CONNECTION_MANAGER, &action, &arguments))
+}
@@ -0,0 +1,743 @@
+use axum::{extract::State, http::HeaderMap, response::Response};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Select,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::{
+    soap::{action, argument, required, response, SoapError},
+    url, CLIENT, CONTENT_DIRECTORY,
+};
+use crate::api::AppState;
+use crate::transcode::resolve;
+use base::setting::{get_settings, Settings};
+use base::util::escape_xml;
+
+pub static SCPD: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+    "<scpd xmlns=\"urn:schemas-upnp-org:service-1-0\">",
+    "<specVersion><major>1</major><minor>0</minor></specVersion>",
+    "<actionList>",
+    "<action><name>Browse</name><argumentList>",
+    "<argument><name>ObjectID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_ObjectID</relatedStateVariable></argument>",
+    "<argument><name>BrowseFlag</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_BrowseFlag</relatedStateVariable></argument>",
+    "<argument><name>Filter</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Filter</relatedStateVariable></argument>",
+    "<argument><name>StartingIndex</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Index</relatedStateVariable></argument>",
+    "<argument><name>RequestedCount</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>",
+    "<argument><name>SortCriteria</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_SortCriteria</relatedStateVariable></argument>",
+    "<argument><name>Result</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Result</relatedStateVariable></argument>",
+    "<argument><name>NumberReturned</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>",
+    "<argument><name>TotalMatches</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_Count</relatedStateVariable></argument>",
+    "<argument><name>UpdateID</name><direction>out</direction><relatedStateVariable>A_ARG_TYPE_UpdateID</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "<action><name>GetSearchCapabilities</name><argumentList>",
+    "<argument><name>SearchCaps</name><direction>out</direction><relatedStateVariable>SearchCapabilities</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "<action><name>GetSortCapabilities</name><argumentList>",
+    "<argument><name>SortCaps</name><direction>out</direction><relatedStateVariable>SortCapabilities</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "<action><name>GetSystemUpdateID</name><argumentList>",
+    "<argument><name>Id</name><direction>out</direction><relatedStateVariable>SystemUpdateID</relatedStateVariable></argument>",
+    "</argumentList></action>",
+    "</actionList>",
+    "<serviceStateTable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_ObjectID</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_Result</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_BrowseFlag</name><dataType>string</dataType>",
+    "<allowedValueList><allowedValue>BrowseMetadata</allowedValue><allowedValue>BrowseDirectChildren</allowedValue></allowedValueList></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_Filter</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_SortCriteria</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_Index</name><dataType>ui4</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_Count</name><dataType>ui4</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>A_ARG_TYPE_UpdateID</name><dataType>ui4</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>SearchCapabilities</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"no\"><name>SortCapabilities</name><dataType>string</dataType></stateVariable>",
+    "<stateVariable sendEvents=\"yes\"><name>SystemUpdateID</name><dataType>ui4</dataType></stateVariable>",
+    "</serviceStateTable>",
+    "</scpd>"
+);
+
+// The library isn't versioned, so devices are never told to refresh their caches
+static UPDATE_ID: &str = "0";
+
+/// The objects of the content directory. Artists, releases and genres are containers,
+/// tracks are the only items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Object {
+    Root,
+    Artists,
+    Releases,
+    Genres,
+    Artist(Uuid),
+    Release(Uuid),
+    Genre(String),
+    Track(Uuid),
+}
+
+impl FromStr for Object {
+    type Err = SoapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let uuid = |id: &str| Uuid::parse_str(id).map_err(|_| SoapError::NoSuchObject);
+        match s.split_once('/') {
+            None if s == "0" => Ok(Object::Root),
+            None if s == "artists" => Ok(Object::Artists),
+            None if s == "releases" => Ok(Object::Releases),
+            None if s == "genres" => Ok(Object::Genres),
+            Some(("artist", id)) => Ok(Object::Artist(uuid(id)?)),
+            Some(("release", id)) => Ok(Object::Release(uuid(id)?)),
+            Some(("genre", id)) => Ok(Object::Genre(id.to_owned())),
+            Some(("track", id)) => Ok(Object::Track(uuid(id)?)),
+            _ => Err(SoapError::NoSuchObject),
+        }
+    }
+}
+
+impl Object {
+    fn id(&self) -> String {
+        match self {
+            Object::Root => "0".to_string(),
+            Object::Artists => "artists".to_string(),
+            Object::Releases => "releases".to_string(),
+            Object::Genres => "genres".to_string(),
+            Object::Artist(id) => format!("artist/{}", id),
+            Object::Release(id) => format!("release/{}", id),
+            Object::Genre(id) => format!("genre/{}", id),
+            Object::Track(id) => format!("track/{}", id),
+        }
+    }
+}
+
+/// An entry of a DIDL-Lite document, as returned by Browse
+enum Entry {
+    Container {
+        object: Object,
+        parent: Option<Object>,
+        title: String,
+        class: &'static str,
+        art: Option<String>,
+    },
+    Item {
+        object: Object,
+        parent: Option<Object>,
+        title: String,
+        artists: Vec<String>,
+        album: Option<String>,
+        number: i32,
+        art: Option<String>,
+        resource: Resource,
+    },
+}
+
+struct Resource {
+    url: String,
+    protocol_info: String,
+    /// In milliseconds
+    duration: i32,
+    size: Option<i64>,
+}
+
+// Durations are formatted as H+:MM:SS.F+
+fn duration(ms: i32) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+// Only the root has no parent, which is written as -1
+fn parent_id(parent: &Option<Object>) -> String {
+    parent.as_ref().map_or("-1".to_string(), Object::id)
+}
+
+fn element(out: &mut String, name: &str, value: &str) {
+    let _ = write!(out, "<{0}>{1}</{0}>", name, escape_xml(value));
+}
+
+impl Entry {
+    fn write(&self, out: &mut String) {
+        match self {
+            Entry::Container {
+                object,
+                parent,
+                title,
+                class,
+                art,
+            } => {
+                let _ = write!(
+                    out,
+                    "<container id=\"{}\" parentID=\"{}\" restricted=\"1\" searchable=\"0\">",
+                    escape_xml(&object.id()),
+                    escape_xml(&parent_id(parent))
+                );
+                element(out, "dc:title", title);
+                element(out, "upnp:class", class);
+                if let Some(art) = art {
+                    element(out, "upnp:albumArtURI", art);
+                }
+                out.push_str("</container>");
+            }
+            Entry::Item {
+                object,
+                parent,
+                title,
+                artists,
+                album,
+                number,
+                art,
+                resource,
+            } => {
+                let _ = write!(
+                    out,
+                    "<item id=\"{}\" parentID=\"{}\" restricted=\"1\">",
+                    escape_xml(&object.id()),
+                    escape_xml(&parent_id(parent))
+                );
+                element(out, "dc:title", title);
+                element(out, "upnp:class", "object.item.audioItem.musicTrack");
+                if !artists.is_empty() {
+                    element(out, "dc:creator", &artists.join(", "));
+                }
+                for artist in artists.iter() {
+                    element(out, "upnp:artist", artist);
+                }
+                if let Some(album) = album {
+                    element(out, "upnp:album", album);
+                }
+                element(out, "upnp:originalTrackNumber", &number.to_string());
+                if let Some(art) = art {
+                    element(out, "upnp:albumArtURI", art);
+                }
+                let _ = write!(
+                    out,
+                    "<res protocolInfo=\"{}\" duration=\"{}\"",
+                    escape_xml(&resource.protocol_info),
+                    duration(resource.duration)
+                );
+                if let Some(size) = resource.size {
+                    let _ = write!(out, " size=\"{}\"", size);
+                }
+                let _ = write!(out, ">{}</res>", escape_xml(&resource.url));
+                out.push_str("</item>");
+            }
+        }
+    }
+}
+
+fn didl(entries: &[Entry]) -> String {
+    let mut out = String::from(concat!(
+        "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" ",
+        "xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+        "xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">"
+    ));
+    for entry in entries.iter() {
+        entry.write(&mut out);
+    }
+    out.push_str("</DIDL-Lite>");
+    out
+}
+
+/// The slice of children requested by a Browse action, a count of 0 meaning all of them
+#[derive(Debug, Clone, Copy)]
+struct Page {
+    start: u64,
+    count: u64,
+}
+
+impl Page {
+    fn apply<T>(&self, items: Vec<T>) -> Vec<T> {
+        let items = items.into_iter().skip(self.start as usize);
+        match self.count {
+            0 => items.collect(),
+            count => items.take(count as usize).collect(),
+        }
+    }
+
+    fn select<E: EntityTrait>(&self, query: Select<E>) -> Select<E> {
+        let query = query.offset(self.start);
+        match self.count {
+            0 => query,
+            count => query.limit(count),
+        }
+    }
+}
+
+fn image_url(settings: &Settings, image: &str) -> String {
+    url(settings, &format!("images/{}", image))
+}
+
+fn container(
+    object: Object,
+    parent: Object,
+    title: &str,
+    class: &'static str,
+    art: Option<String>,
+) -> Entry {
+    Entry::Container {
+        object,
+        parent: Some(parent),
+        title: title.to_owned(),
+        class,
+        art,
+    }
+}
+
+fn artist_entry(settings: &Settings, artist: &entity::Artist, art: Option<&String>) -> Entry {
+    container(
+        Object::Artist(artist.id),
+        Object::Artists,
+        &artist.name,
+        "object.container.person.musicArtist",
+        art.map(|image| image_url(settings, image)),
+    )
+}
+
+fn release_entry(
+    settings: &Settings,
+    release: &entity::Release,
+    parent: Object,
+    art: Option<&String>,
+) -> Entry {
+    container(
+        Object::Release(release.id),
+        parent,
+        &release.title,
+        "object.container.album.musicAlbum",
+        art.map(|image| image_url(settings, image)),
+    )
+}
+
+fn genre_entry(genre: &entity::Genre) -> Entry {
+    container(
+        Object::Genre(genre.id.to_owned()),
+        Object::Genres,
+        &genre.name,
+        "object.container.genre.musicGenre",
+        None,
+    )
+}
+
+async fn artist_images<C>(db: &C, ids: &[Uuid]) -> Result<HashMap<Uuid, String>, SoapError>
+where
+    C: ConnectionTrait,
+{
+    Ok(entity::ImageArtistEntity::find()
+        .filter(entity::ImageArtistColumn::ArtistId.is_in(ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|image| (image.artist_id, image.image_id))
+        .collect())
+}
+
+async fn release_images<C>(db: &C, ids: &[Uuid]) -> Result<HashMap<Uuid, String>, SoapError>
+where
+    C: ConnectionTrait,
+{
+    Ok(entity::ImageReleaseEntity::find()
+        .filter(entity::ImageReleaseColumn::ReleaseId.is_in(ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|image| (image.release_id, image.image_id))
+        .collect())
+}
+
+// Builds the items for the given tracks, with their release and artists resolved
+// through the track links. Items are children of their release unless a parent is given.
+async fn track_entries<C>(
+    db: &C,
+    settings: &Settings,
+    tracks: Vec<entity::Track>,
+    parent: Option<Object>,
+) -> Result<Vec<Entry>, SoapError>
+where
+    C: ConnectionTrait,
+{
+    let ids: Vec<Uuid> = tracks.iter().map(|t| t.id).collect();
+    let releases: HashMap<Uuid, entity::Release> = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(ids.iter().copied()))
+        .find_also_linked(entity::TrackToRelease)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(track, release)| release.map(|r| (track.id, r)))
+        .collect();
+    let mut artists: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (track, artist) in entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(ids.iter().copied()))
+        .find_also_linked(entity::TrackToArtist)
+        .all(db)
+        .await?
+    {
+        if let Some(artist) = artist {
+            artists.entry(track.id).or_default().push(artist.name);
+        }
+    }
+    let release_ids: Vec<Uuid> = releases.values().map(|r| r.id).collect();
+    let images = release_images(db, &release_ids).await?;
+    let transcoding = resolve(
+        &settings.transcoding,
+        "",
+        Some(CLIENT),
+        None,
+        None,
+        None,
+        None,
+    )
+    .map_err(crate::api::Error::from)?;
+
+    Ok(tracks
+        .into_iter()
+        .map(|track| {
+            let release = releases.get(&track.id);
+            let parent = match (&parent, release) {
+                (Some(parent), _) => parent.clone(),
+                (None, Some(release)) => Object::Release(release.id),
+                (None, None) => Object::Releases,
+            };
+            // devices pick what to play from the mime type, which changes when transcoding
+            let (mime, flags, size) = match (transcoding, track.format) {
+                (Some(options), _) => (
+                    Some(options.format.mime().to_string()),
+                    "DLNA.ORG_OP=00;DLNA.ORG_CI=1",
+                    None,
+                ),
+                (None, format) => (
+                    format.map(|f| f.mime().to_string()),
+                    "DLNA.ORG_OP=01;DLNA.ORG_CI=0",
+                    track.size,
+                ),
+            };
+            Entry::Item {
+                object: Object::Track(track.id),
+                parent: Some(parent),
+                title: track.title,
+                artists: artists.remove(&track.id).unwrap_or_default(),
+                album: release.map(|r| r.title.to_owned()),
+                number: track.number,
+                art: release
+                    .and_then(|r| images.get(&r.id))
+                    .map(|image| image_url(settings, image)),
+                resource: Resource {
+                    url: url(settings, &format!("tracks/{}", track.id)),
+                    protocol_info: format!(
+                        "http-get:*:{}:{}",
+                        mime.as_deref().unwrap_or("audio/*"),
+                        flags
+                    ),
+                    duration: track.length,
+                    size,
+                },
+            }
+        })
+        .collect())
+}
+
+// The releases an artist is credited on, either directly or through any of the tracks
+async fn artist_releases<C>(db: &C, id: Uuid) -> Result<Vec<entity::Release>, SoapError>
+where
+    C: ConnectionTrait,
+{
+    let credits: Vec<String> = entity::ArtistCreditEntity::find()
+        .filter(entity::ArtistCreditColumn::ArtistId.eq(id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|credit| credit.id)
+        .collect();
+    let mut release_ids: HashSet<Uuid> = entity::ArtistCreditReleaseEntity::find()
+        .filter(entity::ArtistCreditReleaseColumn::ArtistCreditId.is_in(credits.iter().cloned()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| r.release_id)
+        .collect();
+    let track_ids: Vec<Uuid> = entity::ArtistCreditTrackEntity::find()
+        .filter(entity::ArtistCreditTrackColumn::ArtistCreditId.is_in(credits))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|t| t.track_id)
+        .collect();
+    release_ids.extend(
+        entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(track_ids))
+            .find_also_linked(entity::TrackToRelease)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(_, release)| release.map(|r| r.id)),
+    );
+    Ok(entity::ReleaseEntity::find()
+        .filter(entity::ReleaseColumn::Id.is_in(release_ids))
+        .order_by_asc(entity::ReleaseColumn::Year)
+        .order_by_asc(entity::ReleaseColumn::Title)
+        .all(db)
+        .await?)
+}
+
+async fn release_tracks<C>(db: &C, id: Uuid) -> Result<Vec<entity::Track>, SoapError>
+where
+    C: ConnectionTrait,
+{
+    let mediums: HashMap<Uuid, i32> = entity::MediumEntity::find()
+        .filter(ColumnTrait::eq(&entity::MediumColumn::ReleaseId, id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|medium| (medium.id, medium.position))
+        .collect();
+    let mut tracks = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::MediumId.is_in(mediums.keys().copied()))
+        .all(db)
+        .await?;
+    tracks.sort_by_key(|track| (mediums.get(&track.medium_id).copied(), track.number));
+    Ok(tracks)
+}
+
+/// Returns the requested page of the children of an object, with the total number of children
+async fn children<C>(
+    db: &C,
+    settings: &Settings,
+    object: &Object,
+    page: Page,
+) -> Result<(Vec<Entry>, u64), SoapError>
+where
+    C: ConnectionTrait,
+{
+    match object {
+        Object::Root => {
+            let entries = vec![
+                container(
+                    Object::Artists,
+                    Object::Root,
+                    "Artists",
+                    "object.container.storageFolder",
+                    None,
+                ),
+                container(
+                    Object::Releases,
+                    Object::Root,
+                    "Albums",
+                    "object.container.storageFolder",
+                    None,
+                ),
+                container(
+                    Object::Genres,
+                    Object::Root,
+                    "Genres",
+                    "object.container.storageFolder",
+                    None,
+                ),
+            ];
+            let total = entries.len() as u64;
+            Ok((page.apply(entries), total))
+        }
+        Object::Artists => {
+            let query = entity::ArtistEntity::find().order_by_asc(entity::ArtistColumn::SortName);
+            let total = query.clone().count(db).await?;
+            let artists = page.select(query).all(db).await?;
+            let images =
+                artist_images(db, &artists.iter().map(|a| a.id).collect::<Vec<_>>()).await?;
+            let entries = artists
+                .iter()
+                .map(|artist| artist_entry(settings, artist, images.get(&artist.id)))
+                .collect();
+            Ok((entries, total))
+        }
+        Object::Releases => {
+            let query = entity::ReleaseEntity::find().order_by_asc(entity::ReleaseColumn::Title);
+            let total = query.clone().count(db).await?;
+            let releases = page.select(query).all(db).await?;
+            let images =
+                release_images(db, &releases.iter().map(|r| r.id).collect::<Vec<_>>()).await?;
+            let entries = releases
+                .iter()
+                .map(|release| {
+                    release_entry(settings, release, Object::Releases, images.get(&release.id))
+                })
+                .collect();
+            Ok((entries, total))
+        }
+        Object::Genres => {
+            let query = entity::GenreEntity::find().order_by_asc(entity::GenreColumn::Name);
+            let total = query.clone().count(db).await?;
+            let genres = page.select(query).all(db).await?;
+            Ok((genres.iter().map(genre_entry).collect(), total))
+        }
+        Object::Artist(id) => {
+            let releases = artist_releases(db, *id).await?;
+            let total = releases.len() as u64;
+            let releases = page.apply(releases);
+            let images =
+                release_images(db, &releases.iter().map(|r| r.id).collect::<Vec<_>>()).await?;
+            let entries = releases
+                .iter()
+                .map(|release| {
+                    release_entry(settings, release, object.clone(), images.get(&release.id))
+                })
+                .collect();
+            Ok((entries, total))
+        }
+        Object::Release(id) => {
+            let tracks = release_tracks(db, *id).await?;
+            let total = tracks.len() as u64;
+            let entries = track_entries(db, settings, page.apply(tracks), None).await?;
+            Ok((entries, total))
+        }
+        Object::Genre(id) => {
+            let track_ids: Vec<Uuid> = entity::GenreTrackEntity::find()
+                .filter(entity::GenreTrackColumn::GenreId.eq(id.to_owned()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|genre| genre.track_id)
+                .collect();
+            let total = track_ids.len() as u64;
+            let query = entity::TrackEntity::find()
+                .filter(entity::TrackColumn::Id.is_in(track_ids))
+                .order_by_asc(entity::TrackColumn::Title);
+            let tracks = page.select(query).all(db).await?;
+            let entries = track_entries(db, settings, tracks, Some(object.clone())).await?;
+            Ok((entries, total))
+        }
+        Object::Track(_) => Ok((Vec::new(), 0)),
+    }
+}
+
+/// Returns the entry describing the object itself
+async fn metadata<C>(db: &C, settings: &Settings, object: &Object) -> Result<Entry, SoapError>
+where
+    C: ConnectionTrait,
+{
+    let folder = |title: &str, parent: Object| {
+        container(
+            object.clone(),
+            parent,
+            title,
+            "object.container.storageFolder",
+            None,
+        )
+    };
+    match object {
+        Object::Root => Ok(Entry::Container {
+            object: Object::Root,
+            parent: None,
+            title: settings.dlna.name.to_owned(),
+            class: "object.container.storageFolder",
+            art: None,
+        }),
+        Object::Artists => Ok(folder("Artists", Object::Root)),
+        Object::Releases => Ok(folder("Albums", Object::Root)),
+        Object::Genres => Ok(folder("Genres", Object::Root)),
+        Object::Artist(id) => {
+            let artist = entity::ArtistEntity::find_by_id(*id)
+                .one(db)
+                .await?
+                .ok_or(SoapError::NoSuchObject)?;
+            let images = artist_images(db, &[artist.id]).await?;
+            Ok(artist_entry(settings, &artist, images.get(&artist.id)))
+        }
+        Object::Release(id) => {
+            let release = entity::ReleaseEntity::find_by_id(*id)
+                .one(db)
+                .await?
+                .ok_or(SoapError::NoSuchObject)?;
+            let images = release_images(db, &[release.id]).await?;
+            Ok(release_entry(
+                settings,
+                &release,
+                Object::Releases,
+                images.get(&release.id),
+            ))
+        }
+        Object::Genre(id) => {
+            let genre = entity::GenreEntity::find_by_id(id.to_owned())
+                .one(db)
+                .await?
+                .ok_or(SoapError::NoSuchObject)?;
+            Ok(genre_entry(&genre))
+        }
+        Object::Track(id) => {
+            let track = entity::TrackEntity::find_by_id(*id)
+                .one(db)
+                .await?
+                .ok_or(SoapError::NoSuchObject)?;
+            track_entries(db, settings, vec![track], None)
+                .await?
+                .pop()
+                .ok_or(SoapError::NoSuchObject)
+        }
+    }
+}
+
+async fn browse<C>(db: &C, body: &str) -> Result<Response, SoapError>
+where
+    C: ConnectionTrait,
+{
+    let settings = get_settings().map_err(crate::api::Error::from)?;
+    let object: Object = required(body, "ObjectID")?.parse()?;
+    let number = |name: &str| -> Result<u64, SoapError> {
+        argument(body, name)
+            .filter(|value| !value.is_empty())
+            .map_or(Ok(0), |value| {
+                value.trim().parse().map_err(|_| SoapError::InvalidArgs)
+            })
+    };
+    let page = Page {
+        start: number("StartingIndex")?,
+        count: number("RequestedCount")?,
+    };
+
+    let (entries, total) = match required(body, "BrowseFlag")?.as_str() {
+        "BrowseMetadata" => (vec![metadata(db, settings, &object).await?], 1),
+        "BrowseDirectChildren" => children(db, settings, &object, page).await?,
+        _ => return Err(SoapError::InvalidArgs),
+    };
+    Ok(response(
+        CONTENT_DIRECTORY,
+        "Browse",
+        &[
+            ("Result", didl(&entries)),
+            ("NumberReturned", entries.len().to_string()),
+            ("TotalMatches", total.to_string()),
+            ("UpdateID", UPDATE_ID.to_string()),
+        ],
+    ))
+}
+
+pub async fn control(
+    State(AppState(db)): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, SoapError> {
+    let action = action(&headers)?;
+    match action.as_str() {
+        "Browse" => browse(&db, &body).await,
+        "GetSearchCapabilities" => Ok(response(
+            CONTENT_DIRECTORY,
+            &action,
+            &[("SearchCaps", String::new())],
+        )),
+        "GetSortCapabilities" => Ok(response(
+            CONTENT_DIRECTORY,
+            &action,
+            &[("SortCaps", String::new())],
+        )),
+        "GetSystemUpdateID" => Ok(response(
+            CONTENT_DIRECTORY,
+            &action,
+            &[("Id", UPDATE_ID.to_string())],
+        )),
+        _ => Err(SoapError::InvalidAction),
+    }
+}
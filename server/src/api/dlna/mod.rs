@@ -0,0 +1,190 @@
+pub mod connection_manager;
+pub mod content_directory;
+pub mod soap;
+pub mod ssdp;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
+    routing::{any, get, post},
+    Router,
+};
+use base::setting::{get_settings, Settings};
+use base::util::escape_xml;
+use std::net::{IpAddr, SocketAddr};
+use uuid::Uuid;
+
+use crate::api::{
+    extract::Path,
+    tempo::{
+        images,
+        tracks::{serve_audio, AudioQuery},
+    },
+    AppState, Error,
+};
+
+pub static DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+pub static CONTENT_DIRECTORY: &str = "urn:schemas-upnp-org:service:ContentDirectory:1";
+pub static CONNECTION_MANAGER: &str = "urn:schemas-upnp-org:service:ConnectionManager:1";
+
+/// The client name used to pick the transcoding profile for the audio served to devices
+pub static CLIENT: &str = "dlna";
+
+static XML_MIME: &str = "text/xml; charset=\"utf-8\"";
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/description.xml", get(description))
+        .route("/content_directory.xml", get(content_directory_scpd))
+        .route("/connection_manager.xml", get(connection_manager_scpd))
+        .route(
+            "/control/content_directory",
+            post(content_directory::control),
+        )
+        .route(
+            "/control/connection_manager",
+            post(connection_manager::control),
+        )
+        .route("/events/:service", any(subscribe))
+        .route("/tracks/:id", get(audio))
+        .route("/images/:id", get(images::file))
+        .layer(from_fn(local_only))
+}
+
+// Private, link local and loopback addresses
+fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_link_local() || ip.is_loopback(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_local(IpAddr::V4(ip)),
+            // unique local addresses are in fc00::/7, link local ones in fe80::/10
+            None => {
+                ip.is_loopback()
+                    || ip.segments()[0] & 0xfe00 == 0xfc00
+                    || ip.segments()[0] & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+/// Devices can't log in, so the media server is only served to the local network.
+/// Requests forwarded by a proxy are refused, as they could come from anywhere.
+async fn local_only<B>(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, Error> {
+    let proxied = request.headers().contains_key(header::FORWARDED)
+        || request.headers().contains_key("x-forwarded-for");
+    if proxied || !is_local(addr.ip()) {
+        tracing::debug!(%addr, "Refused media server request from outside the local network");
+        return Err(Error::Unauthorized(Some(
+            "The media server is only available on the local network".to_string(),
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
+/// A stable identifier for the device, so that clients recognize the server across restarts
+pub fn device_uuid(settings: &Settings) -> Uuid {
+    let seed = format!("{}{}", settings.url, settings.dlna.name);
+    uuid::Builder::from_md5_bytes(md5::compute(seed).0).into_uuid()
+}
+
+/// The absolute url of a resource of the media server
+pub fn url(settings: &Settings, path: &str) -> String {
+    let mut url = settings.url.clone();
+    url.set_path(format!("dlna/{}", path).as_str());
+    url.to_string()
+}
+
+fn xml(body: String) -> Response {
+    ([(header::CONTENT_TYPE, XML_MIME)], body).into_response()
+}
+
+fn service(kind: &str, id: &str, name: &str) -> String {
+    format!(
+        concat!(
+            "<service>",
+            "<serviceType>{kind}</serviceType>",
+            "<serviceId>urn:upnp-org:serviceId:{id}</serviceId>",
+            "<SCPDURL>{name}.xml</SCPDURL>",
+            "<controlURL>control/{name}</controlURL>",
+            "<eventSubURL>events/{name}</eventSubURL>",
+            "</service>"
+        ),
+        kind = kind,
+        id = id,
+        name = name
+    )
+}
+
+async fn description() -> Result<Response, Error> {
+    let settings = get_settings()?;
+    Ok(xml(format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<root xmlns=\"urn:schemas-upnp-org:device-1-0\" xmlns:dlna=\"urn:schemas-dlna-org:device-1-0\">",
+            "<specVersion><major>1</major><minor>0</minor></specVersion>",
+            "<device>",
+            "<deviceType>{device_type}</deviceType>",
+            "<friendlyName>{name}</friendlyName>",
+            "<manufacturer>{cli}</manufacturer>",
+            "<modelName>{cli}</modelName>",
+            "<modelNumber>{version}</modelNumber>",
+            "<UDN>uuid:{uuid}</UDN>",
+            "<dlna:X_DLNADOC>DMS-1.50</dlna:X_DLNADOC>",
+            "<serviceList>{content_directory}{connection_manager}</serviceList>",
+            "</device>",
+            "</root>"
+        ),
+        device_type = DEVICE_TYPE,
+        name = escape_xml(&settings.dlna.name),
+        cli = base::CLI_NAME,
+        version = base::VERSION,
+        uuid = device_uuid(settings),
+        content_directory = service(CONTENT_DIRECTORY, "ContentDirectory", "content_directory"),
+        connection_manager = service(
+            CONNECTION_MANAGER,
+            "ConnectionManager",
+            "connection_manager"
+        ),
+    )))
+}
+
+async fn content_directory_scpd() -> Response {
+    xml(content_directory::SCPD.to_string())
+}
+
+async fn connection_manager_scpd() -> Response {
+    xml(connection_manager::SCPD.to_string())
+}
+
+// The library is not evented, but some devices refuse to browse a server which
+// rejects their subscriptions
+async fn subscribe() -> Response {
+    let sid = format!("uuid:{}", Uuid::new_v4());
+    let mut response = StatusCode::OK.into_response();
+    let headers = response.headers_mut();
+    if let Ok(sid) = HeaderValue::from_str(&sid) {
+        headers.insert("SID", sid);
+    }
+    headers.insert("TIMEOUT", HeaderValue::from_static("Second-1800"));
+    response
+}
+
+// Devices are anonymous, so only the transcoding profile of the client applies
+async fn audio(
+    State(AppState(db)): State<AppState>,
+    Path(id): Path<Uuid>,
+    request: Request<Body>,
+) -> Result<Response, Error> {
+    let query = AudioQuery {
+        client: Some(CLIENT.to_string()),
+        ..Default::default()
+    };
+    serve_audio(&db, "", id, query, request).await
+}
@@ -0,0 +1,134 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base::util::{escape_xml, unescape_xml};
+use thiserror::Error;
+
+use super::XML_MIME;
+use crate::api::Error;
+
+#[derive(Error, Debug)]
+pub enum SoapError {
+    #[error("Invalid Action")]
+    InvalidAction,
+    #[error("Invalid Args")]
+    InvalidArgs,
+    #[error("No such object")]
+    NoSuchObject,
+    #[error("Action Failed: {0}")]
+    Api(#[from] Error),
+}
+
+impl From<sea_orm::DbErr> for SoapError {
+    fn from(value: sea_orm::DbErr) -> Self {
+        Self::Api(value.into())
+    }
+}
+
+impl SoapError {
+    fn code(&self) -> u32 {
+        match self {
+            SoapError::InvalidAction => 401,
+            SoapError::InvalidArgs => 402,
+            SoapError::NoSuchObject => 701,
+            SoapError::Api(_) => 501,
+        }
+    }
+}
+
+impl IntoResponse for SoapError {
+    fn into_response(self) -> Response {
+        if let SoapError::Api(error) = &self {
+            tracing::warn!(%error, "DLNA action failed");
+        }
+        let body = envelope(&format!(
+            concat!(
+                "<s:Fault>",
+                "<faultcode>s:Client</faultcode>",
+                "<faultstring>UPnPError</faultstring>",
+                "<detail><UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\">",
+                "<errorCode>{}</errorCode>",
+                "<errorDescription>{}</errorDescription>",
+                "</UPnPError></detail>",
+                "</s:Fault>"
+            ),
+            self.code(),
+            escape_xml(&self.to_string())
+        ));
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(header::CONTENT_TYPE, XML_MIME)],
+            body,
+        )
+            .into_response()
+    }
+}
+
+fn envelope(body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" ",
+            "s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">",
+            "<s:Body>{}</s:Body>",
+            "</s:Envelope>"
+        ),
+        body
+    )
+}
+
+/// The name of the action invoked, from a header like
+/// `SOAPACTION: "urn:schemas-upnp-org:service:ContentDirectory:1#Browse"`
+pub fn action(headers: &HeaderMap) -> Result<String, SoapError> {
+    headers
+        .get("SOAPACTION")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim_matches('"').rsplit_once('#'))
+        .map(|(_, action)| action.to_owned())
+        .ok_or(SoapError::InvalidAction)
+}
+
+/// Reads the value of an argument from the body of a request. Arguments are simple
+/// elements, so a full XML parser is not needed.
+pub fn argument(body: &str, name: &str) -> Option<String> {
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        let tag_name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+        if local_name != name {
+            continue;
+        }
+        if tag.ends_with('/') {
+            return Some(String::new());
+        }
+        let content = &rest[end + 1..];
+        let close = content.find("</")?;
+        return Some(unescape_xml(&content[..close]));
+    }
+    None
+}
+
+/// Like [argument], failing with the error expected by control points
+pub fn required(body: &str, name: &str) -> Result<String, SoapError> {
+    argument(body, name).ok_or(SoapError::InvalidArgs)
+}
+
+/// Renders the successful response of an action with its output arguments
+pub fn response(service: &str, action: &str, arguments: &[(&str, String)]) -> Response {
+    let arguments = arguments
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape_xml(value)))
+        .collect::<String>();
+    let body = envelope(&format!(
+        "<u:{0}Response xmlns:u=\"{1}\">{2}</u:{0}Response>",
+        action, service, arguments
+    ));
+    ([(header::CONTENT_TYPE, XML_MIME)], body).into_response()
+}
@@ -0,0 +1,190 @@
+use eyre::Result;
+use rand::Rng;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+use super::{device_uuid, url, CONNECTION_MANAGER, CONTENT_DIRECTORY, DEVICE_TYPE};
+use base::setting::{get_settings, Settings};
+
+static MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+static PORT: u16 = 1900;
+/// Upper bound for the random delay before answering a search, in seconds
+static MAX_DELAY: u64 = 5;
+
+/// What the server announces itself as: a notification type and the matching
+/// unique service name, one for the device and each of its services
+struct Advertisement {
+    location: String,
+    server: String,
+    max_age: u64,
+    targets: Vec<(String, String)>,
+}
+
+impl Advertisement {
+    fn new(settings: &Settings) -> Self {
+        let uuid = format!("uuid:{}", device_uuid(settings));
+        let targets = [
+            "upnp:rootdevice",
+            DEVICE_TYPE,
+            CONTENT_DIRECTORY,
+            CONNECTION_MANAGER,
+        ]
+        .iter()
+        .map(|target| (target.to_string(), format!("{}::{}", uuid, target)))
+        .chain(std::iter::once((uuid.to_owned(), uuid.to_owned())))
+        .collect();
+        Self {
+            location: url(settings, "description.xml"),
+            server: format!(
+                "{}/1.0 UPnP/1.0 {}/{}",
+                std::env::consts::OS,
+                base::CLI_NAME,
+                base::VERSION
+            ),
+            max_age: settings.dlna.announce_interval.saturating_mul(2),
+            targets,
+        }
+    }
+
+    fn notify(&self, target: &str, usn: &str) -> String {
+        format!(
+            concat!(
+                "NOTIFY * HTTP/1.1\r\n",
+                "HOST: {}:{}\r\n",
+                "CACHE-CONTROL: max-age={}\r\n",
+                "LOCATION: {}\r\n",
+                "NT: {}\r\n",
+                "NTS: ssdp:alive\r\n",
+                "SERVER: {}\r\n",
+                "USN: {}\r\n",
+                "\r\n"
+            ),
+            MULTICAST_ADDRESS, PORT, self.max_age, self.location, target, self.server, usn
+        )
+    }
+
+    fn reply(&self, target: &str, usn: &str) -> String {
+        format!(
+            concat!(
+                "HTTP/1.1 200 OK\r\n",
+                "CACHE-CONTROL: max-age={}\r\n",
+                "EXT:\r\n",
+                "LOCATION: {}\r\n",
+                "SERVER: {}\r\n",
+                "ST: {}\r\n",
+                "USN: {}\r\n",
+                "\r\n"
+            ),
+            self.max_age, self.location, self.server, target, usn
+        )
+    }
+
+    // The targets matching the search target of an M-SEARCH request
+    fn matching(&self, search_target: &str) -> Vec<&(String, String)> {
+        self.targets
+            .iter()
+            .filter(|(target, _)| search_target == "ssdp:all" || search_target == target)
+            .collect()
+    }
+}
+
+// The socket is shared with other UPnP software running on the same host
+fn socket() -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT).into())?;
+    let socket = UdpSocket::from_std(socket.into())?;
+    socket.join_multicast_v4(MULTICAST_ADDRESS, Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket)
+}
+
+/// Parses an M-SEARCH request, returning the search target and maximum wait time
+fn parse_search(message: &str) -> Option<(String, u64)> {
+    let mut lines = message.lines();
+    if !lines.next()?.starts_with("M-SEARCH") {
+        return None;
+    }
+    let mut target = None;
+    let mut mx = 1;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_uppercase().as_str() {
+            "ST" => target = Some(value.trim().to_owned()),
+            "MX" => mx = value.trim().parse().unwrap_or(1),
+            _ => {}
+        }
+    }
+    target.map(|target| (target, mx))
+}
+
+async fn announce(socket: Arc<UdpSocket>, advertisement: Arc<Advertisement>, interval: u64) {
+    let destination = SocketAddr::from((MULTICAST_ADDRESS, PORT));
+    let mut interval = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    loop {
+        interval.tick().await;
+        for (target, usn) in advertisement.targets.iter() {
+            let message = advertisement.notify(target, usn);
+            if let Err(error) = socket.send_to(message.as_bytes(), destination).await {
+                tracing::warn!(%error, "Could not send SSDP announcement");
+            }
+        }
+    }
+}
+
+async fn respond(socket: Arc<UdpSocket>, advertisement: Arc<Advertisement>) {
+    let mut buffer = [0; 2048];
+    loop {
+        let (length, source) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(error) => {
+                tracing::warn!(%error, "Could not receive SSDP message");
+                continue;
+            }
+        };
+        let message = String::from_utf8_lossy(&buffer[..length]);
+        let Some((target, mx)) = parse_search(&message) else {
+            continue;
+        };
+        let replies: Vec<String> = advertisement
+            .matching(&target)
+            .into_iter()
+            .map(|(target, usn)| advertisement.reply(target, usn))
+            .collect();
+        if replies.is_empty() {
+            continue;
+        }
+        tracing::debug!(%source, %target, "Answering SSDP search");
+        // replies are spread over the time the device is willing to wait
+        let delay = rand::thread_rng().gen_range(0..=mx.clamp(1, MAX_DELAY) * 1000);
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            for reply in replies.iter() {
+                if let Err(error) = socket.send_to(reply.as_bytes(), source).await {
+                    tracing::warn!(%error, %source, "Could not answer SSDP search");
+                }
+            }
+        });
+    }
+}
+
+/// Starts announcing the media server on the local network and answering searches
+pub fn start() -> Result<()> {
+    let settings = get_settings()?;
+    let socket = Arc::new(socket()?);
+    let advertisement = Arc::new(Advertisement::new(settings));
+    tracing::info!(location = %advertisement.location, "Announcing DLNA media server");
+    tokio::spawn(announce(
+        socket.clone(),
+        advertisement.clone(),
+        settings.dlna.announce_interval,
+    ));
+    tokio::spawn(respond(socket, advertisement));
+    Ok(())
+}
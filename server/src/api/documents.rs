@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use sea_orm::ColumnTrait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -5,17 +6,31 @@ use time::OffsetDateTime;
 use url::Url;
 use uuid::Uuid;
 
-use crate::api::jsonapi::{InsertResource, Resource};
-use entity::{ArtistTrackRelationType, ArtistUrlType, ConnectionProvider};
+use crate::api::jsonapi::{InsertResource, Resource, UpdateResource};
+use crate::playlist::PlaylistFormat;
+use entity::{
+    ArtistTrackRelationType, ArtistUrlType, ConnectionProvider, SmartPlaylistRules,
+    YearReviewReport,
+};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ResourceType {
     Server,
     Auth,
     User,
     Scrobble,
+    UnmatchedListen,
     Connection,
+    Share,
+    NowPlaying,
+    Playlist,
+    PlaylistImport,
+    SmartPlaylist,
+    PlayQueue,
+    Stats,
+    YearReview,
+    ApiToken,
 
     Image,
     Artist,
@@ -29,8 +44,26 @@ pub type ServerResource = Resource<ResourceType, String, ServerAttributes, Serve
 pub type AuthResource = Resource<ResourceType, String, AuthAttributes, AuthRelation, Meta>;
 pub type UserResource = Resource<ResourceType, String, UserAttributes, UserRelation, Meta>;
 pub type ScrobbleResource = Resource<ResourceType, i64, ScrobbleAttributes, ScrobbleRelation, Meta>;
+pub type UnmatchedListenResource =
+    Resource<ResourceType, i64, UnmatchedListenAttributes, UnmatchedListenRelation, Meta>;
 pub type ConnectionResource =
     Resource<ResourceType, ConnectionProvider, ConnectionAttributes, ConnectionRelation, Meta>;
+pub type ShareResource = Resource<ResourceType, Uuid, ShareAttributes, ShareRelation, Meta>;
+pub type NowPlayingResource =
+    Resource<ResourceType, String, NowPlayingAttributes, NowPlayingRelation, Meta>;
+pub type PlaylistResource =
+    Resource<ResourceType, Uuid, PlaylistAttributes, PlaylistRelation, Meta>;
+pub type PlaylistImportResource =
+    Resource<ResourceType, String, PlaylistImportAttributes, PlaylistImportRelation, Meta>;
+pub type SmartPlaylistResource =
+    Resource<ResourceType, Uuid, SmartPlaylistAttributes, SmartPlaylistRelation, Meta>;
+pub type PlayQueueResource =
+    Resource<ResourceType, String, PlayQueueAttributes, PlayQueueRelation, Meta>;
+pub type StatsResource = Resource<ResourceType, String, StatsAttributes, StatsRelation, Meta>;
+pub type YearReviewResource =
+    Resource<ResourceType, String, YearReviewAttributes, YearReviewRelation, Meta>;
+pub type ApiTokenResource =
+    Resource<ResourceType, Uuid, ApiTokenAttributes, ApiTokenRelation, Meta>;
 pub type ImageResource = Resource<ResourceType, String, ImageAttributes, ImageRelation, Meta>;
 pub type ArtistResource = Resource<ResourceType, Uuid, ArtistAttributes, ArtistRelation, Meta>;
 pub type TrackResource = Resource<ResourceType, Uuid, TrackAttributes, TrackRelation, Meta>;
@@ -43,13 +76,29 @@ pub type GenreResource = Resource<ResourceType, String, GenreAttributes, GenreRe
 // pub type InsertUserResource = InsertResource<UserAttributes, UserRelation>;
 pub type InsertScrobbleResource =
     InsertResource<ResourceType, ScrobbleAttributes, ScrobbleRelation, Meta>;
+pub type InsertShareResource =
+    InsertResource<ResourceType, InsertShareAttributes, ShareRelation, Meta>;
+pub type InsertNowPlayingResource =
+    InsertResource<ResourceType, InsertNowPlayingAttributes, NowPlayingRelation, Meta>;
+pub type InsertPlaylistResource =
+    InsertResource<ResourceType, InsertPlaylistAttributes, PlaylistRelation, Meta>;
+pub type UpdatePlaylistResource =
+    UpdateResource<ResourceType, Uuid, UpdatePlaylistAttributes, PlaylistRelation, Meta>;
+pub type InsertSmartPlaylistResource =
+    InsertResource<ResourceType, InsertSmartPlaylistAttributes, SmartPlaylistRelation, Meta>;
+pub type UpdateSmartPlaylistResource =
+    UpdateResource<ResourceType, Uuid, UpdateSmartPlaylistAttributes, SmartPlaylistRelation, Meta>;
+pub type UpdatePlayQueueResource =
+    UpdateResource<ResourceType, String, UpdatePlayQueueAttributes, PlayQueueRelation, Meta>;
+pub type InsertApiTokenResource =
+    InsertResource<ResourceType, InsertApiTokenAttributes, ApiTokenRelation, Meta>;
 // pub type InsertImageResource = InsertResource<ImageAttributes, ImageRelation>;
 // pub type InsertArtistResource = InsertResource<ArtistAttributes, ArtistRelation>;
 // pub type InsertTrackResource = InsertResource<TrackAttributes, TrackRelation>;
 // pub type InsertMediumResource = InsertResource<MediumAttributes, MediumRelation>;
 // pub type InsertReleaseResource = InsertResource<ReleaseAttributes, ReleaseRelation>;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum Included {
     User(UserResource),
@@ -60,6 +109,9 @@ pub enum Included {
     Medium(MediumResource),
     Release(ReleaseResource),
     Genre(GenreResource),
+    Share(ShareResource),
+    Playlist(PlaylistResource),
+    SmartPlaylist(SmartPlaylistResource),
 }
 
 impl PartialEq for Included {
@@ -73,6 +125,9 @@ impl PartialEq for Included {
             (Included::Medium(a), Included::Medium(b)) => a.id == b.id,
             (Included::Release(a), Included::Release(b)) => a.id == b.id,
             (Included::Genre(a), Included::Genre(b)) => a.id == b.id,
+            (Included::Share(a), Included::Share(b)) => a.id == b.id,
+            (Included::Playlist(a), Included::Playlist(b)) => a.id == b.id,
+            (Included::SmartPlaylist(a), Included::SmartPlaylist(b)) => a.id == b.id,
             (_, _) => false,
         }
     }
@@ -96,12 +151,14 @@ impl std::cmp::Ord for Included {
             (Included::Medium(a), Included::Medium(b)) => a.id.cmp(&b.id),
             (Included::Release(a), Included::Release(b)) => a.id.cmp(&b.id),
             (Included::Genre(a), Included::Genre(b)) => a.id.cmp(&b.id),
+            (Included::Playlist(a), Included::Playlist(b)) => a.id.cmp(&b.id),
+            (Included::SmartPlaylist(a), Included::SmartPlaylist(b)) => a.id.cmp(&b.id),
             (_, _) => std::cmp::Ordering::Less,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum Meta {
     ArtistCredit(ArtistCreditAttributes),
@@ -110,11 +167,44 @@ pub enum Meta {
     Connection(ConnectionMetaAttributes),
 
     SearchResult(SearchResultAttributes),
+    User(UserMetaAttributes),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct SearchResultAttributes {
     pub score: f32,
+    #[serde(flatten)]
+    pub user: UserMetaAttributes,
+}
+
+/// Set on the resources starred or rated by the user
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct UserMetaAttributes {
+    #[serde(
+        default,
+        with = "time::serde::iso8601::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub starred_at: Option<OffsetDateTime>,
+    /// From 1 to 5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+}
+
+impl Meta {
+    /// The attributes of the user meta, merged into the search result meta when
+    /// there is one
+    pub fn user(meta: &mut Option<Meta>) -> &mut UserMetaAttributes {
+        if !matches!(meta, Some(Meta::User(_)) | Some(Meta::SearchResult(_))) {
+            *meta = Some(Meta::User(UserMetaAttributes::default()));
+        }
+        match meta {
+            Some(Meta::User(attributes)) => attributes,
+            Some(Meta::SearchResult(attributes)) => &mut attributes.user,
+            _ => unreachable!(),
+        }
+    }
 }
 
 pub trait IntoColumn<T>
@@ -124,7 +214,7 @@ where
     fn column(&self) -> Option<T>;
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ServerAttributes {
     pub tempo_version: String,
     pub server: String,
@@ -133,22 +223,22 @@ pub struct ServerAttributes {
     pub features: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ServerRelation {}
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ArtistCreditAttributes {
     pub join_phrase: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct RecordingAttributes {
     pub role: ArtistTrackRelationType,
     pub detail: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ImageAttributes {
     pub role: String,
     pub format: String,
@@ -159,7 +249,7 @@ pub struct ImageAttributes {
     pub size: i32,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ImageRelation {
     Artist,
@@ -167,7 +257,7 @@ pub enum ImageRelation {
     // TODO: tracks?
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ArtistAttributes {
     pub name: String,
     pub sort_name: String,
@@ -177,7 +267,7 @@ pub struct ArtistAttributes {
     pub urls: HashMap<ArtistUrlType, String>,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ArtistRelation {
     Recordings,
@@ -188,7 +278,7 @@ pub enum ArtistRelation {
     Cover,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum ArtistInclude {
     #[serde(rename = "images")]
     Images,
@@ -200,7 +290,7 @@ pub enum ArtistInclude {
     ReleasesArtists,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum ArtistFilter {
     #[serde(rename = "id")]
@@ -211,6 +301,8 @@ pub enum ArtistFilter {
     SortName,
     #[serde(rename = "description")]
     Description,
+    #[serde(rename = "starred")]
+    Starred,
 
     Include(ArtistInclude),
 }
@@ -227,7 +319,7 @@ impl IntoColumn<entity::ArtistColumn> for ArtistFilter {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ReleaseAttributes {
     pub title: String,
     pub disambiguation: Option<String>,
@@ -257,7 +349,7 @@ pub struct ReleaseAttributes {
     pub release_group_mbid: Option<Uuid>,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ReleaseRelation {
     Image,
@@ -266,7 +358,7 @@ pub enum ReleaseRelation {
     Genres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum ReleaseInclude {
     #[serde(rename = "image")]
     Image,
@@ -284,7 +376,7 @@ pub enum ReleaseInclude {
     MediumsTracksGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum ReleaseFilter {
     #[serde(rename = "id")]
@@ -313,6 +405,8 @@ pub enum ReleaseFilter {
     ReleaseMbid,
     #[serde(rename = "release_group_mbid")]
     ReleaseGroupMbid,
+    #[serde(rename = "starred")]
+    Starred,
 
     Include(ReleaseInclude),
 }
@@ -336,7 +430,7 @@ impl IntoColumn<entity::ReleaseColumn> for ReleaseFilter {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct MediumAttributes {
     pub position: i32,
     pub tracks: i32,
@@ -345,14 +439,14 @@ pub struct MediumAttributes {
     pub format: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MediumRelation {
     Release,
     Tracks,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum MediumInclude {
     #[serde(rename = "release")]
     Release,
@@ -368,7 +462,7 @@ pub enum MediumInclude {
     TracksGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum MediumFilter {
     #[serde(rename = "id")]
@@ -398,7 +492,7 @@ impl IntoColumn<entity::MediumColumn> for MediumFilter {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct TrackAttributes {
     pub title: String,
     pub track: i32,
@@ -425,10 +519,32 @@ pub struct TrackAttributes {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bitdepth: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub size: Option<i32>,
+    pub size: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_track_gain: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_track_peak: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_track_range: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_album_gain: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_album_peak: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay_gain_album_range: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoder_delay: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoder_padding: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gapless_playback: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum TrackRelation {
     Artists,
@@ -437,7 +553,7 @@ pub enum TrackRelation {
     Genres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum TrackInclude {
     #[serde(rename = "artists")]
     Artists,
@@ -455,7 +571,7 @@ pub enum TrackInclude {
     MediumReleaseGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum TrackFilter {
     #[serde(rename = "id")]
@@ -486,12 +602,14 @@ pub enum TrackFilter {
     Framecount,
     #[serde(rename = "channels")]
     Channels,
-    #[serde(rename = "birate")]
+    #[serde(rename = "bitrate")]
     Bitrate,
     #[serde(rename = "bitdepth")]
     Bitdepth,
     #[serde(rename = "size")]
     Size,
+    #[serde(rename = "starred")]
+    Starred,
 
     Include(TrackInclude),
 }
@@ -506,45 +624,55 @@ impl IntoColumn<entity::TrackColumn> for TrackFilter {
             TrackFilter::TrackMbid => Some(entity::TrackColumn::Id),
             TrackFilter::Mimetype => Some(entity::TrackColumn::Format),
             TrackFilter::Duration => Some(entity::TrackColumn::Length),
+            TrackFilter::Bpm => Some(entity::TrackColumn::Bpm),
+            TrackFilter::Framerate => Some(entity::TrackColumn::Framerate),
+            TrackFilter::Framecount => Some(entity::TrackColumn::Framecount),
+            TrackFilter::Channels => Some(entity::TrackColumn::Channels),
+            TrackFilter::Bitrate => Some(entity::TrackColumn::Bitrate),
+            TrackFilter::Bitdepth => Some(entity::TrackColumn::Bitdepth),
+            TrackFilter::Size => Some(entity::TrackColumn::Size),
             _ => None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct AuthAttributes {
     pub token: Token,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh: Option<Token>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Token {
     pub value: String,
     #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
     pub expires_at: OffsetDateTime,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AuthRelation {
     User,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct UserAttributes {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum UserRelation {
     Scrobbles,
     Connections,
+    Favorites,
+    Ratings,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum UserInclude {
     #[serde(rename = "scrobbles")]
     Scrobbles,
@@ -562,7 +690,7 @@ pub enum UserInclude {
     ScrobblesTracksMediumReleaseGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum UserFilter {
     #[serde(rename = "id")]
@@ -586,20 +714,21 @@ impl IntoColumn<entity::UserColumn> for UserFilter {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct ScrobbleAttributes {
     #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
     pub at: OffsetDateTime,
 }
 
-#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ScrobbleRelation {
     User,
     Track,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum ScrobbleInclude {
     #[serde(rename = "user")]
     User,
@@ -619,7 +748,7 @@ pub enum ScrobbleInclude {
     TrackMediumReleaseGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum ScrobbleFilter {
     #[serde(rename = "id")]
@@ -640,42 +769,577 @@ impl IntoColumn<entity::ScrobbleColumn> for ScrobbleFilter {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A listen submitted by an external scrobbler which could not be matched to
+/// any track of the library
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct UnmatchedListenAttributes {
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub listened_at: OffsetDateTime,
+    pub artist_name: String,
+    pub track_name: String,
+    pub release_name: Option<String>,
+    pub recording_mbid: Option<Uuid>,
+    pub track_mbid: Option<Uuid>,
+    pub release_mbid: Option<Uuid>,
+    /// In milliseconds
+    pub duration: Option<i32>,
+    pub submission_client: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchedListenRelation {
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum UnmatchedListenInclude {
+    #[serde(rename = "user")]
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(untagged)]
+pub enum UnmatchedListenFilter {
+    #[serde(rename = "artist_name")]
+    ArtistName,
+    #[serde(rename = "track_name")]
+    TrackName,
+
+    Include(UnmatchedListenInclude),
+}
+
+impl IntoColumn<entity::UnmatchedListenColumn> for UnmatchedListenFilter {
+    fn column(&self) -> Option<entity::UnmatchedListenColumn> {
+        match self {
+            UnmatchedListenFilter::ArtistName => Some(entity::UnmatchedListenColumn::ArtistName),
+            UnmatchedListenFilter::TrackName => Some(entity::UnmatchedListenColumn::TrackName),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ShareAttributes {
+    pub token: String,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub expires_at: OffsetDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_plays: Option<i32>,
+    pub plays: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InsertShareAttributes {
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub expires_at: OffsetDateTime,
+    #[serde(default)]
+    pub max_plays: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareRelation {
+    User,
+    Track,
+    Release,
+    Playlist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum ShareInclude {
+    #[serde(rename = "track")]
+    Track,
+    #[serde(rename = "release")]
+    Release,
+    #[serde(rename = "playlist")]
+    Playlist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(untagged)]
+pub enum ShareFilter {
+    #[serde(rename = "id")]
+    Id,
+    #[serde(rename = "kind")]
+    Kind,
+
+    Include(ShareInclude),
+}
+
+impl IntoColumn<entity::ShareColumn> for ShareFilter {
+    fn column(&self) -> Option<entity::ShareColumn> {
+        match self {
+            ShareFilter::Id => Some(entity::ShareColumn::Id),
+            ShareFilter::Kind => Some(entity::ShareColumn::Kind),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct NowPlayingAttributes {
+    pub client: String,
+    /// Last reported position in the track, in milliseconds
+    pub position: u32,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub updated_at: OffsetDateTime,
+    pub scrobbled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InsertNowPlayingAttributes {
+    pub client: String,
+    #[serde(default)]
+    pub position: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NowPlayingRelation {
+    User,
+    Track,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum NowPlayingInclude {
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "track")]
+    Track,
+    #[serde(rename = "track.artists")]
+    TrackArtists,
+    #[serde(rename = "track.medium")]
+    TrackMedium,
+    #[serde(rename = "track.medium.release")]
+    TrackMediumRelease,
+    #[serde(rename = "track.medium.release.artists")]
+    TrackMediumReleaseArtists,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum NowPlayingFilter {
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "client")]
+    Client,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PlaylistAttributes {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub public: bool,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InsertPlaylistAttributes {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// Only the attributes which are present are changed. An empty description
+/// removes it.
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+#[serde(default)]
+pub struct UpdatePlaylistAttributes {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub public: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistRelation {
+    User,
+    Image,
+    Tracks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum PlaylistInclude {
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "image")]
+    Image,
+    #[serde(rename = "tracks")]
+    Tracks,
+    #[serde(rename = "tracks.artists")]
+    TracksArtists,
+    #[serde(rename = "tracks.medium")]
+    TracksMedium,
+    #[serde(rename = "tracks.medium.release")]
+    TracksMediumRelease,
+    #[serde(rename = "tracks.medium.release.artists")]
+    TracksMediumReleaseArtists,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(untagged)]
+pub enum PlaylistFilter {
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "name")]
+    Name,
+    #[serde(rename = "public")]
+    Public,
+
+    Include(PlaylistInclude),
+}
+
+impl IntoColumn<entity::PlaylistColumn> for PlaylistFilter {
+    fn column(&self) -> Option<entity::PlaylistColumn> {
+        match self {
+            PlaylistFilter::User => Some(entity::PlaylistColumn::User),
+            PlaylistFilter::Name => Some(entity::PlaylistColumn::Name),
+            PlaylistFilter::Public => Some(entity::PlaylistColumn::Public),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PlaylistImportAttributes {
+    pub format: PlaylistFormat,
+    /// Number of entries in the imported playlist
+    pub entries: usize,
+    pub unmatched: Vec<UnmatchedEntryAttributes>,
+}
+
+/// An entry of an imported playlist which could not be matched to a track
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct UnmatchedEntryAttributes {
+    /// Index of the entry in the playlist, starting from 0
+    pub position: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistImportRelation {
+    Tracks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum PlaylistImportInclude {
+    #[serde(rename = "tracks")]
+    Tracks,
+    #[serde(rename = "tracks.artists")]
+    TracksArtists,
+    #[serde(rename = "tracks.medium")]
+    TracksMedium,
+    #[serde(rename = "tracks.medium.release")]
+    TracksMediumRelease,
+    #[serde(rename = "tracks.medium.release.artists")]
+    TracksMediumReleaseArtists,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct SmartPlaylistAttributes {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub public: bool,
+    pub rules: SmartPlaylistRules,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InsertSmartPlaylistAttributes {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub public: bool,
+    pub rules: SmartPlaylistRules,
+}
+
+/// Only the attributes which are present are changed. An empty description
+/// removes it.
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+#[serde(default)]
+pub struct UpdateSmartPlaylistAttributes {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub public: Option<bool>,
+    pub rules: Option<SmartPlaylistRules>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartPlaylistRelation {
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum SmartPlaylistInclude {
+    #[serde(rename = "user")]
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(untagged)]
+pub enum SmartPlaylistFilter {
+    #[serde(rename = "user")]
+    User,
+    #[serde(rename = "name")]
+    Name,
+    #[serde(rename = "public")]
+    Public,
+
+    Include(SmartPlaylistInclude),
+}
+
+impl IntoColumn<entity::SmartPlaylistColumn> for SmartPlaylistFilter {
+    fn column(&self) -> Option<entity::SmartPlaylistColumn> {
+        match self {
+            SmartPlaylistFilter::User => Some(entity::SmartPlaylistColumn::User),
+            SmartPlaylistFilter::Name => Some(entity::SmartPlaylistColumn::Name),
+            SmartPlaylistFilter::Public => Some(entity::SmartPlaylistColumn::Public),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PlayQueueAttributes {
+    /// Index of the current track in the queue
+    pub index: u32,
+    /// Playback position in the current track, in milliseconds
+    pub position: u64,
+    /// The client which last changed the queue
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Changes with every update of the queue, 0 when it was never saved
+    pub version: i64,
+    #[serde(with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+/// The whole queue is replaced. The version must be the one of the queue the
+/// client last read, otherwise the update is rejected as a conflict.
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct UpdatePlayQueueAttributes {
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
+    pub position: u64,
+    #[serde(default)]
+    pub client: Option<String>,
+    pub version: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayQueueRelation {
+    Tracks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum PlayQueueInclude {
+    #[serde(rename = "tracks")]
+    Tracks,
+    #[serde(rename = "tracks.artists")]
+    TracksArtists,
+    #[serde(rename = "tracks.medium")]
+    TracksMedium,
+    #[serde(rename = "tracks.medium.release")]
+    TracksMediumRelease,
+    #[serde(rename = "tracks.medium.release.artists")]
+    TracksMediumReleaseArtists,
+}
+
+/// The period statistics are computed over, ending now unless custom
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsRange {
+    /// The last 7 days
+    Week,
+    /// The last 30 days
+    Month,
+    /// The last 365 days
+    Year,
+    #[default]
+    All,
+    /// Between the given dates
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct StatsAttributes {
+    pub range: StatsRange,
+    #[serde(with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    pub to: Option<OffsetDateTime>,
+    pub scrobbles: u64,
+    /// Sum of the length of the scrobbled tracks, in milliseconds
+    pub listening_time: u64,
+    pub artists: Vec<StatsEntry<Uuid>>,
+    pub releases: Vec<StatsEntry<Uuid>>,
+    pub tracks: Vec<StatsEntry<Uuid>>,
+    pub genres: Vec<StatsEntry<String>>,
+    /// Scrobbles by hour of the day, in UTC
+    pub hours: Vec<u64>,
+    /// Scrobbles by day of the week, from Monday, in UTC
+    pub weekdays: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct StatsEntry<Id> {
+    pub id: Id,
+    pub name: String,
+    pub scrobbles: u64,
+    /// In milliseconds
+    pub listening_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsRelation {
+    User,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct YearReviewAttributes {
+    pub year: i32,
+    pub report: YearReviewReport,
+    /// Reviews of the current year are built again until it is over
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum YearReviewRelation {
+    User,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ApiTokenAttributes {
+    pub name: String,
+    /// The token itself, only returned when it is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InsertApiTokenAttributes {
+    /// A label to recognize the token by, usually the client it is used in
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenRelation {
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum ApiTokenInclude {
+    #[serde(rename = "user")]
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(untagged)]
+pub enum ApiTokenFilter {
+    #[serde(rename = "id")]
+    Id,
+    #[serde(rename = "name")]
+    Name,
+
+    Include(ApiTokenInclude),
+}
+
+impl IntoColumn<entity::ApiTokenColumn> for ApiTokenFilter {
+    fn column(&self) -> Option<entity::ApiTokenColumn> {
+        match self {
+            ApiTokenFilter::Id => Some(entity::ApiTokenColumn::Id),
+            ApiTokenFilter::Name => Some(entity::ApiTokenColumn::Name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ConnectionFlow {
     Redirect,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ConnectionAttributes {
     pub homepage: Url,
     pub flow: ConnectionFlow,
 }
 
-#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ConnectionRelation {}
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ConnectionMetaAttributes {
     pub username: String,
     pub profile_url: Url,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct GenreAttributes {
     pub name: String,
     pub disambiguation: String,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum GenreRelation {
     Tracks,
     Releases,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum GenreInclude {
     #[serde(rename = "tracks")]
     Tracks,
@@ -707,7 +1371,7 @@ pub enum GenreInclude {
     ReleasesMediumsTracksGenres,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(untagged)]
 pub enum GenreFilter {
     #[serde(rename = "name")]
@@ -728,7 +1392,7 @@ impl IntoColumn<entity::GenreColumn> for GenreFilter {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct GenreMetaAttributes {
     pub count: i32,
 }
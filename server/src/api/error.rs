@@ -10,9 +10,13 @@ use crate::{
         internal::update::UpdateError, jsonapi::Error as JsonAPIError,
         tempo::connections::ConnectionError,
     },
+    hls::HlsError,
     import::ImportError,
+    playlist::PlaylistError,
     search::SearchError,
+    stats::StatsError,
     tasks::TaskError,
+    transcode::TranscodeError,
 };
 use base::setting::SettingsError;
 
@@ -31,6 +35,10 @@ pub enum Error {
     Unauthorized(Option<String>),
     #[error("Bad request")]
     BadRequest(Option<String>),
+    #[error("Conflict")]
+    Conflict(Option<String>),
+    #[error("Gone")]
+    Gone(Option<String>),
     #[error("Internal server error")]
     Internal(Option<String>),
 
@@ -51,6 +59,14 @@ pub enum Error {
     Update(#[from] UpdateError),
     #[error("Could not run import operation: {0}")]
     Import(#[from] ImportError),
+    #[error("Could not transcode: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Could not stream over HLS: {0}")]
+    Hls(#[from] HlsError),
+    #[error("Could not read the playlist: {0}")]
+    Playlist(#[from] PlaylistError),
+    #[error("Could not render the statistics: {0}")]
+    Stats(#[from] StatsError),
 
     #[error("Track does not have an associated path")]
     NoTrackPath,
@@ -59,12 +75,19 @@ pub enum Error {
 }
 
 impl Error {
-    fn status(&self) -> StatusCode {
+    pub fn status(&self) -> StatusCode {
         match self {
             Error::NotFound(_) => StatusCode::NOT_FOUND,
             Error::NotModified => StatusCode::NOT_MODIFIED,
             Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::Claims(ClaimsError::Unauthorized(_)) => StatusCode::UNAUTHORIZED,
             Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Gone(_) => StatusCode::GONE,
+            Error::Hls(HlsError::InvalidSegment(_)) => StatusCode::BAD_REQUEST,
+            Error::Hls(HlsError::Empty) => StatusCode::NOT_FOUND,
+            Error::Playlist(_) => StatusCode::BAD_REQUEST,
+            Error::Stats(StatsError::Empty) => StatusCode::NOT_FOUND,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -80,6 +103,8 @@ impl From<Error> for JsonAPIError {
                 Error::NotFound(o) => o.map(|e| e.into()),
                 Error::Unauthorized(Some(v)) => Some(v.into()),
                 Error::BadRequest(Some(v)) => Some(v.into()),
+                Error::Conflict(Some(v)) => Some(v.into()),
+                Error::Gone(Some(v)) => Some(v.into()),
                 Error::Internal(Some(v)) => Some(v.into()),
                 _ => None,
             },
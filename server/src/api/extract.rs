@@ -24,6 +24,9 @@ use super::jsonapi::Error as JsonAPIError;
 use base::setting::{get_settings, SettingsError};
 
 static HEADER_VALUE: &str = "application/vnd.api+json";
+/// The audience of the tokens issued at login. Each kind of token signed with the
+/// JWT secret has its own, so that none of them can be used in place of another.
+pub static LOGIN_AUDIENCE: &str = "login";
 
 pub struct Json<T>(pub T);
 
@@ -198,6 +201,7 @@ pub struct Claims {
     pub username: String,
     pub exp: usize,
     pub sub: ClaimsSubject,
+    pub aud: String,
 }
 
 #[derive(Debug, Error)]
@@ -250,13 +254,15 @@ where
             .await
             .ok()
         {
-            Some(TypedHeader(header)) => check_token(header.token()).map(|td| td.claims),
+            Some(TypedHeader(header)) => {
+                check_token(header.token(), LOGIN_AUDIENCE).map(|td| td.claims)
+            }
             None => match Query::<ClaimsQuery>::from_request_parts(parts, state)
                 .await
                 .ok()
             {
                 Some(Query(ClaimsQuery { authorization })) => {
-                    check_token(&authorization).map(|td| td.claims)
+                    check_token(&authorization, LOGIN_AUDIENCE).map(|td| td.claims)
                 }
                 None => Err(Self::Rejection::Missing),
             },
@@ -264,15 +270,17 @@ where
     }
 }
 
-pub fn check_token<T>(token: &str) -> Result<TokenData<T>, ClaimsError>
+pub fn check_token<T>(token: &str, audience: &str) -> Result<TokenData<T>, ClaimsError>
 where
     T: for<'de> Deserialize<'de> + std::fmt::Debug,
 {
     let settings = get_settings()?;
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[audience]);
     let claims = decode::<T>(
         token,
         &DecodingKey::from_secret(settings.auth.jwt_secret.as_ref()),
-        &Validation::new(Algorithm::HS256),
+        &validation,
     );
     match claims {
         Ok(token_data) => {
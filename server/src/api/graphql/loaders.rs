@@ -0,0 +1,328 @@
+use async_graphql::dataloader::Loader;
+use sea_orm::{
+    ColumnTrait, DbConn, DbErr, EntityTrait, FromQueryResult, LoaderTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use sea_query::Expr;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Loads the relations of all the objects requested at the same depth of a
+/// query at once, instead of issuing a query for each of them
+pub struct LibraryLoader {
+    pub db: DbConn,
+    /// Scrobbles are only counted for the user making the request
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReleaseOf(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MediumOf(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReleasesOfArtist(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArtistsOfRelease(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArtistsOfTrack(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MediumsOfRelease(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TracksOfMedium(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GenresOfTrack(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScrobblesOfTrack(pub Uuid);
+
+type Credits = Vec<(entity::ArtistCredit, entity::Artist)>;
+
+// Pairs each artist credit with the artist it refers to
+async fn credited_artists(
+    db: &DbConn,
+    credits: Vec<entity::ArtistCredit>,
+) -> Result<HashMap<String, (entity::ArtistCredit, entity::Artist)>, DbErr> {
+    let artists = credits.load_one(entity::ArtistEntity, db).await?;
+    Ok(credits
+        .into_iter()
+        .zip(artists)
+        .filter_map(|(credit, artist)| Some((credit.id.to_owned(), (credit, artist?))))
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl Loader<ReleaseOf> for LibraryLoader {
+    type Value = entity::Release;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[ReleaseOf],
+    ) -> Result<HashMap<ReleaseOf, Self::Value>, Self::Error> {
+        let releases = entity::ReleaseEntity::find()
+            .filter(entity::ReleaseColumn::Id.is_in(keys.iter().map(|key| key.0)))
+            .all(&self.db)
+            .await?;
+        Ok(releases
+            .into_iter()
+            .map(|release| (ReleaseOf(release.id), release))
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<MediumOf> for LibraryLoader {
+    type Value = entity::Medium;
+    type Error = Arc<DbErr>;
+
+    async fn load(&self, keys: &[MediumOf]) -> Result<HashMap<MediumOf, Self::Value>, Self::Error> {
+        let mediums = entity::MediumEntity::find()
+            .filter(entity::MediumColumn::Id.is_in(keys.iter().map(|key| key.0)))
+            .all(&self.db)
+            .await?;
+        Ok(mediums
+            .into_iter()
+            .map(|medium| (MediumOf(medium.id), medium))
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<ReleasesOfArtist> for LibraryLoader {
+    type Value = Vec<entity::Release>;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[ReleasesOfArtist],
+    ) -> Result<HashMap<ReleasesOfArtist, Self::Value>, Self::Error> {
+        let credits = entity::ArtistCreditEntity::find()
+            .filter(entity::ArtistCreditColumn::ArtistId.is_in(keys.iter().map(|key| key.0)))
+            .all(&self.db)
+            .await?;
+        let credit_releases = credits
+            .load_many(entity::ArtistCreditReleaseEntity, &self.db)
+            .await?;
+        let releases: HashMap<Uuid, entity::Release> = entity::ReleaseEntity::find()
+            .filter(
+                entity::ReleaseColumn::Id.is_in(
+                    credit_releases
+                        .iter()
+                        .flatten()
+                        .map(|credit_release| credit_release.release_id),
+                ),
+            )
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|release| (release.id, release))
+            .collect();
+
+        let mut found: HashMap<ReleasesOfArtist, Self::Value> = HashMap::new();
+        for (credit, credit_releases) in credits.iter().zip(credit_releases) {
+            let artist_releases = found.entry(ReleasesOfArtist(credit.artist_id)).or_default();
+            for credit_release in credit_releases {
+                if let Some(release) = releases.get(&credit_release.release_id) {
+                    if !artist_releases.iter().any(|r| r.id == release.id) {
+                        artist_releases.push(release.to_owned());
+                    }
+                }
+            }
+        }
+        for releases in found.values_mut() {
+            releases.sort_by(|a, b| a.year.cmp(&b.year).then_with(|| a.title.cmp(&b.title)));
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<ArtistsOfRelease> for LibraryLoader {
+    type Value = Credits;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[ArtistsOfRelease],
+    ) -> Result<HashMap<ArtistsOfRelease, Self::Value>, Self::Error> {
+        let credit_releases = entity::ArtistCreditReleaseEntity::find()
+            .filter(
+                entity::ArtistCreditReleaseColumn::ReleaseId.is_in(keys.iter().map(|key| key.0)),
+            )
+            .all(&self.db)
+            .await?;
+        let credits = credit_releases
+            .load_one(entity::ArtistCreditEntity, &self.db)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let credits = credited_artists(&self.db, credits).await?;
+
+        let mut found: HashMap<ArtistsOfRelease, Self::Value> = HashMap::new();
+        for credit_release in credit_releases {
+            if let Some(credit) = credits.get(&credit_release.artist_credit_id) {
+                found
+                    .entry(ArtistsOfRelease(credit_release.release_id))
+                    .or_default()
+                    .push(credit.to_owned());
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<ArtistsOfTrack> for LibraryLoader {
+    type Value = Credits;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[ArtistsOfTrack],
+    ) -> Result<HashMap<ArtistsOfTrack, Self::Value>, Self::Error> {
+        let credit_tracks = entity::ArtistCreditTrackEntity::find()
+            .filter(entity::ArtistCreditTrackColumn::TrackId.is_in(keys.iter().map(|key| key.0)))
+            .all(&self.db)
+            .await?;
+        let credits = credit_tracks
+            .load_one(entity::ArtistCreditEntity, &self.db)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let credits = credited_artists(&self.db, credits).await?;
+
+        let mut found: HashMap<ArtistsOfTrack, Self::Value> = HashMap::new();
+        for credit_track in credit_tracks {
+            if let Some(credit) = credits.get(&credit_track.artist_credit_id) {
+                found
+                    .entry(ArtistsOfTrack(credit_track.track_id))
+                    .or_default()
+                    .push(credit.to_owned());
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<MediumsOfRelease> for LibraryLoader {
+    type Value = Vec<entity::Medium>;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[MediumsOfRelease],
+    ) -> Result<HashMap<MediumsOfRelease, Self::Value>, Self::Error> {
+        let mediums = entity::MediumEntity::find()
+            .filter(entity::MediumColumn::ReleaseId.is_in(keys.iter().map(|key| key.0)))
+            .order_by_asc(entity::MediumColumn::Position)
+            .all(&self.db)
+            .await?;
+        let mut found: HashMap<MediumsOfRelease, Self::Value> = HashMap::new();
+        for medium in mediums {
+            found
+                .entry(MediumsOfRelease(medium.release_id))
+                .or_default()
+                .push(medium);
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<TracksOfMedium> for LibraryLoader {
+    type Value = Vec<entity::Track>;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[TracksOfMedium],
+    ) -> Result<HashMap<TracksOfMedium, Self::Value>, Self::Error> {
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::MediumId.is_in(keys.iter().map(|key| key.0)))
+            .order_by_asc(entity::TrackColumn::Number)
+            .all(&self.db)
+            .await?;
+        let mut found: HashMap<TracksOfMedium, Self::Value> = HashMap::new();
+        for track in tracks {
+            found
+                .entry(TracksOfMedium(track.medium_id))
+                .or_default()
+                .push(track);
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait::async_trait]
+impl Loader<GenresOfTrack> for LibraryLoader {
+    type Value = Vec<entity::Genre>;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[GenresOfTrack],
+    ) -> Result<HashMap<GenresOfTrack, Self::Value>, Self::Error> {
+        let genre_tracks = entity::GenreTrackEntity::find()
+            .filter(entity::GenreTrackColumn::TrackId.is_in(keys.iter().map(|key| key.0)))
+            .order_by_desc(entity::GenreTrackColumn::Cnt)
+            .all(&self.db)
+            .await?;
+        let genres = genre_tracks.load_one(entity::GenreEntity, &self.db).await?;
+        let mut found: HashMap<GenresOfTrack, Self::Value> = HashMap::new();
+        for (genre_track, genre) in genre_tracks.into_iter().zip(genres) {
+            if let Some(genre) = genre {
+                found
+                    .entry(GenresOfTrack(genre_track.track_id))
+                    .or_default()
+                    .push(genre);
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[derive(FromQueryResult)]
+struct ScrobbleCount {
+    track: Uuid,
+    count: i64,
+}
+
+#[async_trait::async_trait]
+impl Loader<ScrobblesOfTrack> for LibraryLoader {
+    type Value = i64;
+    type Error = Arc<DbErr>;
+
+    async fn load(
+        &self,
+        keys: &[ScrobblesOfTrack],
+    ) -> Result<HashMap<ScrobblesOfTrack, Self::Value>, Self::Error> {
+        let counts = entity::ScrobbleEntity::find()
+            .select_only()
+            .column(entity::ScrobbleColumn::Track)
+            .column_as(Expr::col(entity::ScrobbleColumn::Id).count(), "count")
+            .filter(ColumnTrait::eq(
+                &entity::ScrobbleColumn::User,
+                self.username.as_str(),
+            ))
+            .filter(entity::ScrobbleColumn::Track.is_in(keys.iter().map(|key| key.0)))
+            .group_by(entity::ScrobbleColumn::Track)
+            .into_model::<ScrobbleCount>()
+            .all(&self.db)
+            .await?;
+        Ok(counts
+            .into_iter()
+            .map(|count| (ScrobblesOfTrack(count.track), count.count))
+            .collect())
+    }
+}
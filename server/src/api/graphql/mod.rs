@@ -0,0 +1,60 @@
+mod loaders;
+mod objects;
+
+use async_graphql::{
+    dataloader::DataLoader, http::GraphiQLSource, EmptyMutation, EmptySubscription, Request,
+    Response as GraphQLResponse, Schema,
+};
+use axum::{
+    extract::{Json as AxumJson, State},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+
+use crate::api::{extract::Claims, AppState, Error};
+use base::setting::get_settings;
+use loaders::LibraryLoader;
+use objects::QueryRoot;
+
+/// Where the router is nested, for the explorer to send its queries to
+static PREFIX: &str = "/graphql";
+
+pub type LibrarySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn router() -> Router<AppState> {
+    let (max_depth, max_complexity) = get_settings().map_or((12, 10000), |settings| {
+        (settings.graphql.max_depth, settings.graphql.max_complexity)
+    });
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(max_depth)
+        .limit_complexity(max_complexity)
+        .finish();
+    Router::new()
+        .route("/", get(explorer).post(graphql))
+        .layer(Extension(schema))
+}
+
+async fn explorer() -> Result<Response, Error> {
+    match get_settings()?.graphql.explorer {
+        true => Ok(Html(GraphiQLSource::build().endpoint(PREFIX).finish()).into_response()),
+        false => Err(Error::NotFound(None)),
+    }
+}
+
+async fn graphql(
+    State(AppState(db)): State<AppState>,
+    Extension(schema): Extension<LibrarySchema>,
+    claims: Claims,
+    AxumJson(request): AxumJson<Request>,
+) -> AxumJson<GraphQLResponse> {
+    // loaders are per request, as scrobbles are counted for the requesting user
+    let loader = DataLoader::new(
+        LibraryLoader {
+            db: db.clone(),
+            username: claims.username,
+        },
+        tokio::spawn,
+    );
+    AxumJson(schema.execute(request.data(db).data(loader)).await)
+}
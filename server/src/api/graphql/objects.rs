@@ -0,0 +1,458 @@
+use async_graphql::{
+    connection::{query, Connection, CursorType, Edge},
+    dataloader::DataLoader,
+    Context, Object, OutputType, Result,
+};
+use sea_orm::{CursorTrait, DbConn, EntityTrait};
+use uuid::Uuid;
+
+use super::loaders::{
+    ArtistsOfRelease, ArtistsOfTrack, GenresOfTrack, LibraryLoader, MediumOf, MediumsOfRelease,
+    ReleaseOf, ReleasesOfArtist, ScrobblesOfTrack, TracksOfMedium,
+};
+
+static DEFAULT_PAGE_SIZE: usize = 20;
+static MAX_PAGE_SIZE: usize = 100;
+
+fn loader<'a>(ctx: &Context<'a>) -> Result<&'a DataLoader<LibraryLoader>> {
+    ctx.data::<DataLoader<LibraryLoader>>()
+}
+
+/// The number of nodes in a page, to weigh the complexity of the fields below it
+fn page_size(first: Option<i32>, last: Option<i32>) -> usize {
+    first
+        .or(last)
+        .map_or(DEFAULT_PAGE_SIZE, |size| size.max(0) as usize)
+        .min(MAX_PAGE_SIZE)
+}
+
+/// Nested lists aren't paginated but are cut at `limit` items, at most
+/// `MAX_PAGE_SIZE`
+fn nested_size(limit: Option<i32>) -> usize {
+    limit.map_or(MAX_PAGE_SIZE, |limit| {
+        (limit.max(0) as usize).min(MAX_PAGE_SIZE)
+    })
+}
+
+fn nested<T>(items: Vec<T>, limit: Option<i32>) -> impl Iterator<Item = T> {
+    items.into_iter().take(nested_size(limit))
+}
+
+/// Connections are paginated by id, as in the JSON:API endpoints
+pub struct IdCursor(Uuid);
+
+impl CursorType for IdCursor {
+    type Error = uuid::Error;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        Uuid::parse_str(s).map(IdCursor)
+    }
+
+    fn encode_cursor(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+async fn paginate<E, N, F>(
+    ctx: &Context<'_>,
+    column: E::Column,
+    (after, before, first, last): (Option<String>, Option<String>, Option<i32>, Option<i32>),
+    node: F,
+) -> Result<Connection<IdCursor, N>>
+where
+    E: EntityTrait,
+    N: OutputType,
+    F: Fn(E::Model) -> (Uuid, N),
+{
+    let db = ctx.data::<DbConn>()?;
+    query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<IdCursor>, before: Option<IdCursor>, first, last| async move {
+            let size = first
+                .or(last)
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .min(MAX_PAGE_SIZE);
+            let backwards = first.is_none() && last.is_some();
+            let (has_after, has_before) = (after.is_some(), before.is_some());
+
+            let mut cursor = E::find().cursor_by(column);
+            if let Some(IdCursor(id)) = after {
+                cursor.after(id);
+            }
+            if let Some(IdCursor(id)) = before {
+                cursor.before(id);
+            }
+            // one more than requested, to know if there are more pages
+            match backwards {
+                true => cursor.last(size as u64 + 1),
+                false => cursor.first(size as u64 + 1),
+            };
+            let mut models = cursor.all(db).await?;
+            let more = models.len() > size;
+            if more {
+                match backwards {
+                    true => {
+                        models.remove(0);
+                    }
+                    false => {
+                        models.pop();
+                    }
+                }
+            }
+
+            let mut connection = match backwards {
+                true => Connection::new(more, has_before),
+                false => Connection::new(has_after, more),
+            };
+            connection.edges.extend(models.into_iter().map(|model| {
+                let (id, node) = node(model);
+                Edge::new(IdCursor(id), node)
+            }));
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn artists(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<IdCursor, Artist>> {
+        paginate::<entity::ArtistEntity, _, _>(
+            ctx,
+            entity::ArtistColumn::Id,
+            (after, before, first, last),
+            |artist| (artist.id, Artist(artist)),
+        )
+        .await
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn releases(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<IdCursor, Release>> {
+        paginate::<entity::ReleaseEntity, _, _>(
+            ctx,
+            entity::ReleaseColumn::Id,
+            (after, before, first, last),
+            |release| (release.id, Release(release)),
+        )
+        .await
+    }
+
+    #[graphql(complexity = "page_size(first, last) * child_complexity")]
+    async fn tracks(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<IdCursor, Track>> {
+        paginate::<entity::TrackEntity, _, _>(
+            ctx,
+            entity::TrackColumn::Id,
+            (after, before, first, last),
+            |track| (track.id, Track(track)),
+        )
+        .await
+    }
+
+    async fn artist(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Artist>> {
+        let db = ctx.data::<DbConn>()?;
+        Ok(entity::ArtistEntity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(Artist))
+    }
+
+    async fn release(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Release>> {
+        Ok(loader(ctx)?.load_one(ReleaseOf(id)).await?.map(Release))
+    }
+
+    async fn medium(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Medium>> {
+        Ok(loader(ctx)?.load_one(MediumOf(id)).await?.map(Medium))
+    }
+
+    async fn track(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Track>> {
+        let db = ctx.data::<DbConn>()?;
+        Ok(entity::TrackEntity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(Track))
+    }
+}
+
+pub struct Artist(entity::Artist);
+
+#[Object]
+impl Artist {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn sort_name(&self) -> &str {
+        &self.0.sort_name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    /// The releases the artist is credited on, by year
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn releases(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<Release>> {
+        let releases = loader(ctx)?
+            .load_one(ReleasesOfArtist(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(releases, limit).map(Release).collect())
+    }
+}
+
+pub struct ArtistCredit(entity::ArtistCredit, entity::Artist);
+
+#[Object]
+impl ArtistCredit {
+    async fn join_phrase(&self) -> Option<&str> {
+        self.0.join_phrase.as_deref()
+    }
+
+    async fn artist(&self) -> Artist {
+        Artist(self.1.to_owned())
+    }
+}
+
+pub struct Release(entity::Release);
+
+#[Object]
+impl Release {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn disambiguation(&self) -> Option<&str> {
+        self.0.disambiguation.as_deref()
+    }
+
+    async fn release_type(&self) -> Option<&str> {
+        self.0.release_type.as_deref()
+    }
+
+    async fn status(&self) -> Option<&str> {
+        self.0.status.as_deref()
+    }
+
+    async fn country(&self) -> Option<&str> {
+        self.0.country.as_deref()
+    }
+
+    async fn label(&self) -> Option<&str> {
+        self.0.label.as_deref()
+    }
+
+    async fn catalog_no(&self) -> Option<&str> {
+        self.0.catalog_no.as_deref()
+    }
+
+    async fn year(&self) -> Option<i32> {
+        self.0.year
+    }
+
+    async fn month(&self) -> Option<i16> {
+        self.0.month
+    }
+
+    async fn day(&self) -> Option<i16> {
+        self.0.day
+    }
+
+    async fn original_year(&self) -> Option<i32> {
+        self.0.original_year
+    }
+
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn artists(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<ArtistCredit>> {
+        let credits = loader(ctx)?
+            .load_one(ArtistsOfRelease(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(credits, limit)
+            .map(|(credit, artist)| ArtistCredit(credit, artist))
+            .collect())
+    }
+
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn mediums(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<Medium>> {
+        let mediums = loader(ctx)?
+            .load_one(MediumsOfRelease(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(mediums, limit).map(Medium).collect())
+    }
+}
+
+pub struct Medium(entity::Medium);
+
+#[Object]
+impl Medium {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn position(&self) -> i32 {
+        self.0.position
+    }
+
+    async fn format(&self) -> Option<&str> {
+        self.0.format.as_deref()
+    }
+
+    async fn track_count(&self) -> i32 {
+        self.0.tracks
+    }
+
+    async fn release(&self, ctx: &Context<'_>) -> Result<Option<Release>> {
+        Ok(loader(ctx)?
+            .load_one(ReleaseOf(self.0.release_id))
+            .await?
+            .map(Release))
+    }
+
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn tracks(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<Track>> {
+        let tracks = loader(ctx)?
+            .load_one(TracksOfMedium(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(tracks, limit).map(Track).collect())
+    }
+}
+
+pub struct Track(entity::Track);
+
+#[Object]
+impl Track {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    /// In milliseconds
+    async fn length(&self) -> i32 {
+        self.0.length
+    }
+
+    async fn number(&self) -> i32 {
+        self.0.number
+    }
+
+    async fn recording_id(&self) -> Uuid {
+        self.0.recording_id
+    }
+
+    async fn mimetype(&self) -> Option<String> {
+        self.0.format.map(|format| format.mime().to_string())
+    }
+
+    async fn bpm(&self) -> Option<i32> {
+        self.0.bpm
+    }
+
+    async fn bitrate(&self) -> Option<i32> {
+        self.0.bitrate
+    }
+
+    async fn bitdepth(&self) -> Option<i32> {
+        self.0.bitdepth
+    }
+
+    async fn channels(&self) -> Option<i32> {
+        self.0.channels
+    }
+
+    async fn size(&self) -> Option<i64> {
+        self.0.size
+    }
+
+    async fn medium(&self, ctx: &Context<'_>) -> Result<Option<Medium>> {
+        Ok(loader(ctx)?
+            .load_one(MediumOf(self.0.medium_id))
+            .await?
+            .map(Medium))
+    }
+
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn artists(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<ArtistCredit>> {
+        let credits = loader(ctx)?
+            .load_one(ArtistsOfTrack(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(credits, limit)
+            .map(|(credit, artist)| ArtistCredit(credit, artist))
+            .collect())
+    }
+
+    /// Most relevant first
+    #[graphql(complexity = "nested_size(limit) * child_complexity")]
+    async fn genres(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<Genre>> {
+        let genres = loader(ctx)?
+            .load_one(GenresOfTrack(self.0.id))
+            .await?
+            .unwrap_or_default();
+        Ok(nested(genres, limit).map(Genre).collect())
+    }
+
+    /// How many times the requesting user has listened to the track
+    async fn scrobbles(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(loader(ctx)?
+            .load_one(ScrobblesOfTrack(self.0.id))
+            .await?
+            .unwrap_or_default())
+    }
+}
+
+pub struct Genre(entity::Genre);
+
+#[Object]
+impl Genre {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn disambiguation(&self) -> &str {
+        &self.0.disambiguation
+    }
+}
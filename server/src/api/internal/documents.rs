@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 use time::OffsetDateTime;
@@ -6,48 +7,48 @@ use uuid::Uuid;
 use crate::api::jsonapi::{InsertResource, Resource, UpdateResource};
 use entity::{InternalRelease, InternalTrack};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum ResourceType {
     Internal(InternalResourceType),
     Tempo(crate::api::documents::ResourceType),
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum InternalResourceType {
     Directory,
     Import,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct FileEntry {
     pub name: String,
     pub path: PathBuf,
     pub size: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct DirectoryAttributes {
     pub name: String,
     pub path: PathBuf,
     pub files: Vec<FileEntry>,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum DirectoryRelation {
     Directories,
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum DirectoryMeta {}
 
 pub type DirectoryResource =
     Resource<ResourceType, String, DirectoryAttributes, DirectoryRelation, DirectoryMeta>;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ImportAttributes {
     pub source_release: InternalRelease,
     pub source_tracks: Vec<InternalTrack>,
@@ -60,34 +61,36 @@ pub struct ImportAttributes {
     pub selected_cover: Option<i32>,
 
     #[serde(with = "time::serde::iso8601")]
+    #[schemars(with = "String")]
     pub started_at: OffsetDateTime,
     #[serde(with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
     pub ended_at: Option<OffsetDateTime>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InsertImportAttributes {
     pub directory: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct UpdateImportRelease {
     pub selected_release: Uuid,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct UpdateImportCover {
     pub selected_cover: i32,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum UpdateImportAttributes {
     Release(UpdateImportRelease),
     Cover(UpdateImportCover),
 }
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ImportRelation {
     Directory,
@@ -97,14 +100,14 @@ pub enum ImportRelation {
     Artists,
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq, JsonSchema)]
 pub enum ImportInclude {}
 
-#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ImportMeta {}
 
-#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ImportFilter {}
 
@@ -115,7 +118,7 @@ pub type InsertImportResource =
 pub type UpdateImportResource =
     UpdateResource<ResourceType, Uuid, UpdateImportAttributes, ImportRelation, ImportMeta>;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum Included {
     Directory(DirectoryResource),
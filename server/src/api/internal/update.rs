@@ -1,5 +1,6 @@
 use axum::extract::State;
 use eyre::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use taskie_client::InsertTask;
@@ -7,22 +8,29 @@ use thiserror::Error;
 
 use crate::api::{extract::Path, AppState, Error};
 use crate::tasks::{
-    artist_description, artist_url, index_search, lastfm_artist_image, push, TaskEntities, TaskName,
+    artist_description, artist_url, index_search, lastfm_artist_image, lyrics, push, replay_gain,
+    waveform, TaskEntities, TaskName,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
 #[serde(untagged)]
 pub enum UpdateType {
     Artist(entity::UpdateArtistType),
     Other(OtherUpdateType),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
 pub enum OtherUpdateType {
     #[serde(rename = "all")]
     All,
     #[serde(rename = "index_search")]
     IndexSearch,
+    #[serde(rename = "replay_gain")]
+    ReplayGain,
+    #[serde(rename = "waveform")]
+    Waveform,
+    #[serde(rename = "lyrics")]
+    Lyrics,
 }
 
 #[derive(Error, Debug)]
@@ -39,6 +47,9 @@ fn unpack_all(update_type: UpdateType) -> Vec<UpdateType> {
             UpdateType::Artist(entity::UpdateArtistType::ArtistDescription),
             UpdateType::Artist(entity::UpdateArtistType::LastFMArtistImage),
             UpdateType::Other(OtherUpdateType::IndexSearch),
+            UpdateType::Other(OtherUpdateType::ReplayGain),
+            UpdateType::Other(OtherUpdateType::Waveform),
+            UpdateType::Other(OtherUpdateType::Lyrics),
         ],
         u => vec![u],
     }
@@ -54,7 +65,10 @@ where
             name,
             payload: Some(json!(data)),
             depends_on: vec![],
-            duration: time::Duration::seconds(60),
+            duration: match name {
+                TaskName::ReplayGain => time::Duration::seconds(600),
+                _ => time::Duration::seconds(60),
+            },
         })
         .collect()
 }
@@ -94,6 +108,15 @@ pub async fn all(
             UpdateType::Other(OtherUpdateType::IndexSearch) => {
                 insert_all_task!(&db, IndexSearch, index_search)
             }
+            UpdateType::Other(OtherUpdateType::ReplayGain) => {
+                insert_all_task!(&db, ReplayGain, replay_gain)
+            }
+            UpdateType::Other(OtherUpdateType::Waveform) => {
+                insert_all_task!(&db, Waveform, waveform)
+            }
+            UpdateType::Other(OtherUpdateType::Lyrics) => {
+                insert_all_task!(&db, Lyrics, lyrics)
+            }
             _ => unreachable!(),
         };
         tracing::info!(?tasks, "Queueing the update tasks");
@@ -128,6 +151,15 @@ pub async fn outdated(
             UpdateType::Other(OtherUpdateType::IndexSearch) => {
                 insert_outdated_task!(&db, IndexSearch, index_search)
             }
+            UpdateType::Other(OtherUpdateType::ReplayGain) => {
+                insert_outdated_task!(&db, ReplayGain, replay_gain)
+            }
+            UpdateType::Other(OtherUpdateType::Waveform) => {
+                insert_outdated_task!(&db, Waveform, waveform)
+            }
+            UpdateType::Other(OtherUpdateType::Lyrics) => {
+                insert_outdated_task!(&db, Lyrics, lyrics)
+            }
             _ => unreachable!(),
         };
 
@@ -7,6 +7,7 @@ use axum::{
 };
 use eyre::ErrReport;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use sea_orm::{
     sea_query::{IntoIden, IntoValueTuple},
     ColumnTrait, Cursor, Order, SelectorTrait,
@@ -20,7 +21,7 @@ use entity::ConnectionProvider;
 
 pub static DEFAULT_PAGE_SIZE: u32 = 10;
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum LinkKey {
     Prev,
@@ -29,7 +30,7 @@ pub enum LinkKey {
     Last,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Document<R, I> {
     pub data: DocumentData<R>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -38,44 +39,44 @@ pub struct Document<R, I> {
     pub links: HashMap<LinkKey, String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InsertDocument<R> {
     pub data: DocumentData<R>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InsertOneDocument<R> {
     pub data: R,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InsertOneRelation<R> {
     pub data: R,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InsertManyRelation<R> {
     pub data: Vec<R>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct UpdateDocument<R> {
     pub data: DocumentData<R>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct UpdateOneDocument<R> {
     pub data: R,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum DocumentData<R> {
     Single(R),
     Multi(Vec<R>),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct Resource<RT, I, T, R: Eq + Hash, M> {
     pub r#type: RT,
     pub id: I,
@@ -87,7 +88,7 @@ pub struct Resource<RT, I, T, R: Eq + Hash, M> {
     pub meta: Option<M>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct InsertResource<RT, T, R: Eq + Hash, M> {
     pub r#type: RT,
     pub attributes: T,
@@ -98,7 +99,7 @@ pub struct InsertResource<RT, T, R: Eq + Hash, M> {
     pub meta: Option<M>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct UpdateResource<RT, I, T, R: Eq + Hash, M> {
     pub r#type: RT,
     pub id: I,
@@ -110,19 +111,19 @@ pub struct UpdateResource<RT, I, T, R: Eq + Hash, M> {
     pub meta: Option<M>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct Relationship<RT, M> {
     pub data: Relation<RT, M>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum Relation<RT, M> {
     Single(Related<RT, M>),
     Multi(Vec<Related<RT, M>>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum Related<RT, M> {
     Uuid(ResourceIdentifier<RT, Uuid, M>),
@@ -131,7 +132,7 @@ pub enum Related<RT, M> {
     ConnectionProvider(ResourceIdentifier<RT, ConnectionProvider, M>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ResourceIdentifier<RT, I, M> {
     pub r#type: RT,
     pub id: I,
@@ -162,7 +163,7 @@ impl From<ErrReport> for Error {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SerializableError {
     pub status: u16,
     pub title: String,
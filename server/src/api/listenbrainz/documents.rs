@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::tempo::scrobbles::TrackQuery;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenType {
+    Single,
+    PlayingNow,
+    Import,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubmitListens {
+    pub listen_type: ListenType,
+    pub payload: Vec<Listen>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Listen {
+    /// Unix timestamp, in seconds. Missing for listens which are playing now.
+    pub listened_at: Option<i64>,
+    pub track_metadata: TrackMetadata,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TrackMetadata {
+    pub artist_name: String,
+    pub track_name: String,
+    pub release_name: Option<String>,
+    #[serde(default)]
+    pub additional_info: AdditionalInfo,
+}
+
+impl TrackMetadata {
+    pub fn query(&self) -> TrackQuery<'_> {
+        TrackQuery {
+            artist: &self.artist_name,
+            title: &self.track_name,
+            release: self.release_name.as_deref(),
+            track_mbid: self.additional_info.track_mbid(),
+            recording_mbid: self.additional_info.recording_mbid(),
+            release_mbid: self.additional_info.release_mbid(),
+        }
+    }
+}
+
+// Clients are sloppy with the optional fields, sending empty strings and the like,
+// so MBIDs are parsed leniently and invalid values are just ignored
+#[derive(Deserialize, Debug, Default)]
+pub struct AdditionalInfo {
+    recording_mbid: Option<String>,
+    track_mbid: Option<String>,
+    release_mbid: Option<String>,
+    pub duration_ms: Option<i64>,
+    /// In seconds, the older alternative to duration_ms
+    pub duration: Option<i64>,
+    pub submission_client: Option<String>,
+    pub media_player: Option<String>,
+}
+
+fn mbid(value: &Option<String>) -> Option<Uuid> {
+    value
+        .as_deref()
+        .and_then(|v| Uuid::parse_str(v.trim()).ok())
+}
+
+impl AdditionalInfo {
+    pub fn recording_mbid(&self) -> Option<Uuid> {
+        mbid(&self.recording_mbid)
+    }
+
+    pub fn track_mbid(&self) -> Option<Uuid> {
+        mbid(&self.track_mbid)
+    }
+
+    pub fn release_mbid(&self) -> Option<Uuid> {
+        mbid(&self.release_mbid)
+    }
+
+    /// The length of the track in milliseconds, from either of the fields
+    pub fn length(&self) -> Option<i32> {
+        self.duration_ms
+            .or_else(|| self.duration.map(|d| d.saturating_mul(1000)))
+            .and_then(|d| i32::try_from(d).ok())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Status {
+    pub status: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ValidateToken {
+    pub code: u16,
+    pub message: &'static str,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_name: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorBody {
+    pub code: u16,
+    pub error: String,
+}
@@ -0,0 +1,260 @@
+pub mod documents;
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{ActiveValue, ConnectionTrait, DbErr, EntityTrait, TransactionTrait};
+use serde::Deserialize;
+use thiserror::Error;
+use time::OffsetDateTime;
+
+use crate::api::{
+    auth::{authenticate_api_token, AuthError},
+    tempo::{
+        now_playing,
+        scrobbles::{match_track, schedule_scrobble_tasks},
+        stats,
+    },
+    AppState, Error,
+};
+use documents::{ErrorBody, Listen, ListenType, Status, SubmitListens, ValidateToken};
+
+/// The maximum number of listens accepted in a single request, as in ListenBrainz
+static MAX_LISTENS_PER_REQUEST: usize = 1000;
+
+/// The client name used for now playing sessions when the listen doesn't have one
+static DEFAULT_CLIENT: &str = "listenbrainz";
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/1/submit-listens", post(submit_listens))
+        .route("/1/validate-token", get(validate_token))
+}
+
+#[derive(Error, Debug)]
+pub enum ListenBrainzError {
+    #[error("You need to provide an Authorization header.")]
+    MissingToken,
+    #[error("Invalid authorization token.")]
+    InvalidToken,
+    #[error("{0}")]
+    InvalidListens(String),
+    #[error("{0}")]
+    Api(#[from] Error),
+}
+
+impl From<DbErr> for ListenBrainzError {
+    fn from(value: DbErr) -> Self {
+        Self::Api(value.into())
+    }
+}
+
+impl IntoResponse for ListenBrainzError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ListenBrainzError::MissingToken | ListenBrainzError::InvalidToken => {
+                StatusCode::UNAUTHORIZED
+            }
+            ListenBrainzError::InvalidListens(_) => StatusCode::BAD_REQUEST,
+            ListenBrainzError::Api(error) => error.status(),
+        };
+        if status.is_server_error() {
+            tracing::warn!(error = %self, "ListenBrainz request failed");
+        }
+        let body = ErrorBody {
+            code: status.as_u16(),
+            error: self.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+fn invalid<S: Into<String>>(message: S) -> ListenBrainzError {
+    ListenBrainzError::InvalidListens(message.into())
+}
+
+// Tokens are sent as `Authorization: Token <token>`, and they are the API tokens
+// users create for their clients
+fn token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Token "))
+        .map(str::trim)
+}
+
+async fn authenticate<C>(db: &C, token: &str) -> Result<String, ListenBrainzError>
+where
+    C: ConnectionTrait,
+{
+    authenticate_api_token(db, token)
+        .await
+        .map_err(|error| match error {
+            AuthError::Database(error) => error.into(),
+            _ => ListenBrainzError::InvalidToken,
+        })
+}
+
+#[derive(Deserialize)]
+pub struct ValidateTokenQuery {
+    token: Option<String>,
+}
+
+pub async fn validate_token(
+    State(AppState(db)): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ValidateTokenQuery>,
+) -> Result<Json<ValidateToken>, ListenBrainzError> {
+    let token = token(&headers)
+        .or(query.token.as_deref())
+        .ok_or(ListenBrainzError::MissingToken)?;
+    Ok(Json(match authenticate(&db, token).await {
+        Ok(username) => ValidateToken {
+            code: 200,
+            message: "Token valid.",
+            valid: true,
+            user_name: Some(username),
+        },
+        Err(ListenBrainzError::InvalidToken) => ValidateToken {
+            code: 200,
+            message: "Token invalid.",
+            valid: false,
+            user_name: None,
+        },
+        Err(error) => return Err(error),
+    }))
+}
+
+// Follows the rules of ListenBrainz, so that clients get the same errors
+fn validate(submission: &SubmitListens) -> Result<(), ListenBrainzError> {
+    if submission.payload.is_empty() {
+        return Err(invalid("JSON document does not contain any listens"));
+    }
+    if submission.payload.len() > MAX_LISTENS_PER_REQUEST {
+        return Err(invalid(format!(
+            "Too many listens. You may not submit more than {} listens at once.",
+            MAX_LISTENS_PER_REQUEST
+        )));
+    }
+    match submission.listen_type {
+        ListenType::Single | ListenType::PlayingNow if submission.payload.len() != 1 => Err(
+            invalid("JSON document must contain exactly one listen for this listen type"),
+        ),
+        ListenType::PlayingNow if submission.payload[0].listened_at.is_some() => Err(invalid(
+            "JSON document must not contain listened_at for playing_now",
+        )),
+        ListenType::Single | ListenType::Import
+            if submission.payload.iter().any(|l| l.listened_at.is_none()) =>
+        {
+            Err(invalid("JSON document must contain listened_at"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn client(listen: &Listen) -> Option<String> {
+    let info = &listen.track_metadata.additional_info;
+    info.submission_client
+        .clone()
+        .or_else(|| info.media_player.clone())
+}
+
+async fn submit<C>(
+    db: &C,
+    username: &str,
+    submission: SubmitListens,
+) -> Result<(), ListenBrainzError>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    validate(&submission)?;
+    if submission.listen_type == ListenType::PlayingNow {
+        let listen = &submission.payload[0];
+        // there's nowhere to keep unknown tracks which are playing
+        if let Some(track) = match_track(db, &listen.track_metadata.query()).await? {
+            let client = client(listen).unwrap_or_else(|| DEFAULT_CLIENT.to_string());
+            now_playing::report(db, username, &client, track.id, 0, true).await?;
+        }
+        return Ok(());
+    }
+
+    let tx = db.begin().await?;
+    let mut scrobbles = Vec::new();
+    let mut unmatched = Vec::new();
+    for listen in submission.payload.iter() {
+        let at = listen
+            .listened_at
+            .and_then(|at| OffsetDateTime::from_unix_timestamp(at).ok())
+            .ok_or_else(|| invalid("Value for key listened_at is invalid."))?;
+        let metadata = &listen.track_metadata;
+        match match_track(&tx, &metadata.query()).await? {
+            Some(track) => scrobbles.push((track.id, at)),
+            None => unmatched.push(entity::UnmatchedListenActive {
+                id: ActiveValue::NotSet,
+                user: ActiveValue::Set(username.to_owned()),
+                listened_at: ActiveValue::Set(at),
+                artist_name: ActiveValue::Set(metadata.artist_name.to_owned()),
+                track_name: ActiveValue::Set(metadata.track_name.to_owned()),
+                release_name: ActiveValue::Set(metadata.release_name.to_owned()),
+                recording_mbid: ActiveValue::Set(metadata.additional_info.recording_mbid()),
+                track_mbid: ActiveValue::Set(metadata.additional_info.track_mbid()),
+                release_mbid: ActiveValue::Set(metadata.additional_info.release_mbid()),
+                duration: ActiveValue::Set(metadata.additional_info.length()),
+                submission_client: ActiveValue::Set(client(listen)),
+            }),
+        }
+    }
+    tracing::info!(
+        user = %username,
+        matched = scrobbles.len(),
+        unmatched = unmatched.len(),
+        "Received ListenBrainz listens"
+    );
+    if !scrobbles.is_empty() {
+        entity::ScrobbleEntity::insert_many(scrobbles.iter().map(|(id, at)| {
+            entity::ScrobbleActive {
+                id: ActiveValue::NotSet,
+                at: ActiveValue::Set(*at),
+                user: ActiveValue::Set(username.to_owned()),
+                track: ActiveValue::Set(*id),
+            }
+        }))
+        .exec(&tx)
+        .await?;
+    }
+    if !unmatched.is_empty() {
+        entity::UnmatchedListenEntity::insert_many(unmatched)
+            .exec(&tx)
+            .await?;
+    }
+    tx.commit().await?;
+    if !scrobbles.is_empty() {
+        stats::invalidate(username).await;
+    }
+
+    // imports are history, which other services most likely already know about
+    if submission.listen_type == ListenType::Single && !scrobbles.is_empty() {
+        if let Err(error) = schedule_scrobble_tasks(username, scrobbles.into_iter()).await {
+            tracing::warn!(%error, user = %username, "Could not schedule scrobble tasks");
+        }
+    }
+    Ok(())
+}
+
+pub async fn submit_listens(
+    State(AppState(db)): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Status>, ListenBrainzError> {
+    let token = token(&headers).ok_or(ListenBrainzError::MissingToken)?;
+    let username = authenticate(&db, token).await?;
+    let submission: SubmitListens = serde_json::from_slice(&body)
+        .map_err(|error| invalid(format!("Invalid JSON document submitted: {}", error)))?;
+    submit(&db, &username, submission).await?;
+    Ok(Json(Status { status: "ok" }))
+}
@@ -1,9 +1,19 @@
+mod audioscrobbler;
 pub mod auth;
+mod dav;
+#[cfg(feature = "dlna")]
+pub mod dlna;
 pub mod documents;
 pub mod error;
 pub mod extract;
+#[cfg(feature = "graphql")]
+mod graphql;
 mod internal;
 pub mod jsonapi;
+mod listenbrainz;
+mod openapi;
+mod share;
+mod subsonic;
 mod tempo;
 
 use axum::{
@@ -11,6 +21,7 @@ use axum::{
         header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
         Method,
     },
+    routing::get,
     Router,
 };
 use base::database::get_database;
@@ -34,16 +45,38 @@ pub fn router() -> Result<Router> {
             Method::POST,
             Method::PUT,
             Method::PATCH,
+            Method::DELETE,
         ])
         .allow_origin(AllowOrigin::mirror_request())
         .allow_credentials(true)
         .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
     let tracing = TraceLayer::new_for_http();
     let conn = get_database()?.clone();
-    Ok(Router::new()
+    let router = Router::new()
         .nest("/tempo", tempo::router())
         .nest("/internal", internal::router())
-        .layer(cors)
-        .layer(tracing)
-        .with_state(AppState(conn)))
+        .nest("/share", share::router())
+        .nest("/rest", subsonic::router())
+        .nest("/listenbrainz", listenbrainz::router())
+        .nest("/audioscrobbler", audioscrobbler::router())
+        .route("/openapi.json", get(openapi::openapi));
+    let router = match base::setting::get_settings()?.openapi.docs {
+        true => router.route("/docs", get(openapi::docs)),
+        false => router,
+    };
+    let router = match base::setting::get_settings()?.dav.enabled {
+        true => router.nest("/dav", dav::router()),
+        false => router,
+    };
+    #[cfg(feature = "graphql")]
+    let router = match base::setting::get_settings()?.graphql.enabled {
+        true => router.nest("/graphql", graphql::router()),
+        false => router,
+    };
+    #[cfg(feature = "dlna")]
+    let router = match base::setting::get_settings()?.dlna.enabled {
+        true => router.nest("/dlna", dlna::router()),
+        false => router,
+    };
+    Ok(router.layer(cors).layer(tracing).with_state(AppState(conn)))
 }
@@ -0,0 +1,1057 @@
+use axum::{response::Html, Json};
+use lazy_static::lazy_static;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    JsonSchema,
+};
+use sea_orm::{ColumnTrait, IdenStatic, Iterable};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::api::{
+    auth::LoginData,
+    documents::{
+        ApiTokenFilter, ApiTokenInclude, ApiTokenResource, ArtistFilter, ArtistInclude,
+        ArtistResource, AuthResource, ConnectionResource, GenreFilter, GenreInclude, GenreResource,
+        ImageResource, Included, InsertApiTokenResource, InsertNowPlayingResource,
+        InsertPlaylistResource, InsertScrobbleResource, InsertShareResource,
+        InsertSmartPlaylistResource, MediumFilter, MediumInclude, MediumResource, Meta,
+        NowPlayingFilter, NowPlayingInclude, NowPlayingResource, PlayQueueInclude,
+        PlayQueueResource, PlaylistFilter, PlaylistImportInclude, PlaylistImportResource,
+        PlaylistInclude, PlaylistResource, ReleaseFilter, ReleaseInclude, ReleaseResource,
+        ResourceType, ScrobbleFilter, ScrobbleInclude, ScrobbleResource, ServerResource,
+        ShareFilter, ShareInclude, ShareResource, SmartPlaylistFilter, SmartPlaylistInclude,
+        SmartPlaylistResource, StatsResource, TrackFilter, TrackInclude, TrackResource,
+        UnmatchedListenFilter, UnmatchedListenInclude, UnmatchedListenResource,
+        UpdatePlayQueueResource, UpdatePlaylistResource, UpdateSmartPlaylistResource, UserInclude,
+        UserMetaAttributes, UserRelation, UserResource, YearReviewResource,
+    },
+    internal::{
+        documents::{
+            DirectoryResource, ImportFilter, ImportInclude, ImportResource,
+            Included as InternalIncluded, InsertImportResource, UpdateImportResource,
+        },
+        update::UpdateType,
+    },
+    jsonapi::{
+        Document, InsertDocument, InsertManyRelation, InsertOneDocument, InsertOneRelation,
+        Related, ResourceIdentifier, SerializableError, UpdateOneDocument, DEFAULT_PAGE_SIZE,
+    },
+    tempo::{
+        connections::CallbackOptions,
+        playlists::{ExportQuery, ImportQuery, InsertTracksQuery, MoveTrackQuery},
+        releases::ArchiveQuery,
+        search::{SearchQuery, SearchResult},
+        stats::StatsQuery,
+        tracks::{AudioQuery, LyricsDocument, WaveformDocument, WaveformQuery},
+        users::InsertExactlyOneRelation,
+    },
+};
+use crate::{hls, playlist::PlaylistFormat};
+use base::setting::get_settings;
+use base::util::escape_xml;
+use entity::SmartPlaylistColumn;
+
+static JSONAPI_MIME: &str = "application/vnd.api+json";
+static JSON_MIME: &str = "application/json";
+static MAX_PAGE_SIZE: u32 = 20;
+
+lazy_static! {
+    static ref SPEC: Value = spec();
+}
+
+/// Collects the schemas referenced by the operations, so that each type is only
+/// described once in the components of the document
+struct Spec {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl Spec {
+    fn new() -> Self {
+        // OpenAPI 3.1 schemas are JSON Schema 2020-12, of which 2019-09 is a subset
+        let settings = SchemaSettings::draft2019_09().with(|settings| {
+            settings.definitions_path = "#/components/schemas/".to_string();
+            settings.meta_schema = None;
+        });
+        Self {
+            generator: SchemaGenerator::new(settings),
+            paths: Map::new(),
+        }
+    }
+
+    fn schema<T: JsonSchema>(&mut self) -> Value {
+        serde_json::to_value(self.generator.subschema_for::<T>()).unwrap_or_default()
+    }
+
+    // The schema of the type itself rather than a reference to it, to inspect it
+    fn inline<T: JsonSchema>(&mut self) -> Value {
+        serde_json::to_value(T::json_schema(&mut self.generator)).unwrap_or_default()
+    }
+
+    fn add(&mut self, method: &str, path: &str, operation: Operation) {
+        let error = self.schema::<SerializableError>();
+        let item = self
+            .paths
+            .entry(path.to_string())
+            .or_insert_with(|| json!({}));
+        item[method] = operation.into_value(error);
+    }
+
+    /// The query parameters of a struct extracted with `axum::extract::Query`
+    fn query<T: JsonSchema>(&mut self) -> Vec<Value> {
+        let schema = self.inline::<T>();
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        schema["properties"]
+            .as_object()
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, schema)| {
+                        json!({
+                            "name": name,
+                            "in": "query",
+                            "required": required.contains(&name.as_str()),
+                            "schema": schema,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn include<I: JsonSchema>(&mut self) -> Value {
+        json!({
+            "name": "include",
+            "in": "query",
+            "description": "Related resources to include in the document",
+            "style": "form",
+            "explode": false,
+            "schema": { "type": "array", "items": self.schema::<I>() },
+        })
+    }
+
+    /// The parameters of the JSON:API collections handled by `jsonapi::Query`
+    fn collection<F, C, I, Id>(&mut self) -> Vec<Value>
+    where
+        F: DeserializeOwned,
+        C: ColumnTrait,
+        I: JsonSchema,
+        Id: JsonSchema,
+    {
+        let columns: Vec<String> = C::iter()
+            .map(|column| column.as_str().to_string())
+            .collect();
+        let includes: Vec<String> = self.inline::<I>()["enum"]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // only the keys the filter type accepts are listed
+        let filters: Map<String, Value> = columns
+            .iter()
+            .chain(includes.iter())
+            .filter(|key| serde_json::from_value::<F>(Value::String(key.to_string())).is_ok())
+            .map(|key| (key.to_owned(), json!({ "type": "string" })))
+            .collect();
+        let sorts: Vec<String> = columns
+            .iter()
+            .flat_map(|column| [column.to_owned(), format!("-{}", column)])
+            .collect();
+        let id = self.schema::<Id>();
+        vec![
+            self.include::<I>(),
+            json!({
+                "name": "filter",
+                "in": "query",
+                "style": "deepObject",
+                "explode": true,
+                "schema": {
+                    "type": "object",
+                    "properties": filters,
+                    "additionalProperties": false,
+                },
+            }),
+            json!({
+                "name": "sort",
+                "in": "query",
+                "description": "Columns to sort by, descending when prefixed with -",
+                "style": "form",
+                "explode": false,
+                "schema": { "type": "array", "items": { "type": "string", "enum": sorts } },
+            }),
+            json!({
+                "name": "page",
+                "in": "query",
+                "style": "deepObject",
+                "explode": true,
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "size": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": MAX_PAGE_SIZE,
+                            "default": DEFAULT_PAGE_SIZE,
+                        },
+                        "before": id,
+                        "after": id,
+                    },
+                },
+            }),
+        ]
+    }
+}
+
+/// An operation of the document, answering with errors as `SerializableError`
+struct Operation {
+    tag: &'static str,
+    summary: &'static str,
+    parameters: Vec<Value>,
+    body: Option<(&'static str, Value)>,
+    responses: Map<String, Value>,
+    public: bool,
+}
+
+impl Operation {
+    fn new(tag: &'static str, summary: &'static str) -> Self {
+        Self {
+            tag,
+            summary,
+            parameters: Vec::new(),
+            body: None,
+            responses: Map::new(),
+            public: false,
+        }
+    }
+
+    fn path(mut self, name: &str, schema: Value) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": schema,
+        }));
+        self
+    }
+
+    fn params(mut self, parameters: Vec<Value>) -> Self {
+        self.parameters.extend(parameters);
+        self
+    }
+
+    fn body(mut self, mime: &'static str, schema: Value) -> Self {
+        self.body = Some((mime, schema));
+        self
+    }
+
+    fn ok(self, mime: &str, schema: Value) -> Self {
+        self.response(200, "Success", &[mime], schema)
+    }
+
+    fn response(mut self, status: u16, description: &str, mimes: &[&str], schema: Value) -> Self {
+        let content: Map<String, Value> = mimes
+            .iter()
+            .map(|mime| (mime.to_string(), json!({ "schema": schema })))
+            .collect();
+        self.responses.insert(
+            status.to_string(),
+            json!({ "description": description, "content": content }),
+        );
+        self
+    }
+
+    fn empty(mut self, status: u16, description: &str) -> Self {
+        self.responses
+            .insert(status.to_string(), json!({ "description": description }));
+        self
+    }
+
+    fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    fn into_value(mut self, error: Value) -> Value {
+        self.responses.insert(
+            "default".to_string(),
+            json!({
+                "description": "Error",
+                "content": { JSON_MIME: { "schema": error } },
+            }),
+        );
+        let mut operation = json!({
+            "tags": [self.tag],
+            "summary": self.summary,
+            "parameters": self.parameters,
+            "responses": self.responses,
+        });
+        if let Some((mime, schema)) = self.body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { mime: { "schema": schema } },
+            });
+        }
+        if self.public {
+            operation["security"] = json!([]);
+        }
+        operation
+    }
+}
+
+// Files served as they are, described by their media type only
+fn binary() -> Value {
+    json!({})
+}
+
+// Adds the rating of the user to the keys the collection can be sorted by
+fn rating_sort(mut params: Vec<Value>) -> Vec<Value> {
+    for param in params.iter_mut().filter(|param| param["name"] == "sort") {
+        if let Some(sorts) = param["schema"]["items"]["enum"].as_array_mut() {
+            sorts.extend([json!("rating"), json!("-rating")]);
+        }
+    }
+    params
+}
+
+fn tempo(spec: &mut Spec) {
+    let uuid = spec.schema::<Uuid>();
+    let string = spec.schema::<String>();
+
+    let op = Operation::new("server", "Server information and supported features")
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<ServerResource, Included>>(),
+        )
+        .public();
+    spec.add("get", "/tempo/server", op);
+
+    let auth = spec.schema::<Document<AuthResource, Included>>();
+    let op = Operation::new("auth", "Check the validity of a token")
+        .ok(JSONAPI_MIME, auth.clone())
+        .public();
+    spec.add("get", "/tempo/auth", op);
+    let op = Operation::new("auth", "Log in with a username and password")
+        .body(JSONAPI_MIME, spec.schema::<LoginData>())
+        .ok(JSONAPI_MIME, auth.clone())
+        .public();
+    spec.add("post", "/tempo/auth", op);
+    let op = Operation::new("auth", "Get a new token pair with a refresh token")
+        .ok(JSONAPI_MIME, auth)
+        .public();
+    spec.add("patch", "/tempo/auth", op);
+
+    let op = Operation::new("images", "Get an image")
+        .path("id", string.clone())
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<ImageResource, Included>>(),
+        );
+    spec.add("get", "/tempo/images/{id}", op);
+    let op = Operation::new("images", "Download the image file")
+        .path("id", string.clone())
+        .ok("image/*", binary());
+    spec.add("get", "/tempo/images/{id}/file", op);
+
+    let artists = spec.schema::<Document<ArtistResource, Included>>();
+    let op = Operation::new("artists", "List artists")
+        .params(spec.collection::<ArtistFilter, entity::ArtistColumn, ArtistInclude, Uuid>())
+        .ok(JSONAPI_MIME, artists.clone());
+    spec.add("get", "/tempo/artists", op);
+    let op = Operation::new("artists", "Get an artist")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<ArtistInclude>()])
+        .ok(JSONAPI_MIME, artists);
+    spec.add("get", "/tempo/artists/{id}", op);
+
+    let releases = spec.schema::<Document<ReleaseResource, Included>>();
+    let op = Operation::new("releases", "List releases")
+        .params(rating_sort(
+            spec.collection::<ReleaseFilter, entity::ReleaseColumn, ReleaseInclude, Uuid>(),
+        ))
+        .ok(JSONAPI_MIME, releases.clone());
+    spec.add("get", "/tempo/releases", op);
+    let op = Operation::new("releases", "Get a release")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<ReleaseInclude>()])
+        .ok(JSONAPI_MIME, releases);
+    spec.add("get", "/tempo/releases/{id}", op);
+    let op = Operation::new("releases", "Download the release as a zip archive")
+        .path("id", uuid.clone())
+        .params(spec.query::<ArchiveQuery>())
+        .ok("application/zip", binary());
+    spec.add("get", "/tempo/releases/{id}/archive", op);
+    let op = Operation::new("releases", "HLS playlist of the whole release")
+        .path("id", uuid.clone())
+        .ok(hls::PLAYLIST_MIME.as_ref(), binary());
+    spec.add("get", "/tempo/releases/{id}/hls/playlist.m3u8", op);
+    let op = Operation::new("releases", "Segment of the HLS playlist of the release")
+        .path("id", uuid.clone())
+        .path("segment", string.clone())
+        .ok(hls::SEGMENT_MIME.as_ref(), binary());
+    spec.add("get", "/tempo/releases/{id}/hls/{segment}", op);
+    let playlists = [
+        PlaylistFormat::M3u8.mime().as_ref(),
+        PlaylistFormat::Xspf.mime().as_ref(),
+        PlaylistFormat::Jspf.mime().as_ref(),
+    ];
+    let op = Operation::new(
+        "playlists",
+        "Export the tracks of the release as a playlist",
+    )
+    .path("id", uuid.clone())
+    .params(spec.query::<ExportQuery>())
+    .response(200, "Success", &playlists, binary());
+    spec.add("get", "/tempo/releases/{id}/playlist", op);
+
+    let mediums = spec.schema::<Document<MediumResource, Included>>();
+    let op = Operation::new("mediums", "List mediums")
+        .params(spec.collection::<MediumFilter, entity::MediumColumn, MediumInclude, Uuid>())
+        .ok(JSONAPI_MIME, mediums.clone());
+    spec.add("get", "/tempo/mediums/", op);
+    let op = Operation::new("mediums", "Get a medium")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<MediumInclude>()])
+        .ok(JSONAPI_MIME, mediums);
+    spec.add("get", "/tempo/mediums/{id}", op);
+    let op = Operation::new("playlists", "Export the tracks of the medium as a playlist")
+        .path("id", uuid.clone())
+        .params(spec.query::<ExportQuery>())
+        .response(200, "Success", &playlists, binary());
+    spec.add("get", "/tempo/mediums/{id}/playlist", op);
+
+    let mut params = spec.query::<ImportQuery>();
+    params.push(spec.include::<PlaylistImportInclude>());
+    let op = Operation::new(
+        "playlists",
+        "Match the entries of a playlist file to tracks",
+    )
+    .params(params)
+    .body("application/octet-stream", binary())
+    .ok(
+        JSONAPI_MIME,
+        spec.schema::<Document<PlaylistImportResource, Included>>(),
+    );
+    spec.add("post", "/tempo/playlists/import", op);
+
+    let playlist = spec.schema::<Document<PlaylistResource, Included>>();
+    let op = Operation::new(
+        "playlists",
+        "List the playlists of the user and the public ones",
+    )
+    .params(spec.collection::<PlaylistFilter, entity::PlaylistColumn, PlaylistInclude, Uuid>())
+    .ok(JSONAPI_MIME, playlist.clone());
+    spec.add("get", "/tempo/playlists", op);
+    let op = Operation::new("playlists", "Create a playlist")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneDocument<InsertPlaylistResource>>(),
+        )
+        .response(201, "Created", &[JSONAPI_MIME], playlist.clone());
+    spec.add("post", "/tempo/playlists", op);
+    let op = Operation::new("playlists", "Get a playlist")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<PlaylistInclude>()])
+        .ok(JSONAPI_MIME, playlist.clone());
+    spec.add("get", "/tempo/playlists/{id}", op);
+    let op = Operation::new("playlists", "Edit a playlist")
+        .path("id", uuid.clone())
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<UpdateOneDocument<UpdatePlaylistResource>>(),
+        )
+        .ok(JSONAPI_MIME, playlist);
+    spec.add("patch", "/tempo/playlists/{id}", op);
+    let op = Operation::new("playlists", "Delete a playlist")
+        .path("id", uuid.clone())
+        .empty(200, "Deleted");
+    spec.add("delete", "/tempo/playlists/{id}", op);
+    let op = Operation::new("playlists", "Export the tracks of the playlist")
+        .path("id", uuid.clone())
+        .params(spec.query::<ExportQuery>())
+        .response(200, "Success", &playlists, binary());
+    spec.add("get", "/tempo/playlists/{id}/playlist", op);
+    let entries = spec.schema::<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>();
+    let path = "/tempo/playlists/{id}/relationships/tracks";
+    let op = Operation::new("playlists", "The tracks of a playlist, in order")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<PlaylistInclude>()])
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<Related<ResourceType, Meta>, Included>>(),
+        );
+    spec.add("get", path, op);
+    let op = Operation::new("playlists", "Insert tracks in a playlist")
+        .path("id", uuid.clone())
+        .params(spec.query::<InsertTracksQuery>())
+        .body(JSONAPI_MIME, entries.clone())
+        .empty(200, "Inserted");
+    spec.add("post", path, op);
+    let op = Operation::new("playlists", "Replace the tracks of a playlist")
+        .path("id", uuid.clone())
+        .body(JSONAPI_MIME, entries.clone())
+        .empty(200, "Replaced");
+    spec.add("patch", path, op);
+    let op = Operation::new("playlists", "Remove tracks from a playlist")
+        .path("id", uuid.clone())
+        .body(JSONAPI_MIME, entries)
+        .empty(200, "Removed");
+    spec.add("delete", path, op);
+    let op = Operation::new("playlists", "Move a track of a playlist")
+        .path("id", uuid.clone())
+        .params(spec.query::<MoveTrackQuery>())
+        .empty(200, "Moved");
+    spec.add(
+        "post",
+        "/tempo/playlists/{id}/relationships/tracks/move",
+        op,
+    );
+
+    let smart_playlist = spec.schema::<Document<SmartPlaylistResource, Included>>();
+    let params =
+        spec.collection::<SmartPlaylistFilter, SmartPlaylistColumn, SmartPlaylistInclude, Uuid>();
+    let op = Operation::new(
+        "smart_playlists",
+        "List the smart playlists of the user and the public ones",
+    )
+    .params(params)
+    .ok(JSONAPI_MIME, smart_playlist.clone());
+    spec.add("get", "/tempo/smart-playlists", op);
+    let op = Operation::new("smart_playlists", "Create a smart playlist")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneDocument<InsertSmartPlaylistResource>>(),
+        )
+        .response(201, "Created", &[JSONAPI_MIME], smart_playlist.clone());
+    spec.add("post", "/tempo/smart-playlists", op);
+    let op = Operation::new("smart_playlists", "Get a smart playlist")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<SmartPlaylistInclude>()])
+        .ok(JSONAPI_MIME, smart_playlist.clone());
+    spec.add("get", "/tempo/smart-playlists/{id}", op);
+    let op = Operation::new("smart_playlists", "Edit a smart playlist")
+        .path("id", uuid.clone())
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<UpdateOneDocument<UpdateSmartPlaylistResource>>(),
+        )
+        .ok(JSONAPI_MIME, smart_playlist);
+    spec.add("patch", "/tempo/smart-playlists/{id}", op);
+    let op = Operation::new("smart_playlists", "Delete a smart playlist")
+        .path("id", uuid.clone())
+        .empty(200, "Deleted");
+    spec.add("delete", "/tempo/smart-playlists/{id}", op);
+    let op = Operation::new(
+        "smart_playlists",
+        "The tracks matching the rules of a smart playlist, in order",
+    )
+    .path("id", uuid.clone())
+    .params(vec![spec.include::<TrackInclude>()])
+    .ok(
+        JSONAPI_MIME,
+        spec.schema::<Document<TrackResource, Included>>(),
+    );
+    spec.add("get", "/tempo/smart-playlists/{id}/tracks", op);
+
+    let tracks = spec.schema::<Document<TrackResource, Included>>();
+    let op = Operation::new("tracks", "List tracks")
+        .params(rating_sort(
+            spec.collection::<TrackFilter, entity::TrackColumn, TrackInclude, Uuid>(),
+        ))
+        .ok(JSONAPI_MIME, tracks.clone());
+    spec.add("get", "/tempo/tracks", op);
+    let op = Operation::new("tracks", "Get a track")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<TrackInclude>()])
+        .ok(JSONAPI_MIME, tracks);
+    spec.add("get", "/tempo/tracks/{id}", op);
+    let op = Operation::new(
+        "tracks",
+        "Stream the audio of a track, transcoded if requested",
+    )
+    .path("id", uuid.clone())
+    .params(spec.query::<AudioQuery>())
+    .ok("audio/*", binary());
+    spec.add("get", "/tempo/tracks/{id}/audio", op);
+    let op = Operation::new("tracks", "Peaks of the waveform of a track")
+        .path("id", uuid.clone())
+        .params(spec.query::<WaveformQuery>())
+        .ok(JSON_MIME, spec.schema::<WaveformDocument>());
+    spec.add("get", "/tempo/tracks/{id}/waveform", op);
+    let lyrics = spec.schema::<LyricsDocument>();
+    let op = Operation::new("tracks", "Get the lyrics of a track")
+        .path("id", uuid.clone())
+        .ok(JSON_MIME, lyrics.clone());
+    spec.add("get", "/tempo/tracks/{id}/lyrics", op);
+    let op = Operation::new(
+        "tracks",
+        "Replace the lyrics of a track, for the owner of the library",
+    )
+    .path("id", uuid.clone())
+    .body(JSON_MIME, lyrics.clone())
+    .ok(JSON_MIME, lyrics);
+    spec.add("put", "/tempo/tracks/{id}/lyrics", op);
+    let op = Operation::new("tracks", "HLS playlist of a track")
+        .path("id", uuid.clone())
+        .ok(hls::PLAYLIST_MIME.as_ref(), binary());
+    spec.add("get", "/tempo/tracks/{id}/hls/playlist.m3u8", op);
+    let op = Operation::new("tracks", "Segment of the HLS playlist of a track")
+        .path("id", uuid.clone())
+        .path("segment", string.clone())
+        .ok(hls::SEGMENT_MIME.as_ref(), binary());
+    spec.add("get", "/tempo/tracks/{id}/hls/{segment}", op);
+
+    let genres = spec.schema::<Document<GenreResource, Included>>();
+    let op = Operation::new("genres", "List genres")
+        .params(spec.collection::<GenreFilter, entity::GenreColumn, GenreInclude, String>())
+        .ok(JSONAPI_MIME, genres.clone());
+    spec.add("get", "/tempo/genres", op);
+    let op = Operation::new("genres", "Get a genre")
+        .path("id", string.clone())
+        .params(vec![spec.include::<GenreInclude>()])
+        .ok(JSONAPI_MIME, genres);
+    spec.add("get", "/tempo/genres/{id}", op);
+
+    let scrobbles = spec.schema::<Document<ScrobbleResource, Included>>();
+    let op = Operation::new("scrobbles", "List the scrobbles of the user")
+        .params(spec.collection::<ScrobbleFilter, entity::ScrobbleColumn, ScrobbleInclude, i64>())
+        .ok(JSONAPI_MIME, scrobbles.clone());
+    spec.add("get", "/tempo/scrobbles", op);
+    let op = Operation::new("scrobbles", "Scrobble tracks")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertDocument<InsertScrobbleResource>>(),
+        )
+        .ok(JSONAPI_MIME, scrobbles.clone());
+    spec.add("put", "/tempo/scrobbles", op);
+    let op = Operation::new("scrobbles", "Get a scrobble")
+        .path("id", spec.schema::<i64>())
+        .params(vec![spec.include::<ScrobbleInclude>()])
+        .ok(JSONAPI_MIME, scrobbles.clone());
+    spec.add("get", "/tempo/scrobbles/{id}", op);
+    let op = Operation::new(
+        "scrobbles",
+        "List the listens of the user which matched no track",
+    )
+    .params(spec.collection::<UnmatchedListenFilter, entity::UnmatchedListenColumn, UnmatchedListenInclude, i64>())
+    .ok(
+        JSONAPI_MIME,
+        spec.schema::<Document<UnmatchedListenResource, Included>>(),
+    );
+    spec.add("get", "/tempo/unmatched-listens", op);
+    let op = Operation::new("scrobbles", "Discard a listen which matched no track")
+        .path("id", spec.schema::<i64>())
+        .empty(200, "Discarded");
+    spec.add("delete", "/tempo/unmatched-listens/{id}", op);
+    let op = Operation::new("scrobbles", "Match a listen to a track, scrobbling it")
+        .path("id", spec.schema::<i64>())
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>(),
+        )
+        .ok(JSONAPI_MIME, scrobbles);
+    spec.add(
+        "patch",
+        "/tempo/unmatched-listens/{id}/relationships/track",
+        op,
+    );
+
+    let now_playing = spec.schema::<Document<NowPlayingResource, Included>>();
+    let op = Operation::new(
+        "now_playing",
+        "What is being played by the user, or by everyone when public",
+    )
+    .params(spec.collection::<NowPlayingFilter, entity::UserColumn, NowPlayingInclude, String>())
+    .ok(JSONAPI_MIME, now_playing.clone());
+    spec.add("get", "/tempo/now-playing", op);
+    let op = Operation::new("now_playing", "Report the track being played")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneDocument<InsertNowPlayingResource>>(),
+        )
+        .ok(JSONAPI_MIME, now_playing);
+    spec.add("put", "/tempo/now-playing", op);
+
+    let shares = spec.schema::<Document<ShareResource, Included>>();
+    let op = Operation::new("shares", "List the shares of the user")
+        .params(spec.collection::<ShareFilter, entity::ShareColumn, ShareInclude, Uuid>())
+        .ok(JSONAPI_MIME, shares.clone());
+    spec.add("get", "/tempo/shares", op);
+    let op = Operation::new("shares", "Share a resource")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneDocument<InsertShareResource>>(),
+        )
+        .ok(JSONAPI_MIME, shares.clone());
+    spec.add("post", "/tempo/shares", op);
+    let op = Operation::new("shares", "Get a share")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<ShareInclude>()])
+        .ok(JSONAPI_MIME, shares);
+    spec.add("get", "/tempo/shares/{id}", op);
+    let op = Operation::new("shares", "Revoke a share")
+        .path("id", uuid.clone())
+        .empty(200, "Revoked");
+    spec.add("delete", "/tempo/shares/{id}", op);
+
+    let api_tokens = spec.schema::<Document<ApiTokenResource, Included>>();
+    let op = Operation::new("api_tokens", "List the API tokens of the user")
+        .params(spec.collection::<ApiTokenFilter, entity::ApiTokenColumn, ApiTokenInclude, Uuid>())
+        .ok(JSONAPI_MIME, api_tokens.clone());
+    spec.add("get", "/tempo/api-tokens", op);
+    let op = Operation::new(
+        "api_tokens",
+        "Create an API token for the compatibility APIs, such as Subsonic or ListenBrainz",
+    )
+    .body(
+        JSONAPI_MIME,
+        spec.schema::<InsertOneDocument<InsertApiTokenResource>>(),
+    )
+    .ok(JSONAPI_MIME, api_tokens);
+    spec.add("post", "/tempo/api-tokens", op);
+    let op = Operation::new("api_tokens", "Revoke an API token")
+        .path("id", uuid)
+        .empty(200, "Revoked");
+    spec.add("delete", "/tempo/api-tokens/{id}", op);
+
+    let op = Operation::new("users", "Get a user")
+        .path("username", string.clone())
+        .params(vec![spec.include::<UserInclude>()])
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<UserResource, Included>>(),
+        );
+    spec.add("get", "/tempo/users/{username}", op);
+    let favorites =
+        spec.schema::<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>();
+    let path = "/tempo/users/{username}/relationships/favorites";
+    let op = Operation::new(
+        "users",
+        "The tracks, releases and artists starred by a user",
+    )
+    .path("username", string.clone())
+    .ok(
+        JSONAPI_MIME,
+        spec.schema::<Document<Related<ResourceType, Meta>, Included>>(),
+    );
+    spec.add("get", path, op);
+    let op = Operation::new("users", "Star tracks, releases or artists")
+        .path("username", string.clone())
+        .body(JSONAPI_MIME, favorites.clone())
+        .empty(200, "Starred");
+    spec.add("post", path, op);
+    let op = Operation::new("users", "Unstar tracks, releases or artists")
+        .path("username", string.clone())
+        .body(JSONAPI_MIME, favorites)
+        .empty(200, "Unstarred");
+    spec.add("delete", path, op);
+    let ratings = spec
+        .schema::<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, UserMetaAttributes>>>();
+    let unrated = spec.schema::<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>();
+    let path = "/tempo/users/{username}/relationships/ratings";
+    let op = Operation::new("users", "The tracks and releases rated by a user")
+        .path("username", string.clone())
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<Related<ResourceType, Meta>, Included>>(),
+        );
+    spec.add("get", path, op);
+    let op = Operation::new(
+        "users",
+        "Rate tracks or releases, from 1 to 5 as the rating meta",
+    )
+    .path("username", string.clone())
+    .body(JSONAPI_MIME, ratings)
+    .empty(200, "Rated");
+    spec.add("post", path, op);
+    let op = Operation::new("users", "Remove the rating of tracks or releases")
+        .path("username", string.clone())
+        .body(JSONAPI_MIME, unrated)
+        .empty(200, "Unrated");
+    spec.add("delete", path, op);
+    let queue = spec.schema::<Document<PlayQueueResource, Included>>();
+    let path = "/tempo/users/{username}/queue";
+    let op = Operation::new("users", "Get the play queue of a user")
+        .path("username", string.clone())
+        .params(vec![spec.include::<PlayQueueInclude>()])
+        .ok(JSONAPI_MIME, queue.clone());
+    spec.add("get", path, op);
+    let op = Operation::new(
+        "users",
+        "Replace the play queue of a user, given the version last read",
+    )
+    .path("username", string.clone())
+    .body(
+        JSONAPI_MIME,
+        spec.schema::<UpdateOneDocument<UpdatePlayQueueResource>>(),
+    )
+    .ok(JSONAPI_MIME, queue)
+    .empty(409, "The queue was changed since the given version");
+    spec.add("put", path, op);
+    let op = Operation::new("users", "Listening statistics of a user over a range")
+        .path("username", string.clone())
+        .params(spec.query::<StatsQuery>())
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<StatsResource, Included>>(),
+        );
+    spec.add("get", "/tempo/users/{username}/stats", op);
+    let review = spec.schema::<Document<YearReviewResource, Included>>();
+    let op = Operation::new(
+        "users",
+        "The yearly reviews of a user, the most recent first",
+    )
+    .path("username", string.clone())
+    .ok(JSONAPI_MIME, review.clone());
+    spec.add("get", "/tempo/users/{username}/reviews", op);
+    let op = Operation::new("users", "The review of a year of listening")
+        .path("username", string.clone())
+        .path("year", spec.schema::<i32>())
+        .ok(JSONAPI_MIME, review);
+    spec.add("get", "/tempo/users/{username}/reviews/{year}", op);
+    let op = Operation::new(
+        "users",
+        "An image to share a review, made of the covers of its top releases",
+    )
+    .path("username", string.clone())
+    .path("year", spec.schema::<i32>())
+    .ok("image/*", binary());
+    spec.add("get", "/tempo/users/{username}/reviews/{year}/image", op);
+    let relation = spec.schema::<UserRelation>();
+    let identifier = spec.schema::<InsertExactlyOneRelation<
+        ResourceIdentifier<ResourceType, entity::ConnectionProvider, Meta>,
+    >>();
+    let path = "/tempo/users/{username}/relationships/{relation}";
+    let op = Operation::new("users", "List the resources related to a user")
+        .path("username", string.clone())
+        .path("relation", relation.clone())
+        .params(vec![spec.include::<UserInclude>()])
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<Related<ResourceType, Meta>, Included>>(),
+        );
+    spec.add("get", path, op);
+    let op = Operation::new("users", "Relate a resource to a user")
+        .path("username", string.clone())
+        .path("relation", relation.clone())
+        .body(JSONAPI_MIME, identifier.clone())
+        .empty(201, "Related, with the resource in the Location header");
+    spec.add("post", path, op);
+    let op = Operation::new(
+        "users",
+        "Remove a resource from the relationships of a user",
+    )
+    .path("username", string)
+    .path("relation", relation)
+    .body(JSONAPI_MIME, identifier)
+    .empty(200, "Removed");
+    spec.add("delete", path, op);
+
+    let provider = spec.schema::<entity::ConnectionProvider>();
+    let connections = spec.schema::<Document<ConnectionResource, Included>>();
+    let op = Operation::new("connections", "List the available connections")
+        .ok(JSONAPI_MIME, connections.clone());
+    spec.add("get", "/tempo/connections", op);
+    let op = Operation::new("connections", "Get a connection")
+        .path("provider", provider.clone())
+        .ok(JSONAPI_MIME, connections);
+    spec.add("get", "/tempo/connections/{provider}", op);
+    let op = Operation::new("connections", "Complete the authorization of a connection")
+        .path("provider", provider)
+        .params(spec.query::<CallbackOptions>())
+        .empty(307, "Redirect to the given URL")
+        .public();
+    spec.add("get", "/tempo/connections/{provider}/callback", op);
+
+    let op = Operation::new("search", "Search artists, releases and tracks")
+        .params(spec.query::<SearchQuery>())
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<SearchResult, Included>>(),
+        );
+    spec.add("get", "/tempo/search", op);
+}
+
+fn internal(spec: &mut Spec) {
+    let uuid = spec.schema::<Uuid>();
+
+    let downloads = spec.schema::<Document<DirectoryResource, DirectoryResource>>();
+    let op = Operation::new("downloads", "List the downloads directory")
+        .ok(JSONAPI_MIME, downloads.clone());
+    spec.add("get", "/internal/downloads", op);
+    let op = Operation::new("downloads", "List a directory in the downloads")
+        .path("id", spec.schema::<String>())
+        .ok(JSONAPI_MIME, downloads);
+    spec.add("get", "/internal/downloads/{id}", op);
+
+    let imports = spec.schema::<Document<ImportResource, InternalIncluded>>();
+    let op = Operation::new("imports", "List imports")
+        .params(spec.collection::<ImportFilter, entity::ImportColumn, ImportInclude, Uuid>())
+        .ok(JSONAPI_MIME, imports.clone());
+    spec.add("get", "/internal/imports", op);
+    let op = Operation::new("imports", "Begin importing a directory")
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<InsertOneDocument<InsertImportResource>>(),
+        )
+        .ok(
+            JSONAPI_MIME,
+            spec.schema::<Document<ImportResource, ImportInclude>>(),
+        );
+    spec.add("put", "/internal/imports", op);
+    let op = Operation::new("imports", "Get an import")
+        .path("id", uuid.clone())
+        .params(vec![spec.include::<ImportInclude>()])
+        .ok(JSONAPI_MIME, imports.clone());
+    spec.add("get", "/internal/imports/{id}", op);
+    let op = Operation::new("imports", "Select the release or cover of an import")
+        .path("id", uuid.clone())
+        .body(
+            JSONAPI_MIME,
+            spec.schema::<UpdateOneDocument<UpdateImportResource>>(),
+        )
+        .ok(JSONAPI_MIME, imports);
+    spec.add("patch", "/internal/imports/{id}", op);
+    let op = Operation::new("imports", "Run an import")
+        .path("id", uuid.clone())
+        .empty(200, "Imported");
+    spec.add("post", "/internal/imports/{id}", op);
+    let op = Operation::new("imports", "Delete an import")
+        .path("id", uuid)
+        .empty(200, "Deleted");
+    spec.add("delete", "/internal/imports/{id}", op);
+
+    let update_type = spec.schema::<UpdateType>();
+    let op = Operation::new("update", "Schedule an update of everything")
+        .path("update_type", update_type.clone())
+        .empty(200, "Scheduled");
+    spec.add("post", "/internal/update/{update_type}/all", op);
+    let op = Operation::new("update", "Schedule an update of what is outdated")
+        .path("update_type", update_type)
+        .empty(200, "Scheduled");
+    spec.add("post", "/internal/update/{update_type}/outdated", op);
+}
+
+fn spec() -> Value {
+    let mut spec = Spec::new();
+    tempo(&mut spec);
+    internal(&mut spec);
+
+    let url = get_settings()
+        .map(|settings| settings.url.to_string())
+        .unwrap_or_default();
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": base::CLI_NAME,
+            "version": base::VERSION,
+            "license": { "name": "AGPL-3.0-or-later", "identifier": "AGPL-3.0-or-later" },
+        },
+        "servers": [{ "url": url.trim_end_matches('/') }],
+        "paths": spec.paths,
+        "components": {
+            "schemas": spec.generator.take_definitions(),
+            "securitySchemes": {
+                "token": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+            },
+        },
+        "security": [{ "token": [] }],
+    })
+}
+
+pub async fn openapi() -> Json<&'static Value> {
+    Json(&*SPEC)
+}
+
+fn schema_html(schema: &Value) -> String {
+    match schema["$ref"].as_str() {
+        Some(reference) => {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            format!("<a href=\"#schema-{0}\">{0}</a>", escape_xml(name))
+        }
+        None => format!("<code>{}</code>", escape_xml(&schema.to_string())),
+    }
+}
+
+fn content_html(content: &Value) -> String {
+    content
+        .as_object()
+        .map(|content| {
+            content
+                .iter()
+                .map(|(mime, media)| {
+                    format!(
+                        "<li><code>{}</code> {}</li>",
+                        escape_xml(mime),
+                        schema_html(&media["schema"])
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A plain rendering of the document, so it can be browsed without any tool
+pub async fn docs() -> Html<String> {
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{} API</title></head><body>",
+        base::CLI_NAME
+    );
+    html += &format!(
+        "<h1>{} {}</h1><p><a href=\"openapi.json\">openapi.json</a></p>",
+        base::CLI_NAME,
+        base::VERSION
+    );
+    let empty = Map::new();
+    for (path, item) in SPEC["paths"].as_object().unwrap_or(&empty) {
+        for (method, operation) in item.as_object().unwrap_or(&empty) {
+            html += &format!(
+                "<h3><code>{} {}</code></h3><p>{}</p>",
+                method.to_uppercase(),
+                escape_xml(path),
+                escape_xml(operation["summary"].as_str().unwrap_or_default())
+            );
+            if let Some(parameters) = operation["parameters"].as_array() {
+                html += "<ul>";
+                for parameter in parameters {
+                    html += &format!(
+                        "<li><code>{}</code> ({}) {}</li>",
+                        escape_xml(parameter["name"].as_str().unwrap_or_default()),
+                        escape_xml(parameter["in"].as_str().unwrap_or_default()),
+                        schema_html(&parameter["schema"])
+                    );
+                }
+                html += "</ul>";
+            }
+            if let Some(body) = operation.get("requestBody") {
+                html += &format!("<p>Body</p><ul>{}</ul>", content_html(&body["content"]));
+            }
+            for (status, response) in operation["responses"].as_object().unwrap_or(&empty) {
+                html += &format!(
+                    "<p>{} {}</p><ul>{}</ul>",
+                    escape_xml(status),
+                    escape_xml(response["description"].as_str().unwrap_or_default()),
+                    content_html(&response["content"])
+                );
+            }
+        }
+    }
+    html += "<h2>Schemas</h2>";
+    for (name, schema) in SPEC["components"]["schemas"].as_object().unwrap_or(&empty) {
+        html += &format!(
+            "<h3 id=\"schema-{0}\">{0}</h3><pre>{1}</pre>",
+            escape_xml(name),
+            escape_xml(&serde_json::to_string_pretty(schema).unwrap_or_default())
+        );
+    }
+    html += "</body></html>";
+    Html(html)
+}
@@ -0,0 +1,248 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::Request,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use lazy_static::lazy_static;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::api::{
+    auth::AuthError,
+    documents::{Included, PlaylistInclude, ReleaseInclude, ShareResource, TrackInclude},
+    extract::{check_token, Json, Path},
+    jsonapi::{Document, DocumentData},
+    tempo::{playlists, releases, shares, tracks},
+    AppState, Error,
+};
+use base::setting::get_settings;
+use base::util::dedup;
+
+/// The audience of share tokens, which are signed with the same secret as login tokens
+pub static SHARE_AUDIENCE: &str = "share";
+/// Requests for a track of a share less than this apart belong to the same play, so
+/// that seeking and resumed downloads don't exhaust the share
+const PLAY_SESSION: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    /// When each track of a share was last requested, by share and track
+    static ref PLAYS: Arc<Mutex<HashMap<(Uuid, Uuid), Instant>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// The claims signed into a share token. Tokens only carry the share id, everything
+/// else is checked against the database so that shares can be revoked.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareClaims {
+    pub share: Uuid,
+    pub exp: usize,
+    pub aud: String,
+}
+
+pub fn token(share: &entity::Share) -> Result<String, Error> {
+    let settings = get_settings()?;
+    let claims = ShareClaims {
+        share: share.id,
+        exp: share.expires_at.unix_timestamp().max(0) as usize,
+        aud: SHARE_AUDIENCE.to_string(),
+    };
+    Ok(encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(settings.auth.jwt_secret.as_ref()),
+    )
+    .map_err(AuthError::from)?)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:token", get(shared))
+        .route("/:token/tracks/:id/audio", get(audio))
+}
+
+async fn find_share<C>(db: &C, token: &str) -> Result<entity::Share, Error>
+where
+    C: ConnectionTrait,
+{
+    let claims = check_token::<ShareClaims>(token, SHARE_AUDIENCE)?.claims;
+    let share = entity::ShareEntity::find_by_id(claims.share)
+        .one(db)
+        .await?
+        .ok_or(Error::Gone(Some(
+            "The share link has been revoked".to_string(),
+        )))?;
+    if share.expires_at <= OffsetDateTime::now_utc() {
+        return Err(Error::Gone(Some("The share link has expired".to_string())));
+    }
+    Ok(share)
+}
+
+async fn shared(
+    State(AppState(db)): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<Document<ShareResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let share = find_share(&tx, &token).await?;
+
+    let mut included = Vec::new();
+    match share.kind {
+        entity::ShareKind::Track => {
+            let track = entity::TrackEntity::find_by_id(share.resource)
+                .one(&tx)
+                .await?
+                .ok_or(Error::NotFound(None))?;
+            let related = tracks::related(&tx, &[track.clone()], false).await?;
+            included.extend(
+                related
+                    .first()
+                    .map(|r| tracks::entity_to_included(&track, r)),
+            );
+            let include = [
+                TrackInclude::Artists,
+                TrackInclude::Medium,
+                TrackInclude::MediumRelease,
+                TrackInclude::MediumReleaseArtists,
+            ];
+            included.extend(tracks::included(&tx, related, &include).await?);
+        }
+        entity::ShareKind::Release => {
+            let release = entity::ReleaseEntity::find_by_id(share.resource)
+                .one(&tx)
+                .await?
+                .ok_or(Error::NotFound(None))?;
+            let related = releases::related(&tx, &[release.clone()], false).await?;
+            included.extend(
+                related
+                    .first()
+                    .map(|r| releases::entity_to_included(&release, r)),
+            );
+            let include = [
+                ReleaseInclude::Artists,
+                ReleaseInclude::Mediums,
+                ReleaseInclude::MediumsTracks,
+                ReleaseInclude::MediumsTracksArtists,
+            ];
+            included.extend(releases::included(&tx, related, &include).await?);
+        }
+        entity::ShareKind::Playlist => {
+            let playlist = entity::PlaylistEntity::find_by_id(share.resource)
+                .one(&tx)
+                .await?
+                .ok_or(Error::NotFound(None))?;
+            let related = playlists::related(&tx, &[playlist.clone()]).await?;
+            included.extend(
+                related
+                    .first()
+                    .map(|r| playlists::entity_to_included(&playlist, r)),
+            );
+            let include = [
+                PlaylistInclude::Tracks,
+                PlaylistInclude::TracksArtists,
+                PlaylistInclude::TracksMedium,
+                PlaylistInclude::TracksMediumRelease,
+            ];
+            included.extend(playlists::included(&tx, None, &[playlist], &related, &include).await?);
+        }
+    }
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(shares::entity_to_resource(&share)?),
+        included: dedup(included),
+    }))
+}
+
+// Starts a play session for the track, or extends the running one
+async fn is_new_play(share: Uuid, track: Uuid) -> bool {
+    let now = Instant::now();
+    let mut plays = PLAYS.lock().await;
+    plays.retain(|_, at| now.duration_since(*at) < PLAY_SESSION);
+    plays.insert((share, track), now).is_none()
+}
+
+async fn audio(
+    State(AppState(db)): State<AppState>,
+    Path((token, id)): Path<(String, Uuid)>,
+    request: Request<Body>,
+) -> Result<Response, Error> {
+    let tx = db.begin().await?;
+    let share = find_share(&tx, &token).await?;
+    let track = entity::TrackEntity::find_by_id(id)
+        .one(&tx)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let shared = match share.kind {
+        entity::ShareKind::Track => track.id == share.resource,
+        entity::ShareKind::Release => entity::MediumEntity::find_by_id(track.medium_id)
+            .one(&tx)
+            .await?
+            .map_or(false, |medium| medium.release_id == share.resource),
+        entity::ShareKind::Playlist => entity::PlaylistTrackEntity::find()
+            .filter(ColumnTrait::eq(
+                &entity::PlaylistTrackColumn::PlaylistId,
+                share.resource,
+            ))
+            .filter(ColumnTrait::eq(
+                &entity::PlaylistTrackColumn::TrackId,
+                track.id,
+            ))
+            .one(&tx)
+            .await?
+            .is_some(),
+    };
+    if !shared {
+        return Err(Error::NotFound(None));
+    }
+
+    if is_new_play(share.id, track.id).await {
+        // increment atomically so that concurrent requests can't exceed the limit
+        let res = entity::ShareEntity::update_many()
+            .col_expr(
+                entity::ShareColumn::Plays,
+                Expr::col(entity::ShareColumn::Plays).add(1),
+            )
+            .filter(ColumnTrait::eq(&entity::ShareColumn::Id, share.id))
+            .filter(
+                Condition::any()
+                    .add(entity::ShareColumn::MaxPlays.is_null())
+                    .add(
+                        Expr::col(entity::ShareColumn::Plays)
+                            .lt(Expr::col(entity::ShareColumn::MaxPlays)),
+                    ),
+            )
+            .exec(&tx)
+            .await?;
+        if res.rows_affected == 0 {
+            PLAYS.lock().await.remove(&(share.id, track.id));
+            return Err(Error::Gone(Some(
+                "The share link has reached its play limit".to_string(),
+            )));
+        }
+    }
+    tx.commit().await?;
+
+    let path = track.path.ok_or(Error::NoTrackPath)?;
+    let mime = track.format.ok_or(Error::NoTrackFormat)?.mime();
+    Ok(
+        tower_http::services::fs::ServeFile::new_with_mime(path, &mime)
+            .oneshot(request)
+            .await
+            .into_response(),
+    )
+}
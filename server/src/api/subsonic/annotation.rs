@@ -0,0 +1,184 @@
+use axum::extract::{RawQuery, State};
+use entity::IgnoreNone;
+use sea_orm::{
+    ActiveValue, ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::{repeated, Params, Reply, SubsonicError, User};
+use crate::api::{
+    tempo::{now_playing, scrobbles::schedule_scrobble_tasks, stats},
+    AppState, Error,
+};
+
+/// The client name used for now playing sessions when the request doesn't have one
+static DEFAULT_CLIENT: &str = "subsonic";
+
+fn ids(query: Option<&str>, key: &'static str) -> Result<Vec<Uuid>, SubsonicError> {
+    repeated(query, key)
+        .iter()
+        .map(|id| {
+            id.parse::<Uuid>()
+                .map_err(|_| SubsonicError::InvalidParameters(format!("Invalid {}: {}", key, id)))
+        })
+        .collect()
+}
+
+fn default_submission() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct ScrobbleParams {
+    #[serde(default = "default_submission")]
+    submission: bool,
+}
+
+async fn submit<C>(
+    db: &C,
+    user: &User,
+    submission: bool,
+    query: Option<&str>,
+) -> Result<(), SubsonicError>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let ids = ids(query, "id")?;
+    if ids.is_empty() {
+        return Err(SubsonicError::MissingParameter("id"));
+    }
+    if !submission {
+        // only the last of the reported tracks can be playing
+        if let Some(id) = ids.last() {
+            let client = user.client.as_deref().unwrap_or(DEFAULT_CLIENT);
+            now_playing::report(db, &user.username, client, *id, 0, true).await?;
+        }
+        return Ok(());
+    }
+
+    let found = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(ids.iter().copied()))
+        .count(db)
+        .await?;
+    if found != ids.len() as u64 {
+        return Err(Error::NotFound(None).into());
+    }
+    // times are in milliseconds since the epoch, and match the ids by position
+    let times = repeated(query, "time");
+    let now = OffsetDateTime::now_utc();
+    let scrobbles: Vec<_> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let at = times
+                .get(i)
+                .and_then(|t| t.parse::<i64>().ok())
+                .and_then(|t| OffsetDateTime::from_unix_timestamp_nanos(t as i128 * 1_000_000).ok())
+                .unwrap_or(now);
+            (*id, at)
+        })
+        .collect();
+    tracing::info!(user = %user.username, ?scrobbles, "Scrobbling from Subsonic client");
+    entity::ScrobbleEntity::insert_many(scrobbles.iter().map(|(id, at)| entity::ScrobbleActive {
+        id: ActiveValue::NotSet,
+        at: ActiveValue::Set(*at),
+        user: ActiveValue::Set(user.username.to_owned()),
+        track: ActiveValue::Set(*id),
+    }))
+    .exec(db)
+    .await?;
+    stats::invalidate(&user.username).await;
+
+    if let Err(error) = schedule_scrobble_tasks(&user.username, scrobbles.into_iter()).await {
+        tracing::warn!(%error, user = %user.username, "Could not schedule scrobble tasks");
+    }
+    Ok(())
+}
+
+pub async fn scrobble(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<ScrobbleParams>,
+    RawQuery(query): RawQuery,
+) -> Reply {
+    user.empty(submit(&db, &user, params.submission, query.as_deref()).await)
+}
+
+fn favorites(query: Option<&str>) -> Result<Vec<(entity::FavoriteKind, Uuid)>, SubsonicError> {
+    let mut favorites = Vec::new();
+    for (key, kind) in [
+        ("id", entity::FavoriteKind::Track),
+        ("albumId", entity::FavoriteKind::Release),
+        ("artistId", entity::FavoriteKind::Artist),
+    ] {
+        favorites.extend(ids(query, key)?.into_iter().map(|id| (kind, id)));
+    }
+    match favorites.is_empty() {
+        true => Err(SubsonicError::MissingParameter("id")),
+        false => Ok(favorites),
+    }
+}
+
+async fn add_favorites<C>(db: &C, username: &str, query: Option<&str>) -> Result<(), SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let now = OffsetDateTime::now_utc();
+    let favorites = favorites(query)?
+        .into_iter()
+        .map(|(kind, resource)| entity::FavoriteActive {
+            user: ActiveValue::Set(username.to_owned()),
+            kind: ActiveValue::Set(kind),
+            resource: ActiveValue::Set(resource),
+            created_at: ActiveValue::Set(now),
+        });
+    entity::FavoriteEntity::insert_many(favorites)
+        .on_conflict(entity::conflict::FAVORITE_CONFLICT.to_owned())
+        .exec(db)
+        .await
+        .ignore_none()?;
+    Ok(())
+}
+
+async fn remove_favorites<C>(
+    db: &C,
+    username: &str,
+    query: Option<&str>,
+) -> Result<(), SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let mut condition = Condition::any();
+    for (kind, resource) in favorites(query)?.into_iter() {
+        condition = condition.add(
+            Condition::all()
+                .add(ColumnTrait::eq(&entity::FavoriteColumn::Kind, kind))
+                .add(ColumnTrait::eq(&entity::FavoriteColumn::Resource, resource)),
+        );
+    }
+    entity::FavoriteEntity::delete_many()
+        .filter(ColumnTrait::eq(&entity::FavoriteColumn::User, username))
+        .filter(condition)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn star(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    RawQuery(query): RawQuery,
+) -> Reply {
+    user.empty(add_favorites(&db, &user.username, query.as_deref()).await)
+}
+
+pub async fn unstar(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    RawQuery(query): RawQuery,
+) -> Reply {
+    user.empty(remove_favorites(&db, &user.username, query.as_deref()).await)
+}
@@ -0,0 +1,401 @@
+use axum::extract::State;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::{
+    documents::{Album, AlbumWithSongs, Artist, ArtistWithAlbums, Artists, Index, Payload, Song},
+    Params, Reply, SubsonicError, User,
+};
+use crate::api::{
+    tempo::{artists, favorites::starred, releases, tracks},
+    AppState, Error,
+};
+
+#[derive(Deserialize)]
+pub struct IdParams {
+    id: Option<Uuid>,
+}
+
+impl IdParams {
+    pub(super) fn id(&self) -> Result<Uuid, SubsonicError> {
+        self.id.ok_or(SubsonicError::MissingParameter("id"))
+    }
+}
+
+async fn artist_names<'a, C, I>(db: &C, credits: I) -> Result<HashMap<Uuid, String>, Error>
+where
+    C: ConnectionTrait,
+    I: Iterator<Item = &'a entity::ArtistCredit>,
+{
+    let ids: HashSet<Uuid> = credits.map(|credit| credit.artist_id).collect();
+    Ok(entity::ArtistEntity::find()
+        .filter(entity::ArtistColumn::Id.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|artist| (artist.id, artist.name))
+        .collect())
+}
+
+async fn genre_names<'a, C, I>(db: &C, genres: I) -> Result<HashMap<String, String>, Error>
+where
+    C: ConnectionTrait,
+    I: Iterator<Item = &'a String>,
+{
+    let ids: HashSet<&String> = genres.collect();
+    Ok(entity::GenreEntity::find()
+        .filter(entity::GenreColumn::Id.is_in(ids.into_iter().cloned()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|genre| (genre.id, genre.name))
+        .collect())
+}
+
+// The artist credits joined in a single string, as displayed on the release
+fn credited(credits: &[entity::ArtistCredit], names: &HashMap<Uuid, String>) -> Option<String> {
+    if credits.is_empty() {
+        return None;
+    }
+    let mut credited = String::new();
+    for credit in credits.iter() {
+        credited += names.get(&credit.artist_id).map_or("", |n| n.as_str());
+        credited += credit.join_phrase.as_deref().unwrap_or_default();
+    }
+    Some(credited)
+}
+
+pub(super) async fn artists_to_documents<C>(
+    db: &C,
+    username: &str,
+    artists: &[entity::Artist],
+) -> Result<Vec<Artist>, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let related = artists::related(db, artists, true).await?;
+    let starred = starred(
+        db,
+        username,
+        entity::FavoriteKind::Artist,
+        artists.iter().map(|a| a.id),
+    )
+    .await?;
+    Ok(artists
+        .iter()
+        .zip(related.into_iter())
+        .map(|(artist, related)| {
+            let albums: HashSet<Uuid> = related
+                .releases
+                .iter()
+                .flatten()
+                .map(|r| r.release_id)
+                .collect();
+            let cover_art = related
+                .pictures
+                .iter()
+                .find(|p| p.r#type == entity::ArtistPictureType::Picture)
+                .map(|p| p.image_id.to_owned())
+                .or_else(|| related.images.first().map(|i| i.image_id.to_owned()));
+            Artist {
+                id: artist.id,
+                name: artist.name.to_owned(),
+                sort_name: artist.sort_name.to_owned(),
+                music_brainz_id: artist.id,
+                album_count: albums.len(),
+                cover_art,
+                starred: starred.get(&artist.id).copied(),
+            }
+        })
+        .collect())
+}
+
+pub(super) async fn albums_to_documents<C>(
+    db: &C,
+    username: &str,
+    releases: &[entity::Release],
+) -> Result<Vec<Album>, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let related = releases::related(db, releases, true).await?;
+    let names = artist_names(db, related.iter().flat_map(|r| r.artist_credits.iter())).await?;
+    let genres = genre_names(
+        db,
+        related
+            .iter()
+            .flat_map(|r| r.genres.iter().map(|g| &g.genre_id)),
+    )
+    .await?;
+    let mut lengths: HashMap<Uuid, i64> = HashMap::new();
+    let medium_ids = related
+        .iter()
+        .flat_map(|r| r.mediums.iter().map(|m| m.id))
+        .collect::<Vec<_>>();
+    for track in entity::TrackEntity::find()
+        .filter(entity::TrackColumn::MediumId.is_in(medium_ids))
+        .all(db)
+        .await?
+    {
+        *lengths.entry(track.medium_id).or_default() += track.length.max(0) as i64;
+    }
+    let starred = starred(
+        db,
+        username,
+        entity::FavoriteKind::Release,
+        releases.iter().map(|r| r.id),
+    )
+    .await?;
+
+    Ok(releases
+        .iter()
+        .zip(related.into_iter())
+        .map(|(release, related)| Album {
+            id: release.id,
+            name: release.title.to_owned(),
+            music_brainz_id: release.id,
+            artist: credited(&related.artist_credits, &names),
+            artist_id: related.artist_credits.first().map(|c| c.artist_id),
+            cover_art: related.image.map(|i| i.image_id),
+            song_count: related
+                .mediums
+                .iter()
+                .map(|m| m.tracks.max(0) as usize)
+                .sum(),
+            duration: (related
+                .mediums
+                .iter()
+                .filter_map(|m| lengths.get(&m.id))
+                .sum::<i64>()
+                / 1000) as u32,
+            year: release.year,
+            genre: related
+                .genres
+                .first()
+                .and_then(|g| genres.get(&g.genre_id).cloned()),
+            starred: starred.get(&release.id).copied(),
+        })
+        .collect())
+}
+
+pub(super) async fn songs_to_documents<C>(
+    db: &C,
+    username: &str,
+    tracks: &[entity::Track],
+) -> Result<Vec<Song>, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let related = tracks::related(db, tracks, true).await?;
+    let release_ids: HashSet<Uuid> = related
+        .iter()
+        .filter_map(|r| r.medium.as_ref().map(|m| m.release_id))
+        .collect();
+    let releases: HashMap<Uuid, entity::Release> = entity::ReleaseEntity::find()
+        .filter(entity::ReleaseColumn::Id.is_in(release_ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|release| (release.id, release))
+        .collect();
+    let images: HashMap<Uuid, String> = entity::ImageReleaseEntity::find()
+        .filter(entity::ImageReleaseColumn::ReleaseId.is_in(release_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|image| (image.release_id, image.image_id))
+        .collect();
+    let names = artist_names(db, related.iter().flat_map(|r| r.artist_credits.iter())).await?;
+    let genres = genre_names(
+        db,
+        related
+            .iter()
+            .flat_map(|r| r.genres.iter().map(|g| &g.genre_id)),
+    )
+    .await?;
+    let starred = starred(
+        db,
+        username,
+        entity::FavoriteKind::Track,
+        tracks.iter().map(|t| t.id),
+    )
+    .await?;
+
+    Ok(tracks
+        .iter()
+        .zip(related.into_iter())
+        .map(|(track, related)| {
+            let release = related
+                .medium
+                .as_ref()
+                .and_then(|m| releases.get(&m.release_id));
+            Song {
+                id: track.id,
+                parent: release.map(|r| r.id),
+                is_dir: false,
+                title: track.title.to_owned(),
+                music_brainz_id: track.id,
+                album: release.map(|r| r.title.to_owned()),
+                album_id: release.map(|r| r.id),
+                artist: credited(&related.artist_credits, &names),
+                artist_id: related.artist_credits.first().map(|c| c.artist_id),
+                track: track.number,
+                disc_number: related.medium.as_ref().map(|m| m.position),
+                year: release.and_then(|r| r.year),
+                genre: related
+                    .genres
+                    .iter()
+                    .max_by_key(|g| g.cnt)
+                    .and_then(|g| genres.get(&g.genre_id).cloned()),
+                cover_art: release.and_then(|r| images.get(&r.id).cloned()),
+                size: track.size,
+                content_type: track.format.map(|f| f.mime().to_string()),
+                suffix: track.format.map(|f| f.ext()),
+                duration: (track.length.max(0) / 1000) as u32,
+                // the probed bitrate is in bits per second
+                bit_rate: track.bitrate.map(|b| b / 1000),
+                bpm: track.bpm,
+                kind: "music",
+                media_type: "song",
+                starred: starred.get(&track.id).copied(),
+            }
+        })
+        .collect())
+}
+
+// Artists are indexed by the first letter of their sort name
+fn index_name(artist: &Artist) -> String {
+    match artist.sort_name.chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => "#".to_string(),
+    }
+}
+
+async fn fetch_artists<C>(db: &C, username: &str) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let artists = entity::ArtistEntity::find()
+        .order_by_asc(entity::ArtistColumn::SortName)
+        .all(db)
+        .await?;
+    let mut index: Vec<Index> = Vec::new();
+    // artists only credited on tracks have nothing to browse
+    for artist in artists_to_documents(db, username, &artists)
+        .await?
+        .into_iter()
+        .filter(|a| a.album_count > 0)
+    {
+        let name = index_name(&artist);
+        match index.iter_mut().find(|i| i.name == name) {
+            Some(i) => i.artist.push(artist),
+            None => index.push(Index {
+                name,
+                artist: vec![artist],
+            }),
+        }
+    }
+    index.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Payload::Artists(Artists {
+        ignored_articles: String::new(),
+        index,
+    }))
+}
+
+pub async fn get_artists(State(AppState(db)): State<AppState>, user: User) -> Reply {
+    user.reply(fetch_artists(&db, &user.username).await)
+}
+
+async fn fetch_artist<C>(db: &C, username: &str, id: Uuid) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let artist = entity::ArtistEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let credits = entity::ArtistCreditEntity::find()
+        .filter(entity::ArtistCreditColumn::ArtistId.eq(id))
+        .all(db)
+        .await?;
+    let release_ids: HashSet<Uuid> = entity::ArtistCreditReleaseEntity::find()
+        .filter(
+            entity::ArtistCreditReleaseColumn::ArtistCreditId
+                .is_in(credits.into_iter().map(|c| c.id)),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| r.release_id)
+        .collect();
+    let releases = entity::ReleaseEntity::find()
+        .filter(entity::ReleaseColumn::Id.is_in(release_ids))
+        .order_by_asc(entity::ReleaseColumn::Year)
+        .order_by_asc(entity::ReleaseColumn::Title)
+        .all(db)
+        .await?;
+
+    let artist = artists_to_documents(db, username, &[artist])
+        .await?
+        .pop()
+        .ok_or(Error::NotFound(None))?;
+    Ok(Payload::Artist(ArtistWithAlbums {
+        artist,
+        album: albums_to_documents(db, username, &releases).await?,
+    }))
+}
+
+pub async fn get_artist(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<IdParams>,
+) -> Reply {
+    match params.id() {
+        Ok(id) => user.reply(fetch_artist(&db, &user.username, id).await),
+        Err(error) => user.fail(error),
+    }
+}
+
+async fn fetch_album<C>(db: &C, username: &str, id: Uuid) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let release = entity::ReleaseEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let mediums: HashMap<Uuid, i32> = entity::MediumEntity::find()
+        .filter(ColumnTrait::eq(&entity::MediumColumn::ReleaseId, id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|medium| (medium.id, medium.position))
+        .collect();
+    let mut tracks = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::MediumId.is_in(mediums.keys().copied()))
+        .all(db)
+        .await?;
+    tracks.sort_by_key(|track| (mediums.get(&track.medium_id).copied(), track.number));
+
+    let album = albums_to_documents(db, username, &[release])
+        .await?
+        .pop()
+        .ok_or(Error::NotFound(None))?;
+    Ok(Payload::Album(AlbumWithSongs {
+        album,
+        song: songs_to_documents(db, username, &tracks).await?,
+    }))
+}
+
+pub async fn get_album(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<IdParams>,
+) -> Reply {
+    match params.id() {
+        Ok(id) => user.reply(fetch_album(&db, &user.username, id).await),
+        Err(error) => user.fail(error),
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub static API_VERSION: &str = "1.16.1";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Failed,
+}
+
+/// The envelope of every Subsonic response. The payload is flattened so that
+/// it ends up as a single named child, as in `{"album": {...}}`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub status: Status,
+    pub version: &'static str,
+    #[serde(rename = "type")]
+    pub server: &'static str,
+    pub server_version: &'static str,
+    pub open_subsonic: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+    #[serde(flatten)]
+    pub payload: Option<Payload>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorBody {
+    pub code: u32,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Payload {
+    License(License),
+    OpenSubsonicExtensions(Vec<OpenSubsonicExtension>),
+    Artists(Artists),
+    Artist(ArtistWithAlbums),
+    Album(AlbumWithSongs),
+    SearchResult3(SearchResult3),
+    Playlists(Playlists),
+    Playlist(PlaylistWithSongs),
+}
+
+#[derive(Serialize, Debug)]
+pub struct License {
+    pub valid: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OpenSubsonicExtension {
+    pub name: &'static str,
+    pub versions: Vec<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Artists {
+    pub ignored_articles: String,
+    pub index: Vec<Index>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Index {
+    pub name: String,
+    pub artist: Vec<Artist>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Artist {
+    pub id: Uuid,
+    pub name: String,
+    pub sort_name: String,
+    pub music_brainz_id: Uuid,
+    pub album_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<String>,
+    #[serde(
+        with = "time::serde::iso8601::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub starred: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ArtistWithAlbums {
+    #[serde(flatten)]
+    pub artist: Artist,
+    pub album: Vec<Album>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    pub id: Uuid,
+    pub name: String,
+    pub music_brainz_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<String>,
+    pub song_count: usize,
+    /// Total length of the album, in seconds
+    pub duration: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(
+        with = "time::serde::iso8601::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub starred: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AlbumWithSongs {
+    #[serde(flatten)]
+    pub album: Album,
+    pub song: Vec<Song>,
+}
+
+/// A track, called a child in the Subsonic schema as it shares the type with folders
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Song {
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
+    pub is_dir: bool,
+    pub title: String,
+    pub music_brainz_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist_id: Option<Uuid>,
+    pub track: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc_number: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<&'static str>,
+    /// Length of the track, in seconds
+    pub duration: u32,
+    /// Bitrate of the file, in kbps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_rate: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<i32>,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub media_type: &'static str,
+    #[serde(
+        with = "time::serde::iso8601::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub starred: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchResult3 {
+    pub artist: Vec<Artist>,
+    pub album: Vec<Album>,
+    pub song: Vec<Song>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Playlists {
+    pub playlist: Vec<Playlist>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub owner: String,
+    pub public: bool,
+    pub song_count: usize,
+    /// Total length of the playlist, in seconds
+    pub duration: u32,
+    #[serde(with = "time::serde::iso8601")]
+    pub created: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub changed: OffsetDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlaylistWithSongs {
+    #[serde(flatten)]
+    pub playlist: Playlist,
+    pub entry: Vec<Song>,
+}
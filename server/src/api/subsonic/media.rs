@@ -0,0 +1,100 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::Request,
+    response::{IntoResponse, Response},
+};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use super::{Params, SubsonicError, User};
+use crate::api::{
+    tempo::tracks::{serve_audio, AudioQuery},
+    AppState, Error,
+};
+use base::setting::TranscodingFormat;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamParams {
+    id: Option<Uuid>,
+    /// In kbps, 0 meaning no limit
+    max_bit_rate: Option<u32>,
+    format: Option<String>,
+    /// In seconds
+    time_offset: Option<f64>,
+}
+
+impl StreamParams {
+    // "raw" asks for the original file, which is what we serve unless a
+    // transcoding profile applies
+    fn format(&self) -> Result<Option<TranscodingFormat>, SubsonicError> {
+        match self.format.as_deref() {
+            None | Some("raw") => Ok(None),
+            Some("opus") => Ok(Some(TranscodingFormat::Opus)),
+            Some("mp3") => Ok(Some(TranscodingFormat::Mp3)),
+            Some("aac") => Ok(Some(TranscodingFormat::Aac)),
+            Some(format) => Err(SubsonicError::InvalidParameters(format!(
+                "Unsupported format: {}",
+                format
+            ))),
+        }
+    }
+}
+
+pub async fn stream(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<StreamParams>,
+    request: Request<Body>,
+) -> Response {
+    let Some(id) = params.id else {
+        return user
+            .fail(SubsonicError::MissingParameter("id"))
+            .into_response();
+    };
+    let format = match params.format() {
+        Ok(format) => format,
+        Err(error) => return user.fail(error).into_response(),
+    };
+    let query = AudioQuery {
+        format,
+        max_bitrate: params.max_bit_rate.filter(|b| *b > 0),
+        offset: params.time_offset,
+        client: user.client.clone(),
+    };
+    match serve_audio(&db, &user.username, id, query, request).await {
+        Ok(response) => response,
+        Err(error) => user.fail(error).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CoverArtParams {
+    id: Option<String>,
+}
+
+// Cover art ids are image ids, scaling with the size parameter is not supported
+pub async fn get_cover_art(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<CoverArtParams>,
+    request: Request<Body>,
+) -> Response {
+    let Some(id) = params.id else {
+        return user
+            .fail(SubsonicError::MissingParameter("id"))
+            .into_response();
+    };
+    let image = match entity::ImageEntity::find_by_id(id).one(&db).await {
+        Ok(Some(image)) => image,
+        Ok(None) => return user.fail(Error::NotFound(None)).into_response(),
+        Err(error) => return user.fail(error).into_response(),
+    };
+    tower_http::services::fs::ServeFile::new_with_mime(image.path, &image.format.mime())
+        .oneshot(request)
+        .await
+        .into_response()
+}
@@ -0,0 +1,457 @@
+pub mod annotation;
+pub mod browsing;
+pub mod documents;
+pub mod media;
+pub mod playlists;
+pub mod searching;
+pub mod system;
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{FromRequest, FromRequestParts},
+    handler::Handler,
+    http::{header, request::Parts, uri::PathAndQuery, Method, Request, StatusCode, Uri},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use sea_orm::{DbConn, DbErr};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::fmt::Write;
+use thiserror::Error;
+
+use crate::api::{
+    auth::{self, AuthError},
+    AppState, Error,
+};
+use base::util::escape_xml;
+use documents::{ErrorBody, Payload, Status, API_VERSION};
+
+static XML_NAMESPACE: &str = "http://subsonic.org/restapi";
+
+pub fn router() -> Router<AppState> {
+    let router = Router::new();
+    let router = endpoint(router, "ping", system::ping);
+    let router = endpoint(router, "getLicense", system::get_license);
+    let router = endpoint(
+        router,
+        "getOpenSubsonicExtensions",
+        system::get_open_subsonic_extensions,
+    );
+    let router = endpoint(router, "getArtists", browsing::get_artists);
+    let router = endpoint(router, "getArtist", browsing::get_artist);
+    let router = endpoint(router, "getAlbum", browsing::get_album);
+    let router = endpoint(router, "stream", media::stream);
+    let router = endpoint(router, "getCoverArt", media::get_cover_art);
+    let router = endpoint(router, "search3", searching::search3);
+    let router = endpoint(router, "scrobble", annotation::scrobble);
+    let router = endpoint(router, "star", annotation::star);
+    let router = endpoint(router, "unstar", annotation::unstar);
+    let router = endpoint(router, "getPlaylists", playlists::get_playlists);
+    let router = endpoint(router, "getPlaylist", playlists::get_playlist);
+    router.layer(from_fn(form_post))
+}
+
+// Parameters may be sent as a form instead of the query string. They are merged into
+// the query so that every extractor reads them from the same place.
+async fn form_post(request: Request<Body>, next: Next<Body>) -> Response {
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if request.method() != Method::POST || !is_form {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let body = match Bytes::from_request(Request::new(body), &()).await {
+        Ok(body) => body,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let query = std::str::from_utf8(&body)
+        .map_err(|error| error.to_string())
+        .and_then(|form| {
+            let query = [parts.uri.query().unwrap_or_default(), form.trim()]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("&");
+            let mut uri = parts.uri.clone().into_parts();
+            uri.path_and_query = Some(
+                PathAndQuery::try_from(format!("{}?{}", parts.uri.path(), query))
+                    .map_err(|error| error.to_string())?,
+            );
+            Uri::from_parts(uri).map_err(|error| error.to_string())
+        });
+    match query {
+        Ok(uri) => {
+            parts.uri = uri;
+            next.run(Request::from_parts(parts, Body::empty())).await
+        }
+        Err(error) => Reply::new(
+            Format::from_parts(&parts),
+            Err(SubsonicError::InvalidParameters(error)),
+        )
+        .into_response(),
+    }
+}
+
+// Clients may or may not append the legacy .view suffix, and use both GET and POST
+fn endpoint<H, T>(router: Router<AppState>, name: &str, handler: H) -> Router<AppState>
+where
+    H: Handler<T, AppState, Body>,
+    T: 'static,
+{
+    router
+        .route(
+            &format!("/{}", name),
+            get(handler.clone()).post(handler.clone()),
+        )
+        .route(
+            &format!("/{}.view", name),
+            get(handler.clone()).post(handler),
+        )
+}
+
+#[derive(Error, Debug)]
+pub enum SubsonicError {
+    #[error("Required parameter is missing: {0}")]
+    MissingParameter(&'static str),
+    #[error("Invalid request parameters: {0}")]
+    InvalidParameters(String),
+    #[error("Wrong username or password")]
+    WrongCredentials,
+    #[error("Token authentication is not supported, use a password or an API key")]
+    TokenAuthentication,
+    #[error("Multiple conflicting authentication mechanisms provided")]
+    ConflictingAuthentication,
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("{0}")]
+    Api(#[from] Error),
+}
+
+impl From<DbErr> for SubsonicError {
+    fn from(value: DbErr) -> Self {
+        Self::Api(value.into())
+    }
+}
+
+impl SubsonicError {
+    fn code(&self) -> u32 {
+        match self {
+            SubsonicError::MissingParameter(_) => 10,
+            SubsonicError::InvalidParameters(_) => 0,
+            SubsonicError::WrongCredentials => 40,
+            SubsonicError::TokenAuthentication => 41,
+            SubsonicError::ConflictingAuthentication => 43,
+            SubsonicError::InvalidApiKey => 44,
+            SubsonicError::Api(Error::NotFound(_)) => 70,
+            SubsonicError::Api(Error::Unauthorized(_)) => 50,
+            SubsonicError::Api(_) => 0,
+        }
+    }
+}
+
+/// The response format requested with the `f` parameter
+#[derive(Debug, Clone, Default)]
+pub enum Format {
+    #[default]
+    Xml,
+    Json,
+    Jsonp(String),
+}
+
+#[derive(Deserialize, Default)]
+struct FormatParams {
+    f: Option<String>,
+    callback: Option<String>,
+}
+
+impl Format {
+    fn from_parts(parts: &Parts) -> Self {
+        let params: FormatParams =
+            serde_urlencoded::from_str(parts.uri.query().unwrap_or_default()).unwrap_or_default();
+        match (params.f.as_deref(), params.callback) {
+            (Some("json"), _) => Format::Json,
+            // the callback ends up in a script, don't allow anything but an identifier
+            (Some("jsonp"), Some(callback))
+                if !callback.is_empty()
+                    && callback
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') =>
+            {
+                Format::Jsonp(callback)
+            }
+            (Some("jsonp"), _) => Format::Json,
+            _ => Format::Xml,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Format
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(parts))
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.to_owned()),
+        _ => None,
+    }
+}
+
+// Subsonic XML maps scalar fields to attributes, objects to child elements and
+// arrays to repeated child elements, which is exactly the shape of the JSON format
+fn write_element(out: &mut String, name: &str, value: &Value) {
+    let _ = write!(out, "<{}", name);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter() {
+                if let Some(value) = scalar(value) {
+                    let _ = write!(out, " {}=\"{}\"", key, escape_xml(&value));
+                }
+            }
+            let children: Vec<_> = map
+                .iter()
+                .filter(|(_, value)| value.is_object() || value.is_array())
+                .collect();
+            if children.is_empty() {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for (key, value) in children.into_iter() {
+                match value {
+                    Value::Array(items) => {
+                        for item in items.iter() {
+                            write_element(out, key, item);
+                        }
+                    }
+                    value => write_element(out, key, value),
+                }
+            }
+        }
+        value => {
+            out.push('>');
+            out.push_str(&escape_xml(&scalar(value).unwrap_or_default()));
+        }
+    }
+    let _ = write!(out, "</{}>", name);
+}
+
+/// The outcome of a Subsonic request, rendered in the requested format. Errors are
+/// reported in the body with a 200 status code, as clients expect.
+pub struct Reply {
+    format: Format,
+    result: Result<Option<Payload>, SubsonicError>,
+}
+
+impl Reply {
+    pub fn new(format: Format, result: Result<Option<Payload>, SubsonicError>) -> Self {
+        Self { format, result }
+    }
+}
+
+impl IntoResponse for Reply {
+    fn into_response(self) -> Response {
+        let (status, error, payload) = match self.result {
+            Ok(payload) => (Status::Ok, None, payload),
+            Err(error) => {
+                let code = error.code();
+                if code == 0 {
+                    tracing::warn!(%error, "Subsonic request failed");
+                }
+                let error = ErrorBody {
+                    code,
+                    message: error.to_string(),
+                };
+                (Status::Failed, Some(error), None)
+            }
+        };
+        let response = documents::Response {
+            status,
+            version: API_VERSION,
+            server: base::CLI_NAME,
+            server_version: base::VERSION,
+            open_subsonic: true,
+            error,
+            payload,
+        };
+        let mut value = match serde_json::to_value(response) {
+            Ok(value) => value,
+            Err(error) => {
+                tracing::error!(%error, "Could not serialize Subsonic response");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+        match self.format {
+            Format::Xml => {
+                if let Value::Object(map) = &mut value {
+                    map.insert("xmlns".to_string(), XML_NAMESPACE.into());
+                }
+                let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+                write_element(&mut xml, "subsonic-response", &value);
+                ([(header::CONTENT_TYPE, "text/xml; charset=utf-8")], xml).into_response()
+            }
+            Format::Json => (
+                [(header::CONTENT_TYPE, "application/json")],
+                json!({ "subsonic-response": value }).to_string(),
+            )
+                .into_response(),
+            Format::Jsonp(callback) => (
+                [(header::CONTENT_TYPE, "application/javascript")],
+                format!("{}({});", callback, json!({ "subsonic-response": value })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthParams {
+    u: Option<String>,
+    p: Option<String>,
+    t: Option<String>,
+    #[serde(rename = "apiKey")]
+    api_key: Option<String>,
+    c: Option<String>,
+}
+
+/// An authenticated Subsonic request
+pub struct User {
+    pub username: String,
+    pub client: Option<String>,
+    pub format: Format,
+}
+
+impl User {
+    pub fn reply(&self, result: Result<Payload, SubsonicError>) -> Reply {
+        Reply::new(self.format.clone(), result.map(Some))
+    }
+
+    pub fn empty(&self, result: Result<(), SubsonicError>) -> Reply {
+        Reply::new(self.format.clone(), result.map(|_| None))
+    }
+
+    pub fn fail<E: Into<SubsonicError>>(&self, error: E) -> Reply {
+        Reply::new(self.format.clone(), Err(error.into()))
+    }
+}
+
+// Passwords may be sent hex encoded, prefixed by "enc:"
+fn decode_password(password: &str) -> Result<String, SubsonicError> {
+    let Some(hex) = password.strip_prefix("enc:") else {
+        return Ok(password.to_owned());
+    };
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(SubsonicError::WrongCredentials)?;
+    String::from_utf8(bytes).map_err(|_| SubsonicError::WrongCredentials)
+}
+
+// The salted token scheme needs the clear text password, which we never store,
+// so only passwords (checked through the usual auth chain) and API keys are accepted.
+// API keys are the API tokens users create for their clients. Passwords are checked on every request, so
+// successful checks are cached for a short while.
+async fn authenticate(db: &DbConn, params: &AuthParams) -> Result<String, SubsonicError> {
+    match (&params.api_key, &params.u, &params.p, &params.t) {
+        (Some(_), Some(_), _, _) => Err(SubsonicError::ConflictingAuthentication),
+        (Some(key), None, _, _) => auth::authenticate_api_token(db, key)
+            .await
+            .map_err(|error| match error {
+                AuthError::Database(error) => error.into(),
+                _ => SubsonicError::InvalidApiKey,
+            }),
+        (None, Some(username), Some(password), _) => {
+            let password = decode_password(password)?;
+            auth::authenticate_cached(db, username, &password)
+                .await
+                .map_err(|error| {
+                    tracing::debug!(%error, user = %username, "Subsonic authentication failed");
+                    SubsonicError::WrongCredentials
+                })
+        }
+        (None, Some(_), None, Some(_)) => Err(SubsonicError::TokenAuthentication),
+        (None, Some(_), None, None) => Err(SubsonicError::MissingParameter("p")),
+        (None, None, _, _) => Err(SubsonicError::MissingParameter("u")),
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for User {
+    type Rejection = Reply;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        AppState(db): &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let format = Format::from_parts(parts);
+        let params: AuthParams = serde_urlencoded::from_str(parts.uri.query().unwrap_or_default())
+            .map_err(|error| {
+                Reply::new(
+                    format.clone(),
+                    Err(SubsonicError::InvalidParameters(error.to_string())),
+                )
+            })?;
+        match authenticate(db, &params).await {
+            Ok(username) => Ok(Self {
+                username,
+                client: params.c,
+                format,
+            }),
+            Err(error) => Err(Reply::new(format, Err(error))),
+        }
+    }
+}
+
+/// The endpoint specific parameters of a Subsonic request
+pub struct Params<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for Params<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Reply;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        serde_urlencoded::from_str(parts.uri.query().unwrap_or_default())
+            .map(Params)
+            .map_err(|error| {
+                Reply::new(
+                    Format::from_parts(parts),
+                    Err(SubsonicError::InvalidParameters(error.to_string())),
+                )
+            })
+    }
+}
+
+/// Returns every value of a parameter which can be repeated, such as `id`
+pub fn repeated(query: Option<&str>, key: &str) -> Vec<String> {
+    url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .filter(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+        .collect()
+}
@@ -0,0 +1,113 @@
+use axum::extract::State;
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::{
+    browsing::{songs_to_documents, IdParams},
+    documents::{Payload, Playlist, PlaylistWithSongs, Playlists},
+    Params, Reply, SubsonicError, User,
+};
+use crate::api::{tempo::playlists, AppState, Error};
+
+// The tracks of each playlist, in order
+async fn playlist_tracks<C>(
+    db: &C,
+    entities: &[entity::Playlist],
+) -> Result<Vec<Vec<entity::Track>>, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let related = playlists::related(db, entities).await?;
+    let tracks: HashMap<Uuid, entity::Track> = entity::TrackEntity::find()
+        .filter(
+            entity::TrackColumn::Id.is_in(
+                related
+                    .iter()
+                    .flat_map(|r| r.entries.iter().map(|entry| entry.track_id)),
+            ),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|track| (track.id, track))
+        .collect();
+    Ok(related
+        .iter()
+        .map(|r| {
+            r.entries
+                .iter()
+                .filter_map(|entry| tracks.get(&entry.track_id).cloned())
+                .collect()
+        })
+        .collect())
+}
+
+fn playlist_to_document(playlist: &entity::Playlist, tracks: &[entity::Track]) -> Playlist {
+    Playlist {
+        id: playlist.id,
+        name: playlist.name.to_owned(),
+        comment: playlist.description.to_owned(),
+        owner: playlist.user.to_owned(),
+        public: playlist.public,
+        song_count: tracks.len(),
+        duration: (tracks.iter().map(|t| t.length.max(0) as i64).sum::<i64>() / 1000) as u32,
+        created: playlist.created_at,
+        changed: playlist.updated_at,
+        cover_art: playlist.image_id.to_owned(),
+    }
+}
+
+// The playlists the user can see: their own and the public ones of other users
+async fn fetch_playlists<C>(db: &C, username: &str) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let entities = entity::PlaylistEntity::find()
+        .filter(
+            Condition::any()
+                .add(ColumnTrait::eq(&entity::PlaylistColumn::User, username))
+                .add(ColumnTrait::eq(&entity::PlaylistColumn::Public, true)),
+        )
+        .order_by_asc(entity::PlaylistColumn::Name)
+        .all(db)
+        .await?;
+    let tracks = playlist_tracks(db, &entities).await?;
+    Ok(Payload::Playlists(Playlists {
+        playlist: entities
+            .iter()
+            .zip(tracks.iter())
+            .map(|(playlist, tracks)| playlist_to_document(playlist, tracks))
+            .collect(),
+    }))
+}
+
+pub async fn get_playlists(State(AppState(db)): State<AppState>, user: User) -> Reply {
+    user.reply(fetch_playlists(&db, &user.username).await)
+}
+
+async fn fetch_playlist<C>(db: &C, username: &str, id: Uuid) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let playlist = playlists::find_playlist(db, username, id, false).await?;
+    let tracks = playlist_tracks(db, &[playlist.to_owned()])
+        .await?
+        .pop()
+        .ok_or(Error::NotFound(None))?;
+    Ok(Payload::Playlist(PlaylistWithSongs {
+        playlist: playlist_to_document(&playlist, &tracks),
+        entry: songs_to_documents(db, username, &tracks).await?,
+    }))
+}
+
+pub async fn get_playlist(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<IdParams>,
+) -> Reply {
+    match params.id() {
+        Ok(id) => user.reply(fetch_playlist(&db, &user.username, id).await),
+        Err(error) => user.fail(error),
+    }
+}
@@ -0,0 +1,145 @@
+use axum::extract::State;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{
+    browsing::{albums_to_documents, artists_to_documents, songs_to_documents},
+    documents::{Payload, SearchResult3},
+    Params, Reply, SubsonicError, User,
+};
+use crate::api::{AppState, Error};
+use crate::search::{
+    db::{do_search, get_ids, Index},
+    get_indexes,
+};
+
+fn default_count() -> u32 {
+    20
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchParams {
+    #[serde(default)]
+    query: String,
+    #[serde(default = "default_count")]
+    artist_count: u32,
+    #[serde(default)]
+    artist_offset: u32,
+    #[serde(default = "default_count")]
+    album_count: u32,
+    #[serde(default)]
+    album_offset: u32,
+    #[serde(default = "default_count")]
+    song_count: u32,
+    #[serde(default)]
+    song_offset: u32,
+}
+
+// Returns the ids of the matches, best first
+fn search(index: Index, query: &str, count: u32, offset: u32) -> Result<Vec<Uuid>, Error> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let results = do_search(index, query, offset + count)?;
+    Ok(get_ids(results)?
+        .into_iter()
+        .skip(offset as usize)
+        .map(|(_, id)| id)
+        .collect())
+}
+
+// Sorts the models in the order of the given ids, as returned by the search
+fn ordered<T, F>(ids: &[Uuid], mut models: Vec<T>, id: F) -> Vec<T>
+where
+    F: Fn(&T) -> Uuid,
+{
+    models.sort_by_key(|model| ids.iter().position(|i| *i == id(model)));
+    models
+}
+
+async fn do_search3<C>(
+    db: &C,
+    username: &str,
+    params: SearchParams,
+) -> Result<Payload, SubsonicError>
+where
+    C: ConnectionTrait,
+{
+    let query = params.query.trim().trim_matches('"');
+    // an empty query lists everything, which clients use to sync the whole library
+    let (artists, releases, tracks) = if query.is_empty() {
+        let artists = entity::ArtistEntity::find()
+            .order_by_asc(entity::ArtistColumn::SortName)
+            .offset(params.artist_offset as u64)
+            .limit(params.artist_count as u64)
+            .all(db)
+            .await?;
+        let releases = entity::ReleaseEntity::find()
+            .order_by_asc(entity::ReleaseColumn::Title)
+            .offset(params.album_offset as u64)
+            .limit(params.album_count as u64)
+            .all(db)
+            .await?;
+        let tracks = entity::TrackEntity::find()
+            .order_by_asc(entity::TrackColumn::Title)
+            .offset(params.song_offset as u64)
+            .limit(params.song_count as u64)
+            .all(db)
+            .await?;
+        (artists, releases, tracks)
+    } else {
+        let indexes = get_indexes().map_err(Error::from)?;
+        let artist_ids = search(
+            Index::Artists(&indexes.artists),
+            query,
+            params.artist_count,
+            params.artist_offset,
+        )?;
+        let release_ids = search(
+            Index::Releases(&indexes.releases),
+            query,
+            params.album_count,
+            params.album_offset,
+        )?;
+        let track_ids = search(
+            Index::Tracks(&indexes.tracks),
+            query,
+            params.song_count,
+            params.song_offset,
+        )?;
+
+        let artists = entity::ArtistEntity::find()
+            .filter(entity::ArtistColumn::Id.is_in(artist_ids.iter().copied()))
+            .all(db)
+            .await?;
+        let releases = entity::ReleaseEntity::find()
+            .filter(entity::ReleaseColumn::Id.is_in(release_ids.iter().copied()))
+            .all(db)
+            .await?;
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(track_ids.iter().copied()))
+            .all(db)
+            .await?;
+        (
+            ordered(&artist_ids, artists, |a| a.id),
+            ordered(&release_ids, releases, |r| r.id),
+            ordered(&track_ids, tracks, |t| t.id),
+        )
+    };
+
+    Ok(Payload::SearchResult3(SearchResult3 {
+        artist: artists_to_documents(db, username, &artists).await?,
+        album: albums_to_documents(db, username, &releases).await?,
+        song: songs_to_documents(db, username, &tracks).await?,
+    }))
+}
+
+pub async fn search3(
+    State(AppState(db)): State<AppState>,
+    user: User,
+    Params(params): Params<SearchParams>,
+) -> Reply {
+    user.reply(do_search3(&db, &user.username, params).await)
+}
@@ -0,0 +1,34 @@
+use super::{
+    documents::{License, OpenSubsonicExtension, Payload},
+    Format, Reply, User,
+};
+
+pub async fn ping(user: User) -> Reply {
+    user.empty(Ok(()))
+}
+
+pub async fn get_license(user: User) -> Reply {
+    user.reply(Ok(Payload::License(License { valid: true })))
+}
+
+// Available without authentication, so that clients can pick an auth scheme
+pub async fn get_open_subsonic_extensions(format: Format) -> Reply {
+    let extensions = vec![
+        OpenSubsonicExtension {
+            name: "apiKeyAuthentication",
+            versions: vec![1],
+        },
+        OpenSubsonicExtension {
+            name: "formPost",
+            versions: vec![1],
+        },
+        OpenSubsonicExtension {
+            name: "transcodeOffset",
+            versions: vec![1],
+        },
+    ];
+    Reply::new(
+        format,
+        Ok(Some(Payload::OpenSubsonicExtensions(extensions))),
+    )
+}
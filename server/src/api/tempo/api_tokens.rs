@@ -0,0 +1,194 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::StatusCode,
+};
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::{
+    auth::api_token_digest,
+    documents::{
+        ApiTokenAttributes, ApiTokenFilter, ApiTokenInclude, ApiTokenRelation, ApiTokenResource,
+        Included, InsertApiTokenResource, IntoColumn, ResourceType,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        links_from_resource, make_cursor, Document, DocumentData, InsertOneDocument, Query,
+        Related, Relation, Relationship, ResourceIdentifier,
+    },
+    tempo::users,
+    AppState, Error,
+};
+use base::util::dedup;
+
+/// The length of generated tokens, in alphanumeric characters
+static TOKEN_LENGTH: usize = 40;
+/// The maximum length of the label of a token
+static MAX_NAME_LENGTH: usize = 128;
+
+pub fn entity_to_resource(entity: &entity::ApiToken, token: Option<String>) -> ApiTokenResource {
+    let relationships = [(
+        ApiTokenRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    )]
+    .into();
+    ApiTokenResource {
+        r#type: ResourceType::ApiToken,
+        id: entity.id,
+        attributes: ApiTokenAttributes {
+            name: entity.name.to_owned(),
+            token,
+            created_at: entity.created_at,
+            last_used_at: entity.last_used_at,
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+pub async fn included<C>(
+    db: &C,
+    entities: &[entity::ApiToken],
+    include: &[ApiTokenInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&ApiTokenInclude::User) {
+        let usernames: HashSet<&str> = entities.iter().map(|t| t.user.as_str()).collect();
+        let users = entity::UserEntity::find()
+            .filter(entity::UserColumn::Username.is_in(usernames))
+            .all(db)
+            .await?;
+        let users_related = users::related(db, &users, true).await?;
+        for (user, related) in users.iter().zip(users_related.iter()) {
+            included.push(users::entity_to_included(user, related));
+        }
+    }
+    Ok(included)
+}
+
+/// Creates a new token for the user. The token is returned along with the entity,
+/// as only its digest is stored and it can't be retrieved afterwards.
+pub async fn create<C>(
+    db: &C,
+    username: &str,
+    name: &str,
+) -> Result<(entity::ApiToken, String), Error>
+where
+    C: ConnectionTrait,
+{
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(Error::BadRequest(Some(format!(
+            "The name of a token must be between 1 and {} characters long",
+            MAX_NAME_LENGTH
+        ))));
+    }
+
+    let token = Alphanumeric.sample_string(&mut rand::thread_rng(), TOKEN_LENGTH);
+    let api_token = entity::ApiTokenActive {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user: ActiveValue::Set(username.to_owned()),
+        name: ActiveValue::Set(name.to_owned()),
+        digest: ActiveValue::Set(api_token_digest(&token)),
+        created_at: ActiveValue::Set(OffsetDateTime::now_utc()),
+        last_used_at: ActiveValue::Set(None),
+    }
+    .insert(db)
+    .await?;
+    Ok((api_token, token))
+}
+
+async fn find_api_token_by_id<C>(
+    db: &C,
+    username: &str,
+    id: Uuid,
+) -> Result<entity::ApiToken, Error>
+where
+    C: ConnectionTrait,
+{
+    entity::ApiTokenEntity::find_by_id(id)
+        .filter(ColumnTrait::eq(&entity::ApiTokenColumn::User, username))
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))
+}
+
+pub async fn api_tokens(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<ApiTokenFilter, entity::ApiTokenColumn, ApiTokenInclude, Uuid>,
+    OriginalUri(uri): OriginalUri,
+    claims: Claims,
+) -> Result<Json<Document<ApiTokenResource, Included>>, Error> {
+    let tx = db.begin().await?;
+
+    let mut tokens_query = entity::ApiTokenEntity::find().filter(ColumnTrait::eq(
+        &entity::ApiTokenColumn::User,
+        claims.username,
+    ));
+    for (filter_key, filter_value) in opts.filter.iter() {
+        if let Some(k) = filter_key.column() {
+            tokens_query = tokens_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        }
+    }
+    let mut _tokens_cursor = tokens_query.cursor_by(entity::ApiTokenColumn::Id);
+    let tokens_cursor = make_cursor(&mut _tokens_cursor, &opts.page);
+    let tokens = tokens_cursor.all(&tx).await?;
+    let data = tokens
+        .iter()
+        .map(|token| entity_to_resource(token, None))
+        .collect::<Vec<_>>();
+    let included = included(&tx, &tokens, &opts.include).await?;
+    Ok(Json(Document {
+        links: links_from_resource(&data, opts, &uri),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+pub async fn insert_api_token(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Json(body): Json<InsertOneDocument<InsertApiTokenResource>>,
+) -> Result<Json<Document<ApiTokenResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let (api_token, token) = create(&tx, &claims.username, &body.data.attributes.name).await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, id = %api_token.id, "Created API token");
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&api_token, Some(token))),
+        included: Vec::new(),
+    }))
+}
+
+pub async fn delete_api_token(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    let api_token = find_api_token_by_id(&tx, &claims.username, id).await?;
+    entity::ApiTokenEntity::delete_by_id(api_token.id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, %id, "Revoked API token");
+
+    Ok(StatusCode::OK)
+}
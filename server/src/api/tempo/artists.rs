@@ -13,12 +13,12 @@ use crate::api::{
         ArtistResource, Included, IntoColumn, Meta, RecordingAttributes, ReleaseInclude,
         ResourceType,
     },
-    extract::{Json, Path},
+    extract::{Claims, Json, Path},
     jsonapi::{
         links_from_resource, make_cursor, Document, DocumentData, Query, Related, Relation,
         Relationship, ResourceIdentifier,
     },
-    tempo::{images, releases, tracks},
+    tempo::{favorites, images, releases, tracks},
     AppState, Error,
 };
 use base::util::dedup;
@@ -295,6 +295,7 @@ pub async fn artists(
     State(AppState(db)): State<AppState>,
     Query(opts): Query<ArtistFilter, entity::ArtistColumn, ArtistInclude, uuid::Uuid>,
     OriginalUri(uri): OriginalUri,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<ArtistResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -302,6 +303,13 @@ pub async fn artists(
     for (filter_key, filter_value) in opts.filter.iter() {
         if let Some(k) = filter_key.column() {
             artists_query = artists_query.filter(ColumnTrait::eq(&k, filter_value));
+        } else if *filter_key == ArtistFilter::Starred {
+            artists_query = artists_query.filter(favorites::filter(
+                entity::ArtistColumn::Id,
+                claims.as_ref(),
+                entity::FavoriteKind::Artist,
+                filter_value,
+            )?);
         } else if let ArtistFilter::Include(incl) = filter_key {
             match (incl, filter_value.as_str()) {
                 (ArtistInclude::Releases, "true") => {
@@ -337,6 +345,13 @@ pub async fn artists(
     for (i, artist) in artists.iter().enumerate() {
         data.push(entity_to_resource(artist, &related_to_artists[i]));
     }
+    favorites::star(
+        &tx,
+        claims.as_ref(),
+        entity::FavoriteKind::Artist,
+        &mut data,
+    )
+    .await?;
     let included = included(&tx, related_to_artists, &opts.include).await?;
     Ok(Json(Document {
         links: links_from_resource(&data, opts, &uri),
@@ -349,6 +364,7 @@ pub async fn artist(
     State(AppState(db)): State<AppState>,
     Path(id): Path<Uuid>,
     Query(opts): Query<ArtistFilter, entity::ArtistColumn, ArtistInclude, uuid::Uuid>,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<ArtistResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -359,7 +375,14 @@ pub async fn artist(
     let related_to_artists = related(&tx, &[artist.clone()], false).await?;
     let empty_relationship = ArtistRelated::default();
     let related = related_to_artists.first().unwrap_or(&empty_relationship);
-    let data = entity_to_resource(&artist, related);
+    let mut data = entity_to_resource(&artist, related);
+    favorites::star(
+        &tx,
+        claims.as_ref(),
+        entity::FavoriteKind::Artist,
+        std::slice::from_mut(&mut data),
+    )
+    .await?;
     let included = included(&tx, related_to_artists, &opts.include).await?;
     Ok(Json(Document {
         data: DocumentData::Single(data),
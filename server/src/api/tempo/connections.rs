@@ -5,6 +5,7 @@ use axum::{
 };
 use lazy_static::lazy_static;
 use reqwest::{Error as ReqwestError, Method, Request};
+use schemars::JsonSchema;
 use sea_orm::{ActiveModelTrait, IntoActiveModel};
 use serde::Deserialize;
 use std::{collections::HashMap, sync::Arc};
@@ -40,7 +41,7 @@ lazy_static! {
     )];
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct CallbackOptions {
     pub token: String,
     pub id: Uuid,
@@ -0,0 +1,270 @@
+use axum::{extract::State, http::StatusCode};
+use entity::IgnoreNone;
+use sea_orm::{
+    sea_query::{Query as SeaQuery, SelectStatement},
+    ActiveValue, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use std::collections::HashMap;
+use std::hash::Hash;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{Included, Meta, ResourceType, UserMetaAttributes},
+    extract::{Claims, Json, Path},
+    jsonapi::{Document, DocumentData, InsertManyRelation, Related, Resource, ResourceIdentifier},
+    AppState, Error,
+};
+
+fn resource_type(kind: entity::FavoriteKind) -> ResourceType {
+    match kind {
+        entity::FavoriteKind::Track => ResourceType::Track,
+        entity::FavoriteKind::Release => ResourceType::Release,
+        entity::FavoriteKind::Artist => ResourceType::Artist,
+    }
+}
+
+pub fn entity_to_related(entity: &entity::Favorite) -> Related<ResourceType, Meta> {
+    Related::Uuid(ResourceIdentifier {
+        r#type: resource_type(entity.kind),
+        id: entity.resource,
+        meta: Some(Meta::User(UserMetaAttributes {
+            starred_at: Some(entity.created_at),
+            ..Default::default()
+        })),
+    })
+}
+
+fn kind(r#type: &ResourceType) -> Result<entity::FavoriteKind, Error> {
+    match r#type {
+        ResourceType::Track => Ok(entity::FavoriteKind::Track),
+        ResourceType::Release => Ok(entity::FavoriteKind::Release),
+        ResourceType::Artist => Ok(entity::FavoriteKind::Artist),
+        _ => Err(Error::BadRequest(Some(
+            "Only tracks, releases and artists can be starred".to_string(),
+        ))),
+    }
+}
+
+/// Returns when each of the given resources was starred by the user
+pub async fn starred<C, I>(
+    db: &C,
+    username: &str,
+    kind: entity::FavoriteKind,
+    ids: I,
+) -> Result<HashMap<Uuid, OffsetDateTime>, DbErr>
+where
+    C: ConnectionTrait,
+    I: IntoIterator<Item = Uuid>,
+{
+    Ok(entity::FavoriteEntity::find()
+        .filter(ColumnTrait::eq(&entity::FavoriteColumn::User, username))
+        .filter(ColumnTrait::eq(&entity::FavoriteColumn::Kind, kind))
+        .filter(entity::FavoriteColumn::Resource.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|favorite| (favorite.resource, favorite.created_at))
+        .collect())
+}
+
+/// Sets the starred meta on the resources starred by the user, when the request
+/// is authenticated
+pub async fn star<C, RT, T, R>(
+    db: &C,
+    claims: Option<&Claims>,
+    kind: entity::FavoriteKind,
+    resources: &mut [Resource<RT, Uuid, T, R, Meta>],
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+    R: Hash + Eq,
+{
+    let Some(claims) = claims else {
+        return Ok(());
+    };
+    let starred = starred(
+        db,
+        &claims.username,
+        kind,
+        resources.iter().map(|resource| resource.id),
+    )
+    .await?;
+    for resource in resources.iter_mut() {
+        if let Some(starred_at) = starred.get(&resource.id) {
+            Meta::user(&mut resource.meta).starred_at = Some(*starred_at);
+        }
+    }
+    Ok(())
+}
+
+fn starred_by(username: &str, kind: entity::FavoriteKind) -> SelectStatement {
+    SeaQuery::select()
+        .column(entity::FavoriteColumn::Resource)
+        .from(entity::FavoriteEntity)
+        .and_where(ColumnTrait::eq(&entity::FavoriteColumn::User, username))
+        .and_where(ColumnTrait::eq(&entity::FavoriteColumn::Kind, kind))
+        .to_owned()
+}
+
+/// The condition for `filter[starred]`, on the id column of the resources
+pub fn filter<C>(
+    column: C,
+    claims: Option<&Claims>,
+    kind: entity::FavoriteKind,
+    value: &str,
+) -> Result<Condition, Error>
+where
+    C: ColumnTrait,
+{
+    let claims = claims.ok_or(Error::Unauthorized(Some(
+        "Only authenticated users have starred resources".to_string(),
+    )))?;
+    let starred = starred_by(&claims.username, kind);
+    match value {
+        "true" => Ok(Condition::all().add(column.in_subquery(starred))),
+        "false" => Ok(Condition::all().add(column.not_in_subquery(starred))),
+        _ => Err(Error::BadRequest(Some(
+            "The starred filter must be true or false".to_string(),
+        ))),
+    }
+}
+
+fn favorite_ids(
+    data: &[ResourceIdentifier<ResourceType, Uuid, Meta>],
+) -> Result<Vec<(entity::FavoriteKind, Uuid)>, Error> {
+    data.iter()
+        .map(|identifier| Ok((kind(&identifier.r#type)?, identifier.id)))
+        .collect()
+}
+
+async fn check_favorites<C>(db: &C, favorites: &[(entity::FavoriteKind, Uuid)]) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    for kind in [
+        entity::FavoriteKind::Track,
+        entity::FavoriteKind::Release,
+        entity::FavoriteKind::Artist,
+    ] {
+        let ids: Vec<Uuid> = favorites
+            .iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, id)| *id)
+            .collect();
+        if ids.is_empty() {
+            continue;
+        }
+        let found = match kind {
+            entity::FavoriteKind::Track => {
+                entity::TrackEntity::find()
+                    .filter(entity::TrackColumn::Id.is_in(ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+            entity::FavoriteKind::Release => {
+                entity::ReleaseEntity::find()
+                    .filter(entity::ReleaseColumn::Id.is_in(ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+            entity::FavoriteKind::Artist => {
+                entity::ArtistEntity::find()
+                    .filter(entity::ArtistColumn::Id.is_in(ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+        };
+        if (found as usize) < ids.len() {
+            return Err(Error::NotFound(None));
+        }
+    }
+    Ok(())
+}
+
+/// The resources starred by the user, the most recent first
+pub async fn favorites(
+    State(AppState(db)): State<AppState>,
+    _claims: Claims,
+    Path(username): Path<String>,
+) -> Result<Json<Document<Related<ResourceType, Meta>, Included>>, Error> {
+    let favorites = entity::FavoriteEntity::find()
+        .filter(ColumnTrait::eq(&entity::FavoriteColumn::User, username))
+        .order_by_desc(entity::FavoriteColumn::CreatedAt)
+        .all(&db)
+        .await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Multi(favorites.iter().map(entity_to_related).collect()),
+        included: Vec::new(),
+    }))
+}
+
+pub async fn insert_favorites(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let favorites = favorite_ids(&body.data)?;
+    if favorites.is_empty() {
+        return Ok(StatusCode::OK);
+    }
+
+    let tx = db.begin().await?;
+    check_favorites(&tx, &favorites).await?;
+    let now = OffsetDateTime::now_utc();
+    entity::FavoriteEntity::insert_many(favorites.into_iter().map(|(kind, resource)| {
+        entity::FavoriteActive {
+            user: ActiveValue::Set(claims.username.to_owned()),
+            kind: ActiveValue::Set(kind),
+            resource: ActiveValue::Set(resource),
+            created_at: ActiveValue::Set(now),
+        }
+    }))
+    .on_conflict(entity::conflict::FAVORITE_CONFLICT.to_owned())
+    .exec(&tx)
+    .await
+    .ignore_none()?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_favorites(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let favorites = favorite_ids(&body.data)?;
+    if favorites.is_empty() {
+        return Ok(StatusCode::OK);
+    }
+
+    let mut condition = Condition::any();
+    for (kind, resource) in favorites.into_iter() {
+        condition = condition.add(
+            Condition::all()
+                .add(ColumnTrait::eq(&entity::FavoriteColumn::Kind, kind))
+                .add(ColumnTrait::eq(&entity::FavoriteColumn::Resource, resource)),
+        );
+    }
+    entity::FavoriteEntity::delete_many()
+        .filter(ColumnTrait::eq(
+            &entity::FavoriteColumn::User,
+            claims.username,
+        ))
+        .filter(condition)
+        .exec(&db)
+        .await?;
+
+    Ok(StatusCode::OK)
+}
@@ -1,15 +1,30 @@
+pub mod api_tokens;
 pub mod artists;
 pub mod connections;
+pub mod favorites;
 pub mod genres;
 pub mod images;
 pub mod mediums;
+pub mod now_playing;
+pub mod play_queue;
+pub mod playlists;
+pub mod ratings;
 pub mod releases;
+pub mod reviews;
 pub mod scrobbles;
 pub mod search;
+pub mod shares;
+pub mod smart_playlists;
+pub mod stats;
 pub mod tracks;
+pub mod unmatched_listens;
 pub mod users;
 
-use axum::{middleware::from_fn, routing::get, Router};
+use axum::{
+    middleware::from_fn,
+    routing::{delete, get, patch, post},
+    Router,
+};
 use std::collections::HashMap;
 
 use super::{
@@ -28,11 +43,60 @@ pub fn router() -> Router<AppState> {
         .route("/artists/:id", get(artists::artist))
         .route("/releases", get(releases::releases))
         .route("/releases/:id", get(releases::release))
+        .route("/releases/:id/archive", get(releases::archive))
+        .route(
+            "/releases/:id/hls/playlist.m3u8",
+            get(releases::hls_playlist),
+        )
+        .route("/releases/:id/hls/:segment", get(releases::hls_segment))
+        .route("/releases/:id/playlist", get(playlists::release_playlist))
         .route("/mediums/", get(mediums::mediums))
         .route("/mediums/:id", get(mediums::medium))
+        .route("/mediums/:id/playlist", get(playlists::medium_playlist))
+        .route(
+            "/playlists",
+            get(playlists::playlists).post(playlists::insert_playlist),
+        )
+        .route("/playlists/import", post(playlists::import))
+        .route(
+            "/playlists/:id",
+            get(playlists::playlist)
+                .patch(playlists::update_playlist)
+                .delete(playlists::delete_playlist),
+        )
+        .route("/playlists/:id/playlist", get(playlists::playlist_file))
+        .route(
+            "/playlists/:id/relationships/tracks",
+            get(playlists::tracks_relation)
+                .post(playlists::insert_tracks)
+                .patch(playlists::replace_tracks)
+                .delete(playlists::delete_tracks),
+        )
+        .route(
+            "/playlists/:id/relationships/tracks/move",
+            post(playlists::move_track),
+        )
+        .route(
+            "/smart-playlists",
+            get(smart_playlists::smart_playlists).post(smart_playlists::insert_smart_playlist),
+        )
+        .route(
+            "/smart-playlists/:id",
+            get(smart_playlists::smart_playlist)
+                .patch(smart_playlists::update_smart_playlist)
+                .delete(smart_playlists::delete_smart_playlist),
+        )
+        .route("/smart-playlists/:id/tracks", get(smart_playlists::tracks))
         .route("/tracks", get(tracks::tracks))
         .route("/tracks/:id", get(tracks::track))
         .route("/tracks/:id/audio", get(tracks::audio))
+        .route("/tracks/:id/waveform", get(tracks::waveform))
+        .route(
+            "/tracks/:id/lyrics",
+            get(tracks::lyrics).put(tracks::edit_lyrics),
+        )
+        .route("/tracks/:id/hls/playlist.m3u8", get(tracks::hls_playlist))
+        .route("/tracks/:id/hls/:segment", get(tracks::hls_segment))
         .route("/genres", get(genres::genres))
         .route("/genres/:id", get(genres::genre))
         .route(
@@ -40,7 +104,53 @@ pub fn router() -> Router<AppState> {
             get(scrobbles::scrobbles).put(scrobbles::insert_scrobbles),
         )
         .route("/scrobbles/:id", get(scrobbles::scrobble))
+        .route(
+            "/unmatched-listens",
+            get(unmatched_listens::unmatched_listens),
+        )
+        .route(
+            "/unmatched-listens/:id",
+            delete(unmatched_listens::delete_unmatched_listen),
+        )
+        .route(
+            "/unmatched-listens/:id/relationships/track",
+            patch(unmatched_listens::match_unmatched_listen),
+        )
+        .route(
+            "/now-playing",
+            get(now_playing::now_playing).put(now_playing::update_now_playing),
+        )
+        .route("/shares", get(shares::shares).post(shares::insert_share))
+        .route(
+            "/shares/:id",
+            get(shares::share).delete(shares::delete_share),
+        )
+        .route(
+            "/api-tokens",
+            get(api_tokens::api_tokens).post(api_tokens::insert_api_token),
+        )
+        .route("/api-tokens/:id", delete(api_tokens::delete_api_token))
         .route("/users/:username", get(users::user))
+        .route(
+            "/users/:username/relationships/favorites",
+            get(favorites::favorites)
+                .post(favorites::insert_favorites)
+                .delete(favorites::delete_favorites),
+        )
+        .route(
+            "/users/:username/relationships/ratings",
+            get(ratings::ratings)
+                .post(ratings::insert_ratings)
+                .delete(ratings::delete_ratings),
+        )
+        .route(
+            "/users/:username/queue",
+            get(play_queue::queue).put(play_queue::update_queue),
+        )
+        .route("/users/:username/stats", get(stats::stats))
+        .route("/users/:username/reviews", get(reviews::reviews))
+        .route("/users/:username/reviews/:year", get(reviews::review))
+        .route("/users/:username/reviews/:year/image", get(reviews::image))
         .route(
             "/users/:username/relationships/:relation",
             get(users::relation)
@@ -80,6 +190,22 @@ async fn server() -> Json<Document<ServerResource, Included>> {
                     "users",
                     "scrobbles",
                     "connections",
+                    "hls",
+                    "shares",
+                    "archive",
+                    "lyrics",
+                    "now_playing",
+                    "playlist_export",
+                    "playlist_import",
+                    "playlists",
+                    "smart_playlists",
+                    "favorites",
+                    "ratings",
+                    "play_queue",
+                    "stats",
+                    "year_reviews",
+                    "api_tokens",
+                    "openapi",
                 ]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -0,0 +1,365 @@
+use axum::extract::State;
+use lazy_static::lazy_static;
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+use taskie_client::InsertTask;
+use time::{Duration, OffsetDateTime};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, InsertNowPlayingResource, NowPlayingAttributes, NowPlayingFilter,
+        NowPlayingInclude, NowPlayingRelation, NowPlayingResource, ResourceType, TrackInclude,
+    },
+    extract::{Claims, Json},
+    jsonapi::{
+        Document, DocumentData, InsertOneDocument, Query, Related, Relation, Relationship,
+        ResourceIdentifier,
+    },
+    tempo::{scrobbles::schedule_scrobble_tasks, stats, tracks, users},
+    AppState, Error,
+};
+use crate::tasks::{self, TaskName};
+use base::setting::{get_settings, NowPlaying};
+use base::util::dedup;
+
+/// Tracks shorter than this, in milliseconds, are never scrobbled
+static MIN_SCROBBLE_LENGTH: u32 = 30_000;
+
+lazy_static! {
+    static ref SESSIONS: Arc<Mutex<HashMap<(String, String), Session>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// A playback session, one for each client of each user
+#[derive(Debug, Clone)]
+pub struct Session {
+    username: String,
+    client: String,
+    track: Uuid,
+    length: u32,
+    position: u32,
+    started_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    scrobbled: bool,
+}
+
+impl Session {
+    fn new(
+        username: &str,
+        client: &str,
+        track: &entity::Track,
+        position: u32,
+        now: OffsetDateTime,
+    ) -> Self {
+        Self {
+            username: username.to_owned(),
+            client: client.to_owned(),
+            track: track.id,
+            length: track.length.max(0) as u32,
+            position,
+            started_at: now - Duration::milliseconds(position as i64),
+            updated_at: now,
+            scrobbled: false,
+        }
+    }
+
+    fn id(&self) -> String {
+        format!("{}:{}", self.username, self.client)
+    }
+
+    // Sessions are kept until their track should have ended, plus the timeout,
+    // so that clients don't have to keep reporting their position
+    fn expires_at(&self, timeout: u64) -> OffsetDateTime {
+        let remaining = self.length.saturating_sub(self.position);
+        self.updated_at
+            + Duration::milliseconds(remaining as i64)
+            + Duration::seconds(timeout as i64)
+    }
+}
+
+// The position after which a track is scrobbled, following the Last.fm rules
+fn scrobble_threshold(settings: &NowPlaying, length: u32) -> Option<u32> {
+    if length < MIN_SCROBBLE_LENGTH {
+        return None;
+    }
+    let fraction = (length as f64 * settings.scrobble_fraction) as u64;
+    Some(fraction.min(settings.scrobble_after.saturating_mul(1000)) as u32)
+}
+
+fn prune(sessions: &mut HashMap<(String, String), Session>, timeout: u64) {
+    let now = OffsetDateTime::now_utc();
+    sessions.retain(|_, session| session.expires_at(timeout) > now);
+}
+
+fn entity_to_resource(session: &Session) -> NowPlayingResource {
+    let mut relationships = HashMap::new();
+    relationships.insert(
+        NowPlayingRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: session.username.to_owned(),
+                meta: None,
+            })),
+        },
+    );
+    relationships.insert(
+        NowPlayingRelation::Track,
+        Relationship {
+            data: Relation::Single(Related::Uuid(ResourceIdentifier {
+                r#type: ResourceType::Track,
+                id: session.track,
+                meta: None,
+            })),
+        },
+    );
+
+    NowPlayingResource {
+        r#type: ResourceType::NowPlaying,
+        id: session.id(),
+        attributes: NowPlayingAttributes {
+            client: session.client.to_owned(),
+            position: session.position,
+            started_at: session.started_at,
+            updated_at: session.updated_at,
+            scrobbled: session.scrobbled,
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+fn map_to_tracks_include(include: &[NowPlayingInclude]) -> Vec<TrackInclude> {
+    include
+        .iter()
+        .filter_map(|i| match *i {
+            NowPlayingInclude::TrackArtists => Some(TrackInclude::Artists),
+            NowPlayingInclude::TrackMedium => Some(TrackInclude::Medium),
+            NowPlayingInclude::TrackMediumRelease => Some(TrackInclude::MediumRelease),
+            NowPlayingInclude::TrackMediumReleaseArtists => {
+                Some(TrackInclude::MediumReleaseArtists)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+async fn included<C>(
+    db: &C,
+    sessions: &[Session],
+    include: &[NowPlayingInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&NowPlayingInclude::User) {
+        let users = entity::UserEntity::find()
+            .filter(entity::UserColumn::Username.is_in(sessions.iter().map(|s| s.username.clone())))
+            .all(db)
+            .await?;
+        let users_related = users::related(db, &users, true).await?;
+        for (i, user) in users.iter().enumerate() {
+            included.push(users::entity_to_included(user, &users_related[i]));
+        }
+    }
+    if include.contains(&NowPlayingInclude::Track) {
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(sessions.iter().map(|s| s.track)))
+            .all(db)
+            .await?;
+        let tracks_related = tracks::related(db, &tracks, true).await?;
+        for (i, track) in tracks.iter().enumerate() {
+            included.push(tracks::entity_to_included(track, &tracks_related[i]));
+        }
+        let tracks_include = map_to_tracks_include(include);
+        included.extend(tracks::included(db, tracks_related, &tracks_include).await?);
+    }
+    Ok(included)
+}
+
+// Tells every service the user is connected to about the track they started playing
+async fn schedule_now_playing_tasks<C>(db: &C, username: &str, track_id: Uuid) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let connections = entity::UserConnectionEntity::find()
+        .filter(ColumnTrait::eq(
+            &entity::UserConnectionColumn::User,
+            username,
+        ))
+        .all(db)
+        .await?;
+    let updates = connections
+        .iter()
+        .map(|connection| InsertTask {
+            name: TaskName::NowPlaying,
+            payload: Some(json!(tasks::now_playing::Data {
+                provider: connection.provider,
+                username: username.to_owned(),
+                track_id,
+            })),
+            depends_on: Vec::new(),
+            duration: Duration::seconds(60),
+        })
+        .collect::<Vec<_>>();
+    if !updates.is_empty() {
+        if let Err(error) = tasks::push(&updates).await {
+            tracing::warn!(%error, user = %username, "Could not schedule now playing updates");
+        }
+    }
+    Ok(())
+}
+
+/// The sessions of the user, or of every user when now playing is public
+pub async fn now_playing(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Query(opts): Query<NowPlayingFilter, entity::UserColumn, NowPlayingInclude, String>,
+) -> Result<Json<Document<NowPlayingResource, Included>>, Error> {
+    let settings = get_settings()?;
+    let mut sessions = {
+        let mut sessions = SESSIONS.lock().await;
+        prune(&mut sessions, settings.now_playing.timeout);
+        sessions
+            .values()
+            .filter(|session| settings.now_playing.public || session.username == claims.username)
+            .filter(|session| {
+                opts.filter.iter().all(|(key, value)| match key {
+                    NowPlayingFilter::User => session.username == *value,
+                    NowPlayingFilter::Client => session.client == *value,
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+
+    let tx = db.begin().await?;
+    let data = sessions.iter().map(entity_to_resource).collect();
+    let included = included(&tx, &sessions, &opts.include).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+/// Records the position of the given track in the user's session for the client,
+/// scrobbling it once the threshold is reached. With `relay`, the track is also
+/// reported to the user's connections.
+pub async fn report<C>(
+    db: &C,
+    username: &str,
+    client: &str,
+    track_id: Uuid,
+    position: u32,
+    relay: bool,
+) -> Result<Session, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let settings = &get_settings()?.now_playing;
+    let tx = db.begin().await?;
+    let track = entity::TrackEntity::find_by_id(track_id)
+        .one(&tx)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let now = OffsetDateTime::now_utc();
+
+    let (session, started, scrobble) = {
+        let mut sessions = SESSIONS.lock().await;
+        prune(&mut sessions, settings.timeout);
+        let threshold = scrobble_threshold(settings, track.length.max(0) as u32);
+        let key = (username.to_owned(), client.to_owned());
+        let started = match sessions.get(&key) {
+            // going back before the threshold after a scrobble means the track is repeated
+            Some(session) if session.track == track.id => {
+                session.scrobbled
+                    && position < session.position
+                    && threshold.map_or(false, |t| position < t)
+            }
+            _ => true,
+        };
+        let session = sessions
+            .entry(key)
+            .or_insert_with(|| Session::new(username, client, &track, position, now));
+        if started {
+            *session = Session::new(username, client, &track, position, now);
+        }
+        session.position = position;
+        session.updated_at = now;
+        let scrobble = !session.scrobbled && threshold.map_or(false, |t| position >= t);
+        session.scrobbled |= scrobble;
+        (session.clone(), started, scrobble)
+    };
+
+    if started {
+        tracing::debug!(user = %username, %client, track = %track.id, "Now playing");
+        if relay {
+            schedule_now_playing_tasks(&tx, username, track.id).await?;
+        }
+    }
+    if scrobble {
+        tracing::info!(user = %username, track = %track.id, "Scrobbling now playing track");
+        entity::ScrobbleEntity::insert(entity::ScrobbleActive {
+            id: ActiveValue::NotSet,
+            at: ActiveValue::Set(session.started_at),
+            user: ActiveValue::Set(username.to_owned()),
+            track: ActiveValue::Set(track.id),
+        })
+        .exec(&tx)
+        .await?;
+    }
+    tx.commit().await?;
+    if scrobble {
+        stats::invalidate(username).await;
+    }
+
+    if scrobble && relay {
+        if let Err(error) =
+            schedule_scrobble_tasks(username, std::iter::once((track.id, session.started_at))).await
+        {
+            tracing::warn!(%error, user = %username, "Could not schedule scrobble tasks");
+        }
+    }
+    Ok(session)
+}
+
+pub async fn update_now_playing(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Json(body): Json<InsertOneDocument<InsertNowPlayingResource>>,
+) -> Result<Json<Document<NowPlayingResource, Included>>, Error> {
+    let track_id = match body
+        .data
+        .relationships
+        .get(&NowPlayingRelation::Track)
+        .map(|r| &r.data)
+    {
+        Some(Relation::Single(Related::Uuid(data))) => Ok(data.id),
+        _ => Err(Error::BadRequest(Some(
+            "The session must be related to a track".to_string(),
+        ))),
+    }?;
+    let session = report(
+        &db,
+        &claims.username,
+        &body.data.attributes.client,
+        track_id,
+        body.data.attributes.position,
+        true,
+    )
+    .await?;
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&session)),
+        included: Vec::new(),
+    }))
+}
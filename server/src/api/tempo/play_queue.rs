@@ -0,0 +1,277 @@
+use axum::extract::State;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, PlayQueueAttributes, PlayQueueInclude, PlayQueueRelation, PlayQueueResource,
+        ResourceType, TrackInclude, UpdatePlayQueueResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        Document, DocumentData, Query, Related, Relation, Relationship, ResourceIdentifier,
+        UpdateOneDocument,
+    },
+    tempo::tracks,
+    AppState, Error,
+};
+use base::util::dedup;
+
+fn entity_to_resource(username: &str, entity: Option<&entity::PlayQueue>) -> PlayQueueResource {
+    let tracks = entity
+        .map(|queue| queue.tracks.0.as_slice())
+        .unwrap_or_default();
+    let relationships = [(
+        PlayQueueRelation::Tracks,
+        Relationship {
+            data: Relation::Multi(
+                tracks
+                    .iter()
+                    .map(|id| {
+                        Related::Uuid(ResourceIdentifier {
+                            r#type: ResourceType::Track,
+                            id: *id,
+                            meta: None,
+                        })
+                    })
+                    .collect(),
+            ),
+        },
+    )]
+    .into();
+
+    PlayQueueResource {
+        r#type: ResourceType::PlayQueue,
+        id: username.to_owned(),
+        attributes: PlayQueueAttributes {
+            index: entity.map(|queue| queue.index as u32).unwrap_or_default(),
+            position: entity
+                .map(|queue| queue.position as u64)
+                .unwrap_or_default(),
+            client: entity.and_then(|queue| queue.client.to_owned()),
+            version: entity.map(|queue| queue.version).unwrap_or_default(),
+            updated_at: entity.map(|queue| queue.updated_at),
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+fn map_to_tracks_include(include: &[PlayQueueInclude]) -> Vec<TrackInclude> {
+    include
+        .iter()
+        .filter_map(|i| match *i {
+            PlayQueueInclude::TracksArtists => Some(TrackInclude::Artists),
+            PlayQueueInclude::TracksMedium => Some(TrackInclude::Medium),
+            PlayQueueInclude::TracksMediumRelease => Some(TrackInclude::MediumRelease),
+            PlayQueueInclude::TracksMediumReleaseArtists => {
+                Some(TrackInclude::MediumReleaseArtists)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+async fn included<C>(
+    db: &C,
+    claims: &Claims,
+    entity: Option<&entity::PlayQueue>,
+    include: &[PlayQueueInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    let Some(queue) = entity else {
+        return Ok(included);
+    };
+    if include.contains(&PlayQueueInclude::Tracks) {
+        let ids: HashSet<Uuid> = queue.tracks.0.iter().copied().collect();
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(ids))
+            .all(db)
+            .await?;
+        let tracks_related = tracks::related(db, &tracks, true).await?;
+        included.extend(
+            tracks::entities_to_included(db, Some(claims), &tracks, &tracks_related).await?,
+        );
+        let tracks_include = map_to_tracks_include(include);
+        included.extend(tracks::included(db, tracks_related, &tracks_include).await?);
+    }
+    Ok(included)
+}
+
+// Tracks removed from the library since the queue was saved are left out
+async fn find_queue<C>(db: &C, username: &str) -> Result<Option<entity::PlayQueue>, Error>
+where
+    C: ConnectionTrait,
+{
+    let Some(mut queue) = entity::PlayQueueEntity::find_by_id(username.to_owned())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let found: HashSet<Uuid> = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(queue.tracks.0.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|track| track.id)
+        .collect();
+    if found.len() < queue.tracks.0.iter().collect::<HashSet<_>>().len() {
+        let current = queue.tracks.0.get(queue.index as usize).copied();
+        queue.tracks.0.retain(|id| found.contains(id));
+        match current.and_then(|current| queue.tracks.0.iter().position(|id| *id == current)) {
+            Some(index) => queue.index = index as i32,
+            None => {
+                queue.index = 0;
+                queue.position = 0;
+            }
+        }
+    }
+    Ok(Some(queue))
+}
+
+/// The play queue of the user, which is empty with version 0 when it was never
+/// saved
+pub async fn queue(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Query(opts): Query<String, entity::PlayQueueColumn, PlayQueueInclude, String>,
+) -> Result<Json<Document<PlayQueueResource, Included>>, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let tx = db.begin().await?;
+    let queue = find_queue(&tx, &username).await?;
+    let included = included(&tx, &claims, queue.as_ref(), &opts.include).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&username, queue.as_ref())),
+        included: dedup(included),
+    }))
+}
+
+/// Replaces the play queue of the user. The update only happens when the
+/// version matches the stored one, so two clients can't overwrite each other's
+/// changes without reading them first.
+pub async fn update_queue(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Json(body): Json<UpdateOneDocument<UpdatePlayQueueResource>>,
+) -> Result<Json<Document<PlayQueueResource, Included>>, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let resource = body.data;
+    if resource.id != username {
+        return Err(Error::BadRequest(Some(
+            "The id of the resource does not match the URL".to_string(),
+        )));
+    }
+    let ids = match resource.relationships.get(&PlayQueueRelation::Tracks) {
+        Some(relationship) => tracks::relation_ids(&relationship.data, "a play queue")?,
+        None => Vec::new(),
+    };
+    let attributes = resource.attributes;
+    if (attributes.index as usize) >= ids.len().max(1) {
+        return Err(Error::BadRequest(Some(
+            "The index of the current track is out of the queue".to_string(),
+        )));
+    }
+
+    let version = attributes
+        .version
+        .checked_add(1)
+        .ok_or(Error::BadRequest(Some(
+            "The version of the play queue is out of range".to_string(),
+        )))?;
+
+    let tx = db.begin().await?;
+    tracks::check_exist(&tx, &ids).await?;
+    let queue = entity::PlayQueue {
+        user: username.to_owned(),
+        tracks: entity::PlayQueueTracks(ids),
+        index: attributes.index as i32,
+        position: attributes.position as i64,
+        client: attributes.client.filter(|c| !c.is_empty()),
+        version,
+        updated_at: OffsetDateTime::now_utc(),
+    };
+    let changed = if attributes.version == 0 {
+        // the first save of the queue, which loses against any concurrent one
+        entity::PlayQueueEntity::insert(entity::PlayQueueActive {
+            user: ActiveValue::Set(queue.user.to_owned()),
+            tracks: ActiveValue::Set(queue.tracks.to_owned()),
+            index: ActiveValue::Set(queue.index),
+            position: ActiveValue::Set(queue.position),
+            client: ActiveValue::Set(queue.client.to_owned()),
+            version: ActiveValue::Set(queue.version),
+            updated_at: ActiveValue::Set(queue.updated_at),
+        })
+        .on_conflict(
+            OnConflict::column(entity::PlayQueueColumn::User)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&tx)
+        .await?
+    } else {
+        entity::PlayQueueEntity::update_many()
+            .col_expr(
+                entity::PlayQueueColumn::Tracks,
+                Expr::value(queue.tracks.to_owned()),
+            )
+            .col_expr(entity::PlayQueueColumn::Index, Expr::value(queue.index))
+            .col_expr(
+                entity::PlayQueueColumn::Position,
+                Expr::value(queue.position),
+            )
+            .col_expr(
+                entity::PlayQueueColumn::Client,
+                Expr::value(queue.client.to_owned()),
+            )
+            .col_expr(entity::PlayQueueColumn::Version, Expr::value(queue.version))
+            .col_expr(
+                entity::PlayQueueColumn::UpdatedAt,
+                Expr::value(queue.updated_at),
+            )
+            .filter(ColumnTrait::eq(
+                &entity::PlayQueueColumn::User,
+                username.as_str(),
+            ))
+            .filter(ColumnTrait::eq(
+                &entity::PlayQueueColumn::Version,
+                attributes.version,
+            ))
+            .exec(&tx)
+            .await?
+            .rows_affected
+    };
+    if changed == 0 {
+        return Err(Error::Conflict(Some(
+            "The play queue was changed since it was last read".to_string(),
+        )));
+    }
+    tx.commit().await?;
+    tracing::trace!(
+        user = %username,
+        version = queue.version,
+        tracks = queue.tracks.0.len(),
+        "Saved play queue"
+    );
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&username, Some(&queue))),
+        included: Vec::new(),
+    }))
+}
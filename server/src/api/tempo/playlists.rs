@@ -0,0 +1,1071 @@
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Query as AxumQuery, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, CursorTrait,
+    EntityTrait, LoaderTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use time::{Duration, OffsetDateTime};
+use url::Url;
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, InsertPlaylistResource, IntoColumn, Meta, PlaylistAttributes, PlaylistFilter,
+        PlaylistImportAttributes, PlaylistImportInclude, PlaylistImportRelation,
+        PlaylistImportResource, PlaylistInclude, PlaylistRelation, PlaylistResource, ResourceType,
+        TrackInclude, UnmatchedEntryAttributes, UpdatePlaylistResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        links_from_resource, make_cursor, Document, DocumentData, InsertManyRelation,
+        InsertOneDocument, Query, Related, Relation, Relationship, ResourceIdentifier,
+        UpdateOneDocument,
+    },
+    share,
+    tempo::{
+        images, releases,
+        scrobbles::{match_track, TrackQuery},
+        tracks, users,
+    },
+    AppState, Error,
+};
+use crate::playlist::{self, Entry, Item, Playlist, PlaylistFormat};
+use base::setting::{get_settings, Settings};
+use base::util::dedup;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExportQuery {
+    #[serde(default)]
+    format: PlaylistFormat,
+}
+
+// Exported playlists end up in other players and files, so the URLs never carry
+// the token of the request: players have to authenticate on their own
+/// How long the track links of an exported playlist keep working
+static EXPORT_LENGTH: Duration = Duration::days(30);
+
+fn url(settings: &Settings, path: &str) -> String {
+    let mut url = settings.url.clone();
+    url.set_path(path);
+    url.to_string()
+}
+
+// The artist credits joined in a single string, as displayed on the track
+fn credited(credits: &[entity::ArtistCredit], names: &HashMap<Uuid, String>) -> Option<String> {
+    if credits.is_empty() {
+        return None;
+    }
+    let mut credited = String::new();
+    for credit in credits.iter() {
+        credited += names.get(&credit.artist_id).map_or("", |n| n.as_str());
+        credited += credit.join_phrase.as_deref().unwrap_or_default();
+    }
+    Some(credited)
+}
+
+// Exported playlists are opened by players which can't log in, so the tracks are
+// linked through a share of the exported resource. It expires after a while and can
+// be revoked like any other share.
+async fn export_share<C>(
+    db: &C,
+    username: &str,
+    kind: entity::ShareKind,
+    resource: Uuid,
+) -> Result<String, Error>
+where
+    C: ConnectionTrait,
+{
+    let now = OffsetDateTime::now_utc();
+    let export = entity::ShareActive {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user: ActiveValue::Set(username.to_owned()),
+        kind: ActiveValue::Set(kind),
+        resource: ActiveValue::Set(resource),
+        created_at: ActiveValue::Set(now),
+        expires_at: ActiveValue::Set(now + EXPORT_LENGTH),
+        max_plays: ActiveValue::Set(None),
+        plays: ActiveValue::Set(0),
+    }
+    .insert(db)
+    .await?;
+    share::token(&export)
+}
+
+async fn items<C>(
+    db: &C,
+    settings: &Settings,
+    token: &str,
+    tracks: &[entity::Track],
+) -> Result<Vec<Item>, Error>
+where
+    C: ConnectionTrait,
+{
+    let related = tracks::related(db, tracks, true).await?;
+    let release_ids: HashSet<Uuid> = related
+        .iter()
+        .filter_map(|r| r.medium.as_ref().map(|m| m.release_id))
+        .collect();
+    let releases: HashMap<Uuid, entity::Release> = entity::ReleaseEntity::find()
+        .filter(entity::ReleaseColumn::Id.is_in(release_ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|release| (release.id, release))
+        .collect();
+    let images: HashMap<Uuid, String> = entity::ImageReleaseEntity::find()
+        .filter(entity::ImageReleaseColumn::ReleaseId.is_in(release_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|image| (image.release_id, image.image_id))
+        .collect();
+    let artist_ids: HashSet<Uuid> = related
+        .iter()
+        .flat_map(|r| r.artist_credits.iter().map(|c| c.artist_id))
+        .collect();
+    let names: HashMap<Uuid, String> = entity::ArtistEntity::find()
+        .filter(entity::ArtistColumn::Id.is_in(artist_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|artist| (artist.id, artist.name))
+        .collect();
+
+    Ok(tracks
+        .iter()
+        .zip(related.iter())
+        .map(|(track, related)| {
+            let release_id = related.medium.as_ref().map(|m| m.release_id);
+            Item {
+                title: track.title.to_owned(),
+                artist: credited(&related.artist_credits, &names),
+                album: release_id
+                    .and_then(|id| releases.get(&id))
+                    .map(|r| r.title.to_owned()),
+                number: Some(track.number),
+                length: track.length,
+                recording_id: track.recording_id,
+                location: url(
+                    settings,
+                    &format!("share/{}/tracks/{}/audio", token, track.id),
+                ),
+                image: release_id
+                    .and_then(|id| images.get(&id))
+                    .map(|image| url(settings, &format!("tempo/images/{}/file", image))),
+            }
+        })
+        .collect())
+}
+
+async fn export<C>(
+    db: &C,
+    username: &str,
+    (kind, resource): (entity::ShareKind, Uuid),
+    title: String,
+    creator: Option<String>,
+    tracks: &[entity::Track],
+    format: PlaylistFormat,
+) -> Result<Response, Error>
+where
+    C: ConnectionTrait,
+{
+    let settings = get_settings()?;
+    let token = export_share(db, username, kind, resource).await?;
+    let playlist = Playlist {
+        items: items(db, settings, &token, tracks).await?,
+        title,
+        creator,
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.mime().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                releases::content_disposition(
+                    "inline",
+                    &format!(
+                        "{}.{}",
+                        releases::sanitize(&playlist.title),
+                        format.extension()
+                    ),
+                ),
+            ),
+        ],
+        playlist.render(format),
+    )
+        .into_response())
+}
+
+pub async fn release_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<ExportQuery>,
+) -> Result<Response, Error> {
+    let release = entity::ReleaseEntity::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let tracks = releases::release_tracks(&db, &release).await?;
+    export(
+        &db,
+        &claims.username,
+        (entity::ShareKind::Release, release.id),
+        release.title,
+        None,
+        &tracks,
+        query.format,
+    )
+    .await
+}
+
+pub async fn medium_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<ExportQuery>,
+) -> Result<Response, Error> {
+    let (medium, release) = entity::MediumEntity::find_by_id(id)
+        .find_also_related(entity::ReleaseEntity)
+        .one(&db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let tracks = entity::TrackEntity::find()
+        .filter(ColumnTrait::eq(&entity::TrackColumn::MediumId, medium.id))
+        .order_by_asc(entity::TrackColumn::Number)
+        .all(&db)
+        .await?;
+    let title = match release {
+        Some(release) => format!("{} (Medium {})", release.title, medium.position),
+        None => format!("Medium {}", medium.position),
+    };
+    export(
+        &db,
+        &claims.username,
+        (entity::ShareKind::Release, medium.release_id),
+        title,
+        None,
+        &tracks,
+        query.format,
+    )
+    .await
+}
+
+// Splits a path from any platform into its components
+fn components(path: &str) -> Vec<&str> {
+    path.split(|c| c == '/' || c == '\\')
+        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+        .collect()
+}
+
+/// Finds a track by the location of an entry, which is either a URL of a track
+/// served by Tempo or a file path. Paths from playlists made on other machines or
+/// relative to the playlist are matched by their longest common suffix.
+async fn find_by_location<C>(db: &C, location: &str) -> Result<Option<entity::Track>, Error>
+where
+    C: ConnectionTrait,
+{
+    let path = match Url::parse(location) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            let segments: Vec<&str> = url
+                .path_segments()
+                .map(Iterator::collect)
+                .unwrap_or_default();
+            let id = segments
+                .windows(2)
+                .find(|pair| pair[0] == "tracks")
+                .and_then(|pair| Uuid::parse_str(pair[1]).ok());
+            return Ok(match id {
+                Some(id) => entity::TrackEntity::find_by_id(id).one(db).await?,
+                None => None,
+            });
+        }
+        Ok(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => return Ok(None),
+        },
+        // Windows paths look like URLs with the drive as the scheme
+        _ => location.to_owned(),
+    };
+
+    if let Some(track) = entity::TrackEntity::find()
+        .filter(ColumnTrait::eq(&entity::TrackColumn::Path, path.as_str()))
+        .one(db)
+        .await?
+    {
+        return Ok(Some(track));
+    }
+    let wanted = components(&path);
+    let Some(name) = wanted.last() else {
+        return Ok(None);
+    };
+    // wildcards in the name can only widen the candidates, which are checked below
+    let candidates = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Path.like(&format!("%{}", name)))
+        .all(db)
+        .await?;
+    let mut scored: Vec<(usize, entity::Track)> = candidates
+        .into_iter()
+        .filter_map(|track| {
+            let common = components(track.path.as_deref()?)
+                .iter()
+                .rev()
+                .zip(wanted.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            (common > 0).then_some((common, track))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    // ambiguous paths are left to the other metadata
+    match scored.as_slice() {
+        [(best, _), (second, _), ..] if best == second => Ok(None),
+        _ => Ok(scored.into_iter().next().map(|(_, track)| track)),
+    }
+}
+
+async fn resolve<C>(db: &C, entry: &Entry) -> Result<Option<entity::Track>, Error>
+where
+    C: ConnectionTrait,
+{
+    if let Some(location) = &entry.location {
+        if let Some(track) = find_by_location(db, location).await? {
+            return Ok(Some(track));
+        }
+    }
+    let query = TrackQuery {
+        artist: entry.artist.as_deref().unwrap_or_default(),
+        title: entry.title.as_deref().unwrap_or_default(),
+        release: entry.album.as_deref(),
+        track_mbid: entry.track_mbid(),
+        recording_mbid: entry.recording_mbid(),
+        release_mbid: None,
+    };
+    if query.title.is_empty() && query.track_mbid.is_none() && query.recording_mbid.is_none() {
+        return Ok(None);
+    }
+    match_track(db, &query).await
+}
+
+fn map_to_tracks_include(include: &[PlaylistImportInclude]) -> Vec<TrackInclude> {
+    include
+        .iter()
+        .filter_map(|i| match *i {
+            PlaylistImportInclude::TracksArtists => Some(TrackInclude::Artists),
+            PlaylistImportInclude::TracksMedium => Some(TrackInclude::Medium),
+            PlaylistImportInclude::TracksMediumRelease => Some(TrackInclude::MediumRelease),
+            PlaylistImportInclude::TracksMediumReleaseArtists => {
+                Some(TrackInclude::MediumReleaseArtists)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportQuery {
+    format: Option<PlaylistFormat>,
+}
+
+/// Resolves the entries of an uploaded playlist against the library, returning the
+/// matched tracks in order along with the entries which couldn't be matched
+pub async fn import(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    AxumQuery(query): AxumQuery<ImportQuery>,
+    Query(opts): Query<String, entity::TrackColumn, PlaylistImportInclude, String>,
+    body: Bytes,
+) -> Result<Json<Document<PlaylistImportResource, Included>>, Error> {
+    let format = match query.format {
+        Some(format) => format,
+        None => PlaylistFormat::detect(&String::from_utf8_lossy(&body)),
+    };
+    let entries = playlist::parse(&body, format)?;
+
+    let tx = db.begin().await?;
+    let mut tracks = Vec::new();
+    let mut unmatched = Vec::new();
+    for (position, entry) in entries.iter().enumerate() {
+        match resolve(&tx, entry).await? {
+            Some(track) => tracks.push(track),
+            None => unmatched.push(UnmatchedEntryAttributes {
+                position,
+                location: entry.location.to_owned(),
+                title: entry.title.to_owned(),
+                artist: entry.artist.to_owned(),
+                album: entry.album.to_owned(),
+            }),
+        }
+    }
+    tracing::info!(
+        user = %claims.username,
+        ?format,
+        entries = entries.len(),
+        unmatched = unmatched.len(),
+        "Imported playlist"
+    );
+
+    let mut included = Vec::new();
+    if opts.include.contains(&PlaylistImportInclude::Tracks) {
+        let related = tracks::related(&tx, &tracks, true).await?;
+        included.extend(tracks::entities_to_included(&tx, Some(&claims), &tracks, &related).await?);
+        let tracks_include = map_to_tracks_include(&opts.include);
+        included.extend(tracks::included(&tx, related, &tracks_include).await?);
+    }
+    let relationships = [(
+        PlaylistImportRelation::Tracks,
+        Relationship {
+            data: Relation::Multi(
+                tracks
+                    .iter()
+                    .map(|track| {
+                        Related::Uuid(ResourceIdentifier {
+                            r#type: ResourceType::Track,
+                            id: track.id,
+                            meta: None,
+                        })
+                    })
+                    .collect(),
+            ),
+        },
+    )]
+    .into();
+
+    Ok(Json(Document {
+        data: DocumentData::Single(PlaylistImportResource {
+            r#type: ResourceType::PlaylistImport,
+            id: claims.username,
+            attributes: PlaylistImportAttributes {
+                format,
+                entries: entries.len(),
+                unmatched,
+            },
+            relationships,
+            meta: None,
+        }),
+        included: dedup(included),
+        links: HashMap::new(),
+    }))
+}
+
+#[derive(Default)]
+pub struct PlaylistRelated {
+    /// Ordered by position
+    pub entries: Vec<entity::PlaylistTrack>,
+}
+
+pub async fn related<C>(
+    db: &C,
+    entities: &[entity::Playlist],
+) -> Result<Vec<PlaylistRelated>, Error>
+where
+    C: ConnectionTrait,
+{
+    let entries = entities.load_many(entity::PlaylistTrackEntity, db).await?;
+    Ok(entries
+        .into_iter()
+        .map(|mut entries| {
+            entries.sort_by_key(|entry| entry.position);
+            PlaylistRelated { entries }
+        })
+        .collect())
+}
+
+pub fn entity_to_resource(
+    entity: &entity::Playlist,
+    related: &PlaylistRelated,
+) -> PlaylistResource {
+    let mut relationships = HashMap::new();
+    relationships.insert(
+        PlaylistRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    );
+    if let Some(image) = &entity.image_id {
+        relationships.insert(
+            PlaylistRelation::Image,
+            Relationship {
+                data: Relation::Single(Related::String(ResourceIdentifier {
+                    r#type: ResourceType::Image,
+                    id: image.to_owned(),
+                    meta: None,
+                })),
+            },
+        );
+    }
+    relationships.insert(
+        PlaylistRelation::Tracks,
+        Relationship {
+            data: Relation::Multi(
+                related
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        Related::Uuid(ResourceIdentifier {
+                            r#type: ResourceType::Track,
+                            id: entry.track_id,
+                            meta: None,
+                        })
+                    })
+                    .collect(),
+            ),
+        },
+    );
+
+    PlaylistResource {
+        r#type: ResourceType::Playlist,
+        id: entity.id,
+        attributes: PlaylistAttributes {
+            name: entity.name.to_owned(),
+            description: entity.description.to_owned(),
+            public: entity.public,
+            created_at: entity.created_at,
+            updated_at: entity.updated_at,
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+pub fn entity_to_included(entity: &entity::Playlist, related: &PlaylistRelated) -> Included {
+    Included::Playlist(entity_to_resource(entity, related))
+}
+
+fn map_playlist_to_tracks_include(include: &[PlaylistInclude]) -> Vec<TrackInclude> {
+    include
+        .iter()
+        .filter_map(|i| match *i {
+            PlaylistInclude::TracksArtists => Some(TrackInclude::Artists),
+            PlaylistInclude::TracksMedium => Some(TrackInclude::Medium),
+            PlaylistInclude::TracksMediumRelease => Some(TrackInclude::MediumRelease),
+            PlaylistInclude::TracksMediumReleaseArtists => Some(TrackInclude::MediumReleaseArtists),
+            _ => None,
+        })
+        .collect()
+}
+
+pub async fn included<C>(
+    db: &C,
+    claims: Option<&Claims>,
+    entities: &[entity::Playlist],
+    related: &[PlaylistRelated],
+    include: &[PlaylistInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&PlaylistInclude::User) {
+        let usernames: HashSet<&str> = entities.iter().map(|p| p.user.as_str()).collect();
+        let users = entity::UserEntity::find()
+            .filter(entity::UserColumn::Username.is_in(usernames))
+            .all(db)
+            .await?;
+        let users_related = users::related(db, &users, true).await?;
+        for (user, related) in users.iter().zip(users_related.iter()) {
+            included.push(users::entity_to_included(user, related));
+        }
+    }
+    if include.contains(&PlaylistInclude::Image) {
+        let images = entity::ImageEntity::find()
+            .filter(
+                entity::ImageColumn::Id.is_in(entities.iter().filter_map(|p| p.image_id.clone())),
+            )
+            .all(db)
+            .await?;
+        included.extend(images.iter().map(images::entity_to_included));
+    }
+    if include.contains(&PlaylistInclude::Tracks) {
+        let ids: HashSet<Uuid> = related
+            .iter()
+            .flat_map(|r| r.entries.iter().map(|entry| entry.track_id))
+            .collect();
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(ids))
+            .all(db)
+            .await?;
+        let tracks_related = tracks::related(db, &tracks, true).await?;
+        included.extend(tracks::entities_to_included(db, claims, &tracks, &tracks_related).await?);
+        let tracks_include = map_playlist_to_tracks_include(include);
+        included.extend(tracks::included(db, tracks_related, &tracks_include).await?);
+    }
+    Ok(included)
+}
+
+/// Finds a playlist the user can see, which are their own and the public ones of
+/// other users. Only the owner can edit a playlist.
+pub async fn find_playlist<C>(
+    db: &C,
+    username: &str,
+    id: Uuid,
+    edit: bool,
+) -> Result<entity::Playlist, Error>
+where
+    C: ConnectionTrait,
+{
+    let playlist = entity::PlaylistEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    match (playlist.user == username, playlist.public, edit) {
+        (true, _, _) | (false, true, false) => Ok(playlist),
+        (false, true, true) => Err(Error::Unauthorized(Some(
+            "Only the owner can edit a playlist".to_string(),
+        ))),
+        (false, false, _) => Err(Error::NotFound(None)),
+    }
+}
+
+fn track_ids(data: &[ResourceIdentifier<ResourceType, Uuid, Meta>]) -> Result<Vec<Uuid>, Error> {
+    data.iter()
+        .map(|identifier| match identifier.r#type {
+            ResourceType::Track => Ok(identifier.id),
+            _ => Err(Error::BadRequest(Some(
+                "Playlists can only contain tracks".to_string(),
+            ))),
+        })
+        .collect()
+}
+
+// A cover can only be chosen among the images already known, an empty list
+// removes it
+async fn relation_image<C>(
+    db: &C,
+    relation: &Relation<ResourceType, Meta>,
+) -> Result<Option<String>, Error>
+where
+    C: ConnectionTrait,
+{
+    match relation {
+        Relation::Single(Related::String(identifier))
+            if identifier.r#type == ResourceType::Image =>
+        {
+            entity::ImageEntity::find_by_id(identifier.id.to_owned())
+                .one(db)
+                .await?
+                .ok_or(Error::NotFound(None))?;
+            Ok(Some(identifier.id.to_owned()))
+        }
+        Relation::Multi(related) if related.is_empty() => Ok(None),
+        _ => Err(Error::BadRequest(Some(
+            "The cover of a playlist must be an image".to_string(),
+        ))),
+    }
+}
+
+// Entries are numbered from 0 without gaps, so they are written again whenever
+// the order changes
+async fn save_entries<C>(db: &C, playlist: &entity::Playlist, ids: &[Uuid]) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    entity::PlaylistTrackEntity::delete_many()
+        .filter(entity::PlaylistTrackColumn::PlaylistId.eq(playlist.id))
+        .exec(db)
+        .await?;
+    if !ids.is_empty() {
+        entity::PlaylistTrackEntity::insert_many(ids.iter().enumerate().map(|(position, id)| {
+            entity::PlaylistTrackActive {
+                playlist_id: ActiveValue::Set(playlist.id),
+                position: ActiveValue::Set(position as i32),
+                track_id: ActiveValue::Set(*id),
+            }
+        }))
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn touch<C>(db: &C, playlist: entity::Playlist) -> Result<entity::Playlist, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut active: entity::PlaylistActive = playlist.into();
+    active.updated_at = ActiveValue::Set(OffsetDateTime::now_utc());
+    Ok(active.update(db).await?)
+}
+
+async fn playlist_document<C>(
+    db: &C,
+    claims: &Claims,
+    playlist: entity::Playlist,
+    include: &[PlaylistInclude],
+) -> Result<Document<PlaylistResource, Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let related = related(db, &[playlist.to_owned()]).await?;
+    let included = included(db, Some(claims), &[playlist.to_owned()], &related, include).await?;
+    let related = related.into_iter().next().unwrap_or_default();
+    Ok(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&playlist, &related)),
+        included: dedup(included),
+    })
+}
+
+pub async fn playlists(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<PlaylistFilter, entity::PlaylistColumn, PlaylistInclude, Uuid>,
+    OriginalUri(uri): OriginalUri,
+    claims: Claims,
+) -> Result<Json<Document<PlaylistResource, Included>>, Error> {
+    let tx = db.begin().await?;
+
+    let mut playlists_query = entity::PlaylistEntity::find().filter(
+        Condition::any()
+            .add(ColumnTrait::eq(
+                &entity::PlaylistColumn::User,
+                claims.username.as_str(),
+            ))
+            .add(ColumnTrait::eq(&entity::PlaylistColumn::Public, true)),
+    );
+    for (filter_key, filter_value) in opts.filter.iter() {
+        playlists_query = match (filter_key, filter_key.column()) {
+            (PlaylistFilter::Public, Some(k)) => {
+                let public = filter_value.parse::<bool>().map_err(|_| {
+                    Error::BadRequest(Some("The public filter must be true or false".to_string()))
+                })?;
+                playlists_query.filter(ColumnTrait::eq(&k, public))
+            }
+            (_, Some(k)) => playlists_query.filter(ColumnTrait::eq(&k, filter_value.to_owned())),
+            (_, None) => playlists_query,
+        };
+    }
+    let mut _playlists_cursor = playlists_query.cursor_by(entity::PlaylistColumn::Id);
+    let playlists_cursor = make_cursor(&mut _playlists_cursor, &opts.page);
+    let playlists = playlists_cursor.all(&tx).await?;
+    let related = related(&tx, &playlists).await?;
+    let data = playlists
+        .iter()
+        .zip(related.iter())
+        .map(|(playlist, related)| entity_to_resource(playlist, related))
+        .collect::<Vec<_>>();
+    let included = included(&tx, Some(&claims), &playlists, &related, &opts.include).await?;
+    Ok(Json(Document {
+        links: links_from_resource(&data, opts, &uri),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+pub async fn playlist(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<PlaylistFilter, entity::PlaylistColumn, PlaylistInclude, Uuid>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<Document<PlaylistResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, false).await?;
+    Ok(Json(
+        playlist_document(&tx, &claims, playlist, &opts.include).await?,
+    ))
+}
+
+pub async fn insert_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Json(body): Json<InsertOneDocument<InsertPlaylistResource>>,
+) -> Result<(StatusCode, Json<Document<PlaylistResource, Included>>), Error> {
+    let resource = body.data;
+    if resource.attributes.name.trim().is_empty() {
+        return Err(Error::BadRequest(Some(
+            "A playlist must have a name".to_string(),
+        )));
+    }
+
+    let tx = db.begin().await?;
+    let mut image = None;
+    let mut ids = Vec::new();
+    for (relation, relationship) in resource.relationships.iter() {
+        match relation {
+            PlaylistRelation::Image => image = relation_image(&tx, &relationship.data).await?,
+            PlaylistRelation::Tracks => {
+                ids = tracks::relation_ids(&relationship.data, "a playlist")?
+            }
+            PlaylistRelation::User => {}
+        }
+    }
+    tracks::check_exist(&tx, &ids).await?;
+
+    let now = OffsetDateTime::now_utc();
+    let playlist = entity::PlaylistActive {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user: ActiveValue::Set(claims.username.to_owned()),
+        name: ActiveValue::Set(resource.attributes.name),
+        description: ActiveValue::Set(resource.attributes.description.filter(|d| !d.is_empty())),
+        public: ActiveValue::Set(resource.attributes.public),
+        image_id: ActiveValue::Set(image),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    }
+    .insert(&tx)
+    .await?;
+    save_entries(&tx, &playlist, &ids).await?;
+    let document = playlist_document(&tx, &claims, playlist, &[]).await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, tracks = ids.len(), "Created playlist");
+
+    Ok((StatusCode::CREATED, Json(document)))
+}
+
+pub async fn update_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateOneDocument<UpdatePlaylistResource>>,
+) -> Result<Json<Document<PlaylistResource, Included>>, Error> {
+    let resource = body.data;
+    if resource.id != id {
+        return Err(Error::BadRequest(Some(
+            "The id of the resource does not match the URL".to_string(),
+        )));
+    }
+
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    let mut active: entity::PlaylistActive = playlist.to_owned().into();
+    if let Some(name) = resource.attributes.name {
+        if name.trim().is_empty() {
+            return Err(Error::BadRequest(Some(
+                "A playlist must have a name".to_string(),
+            )));
+        }
+        active.name = ActiveValue::Set(name);
+    }
+    if let Some(description) = resource.attributes.description {
+        active.description = ActiveValue::Set(Some(description).filter(|d| !d.is_empty()));
+    }
+    if let Some(public) = resource.attributes.public {
+        active.public = ActiveValue::Set(public);
+    }
+    for (relation, relationship) in resource.relationships.iter() {
+        match relation {
+            PlaylistRelation::Image => {
+                active.image_id = ActiveValue::Set(relation_image(&tx, &relationship.data).await?)
+            }
+            PlaylistRelation::Tracks => {
+                let ids = tracks::relation_ids(&relationship.data, "a playlist")?;
+                tracks::check_exist(&tx, &ids).await?;
+                save_entries(&tx, &playlist, &ids).await?;
+            }
+            PlaylistRelation::User => {
+                return Err(Error::BadRequest(Some(
+                    "The owner of a playlist can't be changed".to_string(),
+                )))
+            }
+        }
+    }
+    active.updated_at = ActiveValue::Set(OffsetDateTime::now_utc());
+    let playlist = active.update(&tx).await?;
+    let document = playlist_document(&tx, &claims, playlist, &[]).await?;
+    tx.commit().await?;
+
+    Ok(Json(document))
+}
+
+pub async fn delete_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    entity::PlaylistEntity::delete_by_id(playlist.id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, %id, "Deleted playlist");
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn tracks_relation(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<PlaylistFilter, entity::PlaylistColumn, PlaylistInclude, Uuid>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<Document<Related<ResourceType, Meta>, Included>>, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, false).await?;
+    let document = playlist_document(&tx, &claims, playlist, &opts.include).await?;
+    let data = match document.data {
+        DocumentData::Single(mut resource) => resource
+            .relationships
+            .remove(&PlaylistRelation::Tracks)
+            .map(|relationship| relationship.data),
+        DocumentData::Multi(_) => None,
+    };
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: match data {
+            Some(Relation::Multi(related)) => DocumentData::Multi(related),
+            _ => DocumentData::Multi(Vec::new()),
+        },
+        included: document.included,
+    }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct InsertTracksQuery {
+    /// Where the tracks are inserted, at the end by default
+    position: Option<usize>,
+}
+
+/// Inserts tracks in the playlist, in the order they are given
+pub async fn insert_tracks(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<InsertTracksQuery>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    let inserted = track_ids(&body.data)?;
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    tracks::check_exist(&tx, &inserted).await?;
+
+    let mut ids: Vec<Uuid> = related(&tx, &[playlist.to_owned()])
+        .await?
+        .into_iter()
+        .flat_map(|r| r.entries.into_iter().map(|entry| entry.track_id))
+        .collect();
+    let position = query.position.unwrap_or(ids.len());
+    if position > ids.len() {
+        return Err(Error::BadRequest(Some(
+            "The position is past the end of the playlist".to_string(),
+        )));
+    }
+    ids.splice(position..position, inserted);
+    save_entries(&tx, &playlist, &ids).await?;
+    touch(&tx, playlist).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Replaces all the tracks of the playlist, which is how they are reordered
+pub async fn replace_tracks(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    let ids = track_ids(&body.data)?;
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    tracks::check_exist(&tx, &ids).await?;
+    save_entries(&tx, &playlist, &ids).await?;
+    touch(&tx, playlist).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct MoveTrackQuery {
+    /// Position of the entry to move
+    from: usize,
+    /// Position of the entry once moved
+    to: usize,
+}
+
+/// Moves a single entry, without having to send all the tracks of the playlist
+pub async fn move_track(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<MoveTrackQuery>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    let mut ids: Vec<Uuid> = related(&tx, &[playlist.to_owned()])
+        .await?
+        .into_iter()
+        .flat_map(|r| r.entries.into_iter().map(|entry| entry.track_id))
+        .collect();
+    if query.from >= ids.len() || query.to >= ids.len() {
+        return Err(Error::BadRequest(Some(
+            "The position is past the end of the playlist".to_string(),
+        )));
+    }
+    let moved = ids.remove(query.from);
+    ids.insert(query.to, moved);
+    save_entries(&tx, &playlist, &ids).await?;
+    touch(&tx, playlist).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes every occurrence of the given tracks from the playlist
+pub async fn delete_tracks(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    let removed: HashSet<Uuid> = track_ids(&body.data)?.into_iter().collect();
+    let tx = db.begin().await?;
+    let playlist = find_playlist(&tx, &claims.username, id, true).await?;
+    let ids: Vec<Uuid> = related(&tx, &[playlist.to_owned()])
+        .await?
+        .into_iter()
+        .flat_map(|r| r.entries.into_iter().map(|entry| entry.track_id))
+        .filter(|id| !removed.contains(id))
+        .collect();
+    save_entries(&tx, &playlist, &ids).await?;
+    touch(&tx, playlist).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn playlist_file(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<ExportQuery>,
+) -> Result<Response, Error> {
+    let playlist = find_playlist(&db, &claims.username, id, false).await?;
+    let entries = related(&db, &[playlist.to_owned()])
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+        .entries;
+    let tracks: HashMap<Uuid, entity::Track> = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(entries.iter().map(|entry| entry.track_id)))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|track| (track.id, track))
+        .collect();
+    let tracks: Vec<entity::Track> = entries
+        .iter()
+        .filter_map(|entry| tracks.get(&entry.track_id).cloned())
+        .collect();
+    export(
+        &db,
+        &claims.username,
+        (entity::ShareKind::Playlist, playlist.id),
+        playlist.name,
+        Some(playlist.user),
+        &tracks,
+        query.format,
+    )
+    .await
+}
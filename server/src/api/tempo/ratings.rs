@@ -0,0 +1,363 @@
+use axum::{
+    extract::State,
+    http::{StatusCode, Uri},
+};
+use entity::IgnoreNone;
+use sea_orm::{
+    sea_query::{Expr, Query as SeaQuery, SimpleExpr, SubQueryStatement},
+    ActiveValue, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, JoinType, Order,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait, TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::hash::Hash;
+use taskie_client::InsertTask;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{Included, Meta, ResourceType, UserMetaAttributes},
+    extract::{Claims, Json, Path},
+    jsonapi::{Document, DocumentData, InsertManyRelation, Related, Resource, ResourceIdentifier},
+    AppState, Error,
+};
+use crate::tasks::{self, TaskName};
+use base::setting::get_settings;
+
+fn resource_type(kind: entity::RatingKind) -> ResourceType {
+    match kind {
+        entity::RatingKind::Track => ResourceType::Track,
+        entity::RatingKind::Release => ResourceType::Release,
+    }
+}
+
+pub fn entity_to_related(entity: &entity::Rating) -> Related<ResourceType, Meta> {
+    Related::Uuid(ResourceIdentifier {
+        r#type: resource_type(entity.kind),
+        id: entity.resource,
+        meta: Some(Meta::User(UserMetaAttributes {
+            rating: Some(entity.rating as u8),
+            ..Default::default()
+        })),
+    })
+}
+
+fn kind(r#type: &ResourceType) -> Result<entity::RatingKind, Error> {
+    match r#type {
+        ResourceType::Track => Ok(entity::RatingKind::Track),
+        ResourceType::Release => Ok(entity::RatingKind::Release),
+        _ => Err(Error::BadRequest(Some(
+            "Only tracks and releases can be rated".to_string(),
+        ))),
+    }
+}
+
+/// Returns the rating the user gave to each of the given resources
+pub async fn rated<C, I>(
+    db: &C,
+    username: &str,
+    kind: entity::RatingKind,
+    ids: I,
+) -> Result<HashMap<Uuid, u8>, DbErr>
+where
+    C: ConnectionTrait,
+    I: IntoIterator<Item = Uuid>,
+{
+    Ok(entity::RatingEntity::find()
+        .filter(ColumnTrait::eq(&entity::RatingColumn::User, username))
+        .filter(ColumnTrait::eq(&entity::RatingColumn::Kind, kind))
+        .filter(entity::RatingColumn::Resource.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|rating| (rating.resource, rating.rating as u8))
+        .collect())
+}
+
+/// Sets the rating meta on the resources rated by the user, when the request
+/// is authenticated
+pub async fn rate<C, RT, T, R>(
+    db: &C,
+    claims: Option<&Claims>,
+    kind: entity::RatingKind,
+    resources: &mut [Resource<RT, Uuid, T, R, Meta>],
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+    R: Hash + Eq,
+{
+    let Some(claims) = claims else {
+        return Ok(());
+    };
+    let rated = rated(
+        db,
+        &claims.username,
+        kind,
+        resources.iter().map(|resource| resource.id),
+    )
+    .await?;
+    for resource in resources.iter_mut() {
+        if let Some(rating) = rated.get(&resource.id) {
+            Meta::user(&mut resource.meta).rating = Some(*rating);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SortQuery {
+    sort: Option<String>,
+}
+
+/// The rating the user gave to the resource in the id column, as a correlated
+/// subquery usable for ordering
+fn rating_of<C>(column: C, username: &str, kind: entity::RatingKind) -> SimpleExpr
+where
+    C: ColumnTrait,
+{
+    let select = SeaQuery::select()
+        .column(entity::RatingColumn::Rating)
+        .from(entity::RatingEntity)
+        .and_where(ColumnTrait::eq(&entity::RatingColumn::User, username))
+        .and_where(ColumnTrait::eq(&entity::RatingColumn::Kind, kind))
+        .and_where(
+            Expr::col((entity::RatingEntity, entity::RatingColumn::Resource))
+                .equals((column.entity_name(), column)),
+        )
+        .to_owned();
+    SimpleExpr::SubQuery(None, Box::new(SubQueryStatement::SelectStatement(select)))
+}
+
+/// The ordering for `sort=rating` or `sort=-rating`, which is not a column of
+/// the resources and therefore is not part of the parsed query options
+pub fn order<C>(
+    uri: &Uri,
+    column: C,
+    claims: Option<&Claims>,
+    kind: entity::RatingKind,
+) -> Result<Option<(SimpleExpr, Order)>, Error>
+where
+    C: ColumnTrait,
+{
+    let sort = uri
+        .query()
+        .and_then(|qs| serde_qs::from_str::<SortQuery>(qs).ok())
+        .and_then(|query| query.sort)
+        .unwrap_or_default();
+    let order = sort.split(',').find_map(|key| match key {
+        "rating" => Some(Order::Asc),
+        "-rating" => Some(Order::Desc),
+        _ => None,
+    });
+    let Some(order) = order else {
+        return Ok(None);
+    };
+    let claims = claims.ok_or(Error::Unauthorized(Some(
+        "Only authenticated users have rated resources".to_string(),
+    )))?;
+    Ok(Some((rating_of(column, &claims.username, kind), order)))
+}
+
+fn rating_ids<M>(
+    data: &[ResourceIdentifier<ResourceType, Uuid, M>],
+) -> Result<Vec<(entity::RatingKind, Uuid)>, Error> {
+    data.iter()
+        .map(|identifier| Ok((kind(&identifier.r#type)?, identifier.id)))
+        .collect()
+}
+
+async fn check_ratings<C>(db: &C, ratings: &[(entity::RatingKind, Uuid)]) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    for kind in [entity::RatingKind::Track, entity::RatingKind::Release] {
+        let ids: Vec<Uuid> = ratings
+            .iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, id)| *id)
+            .collect();
+        if ids.is_empty() {
+            continue;
+        }
+        let found = match kind {
+            entity::RatingKind::Track => {
+                entity::TrackEntity::find()
+                    .filter(entity::TrackColumn::Id.is_in(ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+            entity::RatingKind::Release => {
+                entity::ReleaseEntity::find()
+                    .filter(entity::ReleaseColumn::Id.is_in(ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+        };
+        if (found as usize) < ids.len() {
+            return Err(Error::NotFound(None));
+        }
+    }
+    Ok(())
+}
+
+/// Schedules writing the ratings into the files when the user is the one
+/// configured as the owner of the library. Ratings of releases are written
+/// into all of their tracks.
+async fn schedule_rating_tasks<C>(
+    db: &C,
+    username: &str,
+    ratings: &[(entity::RatingKind, Uuid)],
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let settings = get_settings()?;
+    if !settings.library.tagging.write_ratings
+        || settings.library.owner.as_deref() != Some(username)
+    {
+        return Ok(());
+    }
+
+    let mut tracks: Vec<Uuid> = ratings
+        .iter()
+        .filter(|(kind, _)| *kind == entity::RatingKind::Track)
+        .map(|(_, id)| *id)
+        .collect();
+    let releases: Vec<Uuid> = ratings
+        .iter()
+        .filter(|(kind, _)| *kind == entity::RatingKind::Release)
+        .map(|(_, id)| *id)
+        .collect();
+    if !releases.is_empty() {
+        tracks.extend(
+            entity::TrackEntity::find()
+                .join(JoinType::InnerJoin, entity::TrackRelation::Medium.def())
+                .filter(entity::MediumColumn::ReleaseId.is_in(releases))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|track| track.id),
+        );
+    }
+
+    let writes = tracks
+        .into_iter()
+        .map(|id| InsertTask {
+            name: TaskName::Rating,
+            payload: Some(json!(tasks::rating::Data(id))),
+            depends_on: Vec::new(),
+            duration: Duration::seconds(60),
+        })
+        .collect::<Vec<_>>();
+    if !writes.is_empty() {
+        if let Err(error) = tasks::push(&writes).await {
+            tracing::warn!(%error, user = %username, "Could not schedule rating writes");
+        }
+    }
+    Ok(())
+}
+
+/// The resources rated by the user, the most recently rated first
+pub async fn ratings(
+    State(AppState(db)): State<AppState>,
+    _claims: Claims,
+    Path(username): Path<String>,
+) -> Result<Json<Document<Related<ResourceType, Meta>, Included>>, Error> {
+    let ratings = entity::RatingEntity::find()
+        .filter(ColumnTrait::eq(&entity::RatingColumn::User, username))
+        .order_by_desc(entity::RatingColumn::UpdatedAt)
+        .all(&db)
+        .await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Multi(ratings.iter().map(entity_to_related).collect()),
+        included: Vec::new(),
+    }))
+}
+
+/// Rates tracks and releases, the rating being in the meta of each identifier
+pub async fn insert_ratings(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Json(body): Json<
+        InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, UserMetaAttributes>>,
+    >,
+) -> Result<StatusCode, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let ids = rating_ids(&body.data)?;
+    if ids.is_empty() {
+        return Ok(StatusCode::OK);
+    }
+    let values = body
+        .data
+        .iter()
+        .map(
+            |identifier| match identifier.meta.as_ref().and_then(|m| m.rating) {
+                Some(rating @ 1..=5) => Ok(rating as i16),
+                _ => Err(Error::BadRequest(Some(
+                    "Ratings must be between 1 and 5".to_string(),
+                ))),
+            },
+        )
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let tx = db.begin().await?;
+    check_ratings(&tx, &ids).await?;
+    let now = OffsetDateTime::now_utc();
+    entity::RatingEntity::insert_many(ids.iter().zip(values.into_iter()).map(
+        |((kind, resource), rating)| entity::RatingActive {
+            user: ActiveValue::Set(claims.username.to_owned()),
+            kind: ActiveValue::Set(*kind),
+            resource: ActiveValue::Set(*resource),
+            rating: ActiveValue::Set(rating),
+            updated_at: ActiveValue::Set(now),
+        },
+    ))
+    .on_conflict(entity::conflict::RATING_CONFLICT.to_owned())
+    .exec(&tx)
+    .await
+    .ignore_none()?;
+    tx.commit().await?;
+    schedule_rating_tasks(&db, &claims.username, &ids).await?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_ratings(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    Json(body): Json<InsertManyRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<StatusCode, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let ids = rating_ids(&body.data)?;
+    if ids.is_empty() {
+        return Ok(StatusCode::OK);
+    }
+
+    let mut condition = Condition::any();
+    for (kind, resource) in ids.iter() {
+        condition = condition.add(
+            Condition::all()
+                .add(ColumnTrait::eq(&entity::RatingColumn::Kind, *kind))
+                .add(ColumnTrait::eq(&entity::RatingColumn::Resource, *resource)),
+        );
+    }
+    entity::RatingEntity::delete_many()
+        .filter(ColumnTrait::eq(
+            &entity::RatingColumn::User,
+            claims.username.to_owned(),
+        ))
+        .filter(condition)
+        .exec(&db)
+        .await?;
+    schedule_rating_tasks(&db, &claims.username, &ids).await?;
+
+    Ok(StatusCode::OK)
+}
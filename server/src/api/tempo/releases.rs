@@ -1,9 +1,17 @@
-use axum::extract::{OriginalUri, State};
+use axum::extract::{OriginalUri, Query as AxumQuery, State};
+use axum::http::header;
+use axum::{
+    body::{Bytes, StreamBody},
+    response::{IntoResponse, Response},
+};
+use schemars::JsonSchema;
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, LoaderTrait, QueryFilter, QueryOrder,
-    TransactionTrait,
+    ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, LoaderTrait, ModelTrait, QueryFilter,
+    QueryOrder, TransactionTrait,
 };
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::{Path as StdPath, PathBuf};
 use uuid::Uuid;
 
 use crate::api::{
@@ -11,15 +19,21 @@ use crate::api::{
         ArtistCreditAttributes, Included, IntoColumn, MediumInclude, Meta, ReleaseAttributes,
         ReleaseFilter, ReleaseInclude, ReleaseRelation, ReleaseResource, ResourceType,
     },
-    extract::{Json, Path},
+    extract::{Claims, Json, Path},
     jsonapi::{
         links_from_resource, make_cursor, Document, DocumentData, Query, Related, Relation,
         Relationship, ResourceIdentifier,
     },
-    tempo::{artists, genres, images, mediums},
+    tempo::{artists, favorites, genres, images, mediums, ratings},
     AppState, Error,
 };
-use base::util::dedup;
+use crate::archive::{archive as build_archive, Entry, Source};
+use crate::hls::{parse_segment, playlist, segment, Timeline, PLAYLIST_MIME, SEGMENT_MIME};
+use crate::transcode::resolve;
+use base::{
+    setting::{get_settings, TranscodingFormat},
+    util::dedup,
+};
 
 #[derive(Default)]
 pub struct ReleaseRelated {
@@ -257,6 +271,7 @@ pub async fn releases(
     State(AppState(db)): State<AppState>,
     Query(opts): Query<ReleaseFilter, entity::ReleaseColumn, ReleaseInclude, uuid::Uuid>,
     OriginalUri(uri): OriginalUri,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<ReleaseResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -264,8 +279,23 @@ pub async fn releases(
     for (filter_key, filter_value) in opts.filter.iter() {
         if let Some(k) = filter_key.column() {
             releases_query = releases_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        } else if *filter_key == ReleaseFilter::Starred {
+            releases_query = releases_query.filter(favorites::filter(
+                entity::ReleaseColumn::Id,
+                claims.as_ref(),
+                entity::FavoriteKind::Release,
+                filter_value,
+            )?);
         }
     }
+    if let Some((rating, order)) = ratings::order(
+        &uri,
+        entity::ReleaseColumn::Id,
+        claims.as_ref(),
+        entity::RatingKind::Release,
+    )? {
+        releases_query = releases_query.order_by(rating, order);
+    }
     for (sort_key, sort_order) in opts.sort.iter() {
         releases_query = releases_query.order_by(sort_key.to_owned(), sort_order.to_owned());
     }
@@ -277,6 +307,14 @@ pub async fn releases(
     for (i, release) in releases.iter().enumerate() {
         data.push(entity_to_resource(release, &related_to_releases[i]));
     }
+    favorites::star(
+        &tx,
+        claims.as_ref(),
+        entity::FavoriteKind::Release,
+        &mut data,
+    )
+    .await?;
+    ratings::rate(&tx, claims.as_ref(), entity::RatingKind::Release, &mut data).await?;
     let included = included(&tx, related_to_releases, &opts.include).await?;
     Ok(Json(Document {
         links: links_from_resource(&data, opts, &uri),
@@ -289,6 +327,7 @@ pub async fn release(
     State(AppState(db)): State<AppState>,
     Path(id): Path<Uuid>,
     Query(opts): Query<ReleaseFilter, entity::ReleaseColumn, ReleaseInclude, uuid::Uuid>,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<ReleaseResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -299,7 +338,21 @@ pub async fn release(
     let related_to_releases = related(&tx, &[release.clone()], false).await?;
     let empty_relationship = ReleaseRelated::default();
     let related = related_to_releases.first().unwrap_or(&empty_relationship);
-    let data = entity_to_resource(&release, related);
+    let mut data = entity_to_resource(&release, related);
+    favorites::star(
+        &tx,
+        claims.as_ref(),
+        entity::FavoriteKind::Release,
+        std::slice::from_mut(&mut data),
+    )
+    .await?;
+    ratings::rate(
+        &tx,
+        claims.as_ref(),
+        entity::RatingKind::Release,
+        std::slice::from_mut(&mut data),
+    )
+    .await?;
     let included = included(&tx, related_to_releases, &opts.include).await?;
     Ok(Json(Document {
         data: DocumentData::Single(data),
@@ -307,3 +360,196 @@ pub async fn release(
         links: HashMap::new(),
     }))
 }
+
+// Returns all the release's tracks, in playback order
+pub async fn release_tracks<C>(
+    db: &C,
+    release: &entity::Release,
+) -> Result<Vec<entity::Track>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mediums = release
+        .find_related(entity::MediumEntity)
+        .order_by_asc(entity::MediumColumn::Position)
+        .all(db)
+        .await?;
+    let mut tracks = Vec::new();
+    for medium in mediums.iter() {
+        tracks.extend(
+            medium
+                .find_related(entity::TrackEntity)
+                .order_by_asc(entity::TrackColumn::Number)
+                .all(db)
+                .await?,
+        );
+    }
+    Ok(tracks)
+}
+
+async fn release_timeline<C>(db: &C, id: Uuid) -> Result<Timeline, Error>
+where
+    C: ConnectionTrait,
+{
+    let release = entity::ReleaseEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    Ok(Timeline::new(&release_tracks(db, &release).await?)?)
+}
+
+pub async fn hls_playlist(
+    State(AppState(db)): State<AppState>,
+    Path(id): Path<Uuid>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response, Error> {
+    let timeline = release_timeline(&db, id).await?;
+    Ok((
+        [(header::CONTENT_TYPE, PLAYLIST_MIME.to_string())],
+        playlist(get_settings()?, &timeline, uri.query()),
+    )
+        .into_response())
+}
+
+pub async fn hls_segment(
+    State(AppState(db)): State<AppState>,
+    Path((id, name)): Path<(Uuid, String)>,
+) -> Result<Response, Error> {
+    let index = parse_segment(&name)?;
+    let timeline = release_timeline(&db, id).await?;
+    let bytes = segment(
+        get_settings()?,
+        &format!("release-{}", id),
+        &timeline,
+        index,
+    )
+    .await?;
+    Ok(([(header::CONTENT_TYPE, SEGMENT_MIME.to_string())], bytes).into_response())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ArchiveQuery {
+    #[serde(default)]
+    playlist: bool,
+    format: Option<TranscodingFormat>,
+    max_bitrate: Option<u32>,
+}
+
+// Strips the characters most filesystems and the Content-Disposition header choke on
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// A Content-Disposition header value for the file name, as per RFC 6266: non-ASCII
+/// names are given UTF-8 encoded, along with an ASCII fallback for older clients
+pub fn content_disposition(disposition: &str, name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => c,
+            _ => '_',
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition,
+        fallback,
+        urlencoding::encode(name)
+    )
+}
+
+// Names an archive entry after its path relative to the release directory
+fn entry_name(root: &str, base: Option<&StdPath>, path: &StdPath) -> String {
+    let relative = base
+        .and_then(|base| path.strip_prefix(base).ok())
+        .or_else(|| path.file_name().map(StdPath::new))
+        .unwrap_or(path);
+    format!("{}/{}", root, relative.to_string_lossy())
+}
+
+pub async fn archive(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<ArchiveQuery>,
+) -> Result<Response, Error> {
+    let release = entity::ReleaseEntity::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let tracks = release_tracks(&db, &release).await?;
+    let cover = release.find_related(entity::ImageEntity).one(&db).await?;
+    // the original files are archived unless a format or bitrate is explicitly requested
+    let options = match (query.format, query.max_bitrate) {
+        (None, None) => None,
+        (format, max_bitrate) => resolve(
+            &get_settings()?.transcoding,
+            &claims.username,
+            None,
+            format,
+            max_bitrate,
+            None,
+            None,
+        )?,
+    };
+
+    let root = sanitize(&release.title);
+    let base = release.path.as_ref().map(StdPath::new);
+    let mut entries = Vec::new();
+    let mut playlist = String::from("#EXTM3U\n");
+    for track in tracks.iter() {
+        let Some(path) = track.path.as_ref().map(PathBuf::from) else {
+            continue;
+        };
+        let (name, source) = match options {
+            Some(options) => (
+                entry_name(
+                    &root,
+                    base,
+                    &path.with_extension(options.format.extension()),
+                ),
+                Source::Transcoded(path, options),
+            ),
+            None => (entry_name(&root, base, &path), Source::File(path)),
+        };
+        playlist.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            track.length / 1000,
+            track.title,
+            name.strip_prefix(&format!("{}/", root)).unwrap_or(&name)
+        ));
+        entries.push(Entry { name, source });
+    }
+    if let Some(cover) = cover {
+        let path = PathBuf::from(cover.path);
+        entries.push(Entry {
+            name: entry_name(&root, base, &path),
+            source: Source::File(path),
+        });
+    }
+    if query.playlist {
+        entries.push(Entry {
+            name: format!("{}/{}.m3u", root, root),
+            source: Source::Memory(Bytes::from(playlist)),
+        });
+    }
+
+    tracing::info!(user = %claims.username, %id, entries = entries.len(), "Archiving release");
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition("attachment", &format!("{}.zip", root)),
+            ),
+        ],
+        StreamBody::new(build_archive(entries)),
+    )
+        .into_response())
+}
@@ -0,0 +1,190 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::{collections::HashMap, sync::Arc, time::Instant};
+use tag::PictureType;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+use crate::api::{
+    documents::{
+        Included, ResourceType, YearReviewAttributes, YearReviewRelation, YearReviewResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{Document, DocumentData, Related, Relation, Relationship, ResourceIdentifier},
+    AppState, Error,
+};
+use crate::stats;
+use base::setting::get_settings;
+
+/// Number of covers in a share image, for a 3 by 3 grid
+static SHARE_IMAGE_COVERS: usize = 9;
+
+/// Number of share images kept in memory, the least recently served ones are
+/// dropped first
+static SHARE_IMAGE_CACHE: usize = 16;
+
+/// A rendered share image, along with the update time of the review it was made
+/// from and when it was last served
+struct CachedImage {
+    updated_at: OffsetDateTime,
+    used_at: Instant,
+    bytes: Vec<u8>,
+}
+
+lazy_static! {
+    /// The rendered share images by user and year. An image is rendered again
+    /// once the review is regenerated, replacing the previous one.
+    static ref IMAGES: Arc<Mutex<HashMap<(String, i32), CachedImage>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn entity_to_resource(entity: entity::YearReview) -> YearReviewResource {
+    let relationships = [(
+        YearReviewRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    )]
+    .into();
+    YearReviewResource {
+        r#type: ResourceType::YearReview,
+        id: entity.year.to_string(),
+        attributes: YearReviewAttributes {
+            year: entity.year,
+            report: entity.report,
+            updated_at: entity.updated_at,
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+async fn find_review<C>(db: &C, username: String, year: i32) -> Result<entity::YearReview, Error>
+where
+    C: ConnectionTrait,
+{
+    entity::YearReviewEntity::find_by_id((username, year))
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))
+}
+
+/// The yearly reviews of the user, the most recent first
+pub async fn reviews(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+) -> Result<Json<Document<YearReviewResource, Included>>, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let reviews = entity::YearReviewEntity::find()
+        .filter(ColumnTrait::eq(&entity::YearReviewColumn::User, username))
+        .order_by_desc(entity::YearReviewColumn::Year)
+        .all(&db)
+        .await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Multi(reviews.into_iter().map(entity_to_resource).collect()),
+        included: Vec::new(),
+    }))
+}
+
+pub async fn review(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path((username, year)): Path<(String, i32)>,
+) -> Result<Json<Document<YearReviewResource, Included>>, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let review = find_review(&db, username, year).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(review)),
+        included: Vec::new(),
+    }))
+}
+
+/// An image to share the review, made of the front covers of the most listened
+/// releases of the year
+pub async fn image(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path((username, year)): Path<(String, i32)>,
+) -> Result<Response, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    let art = &get_settings()?.library.art;
+    let review = find_review(&db, username.to_owned(), year).await?;
+    let cached = IMAGES
+        .lock()
+        .await
+        .get_mut(&(username.to_owned(), year))
+        .filter(|image| image.updated_at == review.updated_at)
+        .map(|image| {
+            image.used_at = Instant::now();
+            image.bytes.to_owned()
+        });
+    if let Some(bytes) = cached {
+        return Ok((
+            [(header::CONTENT_TYPE, art.format.mime().to_string())],
+            bytes,
+        )
+            .into_response());
+    }
+
+    let releases: Vec<_> = review.report.releases.iter().map(|r| r.id).collect();
+    let covers: HashMap<_, _> = entity::ImageReleaseEntity::find()
+        .filter(entity::ImageReleaseColumn::ReleaseId.is_in(releases.iter().copied()))
+        .find_also_related(entity::ImageEntity)
+        .all(&db)
+        .await?
+        .into_iter()
+        .filter_map(|(release, image)| Some((release.release_id, image?)))
+        .filter(|(_, image)| image.role == PictureType::CoverFront.to_string())
+        .map(|(release, image)| (release, image.path))
+        .collect();
+    let paths = releases
+        .iter()
+        .filter_map(|id| covers.get(id).cloned())
+        .take(SHARE_IMAGE_COVERS)
+        .collect();
+
+    let bytes = stats::collage(paths, art.width, art.format).await?;
+    let mut images = IMAGES.lock().await;
+    let key = (username, year);
+    if images.len() >= SHARE_IMAGE_CACHE && !images.contains_key(&key) {
+        let oldest = images
+            .iter()
+            .min_by_key(|(_, image)| image.used_at)
+            .map(|(key, _)| key.to_owned());
+        if let Some(oldest) = oldest {
+            images.remove(&oldest);
+        }
+    }
+    images.insert(
+        key,
+        CachedImage {
+            updated_at: review.updated_at,
+            used_at: Instant::now(),
+            bytes: bytes.to_owned(),
+        },
+    );
+    drop(images);
+    Ok((
+        [(header::CONTENT_TYPE, art.format.mime().to_string())],
+        bytes,
+    )
+        .into_response())
+}
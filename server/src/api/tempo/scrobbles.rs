@@ -1,11 +1,12 @@
 use axum::extract::{OriginalUri, State};
 use eyre::{eyre, Result};
 use sea_orm::{
+    sea_query::{Expr, Func},
     ActiveValue, ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, LoaderTrait,
     PaginatorTrait, QueryFilter, TransactionTrait, Value,
 };
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use taskie_client::InsertTask;
 use time::Duration;
 use uuid::Uuid;
@@ -20,12 +21,29 @@ use crate::api::{
         links_from_resource, make_cursor, Document, DocumentData, InsertDocument, Page, Query,
         Related, Relation, Relationship, ResourceIdentifier,
     },
-    tempo::{tracks, users},
+    tempo::{stats, tracks, users},
     AppState, Error,
 };
 use crate::tasks::{self, TaskName};
 use base::util::dedup;
 
+/// The phrases joining the artists of a credit, from the loosest to the tightest
+/// so that "A & B feat. C" is first split in "A & B" and "C"
+static JOIN_PHRASES: &[&str] = &[
+    " featuring ",
+    " feat. ",
+    " feat ",
+    " ft. ",
+    " with ",
+    " vs. ",
+    " x ",
+    " / ",
+    ";",
+    " and ",
+    "&",
+    ",",
+];
+
 pub fn entity_to_resource(entity: &entity::Scrobble) -> ScrobbleResource {
     let mut relationships = HashMap::new();
     relationships.insert(
@@ -163,6 +181,119 @@ where
     Ok((data, included))
 }
 
+/// What players scrobbling to other services know about the track they played
+#[derive(Debug, Default)]
+pub struct TrackQuery<'a> {
+    /// The whole artist credit
+    pub artist: &'a str,
+    pub title: &'a str,
+    pub release: Option<&'a str>,
+    pub track_mbid: Option<Uuid>,
+    pub recording_mbid: Option<Uuid>,
+    pub release_mbid: Option<Uuid>,
+}
+
+// Lowercases and collapses whitespace, so that names compare regardless of spacing and case
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// The normalized names an artist credit may be made of. Every split is kept, for
+// artists with a join phrase in their name such as "Earth, Wind & Fire"
+fn credited_artists(credit: &str) -> HashSet<String> {
+    let mut parts = vec![normalize(credit)];
+    let mut names: HashSet<String> = parts.iter().cloned().collect();
+    for phrase in JOIN_PHRASES.iter() {
+        parts = parts
+            .iter()
+            .flat_map(|part| part.split(phrase))
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::to_owned)
+            .collect();
+        names.extend(parts.iter().cloned());
+    }
+    names
+}
+
+// Among tracks sharing a recording or title, prefers the one from the queried release
+async fn pick<C>(
+    db: &C,
+    candidates: Vec<entity::Track>,
+    query: &TrackQuery<'_>,
+) -> Result<Option<entity::Track>, Error>
+where
+    C: ConnectionTrait,
+{
+    if candidates.len() <= 1 {
+        return Ok(candidates.into_iter().next());
+    }
+    let release = query.release.map(str::to_lowercase);
+    let releases: HashMap<Uuid, entity::Release> = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(candidates.iter().map(|t| t.id)))
+        .find_also_linked(entity::TrackToRelease)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(track, release)| release.map(|r| (track.id, r)))
+        .collect();
+    let position = candidates.iter().position(|track| {
+        releases.get(&track.id).map_or(false, |r| {
+            Some(r.id) == query.release_mbid
+                || release.as_deref() == Some(r.title.to_lowercase().as_str())
+        })
+    });
+    Ok(candidates.into_iter().nth(position.unwrap_or(0)))
+}
+
+/// Finds the local track a play from another service refers to, by MusicBrainz
+/// identifiers first and then by its title and artist
+pub async fn match_track<C>(db: &C, query: &TrackQuery<'_>) -> Result<Option<entity::Track>, Error>
+where
+    C: ConnectionTrait,
+{
+    if let Some(id) = query.track_mbid {
+        if let Some(track) = entity::TrackEntity::find_by_id(id).one(db).await? {
+            return Ok(Some(track));
+        }
+    }
+    if let Some(id) = query.recording_mbid {
+        let candidates = entity::TrackEntity::find()
+            .filter(ColumnTrait::eq(&entity::TrackColumn::RecordingId, id))
+            .all(db)
+            .await?;
+        if !candidates.is_empty() {
+            return pick(db, candidates, query).await;
+        }
+    }
+
+    // the artist is the whole credit, so any of the track artists may match one
+    // of the credited names
+    let artists = credited_artists(query.artist);
+    let mut candidates: Vec<entity::Track> = Vec::new();
+    for (track, track_artist) in entity::TrackEntity::find()
+        .filter(
+            Expr::expr(Func::lower(Expr::col((
+                entity::TrackEntity,
+                entity::TrackColumn::Title,
+            ))))
+            .eq(query.title.trim().to_lowercase()),
+        )
+        .find_also_linked(entity::TrackToArtist)
+        .all(db)
+        .await?
+    {
+        let matches = track_artist.map_or(false, |a| artists.contains(&normalize(&a.name)));
+        if matches && !candidates.iter().any(|t| t.id == track.id) {
+            candidates.push(track);
+        }
+    }
+    pick(db, candidates, query).await
+}
+
 pub async fn schedule_scrobble_tasks<I>(username: &str, tracks: I) -> Result<()>
 where
     I: Iterator<Item = (Uuid, time::OffsetDateTime)>,
@@ -221,6 +352,7 @@ pub async fn insert_scrobbles(
         .exec(&tx)
         .await?;
     tx.commit().await?;
+    stats::invalidate(&claims.username).await;
 
     let tx = db.begin().await?;
 
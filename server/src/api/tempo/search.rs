@@ -1,4 +1,5 @@
 use axum::extract::{Query, State};
+use schemars::JsonSchema;
 use sea_orm::{
     ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait,
 };
@@ -10,9 +11,9 @@ use crate::api::{
     documents::{
         ArtistResource, Included, Meta, ReleaseResource, SearchResultAttributes, TrackResource,
     },
-    extract::Json,
+    extract::{Claims, Json},
     jsonapi::{Document, DocumentData},
-    tempo::{artists, releases, tracks},
+    tempo::{artists, favorites, ratings, releases, tracks},
     AppState, Error,
 };
 use crate::search::{
@@ -21,7 +22,7 @@ use crate::search::{
 };
 use base::util::dedup;
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(untagged)]
 pub enum SearchResult {
     Artist(ArtistResource),
@@ -29,7 +30,7 @@ pub enum SearchResult {
     Track(TrackResource),
 }
 
-#[derive(Deserialize, Validate)]
+#[derive(Deserialize, Validate, JsonSchema)]
 pub struct SearchQuery {
     query: String,
     #[serde(default = "default_limit")]
@@ -45,6 +46,7 @@ async fn search_and_map<'a, C>(
     db: &C,
     index: Index<'a>,
     search: &SearchQuery,
+    claims: Option<&Claims>,
 ) -> Result<Vec<SearchResult>, Error>
 where
     C: ConnectionTrait,
@@ -62,13 +64,17 @@ where
             let artists = entity::ArtistEntity::find().filter(cond).all(db).await?;
 
             let related_to_artists = artists::related(db, &artists, false).await?;
+            let mut resources = Vec::new();
             for (i, artist) in artists.iter().enumerate() {
                 let mut entity = artists::entity_to_resource(artist, &related_to_artists[i]);
                 entity.meta = Some(Meta::SearchResult(SearchResultAttributes {
                     score: ids[i].0,
+                    user: Default::default(),
                 }));
-                data.push(SearchResult::Artist(entity));
+                resources.push(entity);
             }
+            favorites::star(db, claims, entity::FavoriteKind::Artist, &mut resources).await?;
+            data.extend(resources.into_iter().map(SearchResult::Artist));
         }
         Index::Releases(_) => {
             let mut cond = Condition::any();
@@ -78,13 +84,18 @@ where
             let releases = entity::ReleaseEntity::find().filter(cond).all(db).await?;
 
             let related_to_releases = releases::related(db, &releases, false).await?;
+            let mut resources = Vec::new();
             for (i, release) in releases.iter().enumerate() {
                 let mut entity = releases::entity_to_resource(release, &related_to_releases[i]);
                 entity.meta = Some(Meta::SearchResult(SearchResultAttributes {
                     score: ids[i].0,
+                    user: Default::default(),
                 }));
-                data.push(SearchResult::Release(entity));
+                resources.push(entity);
             }
+            favorites::star(db, claims, entity::FavoriteKind::Release, &mut resources).await?;
+            ratings::rate(db, claims, entity::RatingKind::Release, &mut resources).await?;
+            data.extend(resources.into_iter().map(SearchResult::Release));
         }
         Index::Tracks(_) => {
             let mut cond = Condition::any();
@@ -94,13 +105,18 @@ where
             let tracks = entity::TrackEntity::find().filter(cond).all(db).await?;
 
             let related_to_tracks = tracks::related(db, &tracks, false).await?;
+            let mut resources = Vec::new();
             for (i, track) in tracks.iter().enumerate() {
                 let mut entity = tracks::entity_to_resource(track, &related_to_tracks[i]);
                 entity.meta = Some(Meta::SearchResult(SearchResultAttributes {
                     score: ids[i].0,
+                    user: Default::default(),
                 }));
-                data.push(SearchResult::Track(entity));
+                resources.push(entity);
             }
+            favorites::star(db, claims, entity::FavoriteKind::Track, &mut resources).await?;
+            ratings::rate(db, claims, entity::RatingKind::Track, &mut resources).await?;
+            data.extend(resources.into_iter().map(SearchResult::Track));
         }
     }
 
@@ -111,12 +127,16 @@ where
 pub async fn search(
     State(AppState(db)): State<AppState>,
     Query(search): Query<SearchQuery>,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<SearchResult, Included>>, Error> {
     let tx = db.begin().await?;
     let indexes = get_indexes()?;
-    let mut artists = search_and_map(&tx, Index::Artists(&indexes.artists), &search).await?;
-    let mut releases = search_and_map(&tx, Index::Releases(&indexes.releases), &search).await?;
-    let mut tracks = search_and_map(&tx, Index::Tracks(&indexes.tracks), &search).await?;
+    let claims = claims.as_ref();
+    let mut artists =
+        search_and_map(&tx, Index::Artists(&indexes.artists), &search, claims).await?;
+    let mut releases =
+        search_and_map(&tx, Index::Releases(&indexes.releases), &search, claims).await?;
+    let mut tracks = search_and_map(&tx, Index::Tracks(&indexes.tracks), &search, claims).await?;
 
     let mut results = Vec::new();
     results.append(&mut artists);
@@ -0,0 +1,292 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::StatusCode,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use std::collections::HashMap;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, InsertShareResource, IntoColumn, ResourceType, ShareAttributes, ShareFilter,
+        ShareInclude, ShareRelation, ShareResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        links_from_resource, make_cursor, Document, DocumentData, InsertOneDocument, Query,
+        Related, Relation, Relationship, ResourceIdentifier,
+    },
+    share::token,
+    tempo::{playlists, releases, tracks},
+    AppState, Error,
+};
+use base::util::dedup;
+
+pub fn entity_to_resource(entity: &entity::Share) -> Result<ShareResource, Error> {
+    let mut relationships = HashMap::new();
+    relationships.insert(
+        ShareRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    );
+    let (relation, r#type) = match entity.kind {
+        entity::ShareKind::Track => (ShareRelation::Track, ResourceType::Track),
+        entity::ShareKind::Release => (ShareRelation::Release, ResourceType::Release),
+        entity::ShareKind::Playlist => (ShareRelation::Playlist, ResourceType::Playlist),
+    };
+    relationships.insert(
+        relation,
+        Relationship {
+            data: Relation::Single(Related::Uuid(ResourceIdentifier {
+                r#type,
+                id: entity.resource,
+                meta: None,
+            })),
+        },
+    );
+
+    Ok(ShareResource {
+        r#type: ResourceType::Share,
+        id: entity.id,
+        attributes: ShareAttributes {
+            token: token(entity)?,
+            created_at: entity.created_at,
+            expires_at: entity.expires_at,
+            max_plays: entity.max_plays,
+            plays: entity.plays,
+        },
+        relationships,
+        meta: None,
+    })
+}
+
+pub async fn included<C>(
+    db: &C,
+    entities: &[entity::Share],
+    include: &[ShareInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&ShareInclude::Track) {
+        let ids = entities
+            .iter()
+            .filter(|s| s.kind == entity::ShareKind::Track)
+            .map(|s| s.resource);
+        let tracks = entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Id.is_in(ids))
+            .all(db)
+            .await?;
+        let tracks_related = tracks::related(db, &tracks, true).await?;
+        for (i, track) in tracks.iter().enumerate() {
+            included.push(tracks::entity_to_included(track, &tracks_related[i]));
+        }
+    }
+    if include.contains(&ShareInclude::Release) {
+        let ids = entities
+            .iter()
+            .filter(|s| s.kind == entity::ShareKind::Release)
+            .map(|s| s.resource);
+        let releases = entity::ReleaseEntity::find()
+            .filter(entity::ReleaseColumn::Id.is_in(ids))
+            .all(db)
+            .await?;
+        let releases_related = releases::related(db, &releases, true).await?;
+        for (i, release) in releases.iter().enumerate() {
+            included.push(releases::entity_to_included(release, &releases_related[i]));
+        }
+    }
+    if include.contains(&ShareInclude::Playlist) {
+        let ids = entities
+            .iter()
+            .filter(|s| s.kind == entity::ShareKind::Playlist)
+            .map(|s| s.resource);
+        let playlists = entity::PlaylistEntity::find()
+            .filter(entity::PlaylistColumn::Id.is_in(ids))
+            .all(db)
+            .await?;
+        let playlists_related = playlists::related(db, &playlists).await?;
+        for (i, playlist) in playlists.iter().enumerate() {
+            included.push(playlists::entity_to_included(
+                playlist,
+                &playlists_related[i],
+            ));
+        }
+    }
+    Ok(included)
+}
+
+async fn resource_to_active_entity<C>(
+    db: &C,
+    username: &str,
+    resource: &InsertShareResource,
+) -> Result<entity::ShareActive, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut targets = resource
+        .relationships
+        .iter()
+        .filter(|(k, _)| **k != ShareRelation::User);
+    let (kind, id) = match (targets.next(), targets.next()) {
+        (Some((relation, relationship)), None) => match (relation, &relationship.data) {
+            (ShareRelation::Track, Relation::Single(Related::Uuid(data))) => {
+                Ok((entity::ShareKind::Track, data.id))
+            }
+            (ShareRelation::Release, Relation::Single(Related::Uuid(data))) => {
+                Ok((entity::ShareKind::Release, data.id))
+            }
+            (ShareRelation::Playlist, Relation::Single(Related::Uuid(data))) => {
+                Ok((entity::ShareKind::Playlist, data.id))
+            }
+            _ => Err(Error::BadRequest(Some(
+                "Invalid share relation".to_string(),
+            ))),
+        },
+        _ => Err(Error::BadRequest(Some(
+            "A share must be related to exactly one track, release or playlist".to_string(),
+        ))),
+    }?;
+    let exists = match kind {
+        entity::ShareKind::Track => entity::TrackEntity::find_by_id(id).one(db).await?.is_some(),
+        entity::ShareKind::Release => entity::ReleaseEntity::find_by_id(id)
+            .one(db)
+            .await?
+            .is_some(),
+        // only the owner can share a playlist, even a public one
+        entity::ShareKind::Playlist => entity::PlaylistEntity::find_by_id(id)
+            .one(db)
+            .await?
+            .map_or(false, |playlist| playlist.user == username),
+    };
+    if !exists {
+        return Err(Error::NotFound(None));
+    }
+
+    let now = OffsetDateTime::now_utc();
+    if resource.attributes.expires_at <= now {
+        return Err(Error::BadRequest(Some(
+            "The expiry date must be in the future".to_string(),
+        )));
+    }
+    if resource.attributes.max_plays.map_or(false, |max| max <= 0) {
+        return Err(Error::BadRequest(Some(
+            "The maximum number of plays must be positive".to_string(),
+        )));
+    }
+
+    Ok(entity::ShareActive {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user: ActiveValue::Set(username.to_owned()),
+        kind: ActiveValue::Set(kind),
+        resource: ActiveValue::Set(id),
+        created_at: ActiveValue::Set(now),
+        expires_at: ActiveValue::Set(resource.attributes.expires_at),
+        max_plays: ActiveValue::Set(resource.attributes.max_plays),
+        plays: ActiveValue::Set(0),
+    })
+}
+
+async fn find_share_by_id<C>(db: &C, username: &str, id: Uuid) -> Result<entity::Share, Error>
+where
+    C: ConnectionTrait,
+{
+    entity::ShareEntity::find_by_id(id)
+        .filter(ColumnTrait::eq(&entity::ShareColumn::User, username))
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))
+}
+
+pub async fn shares(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<ShareFilter, entity::ShareColumn, ShareInclude, Uuid>,
+    OriginalUri(uri): OriginalUri,
+    claims: Claims,
+) -> Result<Json<Document<ShareResource, Included>>, Error> {
+    let tx = db.begin().await?;
+
+    let mut shares_query = entity::ShareEntity::find()
+        .filter(ColumnTrait::eq(&entity::ShareColumn::User, claims.username));
+    for (filter_key, filter_value) in opts.filter.iter() {
+        if let Some(k) = filter_key.column() {
+            shares_query = shares_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        }
+    }
+    let mut _shares_cursor = shares_query.cursor_by(entity::ShareColumn::Id);
+    let shares_cursor = make_cursor(&mut _shares_cursor, &opts.page);
+    let shares = shares_cursor.all(&tx).await?;
+    let data = shares
+        .iter()
+        .map(entity_to_resource)
+        .collect::<Result<Vec<_>, _>>()?;
+    let included = included(&tx, &shares, &opts.include).await?;
+    Ok(Json(Document {
+        links: links_from_resource(&data, opts, &uri),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+pub async fn share(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<ShareFilter, entity::ShareColumn, ShareInclude, Uuid>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<Document<ShareResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let share = find_share_by_id(&tx, &claims.username, id).await?;
+    let data = entity_to_resource(&share)?;
+    let included = included(&tx, &[share], &opts.include).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(data),
+        included: dedup(included),
+    }))
+}
+
+pub async fn insert_share(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Json(body): Json<InsertOneDocument<InsertShareResource>>,
+) -> Result<Json<Document<ShareResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let share = resource_to_active_entity(&tx, &claims.username, &body.data)
+        .await?
+        .insert(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, id = %share.id, "Created share link");
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&share)?),
+        included: Vec::new(),
+    }))
+}
+
+pub async fn delete_share(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    let share = find_share_by_id(&tx, &claims.username, id).await?;
+    entity::ShareEntity::delete_by_id(share.id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, %id, "Revoked share link");
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,613 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::StatusCode,
+};
+use sea_orm::{
+    sea_query::{Expr, Func, LikeExpr, SelectStatement, SimpleExpr, SubQueryStatement},
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, CursorTrait,
+    DatabaseBackend, EntityTrait, JoinType, Order, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait, Select, TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, InsertSmartPlaylistResource, IntoColumn, ResourceType, SmartPlaylistAttributes,
+        SmartPlaylistFilter, SmartPlaylistInclude, SmartPlaylistRelation, SmartPlaylistResource,
+        TrackFilter, TrackInclude, TrackResource, UpdateSmartPlaylistResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        links_from_resource, make_cursor, Document, DocumentData, InsertOneDocument, Query,
+        Related, Relation, Relationship, ResourceIdentifier, UpdateOneDocument,
+    },
+    tempo::{favorites, ratings, tracks, users},
+    AppState, Error,
+};
+use base::util::dedup;
+use entity::{NumberCondition, Rule, RuleOrder, SmartPlaylistRules, TextCondition};
+
+/// Upper bound on the tracks of a smart playlist, which are all returned at once
+const MAX_TRACKS: u64 = 500;
+/// Upper bound on the days of scrobbled within rules, going further back would
+/// overflow the dates
+const MAX_DAYS: u32 = 100 * 366;
+/// Escapes wildcards in LIKE patterns. Backslashes mean different things in
+/// string literals across backends, so another character is used.
+const LIKE_ESCAPE: char = '!';
+
+// Lowercases the value and escapes the wildcards of LIKE in it
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.to_lowercase().chars() {
+        if c == '%' || c == '_' || c == LIKE_ESCAPE {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn text(column: Expr, condition: &TextCondition) -> SimpleExpr {
+    let column = Expr::expr(Func::lower(column));
+    match condition {
+        TextCondition::Is(value) => column.eq(value.to_lowercase()),
+        TextCondition::Contains(value) => {
+            column.like(LikeExpr::new(format!("%{}%", escape_like(value))).escape(LIKE_ESCAPE))
+        }
+        TextCondition::StartsWith(value) => {
+            column.like(LikeExpr::new(format!("{}%", escape_like(value))).escape(LIKE_ESCAPE))
+        }
+    }
+}
+
+fn number(column: SimpleExpr, condition: &NumberCondition) -> SimpleExpr {
+    let column = Expr::expr(column);
+    match *condition {
+        NumberCondition::Is(value) => column.eq(value),
+        NumberCondition::Between(min, max) => column.between(min, max),
+        NumberCondition::AtLeast(value) => column.gte(value),
+        NumberCondition::AtMost(value) => column.lte(value),
+    }
+}
+
+// Releases often are reissues, so the original year is the one users expect
+fn year() -> SimpleExpr {
+    Func::coalesce([
+        Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::OriginalYear)).into(),
+        Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::Year)).into(),
+    ])
+    .into()
+}
+
+fn scrobbles_of(username: &str) -> SelectStatement {
+    SelectStatement::new()
+        .from(entity::ScrobbleEntity)
+        .and_where(
+            Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::Track))
+                .equals((entity::TrackEntity, entity::TrackColumn::Id)),
+        )
+        .and_where(Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::User)).eq(username))
+        .to_owned()
+}
+
+fn scrobble_count(username: &str) -> SimpleExpr {
+    SimpleExpr::SubQuery(
+        None,
+        Box::new(SubQueryStatement::SelectStatement(
+            scrobbles_of(username)
+                .expr(Func::count(Expr::col((
+                    entity::ScrobbleEntity,
+                    entity::ScrobbleColumn::Id,
+                ))))
+                .to_owned(),
+        )),
+    )
+}
+
+fn last_scrobbled(username: &str) -> SimpleExpr {
+    SimpleExpr::SubQuery(
+        None,
+        Box::new(SubQueryStatement::SelectStatement(
+            scrobbles_of(username)
+                .expr(Func::max(Expr::col((
+                    entity::ScrobbleEntity,
+                    entity::ScrobbleColumn::At,
+                ))))
+                .to_owned(),
+        )),
+    )
+}
+
+fn track_id() -> Expr {
+    Expr::col((entity::TrackEntity, entity::TrackColumn::Id))
+}
+
+/// Translates a rule to a condition on tracks, joined with their medium and
+/// release. Scrobbles are the ones of the given user.
+fn condition(rule: &Rule, username: &str) -> Condition {
+    match rule {
+        Rule::All(rules) => rules.iter().fold(Condition::all(), |all, rule| {
+            all.add(condition(rule, username))
+        }),
+        Rule::Any(rules) => rules.iter().fold(Condition::any(), |any, rule| {
+            any.add(condition(rule, username))
+        }),
+        Rule::Not(rule) => condition(rule, username).not(),
+        Rule::Title(c) => Condition::all().add(text(
+            Expr::col((entity::TrackEntity, entity::TrackColumn::Title)),
+            c,
+        )),
+        Rule::Artist(c) => Condition::all().add(
+            track_id().in_subquery(
+                SelectStatement::new()
+                    .column((
+                        entity::ArtistCreditTrackEntity,
+                        entity::ArtistCreditTrackColumn::TrackId,
+                    ))
+                    .from(entity::ArtistCreditTrackEntity)
+                    .inner_join(
+                        entity::ArtistCreditEntity,
+                        Expr::col((entity::ArtistCreditEntity, entity::ArtistCreditColumn::Id))
+                            .equals((
+                                entity::ArtistCreditTrackEntity,
+                                entity::ArtistCreditTrackColumn::ArtistCreditId,
+                            )),
+                    )
+                    .inner_join(
+                        entity::ArtistEntity,
+                        Expr::col((entity::ArtistEntity, entity::ArtistColumn::Id)).equals((
+                            entity::ArtistCreditEntity,
+                            entity::ArtistCreditColumn::ArtistId,
+                        )),
+                    )
+                    .and_where(text(
+                        Expr::col((entity::ArtistEntity, entity::ArtistColumn::Name)),
+                        c,
+                    ))
+                    .to_owned(),
+            ),
+        ),
+        Rule::Release(c) => Condition::all().add(text(
+            Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::Title)),
+            c,
+        )),
+        Rule::Genre(c) => Condition::any()
+            .add(
+                track_id().in_subquery(
+                    SelectStatement::new()
+                        .column((entity::GenreTrackEntity, entity::GenreTrackColumn::TrackId))
+                        .from(entity::GenreTrackEntity)
+                        .inner_join(
+                            entity::GenreEntity,
+                            Expr::col((entity::GenreEntity, entity::GenreColumn::Id)).equals((
+                                entity::GenreTrackEntity,
+                                entity::GenreTrackColumn::GenreId,
+                            )),
+                        )
+                        .and_where(text(
+                            Expr::col((entity::GenreEntity, entity::GenreColumn::Name)),
+                            c,
+                        ))
+                        .to_owned(),
+                ),
+            )
+            .add(
+                Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::Id)).in_subquery(
+                    SelectStatement::new()
+                        .column((
+                            entity::GenreReleaseEntity,
+                            entity::GenreReleaseColumn::ReleaseId,
+                        ))
+                        .from(entity::GenreReleaseEntity)
+                        .inner_join(
+                            entity::GenreEntity,
+                            Expr::col((entity::GenreEntity, entity::GenreColumn::Id)).equals((
+                                entity::GenreReleaseEntity,
+                                entity::GenreReleaseColumn::GenreId,
+                            )),
+                        )
+                        .and_where(text(
+                            Expr::col((entity::GenreEntity, entity::GenreColumn::Name)),
+                            c,
+                        ))
+                        .to_owned(),
+                ),
+            ),
+        Rule::Year(c) => Condition::all().add(number(year(), c)),
+        Rule::Length(c) => Condition::all().add(number(
+            Expr::col((entity::TrackEntity, entity::TrackColumn::Length)).into(),
+            c,
+        )),
+        Rule::Bpm(c) => Condition::all().add(number(
+            Expr::col((entity::TrackEntity, entity::TrackColumn::Bpm)).into(),
+            c,
+        )),
+        Rule::Scrobbles(c) => Condition::all().add(number(scrobble_count(username), c)),
+        Rule::ScrobbledWithin(days) => Condition::all().add(
+            track_id().in_subquery(
+                SelectStatement::new()
+                    .column((entity::ScrobbleEntity, entity::ScrobbleColumn::Track))
+                    .from(entity::ScrobbleEntity)
+                    .and_where(
+                        Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::User))
+                            .eq(username),
+                    )
+                    .and_where(
+                        Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::At))
+                            .gte(OffsetDateTime::now_utc() - Duration::days(*days as i64)),
+                    )
+                    .to_owned(),
+            ),
+        ),
+    }
+}
+
+fn check_rule(rule: &Rule) -> Result<(), Error> {
+    match rule {
+        Rule::All(rules) | Rule::Any(rules) if rules.is_empty() => Err(Error::BadRequest(Some(
+            "Groups of rules can't be empty".to_string(),
+        ))),
+        Rule::All(rules) | Rule::Any(rules) => rules.iter().try_for_each(check_rule),
+        Rule::Not(rule) => check_rule(rule),
+        Rule::Year(NumberCondition::Between(min, max))
+        | Rule::Length(NumberCondition::Between(min, max))
+        | Rule::Bpm(NumberCondition::Between(min, max))
+        | Rule::Scrobbles(NumberCondition::Between(min, max))
+            if min > max =>
+        {
+            Err(Error::BadRequest(Some(
+                "The bounds of a range are in the wrong order".to_string(),
+            )))
+        }
+        Rule::ScrobbledWithin(days) if *days > MAX_DAYS => Err(Error::BadRequest(Some(format!(
+            "Scrobbles can be looked up to {} days back",
+            MAX_DAYS
+        )))),
+        _ => Ok(()),
+    }
+}
+
+fn check_rules(rules: &SmartPlaylistRules) -> Result<(), Error> {
+    if let Some(limit) = rules.limit {
+        if limit == 0 || limit > MAX_TRACKS {
+            return Err(Error::BadRequest(Some(format!(
+                "The limit must be between 1 and {}",
+                MAX_TRACKS
+            ))));
+        }
+    }
+    check_rule(&rules.rule)
+}
+
+/// Builds the query returning the tracks matching a smart playlist, in order
+fn tracks_query(
+    playlist: &entity::SmartPlaylist,
+    backend: DatabaseBackend,
+) -> Select<entity::TrackEntity> {
+    let rules = &playlist.rules;
+    let query = entity::TrackEntity::find()
+        .join(JoinType::LeftJoin, entity::TrackRelation::Medium.def())
+        .join(JoinType::LeftJoin, entity::MediumRelation::Release.def())
+        .filter(condition(&rules.rule, &playlist.user));
+    let order = match rules.descending {
+        true => Order::Desc,
+        false => Order::Asc,
+    };
+    let query = match rules.order {
+        None => query
+            .order_by(entity::ReleaseColumn::Title, order.to_owned())
+            .order_by(entity::MediumColumn::Position, order.to_owned())
+            .order_by(entity::TrackColumn::Number, order),
+        Some(RuleOrder::Random) => query.order_by(
+            SimpleExpr::from(Expr::cust(match backend {
+                DatabaseBackend::MySql => "RAND()",
+                DatabaseBackend::Postgres | DatabaseBackend::Sqlite => "RANDOM()",
+            })),
+            order,
+        ),
+        Some(RuleOrder::Title) => query.order_by(entity::TrackColumn::Title, order),
+        Some(RuleOrder::Year) => query.order_by(year(), order),
+        Some(RuleOrder::Length) => query.order_by(entity::TrackColumn::Length, order),
+        Some(RuleOrder::Bpm) => query.order_by(entity::TrackColumn::Bpm, order),
+        Some(RuleOrder::Scrobbles) => query.order_by(scrobble_count(&playlist.user), order),
+        Some(RuleOrder::LastScrobbled) => query.order_by(last_scrobbled(&playlist.user), order),
+    };
+    query.limit(rules.limit.unwrap_or(MAX_TRACKS).min(MAX_TRACKS))
+}
+
+pub fn entity_to_resource(entity: &entity::SmartPlaylist) -> SmartPlaylistResource {
+    let relationships = [(
+        SmartPlaylistRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    )]
+    .into();
+
+    SmartPlaylistResource {
+        r#type: ResourceType::SmartPlaylist,
+        id: entity.id,
+        attributes: SmartPlaylistAttributes {
+            name: entity.name.to_owned(),
+            description: entity.description.to_owned(),
+            public: entity.public,
+            rules: entity.rules.to_owned(),
+            created_at: entity.created_at,
+            updated_at: entity.updated_at,
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+pub fn entity_to_included(entity: &entity::SmartPlaylist) -> Included {
+    Included::SmartPlaylist(entity_to_resource(entity))
+}
+
+pub async fn included<C>(
+    db: &C,
+    entities: &[entity::SmartPlaylist],
+    include: &[SmartPlaylistInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&SmartPlaylistInclude::User) {
+        let usernames: HashSet<&str> = entities.iter().map(|p| p.user.as_str()).collect();
+        let users = entity::UserEntity::find()
+            .filter(entity::UserColumn::Username.is_in(usernames))
+            .all(db)
+            .await?;
+        let users_related = users::related(db, &users, true).await?;
+        for (user, related) in users.iter().zip(users_related.iter()) {
+            included.push(users::entity_to_included(user, related));
+        }
+    }
+    Ok(included)
+}
+
+/// Finds a smart playlist the user can see, which are their own and the public
+/// ones of other users. Only the owner can edit a smart playlist.
+async fn find_smart_playlist<C>(
+    db: &C,
+    username: &str,
+    id: Uuid,
+    edit: bool,
+) -> Result<entity::SmartPlaylist, Error>
+where
+    C: ConnectionTrait,
+{
+    let playlist = entity::SmartPlaylistEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    match (playlist.user == username, playlist.public, edit) {
+        (true, _, _) | (false, true, false) => Ok(playlist),
+        (false, true, true) => Err(Error::Unauthorized(Some(
+            "Only the owner can edit a smart playlist".to_string(),
+        ))),
+        (false, false, _) => Err(Error::NotFound(None)),
+    }
+}
+
+pub async fn smart_playlists(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<
+        SmartPlaylistFilter,
+        entity::SmartPlaylistColumn,
+        SmartPlaylistInclude,
+        Uuid,
+    >,
+    OriginalUri(uri): OriginalUri,
+    claims: Claims,
+) -> Result<Json<Document<SmartPlaylistResource, Included>>, Error> {
+    let tx = db.begin().await?;
+
+    let mut playlists_query = entity::SmartPlaylistEntity::find().filter(
+        Condition::any()
+            .add(ColumnTrait::eq(
+                &entity::SmartPlaylistColumn::User,
+                claims.username.as_str(),
+            ))
+            .add(ColumnTrait::eq(&entity::SmartPlaylistColumn::Public, true)),
+    );
+    for (filter_key, filter_value) in opts.filter.iter() {
+        playlists_query = match (filter_key, filter_key.column()) {
+            (SmartPlaylistFilter::Public, Some(k)) => {
+                let public = filter_value.parse::<bool>().map_err(|_| {
+                    Error::BadRequest(Some("The public filter must be true or false".to_string()))
+                })?;
+                playlists_query.filter(ColumnTrait::eq(&k, public))
+            }
+            (_, Some(k)) => playlists_query.filter(ColumnTrait::eq(&k, filter_value.to_owned())),
+            (_, None) => playlists_query,
+        };
+    }
+    let mut _playlists_cursor = playlists_query.cursor_by(entity::SmartPlaylistColumn::Id);
+    let playlists_cursor = make_cursor(&mut _playlists_cursor, &opts.page);
+    let playlists = playlists_cursor.all(&tx).await?;
+    let data = playlists.iter().map(entity_to_resource).collect::<Vec<_>>();
+    let included = included(&tx, &playlists, &opts.include).await?;
+    Ok(Json(Document {
+        links: links_from_resource(&data, opts, &uri),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+pub async fn smart_playlist(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<
+        SmartPlaylistFilter,
+        entity::SmartPlaylistColumn,
+        SmartPlaylistInclude,
+        Uuid,
+    >,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<Document<SmartPlaylistResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_smart_playlist(&tx, &claims.username, id, false).await?;
+    let included = included(&tx, &[playlist.to_owned()], &opts.include).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&playlist)),
+        included: dedup(included),
+    }))
+}
+
+pub async fn insert_smart_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Json(body): Json<InsertOneDocument<InsertSmartPlaylistResource>>,
+) -> Result<(StatusCode, Json<Document<SmartPlaylistResource, Included>>), Error> {
+    let attributes = body.data.attributes;
+    if attributes.name.trim().is_empty() {
+        return Err(Error::BadRequest(Some(
+            "A playlist must have a name".to_string(),
+        )));
+    }
+    check_rules(&attributes.rules)?;
+
+    let now = OffsetDateTime::now_utc();
+    let playlist = entity::SmartPlaylistActive {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user: ActiveValue::Set(claims.username.to_owned()),
+        name: ActiveValue::Set(attributes.name),
+        description: ActiveValue::Set(attributes.description.filter(|d| !d.is_empty())),
+        public: ActiveValue::Set(attributes.public),
+        rules: ActiveValue::Set(attributes.rules),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    }
+    .insert(&db)
+    .await?;
+    tracing::info!(user = %claims.username, id = %playlist.id, "Created smart playlist");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Document {
+            links: HashMap::new(),
+            data: DocumentData::Single(entity_to_resource(&playlist)),
+            included: Vec::new(),
+        }),
+    ))
+}
+
+pub async fn update_smart_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateOneDocument<UpdateSmartPlaylistResource>>,
+) -> Result<Json<Document<SmartPlaylistResource, Included>>, Error> {
+    let resource = body.data;
+    if resource.id != id {
+        return Err(Error::BadRequest(Some(
+            "The id of the resource does not match the URL".to_string(),
+        )));
+    }
+    if resource
+        .relationships
+        .contains_key(&SmartPlaylistRelation::User)
+    {
+        return Err(Error::BadRequest(Some(
+            "The owner of a playlist can't be changed".to_string(),
+        )));
+    }
+
+    let tx = db.begin().await?;
+    let playlist = find_smart_playlist(&tx, &claims.username, id, true).await?;
+    let mut active: entity::SmartPlaylistActive = playlist.into();
+    if let Some(name) = resource.attributes.name {
+        if name.trim().is_empty() {
+            return Err(Error::BadRequest(Some(
+                "A playlist must have a name".to_string(),
+            )));
+        }
+        active.name = ActiveValue::Set(name);
+    }
+    if let Some(description) = resource.attributes.description {
+        active.description = ActiveValue::Set(Some(description).filter(|d| !d.is_empty()));
+    }
+    if let Some(public) = resource.attributes.public {
+        active.public = ActiveValue::Set(public);
+    }
+    if let Some(rules) = resource.attributes.rules {
+        check_rules(&rules)?;
+        active.rules = ActiveValue::Set(rules);
+    }
+    active.updated_at = ActiveValue::Set(OffsetDateTime::now_utc());
+    let playlist = active.update(&tx).await?;
+    tx.commit().await?;
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(entity_to_resource(&playlist)),
+        included: Vec::new(),
+    }))
+}
+
+pub async fn delete_smart_playlist(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_smart_playlist(&tx, &claims.username, id, true).await?;
+    entity::SmartPlaylistEntity::delete_by_id(playlist.id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, %id, "Deleted smart playlist");
+
+    Ok(StatusCode::OK)
+}
+
+/// Evaluates the rules of the playlist. The tracks are all returned at once, in
+/// the order of the rules, so they aren't paginated.
+pub async fn tracks(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<TrackFilter, entity::TrackColumn, TrackInclude, Uuid>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<Document<TrackResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let playlist = find_smart_playlist(&tx, &claims.username, id, false).await?;
+
+    let mut tracks_query = tracks_query(&playlist, tx.get_database_backend());
+    for (filter_key, filter_value) in opts.filter.iter() {
+        if let Some(k) = filter_key.column() {
+            tracks_query = tracks_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        } else if *filter_key == TrackFilter::Starred {
+            tracks_query = tracks_query.filter(favorites::filter(
+                entity::TrackColumn::Id,
+                Some(&claims),
+                entity::FavoriteKind::Track,
+                filter_value,
+            )?);
+        }
+    }
+    let tracks = tracks_query.all(&tx).await?;
+    let related_to_tracks = tracks::related(&tx, &tracks, false).await?;
+    let mut data: Vec<TrackResource> = tracks
+        .iter()
+        .zip(related_to_tracks.iter())
+        .map(|(track, related)| tracks::entity_to_resource(track, related))
+        .collect();
+    favorites::star(&tx, Some(&claims), entity::FavoriteKind::Track, &mut data).await?;
+    ratings::rate(&tx, Some(&claims), entity::RatingKind::Track, &mut data).await?;
+    let included = tracks::included(&tx, related_to_tracks, &opts.include).await?;
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
@@ -0,0 +1,244 @@
+use axum::extract::{Query as AxumQuery, State};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use sea_orm::{ConnectionTrait, EntityTrait, TransactionTrait};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+use time::{Duration, OffsetDateTime};
+use tokio::sync::Mutex;
+
+use crate::api::{
+    documents::{
+        Included, ResourceType, StatsAttributes, StatsEntry, StatsRange, StatsRelation,
+        StatsResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{Document, DocumentData, Related, Relation, Relationship, ResourceIdentifier},
+    AppState, Error,
+};
+use crate::stats;
+
+/// Cached statistics are computed again after this long even when no scrobble
+/// came in, so that the ranges ending now move on
+const CACHE_TTL: Duration = Duration::hours(1);
+/// The number of statistics kept for each user, the oldest ones are dropped first
+const CACHE_ENTRIES: usize = 32;
+const DEFAULT_LIMIT: u64 = 10;
+const MAX_LIMIT: u64 = 100;
+/// Time zones are at most 14 hours away from UTC
+const MAX_TZ_OFFSET: i32 = 14 * 60;
+
+/// The cached statistics of a user. The generation changes on every
+/// invalidation, so that statistics computed from older scrobbles are not stored.
+#[derive(Default)]
+struct UserCache {
+    generation: u64,
+    entries: HashMap<StatsQuery, (OffsetDateTime, StatsAttributes)>,
+}
+
+lazy_static! {
+    static ref CACHE: Arc<Mutex<HashMap<String, UserCache>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Drops the cached statistics of the user, to be called whenever new
+/// scrobbles are stored
+pub async fn invalidate(username: &str) {
+    let mut cache = CACHE.lock().await;
+    let user = cache.entry(username.to_owned()).or_default();
+    user.generation += 1;
+    user.entries.clear();
+}
+
+fn default_limit() -> u64 {
+    DEFAULT_LIMIT
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StatsQuery {
+    #[serde(default)]
+    range: StatsRange,
+    /// Start of a custom range, included
+    #[serde(default, with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    from: Option<OffsetDateTime>,
+    /// End of a custom range, excluded
+    #[serde(default, with = "time::serde::iso8601::option")]
+    #[schemars(with = "Option<String>")]
+    to: Option<OffsetDateTime>,
+    /// Number of top artists, releases, tracks and genres
+    #[serde(default = "default_limit")]
+    limit: u64,
+    /// Offset of the time zone of the hours and weekdays, in minutes east of UTC
+    #[serde(default)]
+    tz: i32,
+}
+
+fn bounds(
+    query: &StatsQuery,
+    now: OffsetDateTime,
+) -> Result<(Option<OffsetDateTime>, Option<OffsetDateTime>), Error> {
+    match query.range {
+        StatsRange::Week => Ok((Some(now - Duration::days(7)), None)),
+        StatsRange::Month => Ok((Some(now - Duration::days(30)), None)),
+        StatsRange::Year => Ok((Some(now - Duration::days(365)), None)),
+        StatsRange::All => Ok((None, None)),
+        StatsRange::Custom if query.from.is_none() && query.to.is_none() => Err(Error::BadRequest(
+            Some("A custom range needs a start or an end".to_string()),
+        )),
+        StatsRange::Custom => Ok((query.from, query.to)),
+    }
+}
+
+fn entries<Id>(rows: impl Iterator<Item = (Id, String, i64, Option<i64>)>) -> Vec<StatsEntry<Id>> {
+    rows.map(|(id, name, scrobbles, listening_time)| StatsEntry {
+        id,
+        name,
+        scrobbles: scrobbles as u64,
+        listening_time: listening_time.unwrap_or_default() as u64,
+    })
+    .collect()
+}
+
+async fn compute<C>(db: &C, username: &str, query: &StatsQuery) -> Result<StatsAttributes, Error>
+where
+    C: ConnectionTrait,
+{
+    let backend = db.get_database_backend();
+    let (from, to) = bounds(query, OffsetDateTime::now_utc())?;
+    let limit = query.limit;
+    let base = || stats::scrobbles(username, from, to);
+
+    let totals = stats::totals(db, base()).await?;
+    let artists = stats::top_artists(db, base(), limit).await?;
+    let releases = stats::top_releases(db, base(), limit).await?;
+    let tracks = stats::top_tracks(db, base(), limit).await?;
+    let genres = stats::top_genres(db, base(), limit).await?;
+
+    let mut hours = vec![0; 24];
+    for row in stats::buckets(db, base(), stats::hour(backend, query.tz)).await? {
+        if let Some(count) = hours.get_mut(row.bucket as usize) {
+            *count = row.scrobbles as u64;
+        }
+    }
+    let mut weekdays = vec![0; 7];
+    for row in stats::buckets(db, base(), stats::weekday(backend, query.tz)).await? {
+        let index = stats::weekday_index(row.bucket, backend);
+        if let Some(count) = weekdays.get_mut(index as usize) {
+            *count = row.scrobbles as u64;
+        }
+    }
+
+    Ok(StatsAttributes {
+        range: query.range,
+        from,
+        to,
+        scrobbles: totals.scrobbles as u64,
+        listening_time: totals.listening_time.unwrap_or_default() as u64,
+        artists: entries(
+            artists
+                .into_iter()
+                .map(|r| (r.id, r.name, r.scrobbles, r.listening_time)),
+        ),
+        releases: entries(
+            releases
+                .into_iter()
+                .map(|r| (r.id, r.name, r.scrobbles, r.listening_time)),
+        ),
+        tracks: entries(
+            tracks
+                .into_iter()
+                .map(|r| (r.id, r.name, r.scrobbles, r.listening_time)),
+        ),
+        genres: entries(
+            genres
+                .into_iter()
+                .map(|r| (r.id, r.name, r.scrobbles, r.listening_time)),
+        ),
+        hours,
+        weekdays,
+    })
+}
+
+/// Listening statistics of a user over a range of time
+pub async fn stats(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(username): Path<String>,
+    AxumQuery(mut query): AxumQuery<StatsQuery>,
+) -> Result<Json<Document<StatsResource, Included>>, Error> {
+    if claims.username != username {
+        return Err(Error::Unauthorized(None));
+    }
+    // clamped before it becomes part of the cache key
+    query.limit = query.limit.min(MAX_LIMIT);
+    if query.tz.abs() > MAX_TZ_OFFSET {
+        return Err(Error::BadRequest(Some(format!(
+            "The time zone offset must be within {} minutes of UTC",
+            MAX_TZ_OFFSET
+        ))));
+    }
+    let now = OffsetDateTime::now_utc();
+    let (generation, cached) = {
+        let cache = CACHE.lock().await;
+        let user = cache.get(&username);
+        (
+            user.map_or(0, |user| user.generation),
+            user.and_then(|user| user.entries.get(&query))
+                .filter(|(computed_at, _)| now - *computed_at < CACHE_TTL)
+                .map(|(_, attributes)| attributes.to_owned()),
+        )
+    };
+    let attributes = match cached {
+        Some(attributes) => attributes,
+        None => {
+            let tx = db.begin().await?;
+            entity::UserEntity::find_by_id(username.to_owned())
+                .one(&tx)
+                .await?
+                .ok_or(Error::NotFound(None))?;
+            let attributes = compute(&tx, &username, &query).await?;
+            let mut cache = CACHE.lock().await;
+            let user = cache.entry(username.to_owned()).or_default();
+            // scrobbles came in while computing, these statistics are already stale
+            if user.generation == generation {
+                user.entries
+                    .retain(|_, (computed_at, _)| now - *computed_at < CACHE_TTL);
+                if user.entries.len() >= CACHE_ENTRIES && !user.entries.contains_key(&query) {
+                    let oldest = user
+                        .entries
+                        .iter()
+                        .min_by_key(|(_, (computed_at, _))| *computed_at)
+                        .map(|(query, _)| query.to_owned());
+                    if let Some(oldest) = oldest {
+                        user.entries.remove(&oldest);
+                    }
+                }
+                user.entries.insert(query, (now, attributes.to_owned()));
+            }
+            attributes
+        }
+    };
+
+    let relationships = [(
+        StatsRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: username.to_owned(),
+                meta: None,
+            })),
+        },
+    )]
+    .into();
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(StatsResource {
+            r#type: ResourceType::Stats,
+            id: username,
+            attributes,
+            relationships,
+            meta: None,
+        }),
+        included: Vec::new(),
+    }))
+}
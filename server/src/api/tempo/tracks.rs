@@ -1,11 +1,19 @@
-use axum::extract::{OriginalUri, State};
-use axum::http::Request;
-use axum::{body::Body, response::IntoResponse};
+use axum::extract::{Json as AxumJson, OriginalUri, Query as AxumQuery, State};
+use axum::http::{header, Request};
+use axum::{
+    body::{Body, StreamBody},
+    response::{IntoResponse, Response},
+};
+use entity::IgnoreNone;
+use itertools::Itertools;
+use schemars::JsonSchema;
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, LoaderTrait, QueryFilter, QueryOrder,
-    TransactionTrait,
+    ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, IntoActiveModel, LoaderTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
 };
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use tower::ServiceExt;
 use uuid::Uuid;
 
@@ -15,15 +23,21 @@ use crate::api::{
         ArtistCreditAttributes, Included, IntoColumn, Meta, RecordingAttributes, ResourceType,
         TrackAttributes, TrackFilter, TrackInclude, TrackRelation, TrackResource,
     },
-    extract::{Json, Path},
+    extract::{Claims, Json, Path},
     jsonapi::{
         links_from_resource, make_cursor, Document, DocumentData, Query, Related, Relation,
         Relationship, ResourceIdentifier,
     },
-    tempo::{artists, genres, mediums},
+    tempo::{artists, favorites, genres, mediums, ratings},
     AppState, Error,
 };
+use crate::hls::{parse_segment, playlist, segment, Timeline, PLAYLIST_MIME, SEGMENT_MIME};
+use crate::import::{format_lrc, read_lyrics, sidecar, TrackFile};
+use crate::tasks::waveform::{downsample, WAVEFORM_RESOLUTION};
+use crate::transcode::{resolve, transcode};
+use base::setting::{get_settings, TranscodingFormat};
 use base::util::dedup;
+use tag::TagKey;
 
 #[derive(Default)]
 pub struct TrackRelated {
@@ -167,7 +181,7 @@ pub fn entity_to_resource(entity: &entity::Track, related: &TrackRelated) -> Tra
             title: entity.title.to_owned(),
             track: entity.number,
             disc: medium.as_ref().map(|m| m.position),
-            bpm: None,
+            bpm: entity.bpm,
 
             recording_mbid: entity.recording_id.to_owned(),
             track_mbid: entity.id,
@@ -175,12 +189,24 @@ pub fn entity_to_resource(entity: &entity::Track, related: &TrackRelated) -> Tra
 
             mimetype: entity.format.map(|mime| mime.mime().to_string()),
             duration: entity.length,
-            framerate: None,
-            framecount: None,
-            channels: None,
-            bitrate: None,
-            bitdepth: None,
-            size: None, // TODO
+            framerate: entity.framerate,
+            framecount: entity.framecount,
+            channels: entity.channels,
+            bitrate: entity.bitrate,
+            bitdepth: entity.bitdepth,
+            size: entity.size,
+
+            replay_gain_track_gain: entity.replay_gain_track_gain,
+            replay_gain_track_peak: entity.replay_gain_track_peak,
+            replay_gain_track_range: entity.replay_gain_track_range,
+            replay_gain_album_gain: entity.replay_gain_album_gain,
+            replay_gain_album_peak: entity.replay_gain_album_peak,
+            replay_gain_album_range: entity.replay_gain_album_range,
+
+            encoder_delay: entity.encoder_delay,
+            encoder_padding: entity.encoder_padding,
+            sample_count: entity.sample_count,
+            gapless_playback: entity.gapless_playback,
         },
         relationships,
         meta: None,
@@ -191,6 +217,70 @@ pub fn entity_to_included(entity: &entity::Track, related: &TrackRelated) -> Inc
     Included::Track(entity_to_resource(entity, related))
 }
 
+/// Included tracks with the starred and rating meta of the user, when the
+/// request is authenticated
+pub async fn entities_to_included<C>(
+    db: &C,
+    claims: Option<&Claims>,
+    entities: &[entity::Track],
+    related: &[TrackRelated],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut data: Vec<TrackResource> = entities
+        .iter()
+        .zip(related.iter())
+        .map(|(entity, related)| entity_to_resource(entity, related))
+        .collect();
+    favorites::star(db, claims, entity::FavoriteKind::Track, &mut data).await?;
+    ratings::rate(db, claims, entity::RatingKind::Track, &mut data).await?;
+    Ok(data.into_iter().map(Included::Track).collect())
+}
+
+/// The ids of the tracks in a relationship of the owner, such as a playlist or a
+/// play queue
+pub fn relation_ids(
+    relation: &Relation<ResourceType, Meta>,
+    owner: &str,
+) -> Result<Vec<Uuid>, Error> {
+    match relation {
+        Relation::Multi(related) => related
+            .iter()
+            .map(|related| match related {
+                Related::Uuid(identifier) if identifier.r#type == ResourceType::Track => {
+                    Ok(identifier.id)
+                }
+                _ => Err(Error::BadRequest(Some(format!(
+                    "Only tracks can be added to {}",
+                    owner
+                )))),
+            })
+            .collect(),
+        Relation::Single(_) => Err(Error::BadRequest(Some(format!(
+            "The tracks of {} must be a list",
+            owner
+        )))),
+    }
+}
+
+/// Fails with not found when any of the tracks is missing from the library
+pub async fn check_exist<C>(db: &C, ids: &[Uuid]) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let wanted: HashSet<Uuid> = ids.iter().copied().collect();
+    let found = entity::TrackEntity::find()
+        .filter(entity::TrackColumn::Id.is_in(wanted.iter().copied()))
+        .all(db)
+        .await?
+        .len();
+    match found == wanted.len() {
+        true => Ok(()),
+        false => Err(Error::NotFound(None)),
+    }
+}
+
 fn map_to_mediums_include(include: &[TrackInclude]) -> Vec<MediumInclude> {
     include
         .iter()
@@ -289,6 +379,7 @@ pub async fn tracks(
     State(AppState(db)): State<AppState>,
     Query(opts): Query<TrackFilter, entity::TrackColumn, TrackInclude, uuid::Uuid>,
     OriginalUri(uri): OriginalUri,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<TrackResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -296,8 +387,23 @@ pub async fn tracks(
     for (filter_key, filter_value) in opts.filter.iter() {
         if let Some(k) = filter_key.column() {
             tracks_query = tracks_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        } else if *filter_key == TrackFilter::Starred {
+            tracks_query = tracks_query.filter(favorites::filter(
+                entity::TrackColumn::Id,
+                claims.as_ref(),
+                entity::FavoriteKind::Track,
+                filter_value,
+            )?);
         }
     }
+    if let Some((rating, order)) = ratings::order(
+        &uri,
+        entity::TrackColumn::Id,
+        claims.as_ref(),
+        entity::RatingKind::Track,
+    )? {
+        tracks_query = tracks_query.order_by(rating, order);
+    }
     for (sort_key, sort_order) in opts.sort.iter() {
         tracks_query = tracks_query.order_by(sort_key.to_owned(), sort_order.to_owned());
     }
@@ -309,6 +415,8 @@ pub async fn tracks(
     for (i, track) in tracks.iter().enumerate() {
         data.push(entity_to_resource(track, &related_to_tracks[i]));
     }
+    favorites::star(&tx, claims.as_ref(), entity::FavoriteKind::Track, &mut data).await?;
+    ratings::rate(&tx, claims.as_ref(), entity::RatingKind::Track, &mut data).await?;
     let included = included(&tx, related_to_tracks, &opts.include).await?;
     Ok(Json(Document {
         links: links_from_resource(&data, opts, &uri),
@@ -321,6 +429,7 @@ pub async fn track(
     State(AppState(db)): State<AppState>,
     Path(id): Path<Uuid>,
     Query(opts): Query<TrackFilter, entity::TrackColumn, TrackInclude, uuid::Uuid>,
+    claims: Option<Claims>,
 ) -> Result<Json<Document<TrackResource, Included>>, Error> {
     let tx = db.begin().await?;
 
@@ -328,7 +437,21 @@ pub async fn track(
     let related_to_tracks = related(&tx, &[track.clone()], false).await?;
     let empty_relationship = TrackRelated::default();
     let related = related_to_tracks.first().unwrap_or(&empty_relationship);
-    let data = entity_to_resource(&track, related);
+    let mut data = entity_to_resource(&track, related);
+    favorites::star(
+        &tx,
+        claims.as_ref(),
+        entity::FavoriteKind::Track,
+        std::slice::from_mut(&mut data),
+    )
+    .await?;
+    ratings::rate(
+        &tx,
+        claims.as_ref(),
+        entity::RatingKind::Track,
+        std::slice::from_mut(&mut data),
+    )
+    .await?;
     let included = included(&tx, related_to_tracks, &opts.include).await?;
     Ok(Json(Document {
         data: DocumentData::Single(data),
@@ -337,17 +460,242 @@ pub async fn track(
     }))
 }
 
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct AudioQuery {
+    pub format: Option<TranscodingFormat>,
+    pub max_bitrate: Option<u32>,
+    pub offset: Option<f64>,
+    pub client: Option<String>,
+}
+
 pub async fn audio(
     State(AppState(db)): State<AppState>,
+    claims: Claims,
     Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<AudioQuery>,
     request: Request<Body>,
-) -> Result<impl IntoResponse, Error> {
-    let track = find_track_by_id(&db, id).await?;
+) -> Result<Response, Error> {
+    serve_audio(&db, &claims.username, id, query, request).await
+}
+
+/// Serves the audio of a track, transcoded according to the query and the
+/// profiles of the user and client
+pub async fn serve_audio<C>(
+    db: &C,
+    username: &str,
+    id: Uuid,
+    query: AudioQuery,
+    request: Request<Body>,
+) -> Result<Response, Error>
+where
+    C: ConnectionTrait,
+{
+    let track = find_track_by_id(db, id).await?;
     let path = track.path.ok_or(Error::NoTrackPath)?;
-    let mime = track.format.ok_or(Error::NoTrackFormat)?.mime();
-    Ok(
-        tower_http::services::fs::ServeFile::new_with_mime(path, &mime)
-            .oneshot(request)
-            .await,
+    let settings = &get_settings()?.transcoding;
+    let options = resolve(
+        settings,
+        username,
+        query.client.as_deref(),
+        query.format,
+        query.max_bitrate,
+        query.offset,
+        // the probed bitrate is in bits per second
+        track.bitrate.map(|b| b.max(0) as u32 / 1000),
+    )?;
+
+    // the original file can be served as-is, with support for range requests
+    let Some(options) = options else {
+        let mime = track.format.ok_or(Error::NoTrackFormat)?.mime();
+        return Ok(
+            tower_http::services::fs::ServeFile::new_with_mime(path, &mime)
+                .oneshot(request)
+                .await
+                .into_response(),
+        );
+    };
+
+    let stream = transcode(settings, &PathBuf::from(path), options)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, options.format.mime().to_string()),
+            // the output length is unknown, seeking is done via the offset parameter
+            (header::ACCEPT_RANGES, "none".to_string()),
+        ],
+        StreamBody::new(stream),
+    )
+        .into_response())
+}
+
+pub async fn hls_playlist(
+    State(AppState(db)): State<AppState>,
+    Path(id): Path<Uuid>,
+    OriginalUri(uri): OriginalUri,
+) -> Result<Response, Error> {
+    let track = find_track_by_id(&db, id).await?;
+    let timeline = Timeline::new(&[track])?;
+    Ok((
+        [(header::CONTENT_TYPE, PLAYLIST_MIME.to_string())],
+        playlist(get_settings()?, &timeline, uri.query()),
     )
+        .into_response())
+}
+
+pub async fn hls_segment(
+    State(AppState(db)): State<AppState>,
+    Path((id, name)): Path<(Uuid, String)>,
+) -> Result<Response, Error> {
+    let index = parse_segment(&name)?;
+    let track = find_track_by_id(&db, id).await?;
+    let timeline = Timeline::new(&[track])?;
+    let bytes = segment(get_settings()?, &format!("track-{}", id), &timeline, index).await?;
+    Ok(([(header::CONTENT_TYPE, SEGMENT_MIME.to_string())], bytes).into_response())
+}
+
+#[derive(Deserialize, Default, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveformFormat {
+    #[default]
+    Json,
+    Binary,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct WaveformQuery {
+    #[serde(default = "default_waveform_resolution")]
+    resolution: usize,
+    #[serde(default)]
+    format: WaveformFormat,
+}
+
+fn default_waveform_resolution() -> usize {
+    512
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WaveformDocument {
+    resolution: usize,
+    peaks: Vec<u8>,
+}
+
+pub async fn waveform(
+    State(AppState(db)): State<AppState>,
+    Path(id): Path<Uuid>,
+    AxumQuery(query): AxumQuery<WaveformQuery>,
+) -> Result<Response, Error> {
+    if query.resolution == 0 || query.resolution > WAVEFORM_RESOLUTION {
+        return Err(Error::BadRequest(Some(format!(
+            "The resolution must be between 1 and {}",
+            WAVEFORM_RESOLUTION
+        ))));
+    }
+    let waveform = entity::WaveformEntity::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let peaks = downsample(&waveform.peaks, query.resolution);
+
+    Ok(match query.format {
+        WaveformFormat::Json => AxumJson(WaveformDocument {
+            resolution: peaks.len(),
+            peaks,
+        })
+        .into_response(),
+        WaveformFormat::Binary => (
+            [(
+                header::CONTENT_TYPE,
+                mime::APPLICATION_OCTET_STREAM.to_string(),
+            )],
+            peaks,
+        )
+            .into_response(),
+    })
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LyricsDocument {
+    text: Option<String>,
+    lines: Option<Vec<entity::LyricsLine>>,
+}
+
+impl From<entity::Lyrics> for LyricsDocument {
+    fn from(lyrics: entity::Lyrics) -> Self {
+        Self {
+            text: lyrics.text,
+            lines: lyrics.lines.map(|l| l.0),
+        }
+    }
+}
+
+pub async fn lyrics(
+    State(AppState(db)): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<AxumJson<LyricsDocument>, Error> {
+    let lyrics = entity::LyricsEntity::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    Ok(AxumJson(lyrics.into()))
+}
+
+// Writes the lyrics to the track's tags and LRC sidecar, then reads them back
+// the same way the lyrics task does
+async fn write_lyrics(
+    path: PathBuf,
+    document: LyricsDocument,
+) -> eyre::Result<(Option<String>, Option<Vec<entity::LyricsLine>>)> {
+    let settings = get_settings()?;
+    let file = tokio::task::spawn_blocking(move || -> eyre::Result<TrackFile> {
+        let mut file = TrackFile::open(&settings.library, &path)?;
+        let text = document.text.or_else(|| {
+            document
+                .lines
+                .as_ref()
+                .map(|lines| lines.iter().map(|l| l.text.as_str()).join("\n"))
+        });
+        file.apply([(TagKey::Lyrics, text.into_iter().collect())].into())?;
+        file.write()?;
+
+        let lrc = sidecar(&path);
+        match document.lines {
+            Some(lines) => std::fs::write(&lrc, format_lrc(&lines))?,
+            None if lrc.exists() => std::fs::remove_file(&lrc)?,
+            None => {}
+        }
+        Ok(file)
+    })
+    .await??;
+    read_lyrics(&file).await
+}
+
+/// Only the configured owner of the library can edit lyrics, as they are
+/// written into the files
+pub async fn edit_lyrics(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    AxumJson(document): AxumJson<LyricsDocument>,
+) -> Result<AxumJson<LyricsDocument>, Error> {
+    if get_settings()?.library.owner.as_deref() != Some(claims.username.as_str()) {
+        return Err(Error::Unauthorized(Some(
+            "Only the owner of the library can edit lyrics".to_string(),
+        )));
+    }
+    let track = find_track_by_id(&db, id).await?;
+    let path = PathBuf::from(track.path.ok_or(Error::NoTrackPath)?);
+    let (text, lines) = write_lyrics(path, document)
+        .await
+        .map_err(|e| Error::Internal(Some(e.to_string())))?;
+
+    let lyrics = entity::Lyrics {
+        track_id: id,
+        text,
+        lines: lines.map(entity::LyricsLines),
+    };
+    entity::LyricsEntity::insert(lyrics.clone().into_active_model())
+        .on_conflict(entity::conflict::LYRICS_CONFLICT.to_owned())
+        .exec(&db)
+        .await
+        .ignore_none()?;
+    Ok(AxumJson(lyrics.into()))
 }
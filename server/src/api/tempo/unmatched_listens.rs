@@ -0,0 +1,194 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::StatusCode,
+};
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, CursorTrait, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::api::{
+    documents::{
+        Included, IntoColumn, Meta, ResourceType, ScrobbleResource, UnmatchedListenAttributes,
+        UnmatchedListenFilter, UnmatchedListenInclude, UnmatchedListenRelation,
+        UnmatchedListenResource,
+    },
+    extract::{Claims, Json, Path},
+    jsonapi::{
+        links_from_resource, make_cursor, Document, DocumentData, InsertOneRelation, Query,
+        Related, Relation, Relationship, ResourceIdentifier,
+    },
+    tempo::{scrobbles, stats, users},
+    AppState, Error,
+};
+use base::util::dedup;
+
+pub fn entity_to_resource(entity: &entity::UnmatchedListen) -> UnmatchedListenResource {
+    let relationships = [(
+        UnmatchedListenRelation::User,
+        Relationship {
+            data: Relation::Single(Related::String(ResourceIdentifier {
+                r#type: ResourceType::User,
+                id: entity.user.to_owned(),
+                meta: None,
+            })),
+        },
+    )]
+    .into();
+    UnmatchedListenResource {
+        r#type: ResourceType::UnmatchedListen,
+        id: entity.id,
+        attributes: UnmatchedListenAttributes {
+            listened_at: entity.listened_at,
+            artist_name: entity.artist_name.to_owned(),
+            track_name: entity.track_name.to_owned(),
+            release_name: entity.release_name.to_owned(),
+            recording_mbid: entity.recording_mbid,
+            track_mbid: entity.track_mbid,
+            release_mbid: entity.release_mbid,
+            duration: entity.duration,
+            submission_client: entity.submission_client.to_owned(),
+        },
+        relationships,
+        meta: None,
+    }
+}
+
+pub async fn included<C>(
+    db: &C,
+    entities: &[entity::UnmatchedListen],
+    include: &[UnmatchedListenInclude],
+) -> Result<Vec<Included>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut included = Vec::new();
+    if include.contains(&UnmatchedListenInclude::User) {
+        let usernames: HashSet<&str> = entities.iter().map(|l| l.user.as_str()).collect();
+        let users = entity::UserEntity::find()
+            .filter(entity::UserColumn::Username.is_in(usernames))
+            .all(db)
+            .await?;
+        let users_related = users::related(db, &users, true).await?;
+        for (user, related) in users.iter().zip(users_related.iter()) {
+            included.push(users::entity_to_included(user, related));
+        }
+    }
+    Ok(included)
+}
+
+// Listens of other users are reported as missing
+async fn find_unmatched_listen<C>(
+    db: &C,
+    username: &str,
+    id: i64,
+) -> Result<entity::UnmatchedListen, Error>
+where
+    C: ConnectionTrait,
+{
+    entity::UnmatchedListenEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .filter(|listen| listen.user == username)
+        .ok_or(Error::NotFound(None))
+}
+
+/// The listens of the user which could not be matched to a track when they
+/// were submitted
+pub async fn unmatched_listens(
+    State(AppState(db)): State<AppState>,
+    Query(opts): Query<
+        UnmatchedListenFilter,
+        entity::UnmatchedListenColumn,
+        UnmatchedListenInclude,
+        i64,
+    >,
+    OriginalUri(uri): OriginalUri,
+    claims: Claims,
+) -> Result<Json<Document<UnmatchedListenResource, Included>>, Error> {
+    let tx = db.begin().await?;
+    let mut listens_query = entity::UnmatchedListenEntity::find().filter(ColumnTrait::eq(
+        &entity::UnmatchedListenColumn::User,
+        claims.username.as_str(),
+    ));
+    for (filter_key, filter_value) in opts.filter.iter() {
+        if let Some(k) = filter_key.column() {
+            listens_query = listens_query.filter(ColumnTrait::eq(&k, filter_value.to_owned()));
+        }
+    }
+    let mut _listens_cursor = listens_query.cursor_by(entity::UnmatchedListenColumn::Id);
+    let listens_cursor = make_cursor(&mut _listens_cursor, &opts.page);
+    let listens = listens_cursor.all(&tx).await?;
+    let data = listens.iter().map(entity_to_resource).collect::<Vec<_>>();
+    let included = included(&tx, &listens, &opts.include).await?;
+    Ok(Json(Document {
+        links: links_from_resource(&data, opts, &uri),
+        data: DocumentData::Multi(data),
+        included: dedup(included),
+    }))
+}
+
+/// Matches the listen to a track of the library, turning it into a scrobble
+pub async fn match_unmatched_listen(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+    Json(body): Json<InsertOneRelation<ResourceIdentifier<ResourceType, Uuid, Meta>>>,
+) -> Result<Json<Document<ScrobbleResource, Included>>, Error> {
+    if body.data.r#type != ResourceType::Track {
+        return Err(Error::BadRequest(Some(
+            "Listens can only be matched to tracks".to_string(),
+        )));
+    }
+    let tx = db.begin().await?;
+    let listen = find_unmatched_listen(&tx, &claims.username, id).await?;
+    let track = entity::TrackEntity::find_by_id(body.data.id)
+        .one(&tx)
+        .await?
+        .ok_or(Error::NotFound(None))?;
+    let res = entity::ScrobbleEntity::insert(entity::ScrobbleActive {
+        id: ActiveValue::NotSet,
+        at: ActiveValue::Set(listen.listened_at),
+        user: ActiveValue::Set(listen.user.to_owned()),
+        track: ActiveValue::Set(track.id),
+    })
+    .exec(&tx)
+    .await?;
+    let scrobble = entity::Scrobble {
+        id: res.last_insert_id,
+        at: listen.listened_at,
+        user: listen.user,
+        track: track.id,
+    };
+    entity::UnmatchedListenEntity::delete_by_id(id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    stats::invalidate(&claims.username).await;
+    tracing::info!(user = %claims.username, %id, track = %track.id, "Matched listen");
+
+    Ok(Json(Document {
+        links: HashMap::new(),
+        data: DocumentData::Single(scrobbles::entity_to_resource(&scrobble)),
+        included: Vec::new(),
+    }))
+}
+
+/// Discards a listen which has no match in the library
+pub async fn delete_unmatched_listen(
+    State(AppState(db)): State<AppState>,
+    claims: Claims,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    let tx = db.begin().await?;
+    find_unmatched_listen(&tx, &claims.username, id).await?;
+    entity::UnmatchedListenEntity::delete_by_id(id)
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(user = %claims.username, %id, "Discarded unmatched listen");
+
+    Ok(StatusCode::OK)
+}
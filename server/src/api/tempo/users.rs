@@ -4,6 +4,7 @@ use axum::{
     http::StatusCode,
     TypedHeader,
 };
+use schemars::JsonSchema;
 use sea_orm::{ConnectionTrait, EntityTrait, LoaderTrait, TransactionTrait};
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -18,7 +19,7 @@ use crate::api::{
         Document, DocumentData, InsertManyRelation, Query, Related, Relation, Relationship,
         ResourceIdentifier,
     },
-    tempo::{connections::ProviderImpl, scrobbles},
+    tempo::{connections::ProviderImpl, favorites, ratings, scrobbles},
     AppState, Error,
 };
 use base::setting::get_settings;
@@ -28,6 +29,8 @@ use base::util::dedup;
 pub struct UserRelated {
     scrobbles: Vec<entity::Scrobble>,
     connections: Vec<entity::UserConnection>,
+    favorites: Vec<entity::Favorite>,
+    ratings: Vec<entity::Rating>,
 }
 
 pub async fn related<C>(
@@ -41,13 +44,19 @@ where
     // TODO: limit number of returned scrobbles. Limit even more when light = true
     let scrobbles = entities.load_many(entity::ScrobbleEntity, db).await?;
     let connections = entities.load_many(entity::UserConnectionEntity, db).await?;
+    let favorites = entities.load_many(entity::FavoriteEntity, db).await?;
+    let ratings = entities.load_many(entity::RatingEntity, db).await?;
     let mut result = Vec::with_capacity(entities.len());
     for i in 0..entities.len() {
         let scrobbles = &scrobbles[i];
         let connections = &connections[i];
+        let favorites = &favorites[i];
+        let ratings = &ratings[i];
         result.push(UserRelated {
             scrobbles: scrobbles.to_vec(),
             connections: connections.to_vec(),
+            favorites: favorites.to_vec(),
+            ratings: ratings.to_vec(),
         })
     }
     Ok(result)
@@ -57,6 +66,8 @@ pub fn entity_to_resource(entity: &entity::User, related: &UserRelated) -> UserR
     let UserRelated {
         scrobbles,
         connections,
+        favorites,
+        ratings,
     } = related;
     let mut relationships = HashMap::new();
     if !scrobbles.is_empty() {
@@ -97,6 +108,22 @@ pub fn entity_to_resource(entity: &entity::User, related: &UserRelated) -> UserR
             },
         );
     }
+    if !favorites.is_empty() {
+        relationships.insert(
+            UserRelation::Favorites,
+            Relationship {
+                data: Relation::Multi(favorites.iter().map(favorites::entity_to_related).collect()),
+            },
+        );
+    }
+    if !ratings.is_empty() {
+        relationships.insert(
+            UserRelation::Ratings,
+            Relationship {
+                data: Relation::Multi(ratings.iter().map(ratings::entity_to_related).collect()),
+            },
+        );
+    }
 
     UserResource {
         r#type: ResourceType::User,
@@ -218,7 +245,7 @@ pub async fn relation(
     }))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct InsertExactlyOneRelation<R> {
     pub data: [R; 1],
 }
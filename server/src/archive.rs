@@ -0,0 +1,251 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::pin::Pin;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::io::{AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+
+use crate::transcode::{transcode, Options, TranscodeError};
+use base::setting::{get_settings, SettingsError};
+
+/// The amount of archive data buffered between the writer and the HTTP response
+static BUFFER_SIZE: usize = 64 * 1024;
+
+static LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+static DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+static CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+static ZIP64_END_SIGNATURE: u32 = 0x06064b50;
+static ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+static END_SIGNATURE: u32 = 0x06054b50;
+static ZIP64_EXTRA_ID: u16 = 0x0001;
+/// Version 4.5, the first one supporting ZIP64
+static VERSION: u16 = 45;
+/// Sizes are written in a data descriptor after the content, names are UTF-8
+static FLAGS: u16 = (1 << 3) | (1 << 11);
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("Error during IO operation: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read settings: {0}")]
+    Settings(#[from] SettingsError),
+    #[error("Could not transcode: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Could not read entry {0}: {1}")]
+    Entry(String, std::io::Error),
+}
+
+pub enum Source {
+    File(PathBuf),
+    Transcoded(PathBuf, Options),
+    Memory(Bytes),
+}
+
+pub struct Entry {
+    pub name: String,
+    pub source: Source,
+}
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u64,
+    offset: u64,
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+impl Source {
+    async fn open(self) -> Result<ByteStream, ArchiveError> {
+        Ok(match self {
+            Source::File(path) => Box::pin(ReaderStream::new(tokio::fs::File::open(path).await?)),
+            Source::Transcoded(path, options) => {
+                Box::pin(transcode(&get_settings()?.transcoding, &path, options)?)
+            }
+            Source::Memory(bytes) => Box::pin(futures::stream::once(async { Ok(bytes) })),
+        })
+    }
+}
+
+// Encodes a timestamp in the MS-DOS format used by ZIP headers
+fn dos_datetime(at: OffsetDateTime) -> (u16, u16) {
+    let time = ((at.hour() as u16) << 11) | ((at.minute() as u16) << 5) | (at.second() as u16 / 2);
+    let date =
+        (((at.year() - 1980).max(0) as u16) << 9) | ((at.month() as u16) << 5) | at.day() as u16;
+    (time, date)
+}
+
+/// A ZIP writer for entries of unknown size. Entries are stored uncompressed, as
+/// audio files don't benefit from it, and always use ZIP64 sizes so that the
+/// archive never has to be rewritten once it goes past 4GiB.
+struct ZipWriter<W> {
+    inner: W,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+    time: u16,
+    date: u16,
+}
+
+impl<W: AsyncWrite + Unpin> ZipWriter<W> {
+    fn new(inner: W) -> Self {
+        let (time, date) = dos_datetime(OffsetDateTime::now_utc());
+        Self {
+            inner,
+            offset: 0,
+            entries: Vec::new(),
+            time,
+            date,
+        }
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), ArchiveError> {
+        self.inner.write_all(buf).await?;
+        self.offset += buf.len() as u64;
+        Ok(())
+    }
+
+    async fn add(&mut self, entry: Entry) -> Result<(), ArchiveError> {
+        let offset = self.offset;
+        let mut header = BytesMut::new();
+        header.put_u32_le(LOCAL_HEADER_SIGNATURE);
+        header.put_u16_le(VERSION);
+        header.put_u16_le(FLAGS);
+        header.put_u16_le(0); // stored
+        header.put_u16_le(self.time);
+        header.put_u16_le(self.date);
+        header.put_u32_le(0); // crc, in the data descriptor
+        header.put_u32_le(u32::MAX);
+        header.put_u32_le(u32::MAX);
+        header.put_u16_le(entry.name.len() as u16);
+        header.put_u16_le(20);
+        header.put_slice(entry.name.as_bytes());
+        header.put_u16_le(ZIP64_EXTRA_ID);
+        header.put_u16_le(16);
+        header.put_u64_le(0);
+        header.put_u64_le(0);
+        self.write(&header).await?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size = 0;
+        let mut stream = entry.source.open().await?;
+        while let Some(chunk) = stream.next().await {
+            // a failed read or encode aborts the whole archive, as the entry would
+            // otherwise end up truncated with a valid checksum
+            let chunk = chunk.map_err(|error| ArchiveError::Entry(entry.name.to_owned(), error))?;
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            self.write(&chunk).await?;
+        }
+        let crc = hasher.finalize();
+
+        let mut descriptor = BytesMut::new();
+        descriptor.put_u32_le(DATA_DESCRIPTOR_SIGNATURE);
+        descriptor.put_u32_le(crc);
+        descriptor.put_u64_le(size);
+        descriptor.put_u64_le(size);
+        self.write(&descriptor).await?;
+
+        self.entries.push(CentralEntry {
+            name: entry.name,
+            crc,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(), ArchiveError> {
+        let directory_offset = self.offset;
+        let mut directory = BytesMut::new();
+        for entry in self.entries.iter() {
+            directory.put_u32_le(CENTRAL_HEADER_SIGNATURE);
+            directory.put_u16_le(VERSION);
+            directory.put_u16_le(VERSION);
+            directory.put_u16_le(FLAGS);
+            directory.put_u16_le(0);
+            directory.put_u16_le(self.time);
+            directory.put_u16_le(self.date);
+            directory.put_u32_le(entry.crc);
+            directory.put_u32_le(u32::MAX);
+            directory.put_u32_le(u32::MAX);
+            directory.put_u16_le(entry.name.len() as u16);
+            directory.put_u16_le(28);
+            directory.put_u16_le(0); // comment length
+            directory.put_u16_le(0); // disk number
+            directory.put_u16_le(0); // internal attributes
+            directory.put_u32_le(0); // external attributes
+            directory.put_u32_le(u32::MAX);
+            directory.put_slice(entry.name.as_bytes());
+            directory.put_u16_le(ZIP64_EXTRA_ID);
+            directory.put_u16_le(24);
+            directory.put_u64_le(entry.size);
+            directory.put_u64_le(entry.size);
+            directory.put_u64_le(entry.offset);
+        }
+        let directory_size = directory.len() as u64;
+        let end_offset = directory_offset + directory_size;
+        let count = self.entries.len() as u64;
+
+        directory.put_u32_le(ZIP64_END_SIGNATURE);
+        directory.put_u64_le(44);
+        directory.put_u16_le(VERSION);
+        directory.put_u16_le(VERSION);
+        directory.put_u32_le(0);
+        directory.put_u32_le(0);
+        directory.put_u64_le(count);
+        directory.put_u64_le(count);
+        directory.put_u64_le(directory_size);
+        directory.put_u64_le(directory_offset);
+
+        directory.put_u32_le(ZIP64_LOCATOR_SIGNATURE);
+        directory.put_u32_le(0);
+        directory.put_u64_le(end_offset);
+        directory.put_u32_le(1);
+
+        directory.put_u32_le(END_SIGNATURE);
+        directory.put_u16_le(0);
+        directory.put_u16_le(0);
+        directory.put_u16_le(u16::MAX);
+        directory.put_u16_le(u16::MAX);
+        directory.put_u32_le(u32::MAX);
+        directory.put_u32_le(u32::MAX);
+        directory.put_u16_le(0);
+        self.write(&directory).await?;
+        Ok(self.inner.shutdown().await?)
+    }
+}
+
+async fn write(writer: DuplexStream, entries: Vec<Entry>) -> Result<(), ArchiveError> {
+    let mut zip = ZipWriter::new(writer);
+    for entry in entries.into_iter() {
+        tracing::trace!(name = %entry.name, "Adding entry to archive");
+        zip.add(entry).await?;
+    }
+    zip.finish().await
+}
+
+/// Builds a ZIP archive of the given entries as it is being read. Files are read
+/// (or transcoded) one at a time and only a small buffer is kept in memory.
+pub fn archive(entries: Vec<Entry>) -> impl Stream<Item = std::io::Result<Bytes>> {
+    let (writer, reader) = tokio::io::duplex(BUFFER_SIZE);
+    let (failed, failure) = oneshot::channel();
+    tokio::spawn(async move {
+        // fails as well when the client goes away, closing the reader
+        if let Err(error) = write(writer, entries).await {
+            tracing::warn!(%error, "Could not write archive");
+            let _ = failed.send(error.to_string());
+        }
+    });
+    // a failure ends the stream with an error rather than cleanly, so that the
+    // connection is aborted and the client doesn't keep a truncated archive
+    let failure = futures::stream::once(failure).filter_map(|result| async move {
+        result
+            .ok()
+            .map(|error| Err(std::io::Error::new(ErrorKind::Other, error)))
+    });
+    ReaderStream::new(reader).chain(failure)
+}
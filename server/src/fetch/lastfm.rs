@@ -41,6 +41,7 @@ where
         .fold(String::new(), |concat, (key, value)| concat + key + value)
         + secret;
     let hex = format!("{:x}", md5(concat.as_bytes()));
-    tracing::debug!(signature = ?concat, md5 = ?hex, "LastFM signature");
+    // the concatenation isn't logged, it contains the secret and possibly a password
+    tracing::debug!(md5 = ?hex, "LastFM signature");
     hex
 }
@@ -0,0 +1,335 @@
+use lazy_static::lazy_static;
+use mime::Mime;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::SystemTime;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{watch, Mutex, RwLock};
+
+use base::setting::Settings;
+use base::util::{mkdirp, path_to_str, UtilError};
+
+static SEGMENT_EXTENSION: &str = "ts";
+/// Written in the directory of a timeline once all of its segments are encoded
+static COMPLETE_MARKER: &str = "complete";
+
+/// The progress of the encoding of a timeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Progress {
+    /// The number of segments written so far
+    Encoding(usize),
+    Done,
+    Failed,
+}
+
+lazy_static! {
+    pub static ref PLAYLIST_MIME: Mime = "application/vnd.apple.mpegurl".parse().unwrap();
+    pub static ref SEGMENT_MIME: Mime = "video/mp2t".parse().unwrap();
+    /// The timelines being encoded, by cache directory
+    static ref ENCODES: Mutex<HashMap<PathBuf, watch::Receiver<Progress>>> =
+        Mutex::new(HashMap::new());
+    /// Held for writing while evicting, so that no segment is removed as it is read
+    static ref CACHE_LOCK: RwLock<()> = RwLock::new(());
+}
+
+#[derive(Error, Debug)]
+pub enum HlsError {
+    #[error("Error during IO operation: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error during utility operation: {0}")]
+    Util(#[from] UtilError),
+    #[error("The encoder exited with {0}")]
+    Encoder(ExitStatus),
+    #[error("The encoder did not provide an output stream")]
+    NoOutput,
+    #[error("The encoding of the segments failed")]
+    Failed,
+    #[error("Invalid segment: {0}")]
+    InvalidSegment(String),
+    #[error("There are no playable tracks")]
+    Empty,
+}
+
+#[derive(Debug, Clone)]
+struct Source {
+    path: PathBuf,
+    duration: f64,
+}
+
+/// A sequence of audio files played one after the other without gaps
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    sources: Vec<Source>,
+}
+
+fn track_duration(track: &entity::Track) -> f64 {
+    match (track.framecount, track.framerate) {
+        (Some(count), Some(rate)) if rate > 0 => count as f64 / rate as f64,
+        _ => track.length as f64 / 1000.0,
+    }
+}
+
+impl Timeline {
+    pub fn new(tracks: &[entity::Track]) -> Result<Self, HlsError> {
+        let sources: Vec<_> = tracks
+            .iter()
+            .filter_map(|track| {
+                track.path.as_ref().map(|path| Source {
+                    path: PathBuf::from(path),
+                    duration: track_duration(track),
+                })
+            })
+            .collect();
+        if sources.is_empty() {
+            return Err(HlsError::Empty);
+        }
+        Ok(Self { sources })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.sources.iter().map(|s| s.duration).sum()
+    }
+
+    /// Returns the duration of each segment the timeline is split into
+    pub fn segments(&self, segment_duration: u32) -> Vec<f64> {
+        let total = self.duration();
+        let segment_duration = segment_duration.max(1) as f64;
+        let count = (total / segment_duration).ceil() as usize;
+        (0..count)
+            .map(|i| (total - i as f64 * segment_duration).min(segment_duration))
+            .collect()
+    }
+}
+
+/// Builds a VOD media playlist for the timeline. The query string, if any, is
+/// appended to each segment URI so that authorization is carried over.
+pub fn playlist(settings: &Settings, timeline: &Timeline, query: Option<&str>) -> String {
+    let segments = timeline.segments(settings.hls.segment_duration);
+    let mut lines = vec![
+        "#EXTM3U".to_string(),
+        "#EXT-X-VERSION:3".to_string(),
+        format!(
+            "#EXT-X-TARGETDURATION:{}",
+            settings.hls.segment_duration.max(1)
+        ),
+        "#EXT-X-MEDIA-SEQUENCE:0".to_string(),
+        "#EXT-X-PLAYLIST-TYPE:VOD".to_string(),
+    ];
+    for (i, duration) in segments.iter().enumerate() {
+        lines.push(format!("#EXTINF:{:.3},", duration));
+        match query {
+            Some(query) => lines.push(format!("{}.{}?{}", i, SEGMENT_EXTENSION, query)),
+            None => lines.push(format!("{}.{}", i, SEGMENT_EXTENSION)),
+        }
+    }
+    lines.push("#EXT-X-ENDLIST".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Parses a segment file name, as listed in the playlist, into its index
+pub fn parse_segment(name: &str) -> Result<usize, HlsError> {
+    name.strip_suffix(SEGMENT_EXTENSION)
+        .and_then(|n| n.strip_suffix('.'))
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| HlsError::InvalidSegment(name.to_string()))
+}
+
+fn touch(path: &Path) -> Result<(), HlsError> {
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+async fn dir_size(dir: &Path) -> Result<u64, HlsError> {
+    let mut size = 0;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        size += entry.metadata().await?.len();
+    }
+    Ok(size)
+}
+
+/// Removes the least recently used timelines until the cache fits in the given size.
+/// Timelines being encoded are left alone.
+async fn evict(cache: &Path, max_size: u64) -> Result<(), HlsError> {
+    let _guard = CACHE_LOCK.write().await;
+    let encoding: HashSet<PathBuf> = ENCODES.lock().await.keys().cloned().collect();
+    let mut entries = Vec::new();
+    let mut dir = tokio::fs::read_dir(cache).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let path = entry.path();
+        if !entry.file_type().await?.is_dir() || encoding.contains(&path) {
+            continue;
+        }
+        // timelines which were never completed come first
+        let used = match tokio::fs::metadata(path.join(COMPLETE_MARKER)).await {
+            Ok(metadata) => Some(metadata.modified()?),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        entries.push((path.to_owned(), dir_size(&path).await?, used));
+    }
+
+    let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    entries.sort_unstable_by_key(|(_, _, used)| *used);
+    for (path, len, _) in entries.into_iter() {
+        if size <= max_size {
+            break;
+        }
+        tracing::trace!(?path, "Evicting HLS timeline from the cache");
+        tokio::fs::remove_dir_all(&path).await?;
+        size = size.saturating_sub(len);
+    }
+    Ok(())
+}
+
+// Counts the segments as the encoder lists them, which happens once each of them
+// has been written
+async fn run_encoder(
+    mut child: Child,
+    progress: &watch::Sender<Progress>,
+    dir: &Path,
+) -> Result<(), HlsError> {
+    let stdout = child.stdout.take().ok_or(HlsError::NoOutput)?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut written = 0;
+    while lines.next_line().await?.is_some() {
+        written += 1;
+        progress.send_replace(Progress::Encoding(written));
+    }
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(HlsError::Encoder(status));
+    }
+    File::create(dir.join(COMPLETE_MARKER))?;
+    Ok(())
+}
+
+/// Starts encoding the timeline into the given directory, unless it is already
+/// being encoded. The whole timeline goes through a single encoder which is split
+/// into segments, so that there are no gaps at their boundaries.
+async fn encode(
+    settings: &Settings,
+    dir: &Path,
+    timeline: &Timeline,
+) -> Result<watch::Receiver<Progress>, HlsError> {
+    let mut encodes = ENCODES.lock().await;
+    if let Some(progress) = encodes.get(dir) {
+        return Ok(progress.clone());
+    }
+
+    mkdirp(dir)?;
+    let hls = &settings.hls;
+    let mut command = Command::new(&settings.transcoding.ffmpeg);
+    command.args(["-v", "quiet", "-y"]);
+    for source in timeline.sources.iter() {
+        command.arg("-i").arg(path_to_str(&source.path)?);
+    }
+    let count = timeline.sources.len();
+    let inputs: String = (0..count).map(|i| format!("[{}:a:0]", i)).collect();
+    let child = command
+        .arg("-filter_complex")
+        .arg(format!("{}concat=n={}:v=0:a=1[a]", inputs, count))
+        .args(["-map", "[a]", "-c:a", "aac", "-b:a"])
+        .arg(format!("{}k", hls.bitrate))
+        .args([
+            "-f",
+            "segment",
+            "-segment_format",
+            "mpegts",
+            "-segment_time",
+        ])
+        .arg(hls.segment_duration.max(1).to_string())
+        .args(["-segment_list", "pipe:1", "-segment_list_type", "flat"])
+        .arg(dir.join(format!("%d.{}", SEGMENT_EXTENSION)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let (sender, receiver) = watch::channel(Progress::Encoding(0));
+    encodes.insert(dir.to_owned(), receiver.clone());
+    let dir = dir.to_owned();
+    let cache = hls.cache.to_owned();
+    let max_size = hls.cache_size * 1024 * 1024;
+    // the encoder keeps going when the request which started it is dropped
+    tokio::spawn(async move {
+        let result = run_encoder(child, &sender, &dir).await;
+        ENCODES.lock().await.remove(&dir);
+        match result {
+            Ok(()) => sender.send_replace(Progress::Done),
+            Err(error) => {
+                tracing::warn!(%error, ?dir, "Could not encode HLS timeline");
+                sender.send_replace(Progress::Failed)
+            }
+        };
+        if let Err(error) = evict(&cache, max_size).await {
+            tracing::warn!(%error, "Could not evict timelines from the HLS cache");
+        }
+    });
+    Ok(receiver)
+}
+
+async fn wait(mut progress: watch::Receiver<Progress>, index: usize) -> Result<(), HlsError> {
+    loop {
+        match *progress.borrow_and_update() {
+            Progress::Encoding(written) if written > index => return Ok(()),
+            Progress::Done => return Ok(()),
+            Progress::Failed => return Err(HlsError::Failed),
+            Progress::Encoding(_) => {}
+        }
+        if progress.changed().await.is_err() {
+            return Err(HlsError::Failed);
+        }
+    }
+}
+
+async fn read_segment(path: &Path, index: usize) -> Result<Vec<u8>, HlsError> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => Ok(bytes),
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            Err(HlsError::InvalidSegment(index.to_string()))
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Returns the requested segment, encoding the timeline if it is not cached yet.
+/// The key must uniquely identify the timeline.
+pub async fn segment(
+    settings: &Settings,
+    key: &str,
+    timeline: &Timeline,
+    index: usize,
+) -> Result<Vec<u8>, HlsError> {
+    let hls = &settings.hls;
+    if index >= timeline.segments(hls.segment_duration).len() {
+        return Err(HlsError::InvalidSegment(index.to_string()));
+    }
+    let dir = hls
+        .cache
+        .join(format!("{}-{}-{}", key, hls.bitrate, hls.segment_duration));
+    let path = dir.join(format!("{}.{}", index, SEGMENT_EXTENSION));
+
+    let progress = {
+        // segments are read with the lock held, so that they can't be evicted
+        // in the meantime
+        let _guard = CACHE_LOCK.read().await;
+        let marker = dir.join(COMPLETE_MARKER);
+        if marker.exists() {
+            touch(&marker)?;
+            return read_segment(&path, index).await;
+        }
+        encode(settings, &dir, timeline).await?
+    };
+    wait(progress, index).await?;
+    let _guard = CACHE_LOCK.read().await;
+    read_segment(&path, index).await
+}
@@ -0,0 +1,94 @@
+use entity::LyricsLine;
+use eyre::{Result, WrapErr};
+use std::path::{Path, PathBuf};
+use tag::TagKey;
+
+use super::TrackFile;
+
+static SIDECAR_EXTENSION: &str = "lrc";
+
+/// Returns the path of the LRC file accompanying the given track
+pub fn sidecar(path: &Path) -> PathBuf {
+    path.with_extension(SIDECAR_EXTENSION)
+}
+
+// Parses an LRC timestamp in the mm:ss(.xx) format into milliseconds
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let (minutes, seconds) = value.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+fn format_timestamp(time: u64) -> String {
+    format!(
+        "{:02}:{:02}.{:02}",
+        time / 60_000,
+        (time / 1000) % 60,
+        (time % 1000) / 10
+    )
+}
+
+/// Parses the lines of an LRC file, ignoring the ID tags except for the global offset.
+/// Lines with multiple timestamps are repeated for each of them.
+pub fn parse_lrc(content: &str) -> Vec<LyricsLine> {
+    let mut offset: i64 = 0;
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some((tag, remainder)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            match parse_timestamp(tag) {
+                Some(time) => times.push(time),
+                None => {
+                    if let Some(value) = tag.strip_prefix("offset:") {
+                        offset = value.trim().parse().unwrap_or_default();
+                    }
+                }
+            }
+            rest = remainder;
+        }
+        for time in times.into_iter() {
+            lines.push(LyricsLine {
+                // a positive offset shifts the lyrics earlier
+                time: (time as i64 - offset).max(0) as u64,
+                text: rest.trim().to_string(),
+            });
+        }
+    }
+    lines.sort_by_key(|l| l.time);
+    lines
+}
+
+pub fn format_lrc(lines: &[LyricsLine]) -> String {
+    lines
+        .iter()
+        .map(|l| format!("[{}]{}\n", format_timestamp(l.time), l.text))
+        .collect()
+}
+
+/// Reads the lyrics of a track from its tags and from the LRC sidecar, if any.
+/// Tags that hold LRC content are used as synced lyrics when there is no sidecar.
+pub async fn read_lyrics(file: &TrackFile) -> Result<(Option<String>, Option<Vec<LyricsLine>>)> {
+    let values = file.get_tag(TagKey::Lyrics);
+    let text = match values.is_empty() {
+        true => None,
+        false => Some(values.join(&file.tag.separator().unwrap_or("\n".to_string()))),
+    }
+    .filter(|t| !t.trim().is_empty());
+
+    let path = sidecar(&file.path);
+    let lines = if path.exists() {
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .wrap_err(format!("Could not read lyrics from file: {:?}", path))?;
+        Some(parse_lrc(&content))
+    } else {
+        text.as_deref().map(parse_lrc)
+    }
+    .filter(|lines| !lines.is_empty());
+    Ok((text, lines))
+}
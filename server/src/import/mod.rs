@@ -1,5 +1,7 @@
 mod files;
 mod internal;
+mod lyrics;
+mod probe;
 mod search_result;
 mod track;
 
@@ -18,5 +20,7 @@ pub enum ImportError {
 
 pub use files::all_tracks;
 pub use internal::{IntoInternal, UNKNOWN_ARTIST, UNKNOWN_TITLE};
+pub use lyrics::{format_lrc, read_lyrics, sidecar};
+pub use probe::{probe, AudioProperties};
 pub use search_result::{CombinedSearchResults, SearchResult};
 pub use track::TrackFile;
@@ -0,0 +1,119 @@
+use eyre::{bail, eyre, Result, WrapErr};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use strfmt::strfmt;
+use tokio::process::Command;
+
+use base::setting::Transcoding;
+use base::util::path_to_str;
+
+#[derive(Deserialize, Debug)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeStream {
+    sample_rate: Option<String>,
+    channels: Option<i32>,
+    bits_per_sample: Option<i32>,
+    bits_per_raw_sample: Option<String>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeFormat {
+    bit_rate: Option<String>,
+    size: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioProperties {
+    pub bpm: Option<i32>,
+    pub framerate: Option<i32>,
+    pub framecount: Option<i32>,
+    pub channels: Option<i32>,
+    pub bitrate: Option<i32>,
+    pub bitdepth: Option<i32>,
+    pub size: Option<i64>,
+}
+
+fn parse<T: std::str::FromStr>(value: &Option<String>) -> Option<T> {
+    value.as_ref().and_then(|v| v.trim().parse().ok())
+}
+
+fn find_tag<'a>(tags: &'a HashMap<String, String>, keys: &[&str]) -> Option<&'a String> {
+    tags.iter()
+        .find(|(k, _)| keys.iter().any(|key| k.eq_ignore_ascii_case(key)))
+        .map(|(_, v)| v)
+}
+
+impl From<ProbeOutput> for AudioProperties {
+    fn from(output: ProbeOutput) -> Self {
+        let stream = output.streams.first();
+        let framerate = stream.and_then(|s| parse::<i32>(&s.sample_rate));
+        let duration = stream
+            .and_then(|s| parse::<f64>(&s.duration))
+            .or_else(|| parse::<f64>(&output.format.duration));
+        let bpm = stream
+            .and_then(|s| find_tag(&s.tags, &["BPM", "TBPM"]))
+            .or_else(|| find_tag(&output.format.tags, &["BPM", "TBPM"]))
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .map(|v| v.round() as i32);
+
+        AudioProperties {
+            bpm,
+            framerate,
+            framecount: framerate
+                .zip(duration)
+                .map(|(rate, duration)| (rate as f64 * duration).round() as i32),
+            channels: stream.and_then(|s| s.channels),
+            bitrate: stream
+                .and_then(|s| parse(&s.bit_rate))
+                .or_else(|| parse(&output.format.bit_rate)),
+            // lossy codecs report a bit depth of 0
+            bitdepth: stream
+                .and_then(|s| parse(&s.bits_per_raw_sample).or(s.bits_per_sample))
+                .filter(|depth| *depth > 0),
+            size: parse(&output.format.size),
+        }
+    }
+}
+
+/// Inspects the first audio stream of the given file with the configured prober.
+pub async fn probe(settings: &Transcoding, path: &Path) -> Result<AudioProperties> {
+    let vars: HashMap<String, String> = [
+        ("ffprobe".to_string(), settings.ffprobe.to_owned()),
+        ("input".to_string(), path_to_str(path)?),
+    ]
+    .into();
+    let args = settings
+        .probe_command
+        .iter()
+        .map(|arg| strfmt(arg, &vars))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (program, args) = args
+        .split_first()
+        .ok_or(eyre!("No probe command has been configured"))?;
+
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .wrap_err(eyre!("Could not run the prober on file: {:?}", path))?;
+    if !output.status.success() {
+        bail!("Prober exited with {} on file: {:?}", output.status, path);
+    }
+    let output: ProbeOutput = serde_json::from_slice(&output.stdout)
+        .wrap_err(eyre!("Invalid prober output for file: {:?}", path))?;
+    Ok(output.into())
+}
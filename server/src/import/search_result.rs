@@ -63,6 +63,26 @@ impl From<TrackWithMediumId> for entity::Track {
             recording_id: track.recording.id,
             format: None,
             path: None,
+
+            bpm: None,
+            framerate: None,
+            framecount: None,
+            channels: None,
+            bitrate: None,
+            bitdepth: None,
+            size: None,
+
+            replay_gain_track_gain: None,
+            replay_gain_track_peak: None,
+            replay_gain_track_range: None,
+            replay_gain_album_gain: None,
+            replay_gain_album_peak: None,
+            replay_gain_album_range: None,
+
+            encoder_delay: None,
+            encoder_padding: None,
+            sample_count: None,
+            gapless_playback: None,
         }
     }
 }
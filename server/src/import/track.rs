@@ -56,6 +56,10 @@ impl TrackFile {
         self.tag.set_tag(key, values)
     }
 
+    pub fn set_rating(&mut self, rating: Option<u8>) -> Result<()> {
+        Self::ignore_unsupported(self.tag.set_rating(rating))
+    }
+
     pub fn set_pictures(&mut self, pictures: Vec<Picture>) -> Result<()> {
         self.tag.set_pictures(pictures)
     }
@@ -1,9 +1,14 @@
 mod api;
+pub mod archive;
 pub mod fetch;
+pub mod hls;
 pub mod import;
+pub mod playlist;
 pub mod scheduling;
 pub mod search;
+pub mod stats;
 pub mod tasks;
+pub mod transcode;
 
 use clap::{Parser, Subcommand};
 use eyre::{eyre, Result, WrapErr};
@@ -163,6 +168,11 @@ async fn main() -> Result<()> {
             }
             scheduling::start(&mut scheduler).await?;
 
+            #[cfg(feature = "dlna")]
+            if get_settings()?.dlna.enabled {
+                api::dlna::ssdp::start()?;
+            }
+
             let addr: SocketAddr = cli
                 .listen_address
                 .parse()
@@ -170,7 +180,7 @@ async fn main() -> Result<()> {
             tracing::info! {%addr, "Listening"};
             let router = api::router()?;
             axum::Server::bind(&addr)
-                .serve(router.into_make_service())
+                .serve(router.into_make_service_with_connect_info::<SocketAddr>())
                 .await
                 .unwrap();
             Ok(())
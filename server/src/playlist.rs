@@ -0,0 +1,496 @@
+use lazy_static::lazy_static;
+use mime::Mime;
+use quick_xml::{events::Event, Reader};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use base::util::escape_xml;
+
+lazy_static! {
+    pub static ref M3U8_MIME: Mime = "audio/x-mpegurl".parse().unwrap();
+    pub static ref XSPF_MIME: Mime = "application/xspf+xml".parse().unwrap();
+    pub static ref JSPF_MIME: Mime = "application/json".parse().unwrap();
+}
+
+static MUSICBRAINZ_RECORDING: &str = "https://musicbrainz.org/recording/";
+static MUSICBRAINZ_TRACK: &str = "https://musicbrainz.org/track/";
+
+#[derive(Error, Debug)]
+pub enum PlaylistError {
+    #[error("The playlist is not valid XSPF: {0}")]
+    InvalidXspf(&'static str),
+    #[error("The playlist is not valid XML: {0}")]
+    InvalidXml(#[from] quick_xml::Error),
+    #[error("The playlist is not valid JSPF: {0}")]
+    InvalidJspf(#[from] serde_json::Error),
+    #[error("The playlist is not UTF-8 text")]
+    Encoding,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    #[default]
+    M3u8,
+    Xspf,
+    Jspf,
+}
+
+impl PlaylistFormat {
+    pub fn mime(&self) -> &'static Mime {
+        match self {
+            PlaylistFormat::M3u8 => &M3U8_MIME,
+            PlaylistFormat::Xspf => &XSPF_MIME,
+            PlaylistFormat::Jspf => &JSPF_MIME,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u8 => "m3u8",
+            PlaylistFormat::Xspf => "xspf",
+            PlaylistFormat::Jspf => "jspf",
+        }
+    }
+
+    /// Guesses the format of an uploaded playlist from its content
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with('<') {
+            PlaylistFormat::Xspf
+        } else if content.starts_with('{') {
+            PlaylistFormat::Jspf
+        } else {
+            PlaylistFormat::M3u8
+        }
+    }
+}
+
+/// A track of an exported playlist
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub number: Option<i32>,
+    /// In milliseconds
+    pub length: i32,
+    pub recording_id: Uuid,
+    /// An absolute URL the track can be streamed from
+    pub location: String,
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub title: String,
+    pub creator: Option<String>,
+    pub items: Vec<Item>,
+}
+
+impl Playlist {
+    pub fn render(&self, format: PlaylistFormat) -> String {
+        match format {
+            PlaylistFormat::M3u8 => self.m3u8(),
+            PlaylistFormat::Xspf => self.xspf(),
+            PlaylistFormat::Jspf => self.jspf().to_string(),
+        }
+    }
+
+    pub fn m3u8(&self) -> String {
+        let mut lines = vec!["#EXTM3U".to_string(), format!("#PLAYLIST:{}", self.title)];
+        for item in self.items.iter() {
+            let name = match &item.artist {
+                Some(artist) => format!("{} - {}", artist, item.title),
+                None => item.title.to_owned(),
+            };
+            lines.push(format!("#EXTINF:{},{}", item.length.max(0) / 1000, name));
+            if let Some(album) = &item.album {
+                lines.push(format!("#EXTALB:{}", album));
+            }
+            lines.push(item.location.to_owned());
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn xspf(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        xml += "<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">";
+        xml += &element("title", &self.title);
+        if let Some(creator) = &self.creator {
+            xml += &element("creator", creator);
+        }
+        xml += "<trackList>";
+        for item in self.items.iter() {
+            xml += "<track>";
+            xml += &element("location", &item.location);
+            xml += &element(
+                "identifier",
+                &format!("{}{}", MUSICBRAINZ_RECORDING, item.recording_id),
+            );
+            xml += &element("title", &item.title);
+            if let Some(artist) = &item.artist {
+                xml += &element("creator", artist);
+            }
+            if let Some(album) = &item.album {
+                xml += &element("album", album);
+            }
+            if let Some(number) = item.number {
+                xml += &element("trackNum", &number.to_string());
+            }
+            xml += &element("duration", &item.length.max(0).to_string());
+            if let Some(image) = &item.image {
+                xml += &element("image", image);
+            }
+            xml += "</track>";
+        }
+        xml += "</trackList></playlist>";
+        xml
+    }
+
+    // Follows the flavour of JSPF used by ListenBrainz
+    pub fn jspf(&self) -> Value {
+        let tracks: Vec<Value> = self
+            .items
+            .iter()
+            .map(|item| {
+                json!({
+                    "title": item.title,
+                    "creator": item.artist,
+                    "album": item.album,
+                    "trackNum": item.number,
+                    "duration": item.length.max(0),
+                    "location": [item.location],
+                    "identifier": [format!("{}{}", MUSICBRAINZ_RECORDING, item.recording_id)],
+                    "image": item.image,
+                })
+            })
+            .collect();
+        json!({
+            "playlist": {
+                "title": self.title,
+                "creator": self.creator,
+                "track": tracks,
+            }
+        })
+    }
+}
+
+fn element(name: &str, value: &str) -> String {
+    format!("<{name}>{}</{name}>", escape_xml(value), name = name)
+}
+
+/// An entry of an imported playlist, with whatever the file knows about it
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct Entry {
+    pub location: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub identifiers: Vec<String>,
+}
+
+impl Entry {
+    fn mbid(&self, prefix: &str) -> Option<Uuid> {
+        self.identifiers.iter().find_map(|identifier| {
+            let id = identifier
+                .strip_prefix(prefix)
+                .or_else(|| identifier.strip_prefix(&prefix.replacen("https", "http", 1)))?;
+            Uuid::parse_str(id.trim_end_matches('/')).ok()
+        })
+    }
+
+    pub fn recording_mbid(&self) -> Option<Uuid> {
+        self.mbid(MUSICBRAINZ_RECORDING)
+    }
+
+    pub fn track_mbid(&self) -> Option<Uuid> {
+        self.mbid(MUSICBRAINZ_TRACK)
+    }
+}
+
+pub fn parse(content: &[u8], format: PlaylistFormat) -> Result<Vec<Entry>, PlaylistError> {
+    let content = std::str::from_utf8(content).map_err(|_| PlaylistError::Encoding)?;
+    let content = content.trim_start_matches('\u{feff}');
+    match format {
+        PlaylistFormat::M3u8 => Ok(parse_m3u(content)),
+        PlaylistFormat::Xspf => parse_xspf(content),
+        PlaylistFormat::Jspf => parse_jspf(content),
+    }
+}
+
+/// Parses an M3U playlist, extended or not. The display name of `#EXTINF` is
+/// conventionally `Artist - Title`.
+pub fn parse_m3u(content: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut current = Entry::default();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let name = info.split_once(',').map_or("", |(_, name)| name.trim());
+            match name.split_once(" - ") {
+                Some((artist, title)) => {
+                    current.artist = Some(artist.trim().to_owned());
+                    current.title = Some(title.trim().to_owned());
+                }
+                None if !name.is_empty() => current.title = Some(name.to_owned()),
+                None => {}
+            }
+        } else if let Some(album) = line.strip_prefix("#EXTALB:") {
+            current.album = Some(album.trim().to_owned());
+        } else if !line.starts_with('#') {
+            current.location = Some(line.to_owned());
+            entries.push(std::mem::take(&mut current));
+        }
+    }
+    entries
+}
+
+// Locations are URIs, relative ones are percent encoded paths which are matched
+// against the files of the library once decoded
+fn location(value: String) -> String {
+    match Url::parse(&value) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => urlencoding::decode(&value)
+            .map(|decoded| decoded.into_owned())
+            .unwrap_or(value),
+        _ => value,
+    }
+}
+
+fn set(field: &mut Option<String>, value: &str) {
+    if field.is_none() && !value.is_empty() {
+        *field = Some(value.to_owned());
+    }
+}
+
+/// Parses the tracks of an XSPF playlist. Elements are matched by their local name
+/// so that the XSPF namespace may be bound to a prefix, and only the direct children
+/// of a track are read, skipping extensions.
+pub fn parse_xspf(content: &str) -> Result<Vec<Entry>, PlaylistError> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+    let mut path: Vec<String> = Vec::new();
+    let mut has_list = false;
+    let mut entries = Vec::new();
+    let mut current: Option<Entry> = None;
+    let mut text = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                let parent = path.last().map(String::as_str);
+                match (parent, name.as_str()) {
+                    (_, "trackList") => has_list = true,
+                    (Some("trackList"), "track") => current = Some(Entry::default()),
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(value) => text += &value.unescape()?,
+            Event::CData(value) => {
+                text += std::str::from_utf8(&value).map_err(|_| PlaylistError::Encoding)?
+            }
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parents: Vec<&str> = path.iter().rev().take(2).map(String::as_str).collect();
+                match (parents.as_slice(), current.as_mut()) {
+                    (["trackList", ..], _) if name == "track" => entries.extend(current.take()),
+                    (["track", "trackList"], Some(entry)) => {
+                        let value = text.trim();
+                        match name.as_str() {
+                            "location" => set(&mut entry.location, value),
+                            "title" => set(&mut entry.title, value),
+                            "creator" => set(&mut entry.artist, value),
+                            "album" => set(&mut entry.album, value),
+                            "identifier" if !value.is_empty() => {
+                                entry.identifiers.push(value.to_owned())
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !has_list {
+        return Err(PlaylistError::InvalidXspf("missing trackList"));
+    }
+    Ok(entries
+        .into_iter()
+        .map(|entry| Entry {
+            location: entry.location.map(location),
+            ..entry
+        })
+        .collect())
+}
+
+// Locations and identifiers are lists, but some tools write a single string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JspfDocument {
+    playlist: JspfPlaylist,
+}
+
+#[derive(Deserialize)]
+struct JspfPlaylist {
+    #[serde(default)]
+    track: Vec<JspfTrack>,
+}
+
+#[derive(Deserialize)]
+struct JspfTrack {
+    location: Option<OneOrMany>,
+    identifier: Option<OneOrMany>,
+    title: Option<String>,
+    creator: Option<String>,
+    album: Option<String>,
+}
+
+pub fn parse_jspf(content: &str) -> Result<Vec<Entry>, PlaylistError> {
+    let document: JspfDocument = serde_json::from_str(content)?;
+    Ok(document
+        .playlist
+        .track
+        .into_iter()
+        .map(|track| Entry {
+            location: track
+                .location
+                .and_then(|value| value.into_vec().into_iter().next())
+                .map(location),
+            title: track.title,
+            artist: track.creator,
+            album: track.album,
+            identifiers: track
+                .identifier
+                .map(OneOrMany::into_vec)
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_extended() {
+        let entries = parse_m3u(
+            "#EXTM3U\n#EXTINF:215,Artist - Title\n#EXTALB:Album\nmusic/01 Title.flac\n\n/abs/02.mp3\n",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(entries[0].title.as_deref(), Some("Title"));
+        assert_eq!(entries[0].album.as_deref(), Some("Album"));
+        assert_eq!(entries[0].location.as_deref(), Some("music/01 Title.flac"));
+        assert_eq!(entries[1].title, None);
+        assert_eq!(entries[1].location.as_deref(), Some("/abs/02.mp3"));
+    }
+
+    #[test]
+    fn xspf() {
+        let entries = parse_xspf(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Not a track</title>
+              <trackList>
+                <track>
+                  <location>Artist%20Name/01%20Title.flac</location>
+                  <location>http://example.com/second</location>
+                  <title>Rock &amp; Roll</title>
+                  <creator><![CDATA[Simon & Garfunkel]]></creator>
+                  <!-- <album>Commented out</album> -->
+                  <identifier>https://musicbrainz.org/recording/ab5b4a04-3a20-4b61-a0cb-e0fbd8a2f5e7</identifier>
+                  <extension application="http://example.com"><title>Nested</title></extension>
+                </track>
+                <track><location>https://tempo.example/tempo/tracks/x/audio?a=1%202</location></track>
+              </trackList>
+            </playlist>"#,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].location.as_deref(),
+            Some("Artist Name/01 Title.flac")
+        );
+        assert_eq!(entries[0].title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(entries[0].artist.as_deref(), Some("Simon & Garfunkel"));
+        assert_eq!(entries[0].album, None);
+        assert_eq!(
+            entries[0].recording_mbid(),
+            Uuid::parse_str("ab5b4a04-3a20-4b61-a0cb-e0fbd8a2f5e7").ok()
+        );
+        // absolute URLs are left encoded
+        assert_eq!(
+            entries[1].location.as_deref(),
+            Some("https://tempo.example/tempo/tracks/x/audio?a=1%202")
+        );
+    }
+
+    #[test]
+    fn xspf_prefixed_namespace() {
+        let entries = parse_xspf(
+            r#"<x:playlist xmlns:x="http://xspf.org/ns/0/"><x:trackList>
+              <x:track><x:title>Title</x:title><x:creator>Artist</x:creator></x:track>
+            </x:trackList></x:playlist>"#,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title.as_deref(), Some("Title"));
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+    }
+
+    #[test]
+    fn xspf_invalid() {
+        assert!(parse_xspf("<playlist><title>Empty</title></playlist>").is_err());
+        assert!(parse_xspf("<playlist><trackList></playlist>").is_err());
+    }
+
+    #[test]
+    fn jspf() {
+        let entries = parse_jspf(
+            r#"{"playlist": {"title": "List", "track": [
+                {"title": "Title", "creator": "Artist", "album": "Album",
+                 "location": ["music/01%20Title.flac"],
+                 "identifier": "https://musicbrainz.org/track/ab5b4a04-3a20-4b61-a0cb-e0fbd8a2f5e7"},
+                {"title": "Only a title"}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].location.as_deref(), Some("music/01 Title.flac"));
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(
+            entries[0].track_mbid(),
+            Uuid::parse_str("ab5b4a04-3a20-4b61-a0cb-e0fbd8a2f5e7").ok()
+        );
+        assert_eq!(entries[1].location, None);
+        assert!(entries[1].identifiers.is_empty());
+        assert!(parse_jspf("{\"tracks\": []}").is_err());
+    }
+}
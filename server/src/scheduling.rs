@@ -42,6 +42,9 @@ where
         JobType::ArtistDescription => TaskName::ArtistDescription,
         JobType::IndexSearch => TaskName::IndexSearch,
         JobType::LastFMArtistImage => TaskName::LastFMArtistImage,
+        JobType::ReplayGain => TaskName::ReplayGain,
+        JobType::Waveform => TaskName::Waveform,
+        JobType::YearReview => TaskName::YearReview,
     };
     let data: Vec<_> = match task {
         JobType::ArtistUrl => tasks::artist_url::Data::all(db)
@@ -66,14 +69,38 @@ where
             .into_iter()
             .map(|data| json!(data))
             .collect(),
+
+        // only analyze the releases and tracks which have not been processed yet
+        JobType::ReplayGain => tasks::replay_gain::Data::outdated(db)
+            .await?
+            .into_iter()
+            .map(|data| json!(data))
+            .collect(),
+        JobType::Waveform => tasks::waveform::Data::outdated(db)
+            .await?
+            .into_iter()
+            .map(|data| json!(data))
+            .collect(),
+        // reviews are built again until the year is over, to include the latest scrobbles
+        JobType::YearReview => tasks::year_review::Data::all(db)
+            .await?
+            .into_iter()
+            .map(|data| json!(data))
+            .collect(),
     };
 
+    // decoding whole releases takes considerably longer than the other jobs
+    let duration = match task {
+        JobType::ReplayGain => Duration::seconds(600),
+        JobType::YearReview => Duration::seconds(300),
+        _ => Duration::seconds(60),
+    };
     let tasks: Vec<_> = data
         .into_iter()
         .map(|data| InsertTask {
             name,
             payload: Some(data),
-            duration: Duration::seconds(60),
+            duration,
             depends_on: vec![],
         })
         .collect();
@@ -0,0 +1,397 @@
+use image::{
+    imageops::{overlay, FilterType},
+    io::Reader as ImageReader,
+    DynamicImage, ImageOutputFormat,
+};
+use sea_orm::{
+    sea_query::{Alias, Expr, Func, SimpleExpr},
+    ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr, EntityTrait, FromQueryResult, JoinType,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait, Select,
+};
+use std::io::Cursor;
+use thiserror::Error;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use base::ImageFormat;
+
+#[derive(Error, Debug)]
+pub enum StatsError {
+    #[error("Could not read or write an image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Could not render the image: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    #[error("There are no images to render")]
+    Empty,
+}
+
+#[derive(FromQueryResult)]
+pub struct Totals {
+    pub scrobbles: i64,
+    pub listening_time: Option<i64>,
+}
+
+#[derive(FromQueryResult)]
+pub struct TopResource {
+    pub id: Uuid,
+    pub name: String,
+    pub scrobbles: i64,
+    pub listening_time: Option<i64>,
+}
+
+#[derive(FromQueryResult)]
+pub struct TopGenre {
+    pub id: String,
+    pub name: String,
+    pub scrobbles: i64,
+    pub listening_time: Option<i64>,
+}
+
+#[derive(FromQueryResult)]
+pub struct Bucket {
+    pub bucket: i64,
+    pub scrobbles: i64,
+}
+
+// Aggregates have a different type on each backend, so they are all read as
+// 64 bit integers
+fn integer(expr: SimpleExpr, backend: DatabaseBackend) -> SimpleExpr {
+    let r#type = match backend {
+        DatabaseBackend::MySql => "SIGNED",
+        DatabaseBackend::Postgres => "BIGINT",
+        DatabaseBackend::Sqlite => "INTEGER",
+    };
+    Func::cast_as(expr, Alias::new(r#type)).into()
+}
+
+fn at() -> Expr {
+    Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::At))
+}
+
+/// The time of the scrobble in the time zone `offset` minutes east of UTC, as a
+/// date and time without a zone. Postgres and MySQL otherwise use the time zone
+/// of the session.
+fn local_at(backend: DatabaseBackend, offset: i32) -> SimpleExpr {
+    match backend {
+        DatabaseBackend::MySql => {
+            let (sign, minutes) = match offset < 0 {
+                true => ('-', -offset),
+                false => ('+', offset),
+            };
+            let zone = format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60);
+            Func::cust(Alias::new("CONVERT_TZ"))
+                .args([
+                    at().into(),
+                    Expr::cust("@@session.time_zone"),
+                    Expr::val(zone).into(),
+                ])
+                .into()
+        }
+        DatabaseBackend::Postgres => Expr::expr(
+            Func::cust(Alias::new("timezone")).args([Expr::val("UTC").into(), at().into()]),
+        )
+        .add(Expr::cust(&format!("INTERVAL '{} minutes'", offset))),
+        DatabaseBackend::Sqlite => Func::cust(Alias::new("datetime"))
+            .args([
+                at().into(),
+                Expr::val(format!("{:+} minutes", offset)).into(),
+            ])
+            .into(),
+    }
+}
+
+/// The hour of the scrobble, in the time zone `offset` minutes east of UTC
+pub fn hour(backend: DatabaseBackend, offset: i32) -> SimpleExpr {
+    let at = local_at(backend, offset);
+    let hour = match backend {
+        DatabaseBackend::MySql => Func::cust(Alias::new("HOUR")).arg(at),
+        DatabaseBackend::Postgres => {
+            Func::cust(Alias::new("date_part")).args([Expr::val("hour").into(), at])
+        }
+        DatabaseBackend::Sqlite => {
+            Func::cust(Alias::new("strftime")).args([Expr::val("%H").into(), at])
+        }
+    };
+    integer(hour.into(), backend)
+}
+
+/// The day of the week of the scrobble, in the time zone `offset` minutes east
+/// of UTC, to be read with [`weekday_index`]
+pub fn weekday(backend: DatabaseBackend, offset: i32) -> SimpleExpr {
+    let at = local_at(backend, offset);
+    let weekday = match backend {
+        DatabaseBackend::MySql => Func::cust(Alias::new("WEEKDAY")).arg(at),
+        DatabaseBackend::Postgres => {
+            Func::cust(Alias::new("date_part")).args([Expr::val("isodow").into(), at])
+        }
+        DatabaseBackend::Sqlite => {
+            Func::cust(Alias::new("strftime")).args([Expr::val("%w").into(), at])
+        }
+    };
+    integer(weekday.into(), backend)
+}
+
+/// Days are numbered from Monday on MySQL, from Monday starting at 1 on
+/// Postgres and from Sunday on SQLite. Returns them from Monday starting at 0.
+pub fn weekday_index(value: i64, backend: DatabaseBackend) -> i64 {
+    match backend {
+        DatabaseBackend::MySql => value,
+        DatabaseBackend::Postgres => value - 1,
+        DatabaseBackend::Sqlite => (value + 6) % 7,
+    }
+}
+
+/// The day of the scrobble, as the number of days since the Unix epoch in UTC
+pub fn day(backend: DatabaseBackend) -> SimpleExpr {
+    let day = match backend {
+        // TO_DAYS counts from year 0, which is 719528 days before the epoch
+        DatabaseBackend::MySql => {
+            Expr::expr(Func::cust(Alias::new("TO_DAYS")).arg(local_at(backend, 0))).sub(719528)
+        }
+        DatabaseBackend::Postgres => Func::cust(Alias::new("floor"))
+            .arg(
+                Expr::expr(
+                    Func::cust(Alias::new("date_part"))
+                        .args([Expr::val("epoch").into(), at().into()]),
+                )
+                .div(86400),
+            )
+            .into(),
+        DatabaseBackend::Sqlite => {
+            Expr::expr(Func::cust(Alias::new("julianday")).arg(at())).sub(2440587.5)
+        }
+    };
+    integer(day, backend)
+}
+
+/// The scrobbles of the user in the range, joined with their track. Nothing is
+/// selected yet.
+pub fn scrobbles(
+    username: &str,
+    from: Option<OffsetDateTime>,
+    to: Option<OffsetDateTime>,
+) -> Select<entity::ScrobbleEntity> {
+    let mut query = entity::ScrobbleEntity::find()
+        .select_only()
+        .join(JoinType::InnerJoin, entity::ScrobbleRelation::Track.def())
+        .filter(ColumnTrait::eq(&entity::ScrobbleColumn::User, username));
+    if let Some(from) = from {
+        query = query.filter(entity::ScrobbleColumn::At.gte(from));
+    }
+    if let Some(to) = to {
+        query = query.filter(entity::ScrobbleColumn::At.lt(to));
+    }
+    query
+}
+
+/// Joins the artists credited on the scrobbled tracks
+pub fn with_artists(query: Select<entity::ScrobbleEntity>) -> Select<entity::ScrobbleEntity> {
+    query
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditTrackRelation::Track.def().rev(),
+        )
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditTrackRelation::ArtistCredit.def(),
+        )
+        .join(
+            JoinType::InnerJoin,
+            entity::ArtistCreditRelation::Artist.def(),
+        )
+}
+
+fn counted(
+    query: Select<entity::ScrobbleEntity>,
+    backend: DatabaseBackend,
+) -> Select<entity::ScrobbleEntity> {
+    query
+        .column_as(
+            integer(
+                Expr::col((entity::ScrobbleEntity, entity::ScrobbleColumn::Id)).count(),
+                backend,
+            ),
+            "scrobbles",
+        )
+        .column_as(
+            integer(
+                Expr::col((entity::TrackEntity, entity::TrackColumn::Length)).sum(),
+                backend,
+            ),
+            "listening_time",
+        )
+}
+
+// Groups the scrobbles by the given id and name, the most listened first
+fn top(
+    query: Select<entity::ScrobbleEntity>,
+    backend: DatabaseBackend,
+    id: Expr,
+    name: Expr,
+    limit: u64,
+) -> Select<entity::ScrobbleEntity> {
+    let (id, name) = (SimpleExpr::from(id), SimpleExpr::from(name));
+    counted(query, backend)
+        .column_as(id.clone(), "id")
+        .column_as(name.clone(), "name")
+        .group_by(id)
+        .group_by(name)
+        .order_by_desc(SimpleExpr::from(Expr::col(Alias::new("scrobbles"))))
+        .limit(limit)
+}
+
+pub async fn totals<C>(db: &C, query: Select<entity::ScrobbleEntity>) -> Result<Totals, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(counted(query, db.get_database_backend())
+        .into_model::<Totals>()
+        .one(db)
+        .await?
+        .unwrap_or(Totals {
+            scrobbles: 0,
+            listening_time: None,
+        }))
+}
+
+pub async fn top_artists<C>(
+    db: &C,
+    query: Select<entity::ScrobbleEntity>,
+    limit: u64,
+) -> Result<Vec<TopResource>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    top(
+        with_artists(query),
+        db.get_database_backend(),
+        Expr::col((entity::ArtistEntity, entity::ArtistColumn::Id)),
+        Expr::col((entity::ArtistEntity, entity::ArtistColumn::Name)),
+        limit,
+    )
+    .into_model::<TopResource>()
+    .all(db)
+    .await
+}
+
+pub async fn top_releases<C>(
+    db: &C,
+    query: Select<entity::ScrobbleEntity>,
+    limit: u64,
+) -> Result<Vec<TopResource>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    top(
+        query
+            .join(JoinType::InnerJoin, entity::TrackRelation::Medium.def())
+            .join(JoinType::InnerJoin, entity::MediumRelation::Release.def()),
+        db.get_database_backend(),
+        Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::Id)),
+        Expr::col((entity::ReleaseEntity, entity::ReleaseColumn::Title)),
+        limit,
+    )
+    .into_model::<TopResource>()
+    .all(db)
+    .await
+}
+
+pub async fn top_tracks<C>(
+    db: &C,
+    query: Select<entity::ScrobbleEntity>,
+    limit: u64,
+) -> Result<Vec<TopResource>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    top(
+        query,
+        db.get_database_backend(),
+        Expr::col((entity::TrackEntity, entity::TrackColumn::Id)),
+        Expr::col((entity::TrackEntity, entity::TrackColumn::Title)),
+        limit,
+    )
+    .into_model::<TopResource>()
+    .all(db)
+    .await
+}
+
+pub async fn top_genres<C>(
+    db: &C,
+    query: Select<entity::ScrobbleEntity>,
+    limit: u64,
+) -> Result<Vec<TopGenre>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    top(
+        query
+            .join(
+                JoinType::InnerJoin,
+                entity::TrackRelation::GentreTrack.def(),
+            )
+            .join(JoinType::InnerJoin, entity::GenreTrackRelation::Genre.def()),
+        db.get_database_backend(),
+        Expr::col((entity::GenreEntity, entity::GenreColumn::Id)),
+        Expr::col((entity::GenreEntity, entity::GenreColumn::Name)),
+        limit,
+    )
+    .into_model::<TopGenre>()
+    .all(db)
+    .await
+}
+
+/// Counts the scrobbles for each value of the expression
+pub async fn buckets<C>(
+    db: &C,
+    query: Select<entity::ScrobbleEntity>,
+    bucket: SimpleExpr,
+) -> Result<Vec<Bucket>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    counted(query, db.get_database_backend())
+        .column_as(bucket, "bucket")
+        .group_by(SimpleExpr::from(Expr::col(Alias::new("bucket"))))
+        .into_model::<Bucket>()
+        .all(db)
+        .await
+}
+
+/// Renders the images in a square grid of the given size, cropping each of them
+/// to a square tile. Images which can't be read are left out.
+pub async fn collage(
+    paths: Vec<String>,
+    size: u32,
+    format: ImageFormat,
+) -> Result<Vec<u8>, StatsError> {
+    tokio::task::spawn_blocking(move || {
+        let images: Vec<DynamicImage> = paths
+            .iter()
+            .filter_map(|path| match ImageReader::open(path) {
+                Ok(reader) => reader.with_guessed_format().ok()?.decode().ok(),
+                Err(error) => {
+                    tracing::warn!(%error, %path, "Could not open image for collage");
+                    None
+                }
+            })
+            .collect();
+        if images.is_empty() {
+            return Err(StatsError::Empty);
+        }
+        // the largest full square grid, so that there are no empty tiles
+        let side = (images.len() as f64).sqrt().floor() as u32;
+        let tile = size / side;
+        let mut canvas = DynamicImage::new_rgb8(side * tile, side * tile);
+        for (i, image) in images.iter().take((side * side) as usize).enumerate() {
+            let (x, y) = (i as u32 % side, i as u32 / side);
+            let image = image.resize_to_fill(tile, tile, FilterType::Lanczos3);
+            overlay(&mut canvas, &image, (x * tile) as i64, (y * tile) as i64);
+        }
+        let mut bytes = Vec::new();
+        let format: ImageOutputFormat = format.into();
+        canvas.write_to(&mut Cursor::new(&mut bytes), format)?;
+        Ok(bytes)
+    })
+    .await?
+}
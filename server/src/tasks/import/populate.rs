@@ -16,7 +16,7 @@ use uuid::Uuid;
 
 use crate::{
     fetch::{deezer, itunes, musicbrainz},
-    tasks::{push, TaskName},
+    tasks::{push, replay_gain, TaskName},
 };
 use base::{
     setting::{get_settings, ArtProvider, Settings},
@@ -238,7 +238,7 @@ impl crate::tasks::TaskTrait for Data {
         } else {
             None
         };
-        push(
+        let track_tasks = push(
             &import_rc
                 .release_matches
                 .0
@@ -261,6 +261,14 @@ impl crate::tasks::TaskTrait for Data {
                 .collect::<Vec<_>>(),
         )
         .await?;
+        // analyze the loudness once all tracks have been imported
+        push(&[InsertTask {
+            name: TaskName::ReplayGain,
+            payload: Some(json!(replay_gain::Data(release_id))),
+            depends_on: track_tasks.into_iter().map(|t| t.id).collect(),
+            duration: Duration::seconds(600),
+        }])
+        .await?;
 
         Ok(tx.commit().await?)
     }
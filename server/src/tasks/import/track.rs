@@ -1,12 +1,17 @@
 use eyre::{eyre, Result, WrapErr};
 use sea_orm::{ActiveValue, ConnectionTrait, EntityTrait, IntoActiveModel, TransactionTrait};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{path::PathBuf, sync::Arc};
 use strfmt::strfmt;
-use taskie_client::{Task as TaskieTask, TaskKey};
+use taskie_client::{InsertTask, Task as TaskieTask, TaskKey};
+use time::Duration;
 use uuid::Uuid;
 
-use crate::{import::TrackFile, tasks::TaskName};
+use crate::{
+    import::{probe, sidecar, TrackFile},
+    tasks::{lyrics, push, waveform, TaskName},
+};
 use base::{
     setting::get_settings,
     util::{dedup, path_to_str},
@@ -19,7 +24,9 @@ use entity::{
     full::{ArtistInfo, GenreInfo, GetArtistCredits},
     IgnoreNone,
 };
-use tag::{sanitize_map, tag_to_string_map, tags_from_combination, Picture, PictureType};
+use tag::{
+    read_gapless, sanitize_map, tag_to_string_map, tags_from_combination, Picture, PictureType,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Data {
@@ -35,7 +42,7 @@ pub struct Data {
 
 #[async_trait::async_trait]
 impl crate::tasks::TaskTrait for Data {
-    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    async fn run<C>(&self, db: &C, task: TaskieTask<TaskName, TaskKey>) -> Result<()>
     where
         C: ConnectionTrait + TransactionTrait,
     {
@@ -58,6 +65,11 @@ impl crate::tasks::TaskTrait for Data {
             .get(self.source)
             .ok_or(eyre!("Invalid track mapping"))?;
         let mut file = TrackFile::open(&settings.library, &internal_track.path.parse()?)?;
+        // read before the tags are replaced, as the source tags may be cleared
+        let gapless = read_gapless(file.tag.as_ref(), &file.path).unwrap_or_else(|error| {
+            tracing::warn!(%error, path = ?file.path, "Could not read gapless information");
+            Default::default()
+        });
         let tags = tags_from_combination(&full_release, &full_track)?;
 
         let release_path: PathBuf = release
@@ -74,6 +86,16 @@ impl crate::tasks::TaskTrait for Data {
         );
         let track_path = release_path.join(track_path);
 
+        let lyrics_path = sidecar(&file.path);
+        if lyrics_path.exists() {
+            tokio::fs::copy(&lyrics_path, sidecar(&track_path))
+                .await
+                .wrap_err(eyre!(
+                    "Could not copy lyrics {:?} next to the track: {:?}",
+                    lyrics_path,
+                    track_path
+                ))?;
+        }
         file.duplicate_to(&settings.library, &track_path)
             .wrap_err(eyre!(
                 "Could not copy track {:?} to its new location: {:?}",
@@ -101,9 +123,27 @@ impl crate::tasks::TaskTrait for Data {
                 .wrap_err(eyre!("Could not add picture tag to file: {:?}", track_path))?;
         }
 
+        let properties = probe(&settings.transcoding, &track_path)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!(%error, path = ?track_path, "Could not probe audio properties");
+                Default::default()
+            });
+
         let mut track = full_track.get_track().clone().into_active_model();
         track.path = ActiveValue::Set(Some(path_to_str(&track_path)?));
         track.format = ActiveValue::Set(Some(file.format));
+        track.bpm = ActiveValue::Set(properties.bpm);
+        track.framerate = ActiveValue::Set(properties.framerate);
+        track.framecount = ActiveValue::Set(properties.framecount);
+        track.channels = ActiveValue::Set(properties.channels);
+        track.bitrate = ActiveValue::Set(properties.bitrate);
+        track.bitdepth = ActiveValue::Set(properties.bitdepth);
+        track.size = ActiveValue::Set(properties.size);
+        track.encoder_delay = ActiveValue::Set(gapless.encoder_delay);
+        track.encoder_padding = ActiveValue::Set(gapless.encoder_padding);
+        track.sample_count = ActiveValue::Set(gapless.sample_count);
+        track.gapless_playback = ActiveValue::Set(gapless.gapless_playback);
         entity::TrackEntity::insert(track)
             .on_conflict(TRACK_CONFLICT.to_owned())
             .exec(&tx)
@@ -195,6 +235,23 @@ impl crate::tasks::TaskTrait for Data {
                 .await
                 .ignore_none()?;
         }
-        Ok(tx.commit().await?)
+        tx.commit().await?;
+
+        push(&[
+            InsertTask {
+                name: TaskName::Waveform,
+                payload: Some(json!(waveform::Data(self.track))),
+                depends_on: vec![task.id.clone()],
+                duration: Duration::seconds(60),
+            },
+            InsertTask {
+                name: TaskName::Lyrics,
+                payload: Some(json!(lyrics::Data(self.track))),
+                depends_on: vec![task.id.clone()],
+                duration: Duration::seconds(60),
+            },
+        ])
+        .await?;
+        Ok(())
     }
 }
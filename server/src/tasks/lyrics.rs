@@ -0,0 +1,88 @@
+use entity::IgnoreNone;
+use eyre::{eyre, Result};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, JoinType, QueryFilter, QuerySelect,
+    RelationTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use taskie_client::{Task as TaskieTask, TaskKey};
+use uuid::Uuid;
+
+use crate::import::{read_lyrics, TrackFile};
+use crate::tasks::TaskName;
+use base::setting::get_settings;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Data(pub Uuid);
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let settings = get_settings()?;
+        let tx = db.begin().await?;
+        let track = entity::TrackEntity::find_by_id(self.0)
+            .one(&tx)
+            .await?
+            .ok_or(eyre!("Track not found"))?;
+        let path: PathBuf = track
+            .path
+            .ok_or(eyre!("Track {} does not have an associated path", self.0))?
+            .into();
+
+        let file = TrackFile::open(&settings.library, &path)?;
+        let (text, lines) = read_lyrics(&file).await?;
+        tracing::trace!(id = %self.0, synced = lines.is_some(), "Read track lyrics");
+        // an empty row is stored as well, so that the track is not considered outdated
+        entity::LyricsEntity::insert(
+            entity::Lyrics {
+                track_id: self.0,
+                text,
+                lines: lines.map(entity::LyricsLines),
+            }
+            .into_active_model(),
+        )
+        .on_conflict(entity::conflict::LYRICS_CONFLICT.to_owned())
+        .exec(&tx)
+        .await
+        .ignore_none()?;
+
+        Ok(tx.commit().await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl super::TaskEntities for Data {
+    async fn all<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Path.is_not_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| Self(t.id))
+            .collect())
+    }
+
+    async fn outdated<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::TrackEntity::find()
+            .join(JoinType::LeftJoin, entity::TrackRelation::Lyrics.def())
+            .filter(entity::TrackColumn::Path.is_not_null())
+            .filter(entity::LyricsColumn::TrackId.is_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| Self(t.id))
+            .collect())
+    }
+}
@@ -3,7 +3,13 @@ pub mod artist_url;
 pub mod import;
 pub mod index_search;
 pub mod lastfm_artist_image;
+pub mod lyrics;
+pub mod now_playing;
+pub mod rating;
+pub mod replay_gain;
 pub mod scrobble;
+pub mod waveform;
+pub mod year_review;
 
 use async_once_cell::OnceCell;
 use base::{
@@ -42,11 +48,17 @@ pub trait TaskEntities {
 #[serde(rename_all = "snake_case")]
 pub enum TaskName {
     Scrobble,
+    NowPlaying,
     ArtistUrl,
     IndexSearch,
     ArtistDescription,
     #[serde(rename = "lastfm_artist_image")]
     LastFMArtistImage,
+    ReplayGain,
+    Waveform,
+    Lyrics,
+    Rating,
+    YearReview,
 
     ImportFetch,
     ImportFetchRelease,
@@ -107,6 +119,11 @@ where
                 .run(db, task)
                 .await?
         }
+        TaskName::NowPlaying => {
+            serde_json::from_value::<now_playing::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
         TaskName::ArtistUrl => {
             serde_json::from_value::<artist_url::Data>(task.payload.clone().into())?
                 .run(db, task)
@@ -127,6 +144,31 @@ where
                 .run(db, task)
                 .await?
         }
+        TaskName::ReplayGain => {
+            serde_json::from_value::<replay_gain::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
+        TaskName::Waveform => {
+            serde_json::from_value::<waveform::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
+        TaskName::Lyrics => {
+            serde_json::from_value::<lyrics::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
+        TaskName::Rating => {
+            serde_json::from_value::<rating::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
+        TaskName::YearReview => {
+            serde_json::from_value::<year_review::Data>(task.payload.clone().into())?
+                .run(db, task)
+                .await?
+        }
 
         TaskName::ImportFetch => {
             serde_json::from_value::<import::fetch::Data>(task.payload.clone().into())?
@@ -0,0 +1,45 @@
+use eyre::Result;
+use sea_orm::{ConnectionTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use taskie_client::{Task as TaskieTask, TaskKey};
+use uuid::Uuid;
+
+use super::scrobble::{lastfm_call, track_with_artists};
+use crate::tasks::TaskName;
+use entity::full::ArtistInfo;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Data {
+    pub provider: entity::ConnectionProvider,
+    pub username: String,
+
+    pub track_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let tx = db.begin().await?;
+        let track_with_artists = track_with_artists(&tx, self.track_id).await?;
+        match self.provider {
+            entity::ConnectionProvider::LastFM => {
+                let params = vec![
+                    (
+                        "artist".to_string(),
+                        track_with_artists.get_joined_artists()?,
+                    ),
+                    ("track".to_string(), track_with_artists.0.title.to_owned()),
+                    (
+                        "duration".to_string(),
+                        (track_with_artists.0.length / 1000).to_string(),
+                    ),
+                    ("mbid".to_string(), self.track_id.to_string()),
+                ];
+                lastfm_call(&tx, &self.username, "track.updateNowPlaying", params).await
+            }
+        }
+    }
+}
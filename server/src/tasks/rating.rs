@@ -0,0 +1,87 @@
+use eyre::{eyre, Result};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use taskie_client::{Task as TaskieTask, TaskKey};
+use uuid::Uuid;
+
+use crate::{import::TrackFile, tasks::TaskName};
+use base::setting::get_settings;
+
+/// Writes the rating the library owner gave to the track into its file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Data(pub Uuid);
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let settings = get_settings()?;
+        let username = match settings.library.tagging.write_ratings {
+            true => settings.library.owner.as_ref(),
+            false => None,
+        };
+        let Some(username) = username else {
+            tracing::trace!(id = %self.0, "Writing ratings is disabled");
+            return Ok(());
+        };
+        let track = entity::TrackEntity::find_by_id(self.0)
+            .one(db)
+            .await?
+            .ok_or(eyre!("Track not found"))?;
+        let medium = entity::MediumEntity::find_by_id(track.medium_id)
+            .one(db)
+            .await?
+            .ok_or(eyre!("Medium not found"))?;
+        let path: PathBuf = track
+            .path
+            .ok_or(eyre!("Track {} does not have an associated path", self.0))?
+            .into();
+
+        let ratings = entity::RatingEntity::find()
+            .filter(ColumnTrait::eq(&entity::RatingColumn::User, username))
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(ColumnTrait::eq(
+                                &entity::RatingColumn::Kind,
+                                entity::RatingKind::Track,
+                            ))
+                            .add(ColumnTrait::eq(&entity::RatingColumn::Resource, track.id)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(ColumnTrait::eq(
+                                &entity::RatingColumn::Kind,
+                                entity::RatingKind::Release,
+                            ))
+                            .add(ColumnTrait::eq(
+                                &entity::RatingColumn::Resource,
+                                medium.release_id,
+                            )),
+                    ),
+            )
+            .all(db)
+            .await?;
+        // tracks which were not rated themselves get the rating of their release
+        let rating = [entity::RatingKind::Track, entity::RatingKind::Release]
+            .into_iter()
+            .find_map(|kind| ratings.iter().find(|r| r.kind == kind))
+            .map(|r| r.rating as u8);
+
+        let library = settings.library.to_owned();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut file = TrackFile::open(&library, &path)?;
+            file.set_rating(rating)?;
+            Ok(file.write()?)
+        })
+        .await??;
+        tracing::trace!(id = %self.0, ?rating, "Wrote track rating");
+        Ok(())
+    }
+}
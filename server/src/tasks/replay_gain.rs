@@ -0,0 +1,225 @@
+use eyre::{bail, eyre, Result, WrapErr};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel,
+    JoinType, ModelTrait, QueryFilter, QuerySelect, RelationTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use taskie_client::{Task as TaskieTask, TaskKey};
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::{import::TrackFile, tasks::TaskName};
+use base::setting::get_settings;
+use base::util::path_to_str;
+use tag::TagKey;
+
+/// The reference loudness, in LUFS, used by ReplayGain 2.0
+static REFERENCE_LOUDNESS: f64 = -18.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Data(pub Uuid);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Loudness {
+    /// Integrated loudness, in LUFS
+    integrated: Option<f64>,
+    /// Loudness range, in LU
+    range: Option<f64>,
+    /// True peak, in dBFS
+    peak: Option<f64>,
+}
+
+impl Loudness {
+    fn gain(&self) -> Option<f64> {
+        self.integrated.map(|i| REFERENCE_LOUDNESS - i)
+    }
+
+    fn linear_peak(&self) -> Option<f64> {
+        self.peak.map(|p| 10f64.powf(p / 20.0))
+    }
+}
+
+fn parse_value(line: &str, prefix: &str) -> Option<f64> {
+    line.strip_prefix(prefix)
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
+
+// Parses the summary printed by ffmpeg's ebur128 filter once the stream has ended
+fn parse_summary(output: &str) -> Loudness {
+    let summary = output
+        .rfind("Summary:")
+        .map(|i| &output[i..])
+        .unwrap_or_default();
+    let mut loudness = Loudness::default();
+    for line in summary.lines().map(|l| l.trim()) {
+        if let Some(v) = parse_value(line, "I:") {
+            loudness.integrated = Some(v);
+        } else if let Some(v) = parse_value(line, "LRA:") {
+            loudness.range = Some(v);
+        } else if let Some(v) = parse_value(line, "Peak:") {
+            loudness.peak = Some(v);
+        }
+    }
+    loudness
+}
+
+/// Measures the loudness of the given files as if they were played one after the other
+async fn measure(ffmpeg: &str, paths: &[PathBuf]) -> Result<Loudness> {
+    let mut command = Command::new(ffmpeg);
+    command.args(["-hide_banner", "-nostats"]);
+    for path in paths.iter() {
+        command.arg("-i").arg(path_to_str(path)?);
+    }
+    let inputs: String = (0..paths.len()).map(|i| format!("[{}:a:0]", i)).collect();
+    command
+        .arg("-filter_complex")
+        .arg(format!(
+            "{}concat=n={}:v=0:a=1,ebur128=peak=true",
+            inputs,
+            paths.len()
+        ))
+        .args(["-f", "null", "-"]);
+
+    let output = command
+        .output()
+        .await
+        .wrap_err(eyre!("Could not run ffmpeg on files: {:?}", paths))?;
+    if !output.status.success() {
+        bail!("ffmpeg exited with {} on files: {:?}", output.status, paths);
+    }
+    let loudness = parse_summary(&String::from_utf8_lossy(&output.stderr));
+    if loudness.integrated.is_none() {
+        bail!("Could not find the loudness summary for files: {:?}", paths);
+    }
+    Ok(loudness)
+}
+
+fn format_gain(value: Option<f64>) -> Vec<String> {
+    value.map(|v| format!("{:.2} dB", v)).into_iter().collect()
+}
+
+fn format_peak(value: Option<f64>) -> Vec<String> {
+    value.map(|v| format!("{:.6}", v)).into_iter().collect()
+}
+
+fn write_tags(path: &Path, track: &Loudness, album: &Loudness) -> Result<()> {
+    let settings = get_settings()?;
+    let mut file = TrackFile::open(&settings.library, &path.to_path_buf())?;
+    let values = [
+        (TagKey::ReplayGainTrackGain, format_gain(track.gain())),
+        (
+            TagKey::ReplayGainTrackPeak,
+            format_peak(track.linear_peak()),
+        ),
+        (TagKey::ReplayGainTrackRange, format_gain(track.range)),
+        (TagKey::ReplayGainAlbumGain, format_gain(album.gain())),
+        (
+            TagKey::ReplayGainAlbumPeak,
+            format_peak(album.linear_peak()),
+        ),
+        (TagKey::ReplayGainAlbumRange, format_gain(album.range)),
+        (
+            TagKey::ReplayGainReferenceLoudness,
+            vec![format!("{:.2} LUFS", REFERENCE_LOUDNESS)],
+        ),
+    ];
+    file.apply(values.into_iter().collect())?;
+    file.write()
+}
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let settings = get_settings()?;
+        let release = entity::ReleaseEntity::find_by_id(self.0)
+            .one(db)
+            .await?
+            .ok_or(eyre!("Release not found"))?;
+        let mut mediums = release.find_related(entity::MediumEntity).all(db).await?;
+        mediums.sort_unstable_by_key(|m| m.position);
+
+        let mut tracks = Vec::new();
+        for medium in mediums.iter() {
+            let mut medium_tracks = medium.find_related(entity::TrackEntity).all(db).await?;
+            medium_tracks.sort_unstable_by_key(|t| t.number);
+            tracks.extend(medium_tracks.into_iter().filter(|t| t.path.is_some()));
+        }
+        if tracks.is_empty() {
+            tracing::trace!(id = %self.0, "Release has no tracks to analyze");
+            return Ok(());
+        }
+
+        // decoding takes a while, so no transaction is held during the analysis
+        let paths: Vec<PathBuf> = tracks
+            .iter()
+            .filter_map(|t| t.path.as_ref().map(PathBuf::from))
+            .collect();
+        let ffmpeg = &settings.transcoding.ffmpeg;
+        let album = measure(ffmpeg, &paths).await?;
+        let mut measured = Vec::new();
+        for path in paths.iter() {
+            measured.push(measure(ffmpeg, &[path.clone()]).await?);
+        }
+
+        let tx = db.begin().await?;
+        for (track, loudness) in tracks.into_iter().zip(measured.iter()) {
+            let mut track = track.into_active_model();
+            track.replay_gain_track_gain = ActiveValue::Set(loudness.gain());
+            track.replay_gain_track_peak = ActiveValue::Set(loudness.linear_peak());
+            track.replay_gain_track_range = ActiveValue::Set(loudness.range);
+            track.replay_gain_album_gain = ActiveValue::Set(album.gain());
+            track.replay_gain_album_peak = ActiveValue::Set(album.linear_peak());
+            track.replay_gain_album_range = ActiveValue::Set(album.range);
+            track.update(&tx).await?;
+        }
+        tx.commit().await?;
+
+        // the files are only changed once the values are stored
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            for (path, loudness) in paths.iter().zip(measured.iter()) {
+                write_tags(path, loudness, &album)
+                    .wrap_err(eyre!("Could not write ReplayGain tags to file: {:?}", path))?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+}
+
+#[async_trait::async_trait]
+impl super::TaskEntities for Data {
+    async fn all<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::ReleaseEntity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|r| Self(r.id))
+            .collect())
+    }
+
+    async fn outdated<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::ReleaseEntity::find()
+            .join(JoinType::InnerJoin, entity::ReleaseRelation::Medium.def())
+            .join(JoinType::InnerJoin, entity::MediumRelation::Track.def())
+            .filter(entity::TrackColumn::ReplayGainTrackGain.is_null())
+            .distinct()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|r| Self(r.id))
+            .collect())
+    }
+}
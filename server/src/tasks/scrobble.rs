@@ -38,8 +38,8 @@ struct LastFMScrobbleResponseError {
     message: String,
 }
 
-struct TrackWithArtists(
-    entity::Track,
+pub(super) struct TrackWithArtists(
+    pub entity::Track,
     Vec<entity::ArtistCredit>,
     Vec<entity::Artist>,
 );
@@ -56,6 +56,89 @@ impl GetArtist for TrackWithArtists {
     }
 }
 
+pub(super) async fn track_with_artists<C>(db: &C, id: Uuid) -> Result<TrackWithArtists>
+where
+    C: ConnectionTrait,
+{
+    let track = entity::TrackEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or(eyre!("Track doesn't exist: {}", id))?;
+    let artist_credits = track
+        .find_related(entity::ArtistCreditEntity)
+        .all(db)
+        .await?;
+    let artists: Vec<_> = artist_credits
+        .load_one(entity::ArtistEntity, db)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
+    Ok(TrackWithArtists(track, artist_credits, artists))
+}
+
+/// Signs and sends a call to an authenticated Last.fm method on behalf of the user
+pub(super) async fn lastfm_call<C>(
+    db: &C,
+    username: &str,
+    method: &str,
+    params: Vec<(String, String)>,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let settings = get_settings()?;
+    let provider = entity::ConnectionProvider::LastFM;
+    let lastfm = settings
+        .connections
+        .lastfm
+        .as_ref()
+        .ok_or(eyre!("Provider {} not configured", provider.name()))?;
+    let connection = entity::UserConnectionEntity::find_by_id((username.to_owned(), provider))
+        .one(db)
+        .await?
+        .ok_or(eyre!("User is not connected to the required service"))?;
+
+    let connection_self: entity::user_connection::LastFMData =
+        serde_json::from_value(connection.data.to_owned())?;
+    let url = lastfm::LASTFM_BASE_URL.clone();
+    let mut body = params;
+    body.extend([
+        ("method".to_string(), method.to_string()),
+        ("format".to_string(), "json".to_string()),
+        ("api_key".to_string(), lastfm.apikey.to_owned()),
+        ("sk".to_string(), connection_self.token),
+    ]);
+    let signature = lastfm::signature(
+        body.iter().map(|(k, v)| (k, v)),
+        lastfm.shared_secret.as_str(),
+    );
+    body.push(("api_sig".to_string(), signature));
+    tracing::trace! {?body, %method, "Calling last.fm"};
+
+    // taken from https://docs.rs/reqwest/latest/src/reqwest/async_impl/request.rs.html#406-424
+    let body = serde_urlencoded::to_string(body)?;
+    let mut req = Request::new(Method::POST, url);
+    req.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-www-form-urlencoded"),
+    );
+    *req.body_mut() = Some(body.into());
+    let res = lastfm::send_request(req).await?;
+    let raw_self: LastFMScrobbleResponse = res.json().await.map_err(|e| eyre!(e))?;
+    tracing::trace! {?raw_self, %method, "Last.fm response"}
+
+    match raw_self {
+        LastFMScrobbleResponse::Success(_) => Ok(()),
+        LastFMScrobbleResponse::Error(e) => Err(eyre!(
+            "Last.fm returned an error while calling {} (code: {}): {}",
+            method,
+            e.code,
+            e.message
+        )),
+    }
+}
+
 #[async_trait::async_trait]
 impl super::TaskTrait for Data {
     async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
@@ -63,46 +146,10 @@ impl super::TaskTrait for Data {
         C: ConnectionTrait + TransactionTrait,
     {
         let tx = db.begin().await?;
-        let settings = get_settings()?;
-        let track = entity::TrackEntity::find_by_id(self.track_id)
-            .one(&tx)
-            .await?
-            .ok_or(eyre!(
-                "Track to be scrobbled doesn't exist: {}",
-                self.track_id
-            ))?;
-        let artist_credits = track
-            .find_related(entity::ArtistCreditEntity)
-            .all(&tx)
-            .await?;
-        let artists: Vec<_> = artist_credits
-            .load_one(entity::ArtistEntity, &tx)
-            .await?
-            .into_iter()
-            .flatten()
-            .collect();
-        let track_with_artists = TrackWithArtists(track, artist_credits, artists);
+        let track_with_artists = track_with_artists(&tx, self.track_id).await?;
         match self.provider {
             entity::ConnectionProvider::LastFM => {
-                let lastfm = settings
-                    .connections
-                    .lastfm
-                    .as_ref()
-                    .ok_or(eyre!("Provider {} not configured", self.provider.name()))?;
-                let connection = entity::UserConnectionEntity::find_by_id((
-                    self.username.to_owned(),
-                    self.provider,
-                ))
-                .one(&tx)
-                .await?
-                .ok_or(eyre!(
-                    "Scrobbling user is not connected to the required service"
-                ))?;
-
-                let connection_self: entity::user_connection::LastFMData =
-                    serde_json::from_value(connection.data.to_owned())?;
-                let url = lastfm::LASTFM_BASE_URL.clone();
-                let mut body: Vec<(String, String)> = vec![
+                let params = vec![
                     (
                         "artist".to_string(),
                         track_with_artists.get_joined_artists()?,
@@ -113,38 +160,8 @@ impl super::TaskTrait for Data {
                         self.time.unix_timestamp().to_string(),
                     ),
                     ("mbid".to_string(), self.track_id.to_string()),
-                    ("method".to_string(), "track.scrobble".to_string()),
-                    ("format".to_string(), "json".to_string()),
-                    ("api_key".to_string(), lastfm.apikey.to_owned()),
-                    ("sk".to_string(), connection_self.token),
                 ];
-                let signature = lastfm::signature(
-                    body.iter().map(|(k, v)| (k, v)),
-                    lastfm.shared_secret.as_str(),
-                );
-                body.push(("api_sig".to_string(), signature));
-                tracing::trace! {?body,"Scrobbling to last.fm"};
-
-                // taken from https://docs.rs/reqwest/latest/src/reqwest/async_impl/request.rs.html#406-424
-                let body = serde_urlencoded::to_string(body)?;
-                let mut req = Request::new(Method::POST, url);
-                req.headers_mut().insert(
-                    CONTENT_TYPE,
-                    HeaderValue::from_static("application/x-www-form-urlencoded"),
-                );
-                *req.body_mut() = Some(body.into());
-                let res = lastfm::send_request(req).await?;
-                let raw_self: LastFMScrobbleResponse = res.json().await.map_err(|e| eyre!(e))?;
-                tracing::trace! {?raw_self, "Last.fm scrobble response"}
-
-                match raw_self {
-                    LastFMScrobbleResponse::Success(_) => Ok(()),
-                    LastFMScrobbleResponse::Error(e) => Err(eyre!(
-                        "Last.fm returned an error while scrobbling (code: {}): {}",
-                        e.code,
-                        e.message
-                    )),
-                }
+                lastfm_call(&tx, &self.username, "track.scrobble", params).await
             }
         }
     }
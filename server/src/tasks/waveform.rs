@@ -0,0 +1,183 @@
+use entity::IgnoreNone;
+use eyre::{bail, eyre, Result, WrapErr};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, JoinType, QueryFilter, QuerySelect,
+    RelationTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::process::Stdio;
+use taskie_client::{Task as TaskieTask, TaskKey};
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::tasks::TaskName;
+use base::setting::get_settings;
+
+/// The number of peaks stored for each track. Lower resolutions are computed on request
+pub static WAVEFORM_RESOLUTION: usize = 2048;
+/// The sample rate the audio is decoded at before extracting peaks
+static DECODE_SAMPLE_RATE: u64 = 8000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Data(pub Uuid);
+
+/// Groups the samples in `resolution` buckets as they are decoded, keeping the highest
+/// amplitude of each bucket scaled to the 0-255 range. The size of the buckets comes
+/// from the expected number of samples, samples past it end up in the last bucket.
+struct Peaks {
+    resolution: usize,
+    bucket: u64,
+    filled: u64,
+    max: u16,
+    peaks: Vec<u8>,
+}
+
+impl Peaks {
+    fn new(expected: u64, resolution: usize) -> Self {
+        Peaks {
+            resolution,
+            bucket: expected.div_ceil(resolution as u64).max(1),
+            filled: 0,
+            max: 0,
+            peaks: Vec::with_capacity(resolution),
+        }
+    }
+
+    fn push(&mut self, sample: i16) {
+        self.max = self.max.max(sample.unsigned_abs());
+        self.filled += 1;
+        if self.filled == self.bucket {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let peak = ((self.max as u32 * u8::MAX as u32) / (i16::MAX as u32 + 1)) as u8;
+        let full = self.peaks.len() == self.resolution;
+        match self.peaks.last_mut() {
+            Some(last) if full => *last = (*last).max(peak),
+            _ => self.peaks.push(peak),
+        }
+        self.filled = 0;
+        self.max = 0;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.flush();
+        }
+        self.peaks
+    }
+}
+
+/// Reduces the stored peaks to the requested resolution, keeping the highest peak
+/// of each group
+pub fn downsample(peaks: &[u8], resolution: usize) -> Vec<u8> {
+    if resolution == 0 || peaks.len() <= resolution {
+        return peaks.to_vec();
+    }
+    let bucket = (peaks.len() as f64 / resolution as f64).ceil() as usize;
+    peaks
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().copied().max().unwrap_or(0))
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let settings = get_settings()?;
+        let track = entity::TrackEntity::find_by_id(self.0)
+            .one(db)
+            .await?
+            .ok_or(eyre!("Track not found"))?;
+        let path = track
+            .path
+            .ok_or(eyre!("Track {} does not have an associated path", self.0))?;
+
+        // the decoded audio is folded into peaks as it comes, so that long tracks
+        // are never held in memory. No transaction is held while decoding.
+        let rate = DECODE_SAMPLE_RATE.to_string();
+        let mut child = Command::new(&settings.transcoding.ffmpeg)
+            .args(["-v", "quiet", "-i", path.as_str(), "-map", "0:a:0"])
+            .args(["-ac", "1", "-ar", rate.as_str(), "-f", "s16le", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .wrap_err(eyre!("Could not run ffmpeg on file: {:?}", path))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or(eyre!("ffmpeg did not provide an output stream"))?;
+        let mut reader = BufReader::new(stdout);
+        let expected = track.length.max(0) as u64 * DECODE_SAMPLE_RATE / 1000;
+        let mut peaks = Peaks::new(expected, WAVEFORM_RESOLUTION);
+        loop {
+            match reader.read_i16_le().await {
+                Ok(sample) => peaks.push(sample),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => {
+                    return Err(err).wrap_err(eyre!("Could not read ffmpeg output: {:?}", path))
+                }
+            }
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            bail!("ffmpeg exited with {} on file: {:?}", status, path);
+        }
+
+        let tx = db.begin().await?;
+        entity::WaveformEntity::insert(
+            entity::Waveform {
+                track_id: self.0,
+                peaks: peaks.finish(),
+            }
+            .into_active_model(),
+        )
+        .on_conflict(entity::conflict::WAVEFORM_CONFLICT.to_owned())
+        .exec(&tx)
+        .await
+        .ignore_none()?;
+
+        Ok(tx.commit().await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl super::TaskEntities for Data {
+    async fn all<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::TrackEntity::find()
+            .filter(entity::TrackColumn::Path.is_not_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| Self(t.id))
+            .collect())
+    }
+
+    async fn outdated<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        Ok(entity::TrackEntity::find()
+            .join(JoinType::LeftJoin, entity::TrackRelation::Waveform.def())
+            .filter(entity::TrackColumn::Path.is_not_null())
+            .filter(entity::WaveformColumn::TrackId.is_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| Self(t.id))
+            .collect())
+    }
+}
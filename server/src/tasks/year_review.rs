@@ -0,0 +1,227 @@
+use entity::IgnoreNone;
+use eyre::Result;
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect, QueryTrait,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use taskie_client::{Task as TaskieTask, TaskKey};
+use time::{Date, Duration, Month, OffsetDateTime};
+
+use crate::stats::{self, Bucket, TopGenre, TopResource};
+use crate::tasks::TaskName;
+
+/// Number of top artists, releases, tracks and genres in a review
+static REVIEW_LIMIT: u64 = 10;
+/// Number of top genres for each month of a review
+static MONTH_LIMIT: u64 = 3;
+
+/// Builds the review of the given year for the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Data {
+    pub user: String,
+    pub year: i32,
+}
+
+/// The year to review: the current one when reviews are built in December,
+/// otherwise the one which just ended
+pub fn reviewed_year(now: OffsetDateTime) -> i32 {
+    match now.month() {
+        Month::December => now.year(),
+        _ => now.year() - 1,
+    }
+}
+
+fn start_of(year: i32, month: Month) -> Result<OffsetDateTime> {
+    Ok(Date::from_calendar_date(year, month, 1)?
+        .midnight()
+        .assume_utc())
+}
+
+// days are counted from the Unix epoch
+fn date(day: i64) -> String {
+    let date = (OffsetDateTime::UNIX_EPOCH + Duration::days(day)).date();
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+fn resource_entry(top: TopResource) -> entity::YearReviewEntry<uuid::Uuid> {
+    entity::YearReviewEntry {
+        id: top.id,
+        name: top.name,
+        scrobbles: top.scrobbles as u64,
+        listening_time: top.listening_time.unwrap_or_default() as u64,
+    }
+}
+
+fn genre_entry(top: TopGenre) -> entity::YearReviewEntry<String> {
+    entity::YearReviewEntry {
+        id: top.id,
+        name: top.name,
+        scrobbles: top.scrobbles as u64,
+        listening_time: top.listening_time.unwrap_or_default() as u64,
+    }
+}
+
+/// The longest run of consecutive days, the earliest one when there is a tie
+fn longest_streak(days: &[Bucket]) -> Option<entity::YearReviewStreak> {
+    let mut days: Vec<i64> = days.iter().map(|day| day.bucket).collect();
+    days.sort_unstable();
+    let (mut best, mut start) = (None, 0);
+    for (i, day) in days.iter().enumerate() {
+        if i > 0 && *day != days[i - 1] + 1 {
+            start = i;
+        }
+        let length = i - start + 1;
+        if best.map_or(true, |(_, best_length)| length > best_length) {
+            best = Some((days[start], length));
+        }
+    }
+    best.map(|(first, length)| entity::YearReviewStreak {
+        from: date(first),
+        to: date(first + length as i64 - 1),
+        days: length as u32,
+    })
+}
+
+fn top_day(days: &[Bucket]) -> Option<entity::YearReviewDay> {
+    days.iter()
+        .max_by(|a, b| a.scrobbles.cmp(&b.scrobbles).then(b.bucket.cmp(&a.bucket)))
+        .map(|day| entity::YearReviewDay {
+            date: date(day.bucket),
+            scrobbles: day.scrobbles as u64,
+        })
+}
+
+impl Data {
+    async fn report<C>(&self, db: &C) -> Result<Option<entity::YearReviewReport>>
+    where
+        C: ConnectionTrait,
+    {
+        let from = start_of(self.year, Month::January)?;
+        let to = start_of(self.year + 1, Month::January)?;
+        let base = || stats::scrobbles(&self.user, Some(from), Some(to));
+
+        let totals = stats::totals(db, base()).await?;
+        if totals.scrobbles == 0 {
+            return Ok(None);
+        }
+        let artists = stats::top_artists(db, base(), REVIEW_LIMIT).await?;
+        let releases = stats::top_releases(db, base(), REVIEW_LIMIT).await?;
+        let tracks = stats::top_tracks(db, base(), REVIEW_LIMIT).await?;
+        let genres = stats::top_genres(db, base(), REVIEW_LIMIT).await?;
+
+        let known = stats::with_artists(stats::scrobbles(&self.user, None, Some(from)))
+            .column(entity::ArtistColumn::Id)
+            .into_query();
+        let new_artists = stats::top_artists(
+            db,
+            base().filter(entity::ArtistColumn::Id.not_in_subquery(known)),
+            REVIEW_LIMIT,
+        )
+        .await?;
+
+        let days = stats::buckets(db, base(), stats::day(db.get_database_backend())).await?;
+
+        let mut months = Vec::new();
+        for number in 1..=12u8 {
+            let month = Month::try_from(number)?;
+            let start = start_of(self.year, month)?;
+            let end = match month {
+                Month::December => to,
+                _ => start_of(self.year, month.next())?,
+            };
+            let range = || stats::scrobbles(&self.user, Some(start), Some(end));
+            let totals = stats::totals(db, range()).await?;
+            let genres = stats::top_genres(db, range(), MONTH_LIMIT).await?;
+            months.push(entity::YearReviewMonth {
+                scrobbles: totals.scrobbles as u64,
+                listening_time: totals.listening_time.unwrap_or_default() as u64,
+                genres: genres.into_iter().map(genre_entry).collect(),
+            });
+        }
+
+        Ok(Some(entity::YearReviewReport {
+            scrobbles: totals.scrobbles as u64,
+            listening_time: totals.listening_time.unwrap_or_default() as u64,
+            artists: artists.into_iter().map(resource_entry).collect(),
+            releases: releases.into_iter().map(resource_entry).collect(),
+            tracks: tracks.into_iter().map(resource_entry).collect(),
+            genres: genres.into_iter().map(genre_entry).collect(),
+            new_artists: new_artists.into_iter().map(resource_entry).collect(),
+            longest_streak: longest_streak(&days),
+            top_day: top_day(&days),
+            months,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl super::TaskTrait for Data {
+    async fn run<C>(&self, db: &C, _task: TaskieTask<TaskName, TaskKey>) -> Result<()>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let Some(report) = self.report(db).await? else {
+            tracing::trace!(user = %self.user, year = self.year, "No scrobbles to review");
+            return Ok(());
+        };
+        entity::YearReviewEntity::insert(entity::YearReviewActive {
+            user: ActiveValue::Set(self.user.to_owned()),
+            year: ActiveValue::Set(self.year),
+            report: ActiveValue::Set(report),
+            updated_at: ActiveValue::Set(OffsetDateTime::now_utc()),
+        })
+        .on_conflict(entity::conflict::YEAR_REVIEW_CONFLICT.to_owned())
+        .exec(db)
+        .await
+        .ignore_none()?;
+        tracing::info!(user = %self.user, year = self.year, "Built year review");
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl super::TaskEntities for Data {
+    async fn all<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        let year = reviewed_year(OffsetDateTime::now_utc());
+        Ok(entity::UserEntity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|user| Self {
+                user: user.username,
+                year,
+            })
+            .collect())
+    }
+
+    async fn outdated<C>(db: &C) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+        Self: Sized,
+    {
+        let year = reviewed_year(OffsetDateTime::now_utc());
+        let reviewed: HashSet<String> = entity::YearReviewEntity::find()
+            .filter(ColumnTrait::eq(&entity::YearReviewColumn::Year, year))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|review| review.user)
+            .collect();
+        Ok(Self::all(db)
+            .await?
+            .into_iter()
+            .filter(|data| !reviewed.contains(&data.user))
+            .collect())
+    }
+}
@@ -0,0 +1,140 @@
+use bytes::Bytes;
+use futures::{future, stream, Stream, StreamExt};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use strfmt::{strfmt, FmtError};
+use thiserror::Error;
+use tokio::process::Command;
+use tokio_util::io::ReaderStream;
+
+use base::setting::{Transcoding, TranscodingFormat, TranscodingProfile};
+use base::util::{path_to_str, UtilError};
+
+#[derive(Error, Debug)]
+pub enum TranscodeError {
+    #[error("No transcoding command has been configured")]
+    NoCommand,
+    #[error("Invalid transcoding profile: {0}")]
+    UnknownProfile(String),
+    #[error("Could not format the transcoding command: {0}")]
+    Format(#[from] FmtError),
+    #[error("Invalid input path: {0}")]
+    Path(#[from] UtilError),
+    #[error("Could not spawn the encoder: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("The encoder did not provide an output stream")]
+    NoOutput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub format: TranscodingFormat,
+    pub bitrate: u32,
+    pub offset: f64,
+}
+
+fn profile<'a>(
+    settings: &'a Transcoding,
+    name: Option<&String>,
+) -> Result<Option<&'a TranscodingProfile>, TranscodeError> {
+    name.map(|name| {
+        settings
+            .profiles
+            .get(name)
+            .ok_or_else(|| TranscodeError::UnknownProfile(name.to_owned()))
+    })
+    .transpose()
+}
+
+/// Computes the transcoding options for a request, taking into account the default
+/// profiles configured for the client and the user. Returns None when the original
+/// file should be served as-is, which is also the case when only a maximum bitrate
+/// is requested and the source, in kbit/s when known, is already under it.
+pub fn resolve(
+    settings: &Transcoding,
+    username: &str,
+    client: Option<&str>,
+    format: Option<TranscodingFormat>,
+    max_bitrate: Option<u32>,
+    offset: Option<f64>,
+    source_bitrate: Option<u32>,
+) -> Result<Option<Options>, TranscodeError> {
+    let client_profile = profile(settings, client.and_then(|c| settings.clients.get(c)))?;
+    let user_profile = profile(settings, settings.users.get(username))?;
+    let default = client_profile.or(user_profile);
+
+    let format = match (format, default) {
+        (Some(format), _) => format,
+        (None, Some(profile)) => profile.format,
+        (None, None) => match (max_bitrate, source_bitrate) {
+            (Some(max), Some(source)) if source <= max => return Ok(None),
+            (Some(_), _) => TranscodingFormat::default(),
+            (None, _) => return Ok(None),
+        },
+    };
+    let bitrate = [max_bitrate, default.and_then(|p| p.max_bitrate)]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(settings.default_bitrate);
+
+    Ok(Some(Options {
+        format,
+        bitrate,
+        offset: offset.unwrap_or_default().max(0.0),
+    }))
+}
+
+/// Spawns the configured encoder on the given file, returning its output as a stream.
+/// The encoder process is killed as soon as the stream is dropped, and the stream
+/// ends with an error when the encoder exits unsuccessfully.
+pub fn transcode(
+    settings: &Transcoding,
+    input: &Path,
+    options: Options,
+) -> Result<impl Stream<Item = std::io::Result<Bytes>>, TranscodeError> {
+    let vars: HashMap<String, String> = [
+        ("ffmpeg".to_string(), settings.ffmpeg.to_owned()),
+        ("input".to_string(), path_to_str(input)?),
+        ("offset".to_string(), format!("{:.3}", options.offset)),
+        ("codec".to_string(), options.format.codec().to_string()),
+        (
+            "container".to_string(),
+            options.format.container().to_string(),
+        ),
+        ("bitrate".to_string(), options.bitrate.to_string()),
+    ]
+    .into();
+    let args = settings
+        .command
+        .iter()
+        .map(|arg| strfmt(arg, &vars))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (program, args) = args.split_first().ok_or(TranscodeError::NoCommand)?;
+
+    tracing::debug! {%program, ?args, "Spawning encoder"};
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or(TranscodeError::NoOutput)?;
+
+    // the child is kept alive for as long as its output is being read, and waited on
+    // once it is over so that a crashed encoder doesn't look like a short track
+    let exit = stream::once(async move {
+        match child.wait().await {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(Err(std::io::Error::other(format!(
+                "The encoder exited with {}",
+                status
+            )))),
+            Err(err) => Some(Err(err)),
+        }
+    })
+    .filter_map(future::ready);
+    Ok(ReaderStream::new(stdout).chain(exit))
+}
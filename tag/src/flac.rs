@@ -201,6 +201,7 @@ impl crate::Tag for Tag {
             TagKey::OriginalReleaseYear => vec!["ORIGINALYEAR"],
             TagKey::Performer => vec!["PERFORMER"],
             TagKey::Producer => vec!["PRODUCER"],
+            TagKey::Rating => vec!["RATING"],
             TagKey::RecordLabel => vec!["Label"],
             TagKey::ReleaseCountry => vec!["RELEASECOUNTRY"],
             TagKey::ReleaseDate => vec!["DATE"],
@@ -229,6 +230,7 @@ impl crate::Tag for Tag {
 
             // Internal, not mapped from picard
             TagKey::Duration => vec!["LENGTH"],
+            TagKey::ITunSMPB => vec!["ITUNSMPB"],
             _ => vec![],
         }
     }
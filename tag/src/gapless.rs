@@ -0,0 +1,252 @@
+use entity::TrackFormat;
+use eyre::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{Tag, TagKey};
+
+/// The amount of audio data scanned when looking for the first MPEG frame
+static MAX_FRAME_SEARCH: usize = 64 * 1024;
+/// The largest Xing/LAME header we need to read past the frame header
+static XING_HEADER_SIZE: usize = 192;
+/// Samples output by MP3 decoders before the first encoded one, which the LAME
+/// header leaves out of the delay and includes in the padding
+static DECODER_DELAY: i32 = 529;
+/// Encoders known to write the LAME extension after the Xing/Info header
+static LAME_ENCODERS: [&[u8]; 3] = [b"LAME", b"Lavf", b"Lavc"];
+
+/// Information needed by players to trim the silence added by encoders. Delay
+/// and padding follow the iTunSMPB convention: they count decoded samples, so
+/// the delay of MP3 decoders is part of them.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gapless {
+    /// Samples of silence prepended by the encoder
+    pub encoder_delay: Option<i32>,
+    /// Samples of silence appended by the encoder to fill the last frame
+    pub encoder_padding: Option<i32>,
+    /// Number of samples of the original audio, without delay and padding
+    pub sample_count: Option<i64>,
+    /// Whether the track is part of a gapless album, as set by iTunes
+    pub gapless_playback: Option<bool>,
+}
+
+impl Gapless {
+    fn is_empty(&self) -> bool {
+        self.encoder_delay.is_none()
+            && self.encoder_padding.is_none()
+            && self.sample_count.is_none()
+    }
+}
+
+/// Parses the iTunSMPB comment, made of space-separated hexadecimal values:
+/// a reserved field, the delay, the padding and the original sample count
+pub fn parse_itunsmpb(value: &str) -> Option<Gapless> {
+    let fields: Vec<_> = value
+        .split_whitespace()
+        .map(|f| i64::from_str_radix(f, 16).ok())
+        .collect();
+    match fields.as_slice() {
+        [_, Some(delay), Some(padding), Some(count), ..] => Some(Gapless {
+            encoder_delay: i32::try_from(*delay).ok(),
+            encoder_padding: i32::try_from(*padding).ok(),
+            sample_count: Some(*count),
+            gapless_playback: None,
+        }),
+        _ => None,
+    }
+}
+
+// Returns the size of the ID3v2 tag at the beginning of the file, if any
+fn id3v2_size(header: &[u8; 10]) -> u64 {
+    if &header[0..3] != b"ID3" {
+        return 0;
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    buf.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Reads the Xing/Info header written by LAME (and compatible encoders) in the
+/// first MPEG Layer III frame of the file. Delay and padding are only read when
+/// the header carries the LAME extension.
+pub fn read_lame_header<R: Read + Seek>(reader: &mut R) -> Result<Option<Gapless>> {
+    let mut id3 = [0u8; 10];
+    reader.read_exact(&mut id3)?;
+    reader.seek(SeekFrom::Start(id3v2_size(&id3)))?;
+    let mut buf = Vec::with_capacity(MAX_FRAME_SEARCH);
+    reader
+        .take((MAX_FRAME_SEARCH + XING_HEADER_SIZE) as u64)
+        .read_to_end(&mut buf)?;
+
+    let Some(start) = buf
+        .windows(2)
+        .take(MAX_FRAME_SEARCH)
+        .position(|w| w[0] == 0xff && w[1] & 0xe0 == 0xe0)
+    else {
+        return Ok(None);
+    };
+    let frame = &buf[start..];
+    let version = (frame[1] >> 3) & 0x03;
+    let layer = (frame[1] >> 1) & 0x03;
+    // only Layer III (encoded as 1) carries the LAME header
+    if layer != 1 || frame.len() < 4 {
+        return Ok(None);
+    }
+    let mpeg1 = version == 3;
+    let mono = (frame[3] >> 6) & 0x03 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+    let samples_per_frame: i64 = if mpeg1 { 1152 } else { 576 };
+
+    let xing = 4 + side_info;
+    match frame.get(xing..xing + 4) {
+        Some(b"Xing") | Some(b"Info") => {}
+        _ => return Ok(None),
+    }
+    let Some(flags) = read_u32(frame, xing + 4) else {
+        return Ok(None);
+    };
+    let mut offset = xing + 8;
+    let frames = if flags & 0x1 != 0 {
+        let frames = read_u32(frame, offset);
+        offset += 4;
+        frames
+    } else {
+        None
+    };
+    if flags & 0x2 != 0 {
+        offset += 4;
+    }
+    if flags & 0x4 != 0 {
+        offset += 100;
+    }
+    if flags & 0x8 != 0 {
+        offset += 4;
+    }
+
+    // the LAME extension: a 9 byte encoder version, followed by delay and padding
+    // packed as two 12 bit values after 12 bytes of other encoding information
+    let mut gapless = Gapless::default();
+    let encoder = frame.get(offset..offset + 4);
+    if !LAME_ENCODERS.iter().any(|e| encoder == Some(*e)) {
+        return Ok(None);
+    }
+    if let Some(delays) = frame.get(offset + 21..offset + 24) {
+        let delay = ((delays[0] as i32) << 4) | (delays[1] as i32 >> 4);
+        let padding = (((delays[1] & 0x0f) as i32) << 8) | delays[2] as i32;
+        gapless.encoder_delay = Some(delay + DECODER_DELAY);
+        gapless.encoder_padding = Some((padding - DECODER_DELAY).max(0));
+        gapless.sample_count = frames
+            .map(|frames| frames as i64 * samples_per_frame - delay as i64 - padding as i64)
+            .filter(|count| *count > 0);
+    }
+    Ok(Some(gapless).filter(|g| !g.is_empty()))
+}
+
+/// Reads the gapless playback information of a track, preferring the iTunSMPB
+/// tag and falling back to the LAME header for MP3 files
+pub fn read_gapless(tag: &dyn Tag, path: &Path) -> Result<Gapless> {
+    let gapless_playback = tag
+        .get_tag(TagKey::GaplessPlayback)
+        .first()
+        .map(|v| v.trim() == "1" || v.trim().eq_ignore_ascii_case("true"));
+    let from_tag = tag
+        .get_tag(TagKey::ITunSMPB)
+        .iter()
+        .find_map(|v| parse_itunsmpb(v));
+    let gapless = match from_tag {
+        Some(gapless) => Some(gapless),
+        None if tag.format() == TrackFormat::Id3 => read_lame_header(&mut File::open(path)?)?,
+        None => None,
+    };
+    Ok(Gapless {
+        gapless_playback,
+        ..gapless.unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // An MPEG-1 Layer III stereo frame with an Info header for 100 frames and a
+    // LAME extension for a delay of 576 samples and a padding of 1500 samples
+    fn lame_frame(encoder: &[u8; 9]) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.extend([0; 32]);
+        frame.extend(b"Info");
+        frame.extend(0x0fu32.to_be_bytes());
+        frame.extend(100u32.to_be_bytes());
+        frame.extend(41_800u32.to_be_bytes());
+        frame.extend([0; 100]);
+        frame.extend(50u32.to_be_bytes());
+        frame.extend(encoder);
+        frame.extend([0; 12]);
+        frame.extend([0x24, 0x05, 0xdc]);
+        frame.extend([0; 256]);
+        frame
+    }
+
+    #[test]
+    fn lame_header() {
+        let gapless = read_lame_header(&mut Cursor::new(lame_frame(b"LAME3.100"))).unwrap();
+        assert_eq!(
+            gapless,
+            Some(Gapless {
+                encoder_delay: Some(1105),
+                encoder_padding: Some(971),
+                sample_count: Some(113_124),
+                gapless_playback: None,
+            })
+        );
+    }
+
+    #[test]
+    fn lame_header_after_id3() {
+        let mut file = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0x01, 0x00];
+        file.extend([0; 128]);
+        file.extend(lame_frame(b"Lavf58.76"));
+        let gapless = read_lame_header(&mut Cursor::new(file)).unwrap();
+        assert_eq!(gapless.and_then(|g| g.encoder_delay), Some(1105));
+    }
+
+    #[test]
+    fn unknown_encoder() {
+        let gapless = read_lame_header(&mut Cursor::new(lame_frame(b"Xing\0\0\0\0\0"))).unwrap();
+        assert_eq!(gapless, None);
+    }
+
+    #[test]
+    fn no_frame() {
+        let gapless = read_lame_header(&mut Cursor::new(vec![0; 512])).unwrap();
+        assert_eq!(gapless, None);
+    }
+
+    #[test]
+    fn itunsmpb() {
+        let value = " 00000000 00000840 000001CA 00000000001CF4EA 00000000 00000000";
+        assert_eq!(
+            parse_itunsmpb(value),
+            Some(Gapless {
+                encoder_delay: Some(2112),
+                encoder_padding: Some(458),
+                sample_count: Some(0x1cf4ea),
+                gapless_playback: None,
+            })
+        );
+        assert_eq!(parse_itunsmpb("not gapless"), None);
+    }
+}
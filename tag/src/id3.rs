@@ -7,7 +7,7 @@ use core::convert::AsRef;
 use entity::TrackFormat;
 use eyre::{eyre, Result};
 use id3::frame::PictureType as ID3PictureType;
-use id3::frame::{ExtendedText, Picture as ID3Picture};
+use id3::frame::{ExtendedText, Lyrics as ID3Lyrics, Picture as ID3Picture, Popularimeter};
 use id3::{Content, Frame, TagLike, Version};
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -29,6 +29,38 @@ pub struct Tag {
 }
 
 static EXTENDED_LEN_4: [&str; 1] = ["ASIN"];
+/// Unsynchronised lyrics are stored in their own frame type rather than as text
+static LYRICS_FRAME: &str = "USLT";
+/// ISO-639-2 code for an undetermined language
+static LYRICS_LANGUAGE: &str = "und";
+/// iTunes stores its gapless information in a comment frame
+static ITUNSMPB_COMMENT: &str = "COMM:iTunSMPB";
+static ITUNSMPB_DESCRIPTION: &str = "iTunSMPB";
+/// Ratings are a popularimeter frame with a 0-255 value. The user is the one
+/// Windows Media Player writes, which most players read.
+static RATING_FRAME: &str = "POPM";
+static RATING_USER: &str = "Windows Media Player 9 Series";
+/// The popularimeter value for each star, as written by Windows Media Player
+static RATING_STARS: [u8; 5] = [1, 64, 128, 196, 255];
+
+// Converts a percentage to its popularimeter value, 0 meaning unrated
+fn percent_to_popm(percent: u8) -> u8 {
+    match percent.min(100) {
+        0 => 0,
+        p => RATING_STARS[((usize::from(p) + 10) / 20).clamp(1, 5) - 1],
+    }
+}
+
+fn popm_to_percent(value: u8) -> u8 {
+    if value == 0 {
+        return 0;
+    }
+    let stars = RATING_STARS
+        .iter()
+        .position(|stars| value <= *stars)
+        .unwrap_or(RATING_STARS.len() - 1);
+    (stars as u8 + 1) * 20
+}
 
 impl super::TagFrom for Tag {
     fn from_path<P>(library: &Library, path: P) -> Result<Box<dyn crate::Tag>>
@@ -58,7 +90,23 @@ impl super::Tag for Tag {
         Some(self.separator.clone())
     }
     fn get_str(&self, key: &str) -> Option<Vec<String>> {
-        if key.len() != 4 || EXTENDED_LEN_4.contains(&key) {
+        if key.starts_with(LYRICS_FRAME) {
+            self.tag.lyrics().next().map(|l| vec![l.text.to_string()])
+        } else if key == RATING_FRAME {
+            self.tag
+                .frames()
+                .filter_map(|f| match f.content() {
+                    Content::Popularimeter(p) => Some(p),
+                    _ => None,
+                })
+                .find(|p| p.user == RATING_USER)
+                .map(|p| vec![popm_to_percent(p.rating).to_string()])
+        } else if key == ITUNSMPB_COMMENT {
+            self.tag
+                .comments()
+                .find(|c| c.description == ITUNSMPB_DESCRIPTION)
+                .map(|c| vec![c.text.to_string()])
+        } else if key.len() != 4 || EXTENDED_LEN_4.contains(&key) {
             self.tag
                 .extended_texts()
                 .find(|t| t.description == key)
@@ -78,6 +126,41 @@ impl super::Tag for Tag {
     }
 
     fn set_str(&mut self, key: &str, values: Vec<String>) -> Result<()> {
+        if key.starts_with(LYRICS_FRAME) {
+            self.tag.remove_all_lyrics();
+            if !values.is_empty() {
+                self.tag.add_frame(ID3Lyrics {
+                    lang: LYRICS_LANGUAGE.to_string(),
+                    description: String::new(),
+                    text: values.join("\n"),
+                });
+            }
+            return Ok(());
+        }
+        if key == RATING_FRAME {
+            // ratings of other players are kept, along with our play counter
+            let mut counter = 0;
+            for frame in self.tag.remove(RATING_FRAME) {
+                let ours = match frame.content() {
+                    Content::Popularimeter(p) if p.user == RATING_USER => Some(p.counter),
+                    _ => None,
+                };
+                match ours {
+                    Some(ours) => counter = ours,
+                    None => {
+                        self.tag.add_frame(frame);
+                    }
+                }
+            }
+            if let Some(percent) = values.first() {
+                self.tag.add_frame(Popularimeter {
+                    user: RATING_USER.to_string(),
+                    rating: percent_to_popm(percent.parse()?),
+                    counter,
+                });
+            }
+            return Ok(());
+        }
         let frame = if key.len() != 4 || EXTENDED_LEN_4.contains(&key) {
             ExtendedText {
                 description: key.to_string(),
@@ -285,6 +368,7 @@ impl super::Tag for Tag {
 
             // Internal, not mapped from picard
             TagKey::Duration => vec!["TLEN"],
+            TagKey::ITunSMPB => vec![ITUNSMPB_COMMENT, "iTunSMPB"],
             _ => vec![],
         }
     }
@@ -100,6 +100,7 @@ pub enum TagKey {
 
     // Internal, not mapped from picard
     Duration,
+    ITunSMPB,
 }
 
 impl Display for TagKey {
@@ -200,6 +201,7 @@ impl Display for TagKey {
 
             // Internal, not mapped from picard
             TagKey::Duration => write!(f, "duration"),
+            TagKey::ITunSMPB => write!(f, "itunsmpb"),
         }
     }
 }
@@ -7,6 +7,7 @@ pub mod id3;
 #[cfg(feature = "mp4")]
 pub mod mp4;
 
+pub mod gapless;
 pub mod key;
 pub mod map;
 pub mod picture;
@@ -14,6 +15,7 @@ pub mod picture;
 use base::setting::Library;
 pub use core::convert::AsRef;
 pub use eyre::{Report, Result};
+pub use gapless::{read_gapless, Gapless};
 pub use key::TagKey;
 pub use map::{
     sanitize_filename, sanitize_map, strs_from_combination, tag_to_string_map,
@@ -107,5 +109,15 @@ pub trait Tag: TagClone {
         })
     }
 
+    /// Sets the rating, from 1 to 5 stars, or removes it. It is stored as a
+    /// percentage unless the format has a better suited frame.
+    fn set_rating(&mut self, rating: Option<u8>) -> Result<(), TagError> {
+        let values = rating
+            .map(|stars| (u16::from(stars.min(5)) * 20).to_string())
+            .into_iter()
+            .collect();
+        self.set_tag(TagKey::Rating, values)
+    }
+
     fn write_to_path(&mut self, path: &Path) -> Result<()>;
 }
@@ -254,6 +254,7 @@ impl crate::track::Tag for Tag {
 
             // Internal, not mapped from picard
             TagKey::Duration => vec!["LENGTH"],
+            TagKey::ITunSMPB => vec!["----:com.apple.iTunes:iTunSMPB"],
             _ => vec![],
         }
     }